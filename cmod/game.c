@@ -23,3 +23,11 @@ void exports_zurie_mod_key_event(uint32_t key_code) {}
 void exports_zurie_mod_scroll(float amount) {
     zurie_engine_camera_set_zoom(zurie_engine_camera_get_zoom()+amount);
 }
+
+// The mod registers no console commands, so the engine never calls this
+bool exports_zurie_mod_console_command(zurie_mod_string_t *name, zurie_mod_list_arg_value_t *args, zurie_mod_string_t *ret, zurie_mod_string_t *err) {
+    zurie_mod_string_free(name);
+    zurie_mod_list_arg_value_free(args);
+    zurie_mod_string_dup(err, "cpp_hell has no console commands");
+    return false;
+}
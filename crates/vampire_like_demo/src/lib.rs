@@ -1,19 +1,21 @@
 use std::time::{Duration, Instant};
 use zurie_mod_interface::ecs::get_entities_with_component;
-use zurie_mod_interface::engine;
-use zurie_mod_interface::engine::camera::set_zoom;
-use zurie_mod_interface::engine::core::{ComponentId, SpriteHandle};
-
-use zurie_mod_interface::ecs::get_entities_with_components;
+use zurie_mod_interface::engine::camera::{add_trauma, follow_entity, set_zoom};
+use zurie_mod_interface::engine::core::{ComponentId, EventHandle};
+use zurie_mod_interface::engine::events::{EventData, subscribe_by_name};
+use zurie_mod_interface::engine::physics::{QueryFilter, Shape, overlap, set_velocity};
+
+use zurie_mod_interface::console::{ArgValue, register_command};
+use zurie_mod_interface::ecs::nearest_k;
+use zurie_mod_interface::engine::console::{ArgKind, CommandArg};
 use zurie_mod_interface::engine::input::key_clicked;
-use zurie_mod_interface::engine::sprite::load_sprite_bin;
 use zurie_mod_interface::{
     ZurieMod,
     ecs::Entity,
     engine::{
-        audio::{load_sound, play_sound},
+        audio::{PlaySettings, load_sound, play_sound_at_position},
         camera::get_zoom,
-        ecs::{ComponentData, register_component, spawn_entity},
+        ecs::{ComponentData, ComponentFilter, register_component},
         gui::{Widget, WidgetResponse, create_window},
     },
     glam::{self, Vec2},
@@ -21,20 +23,24 @@ use zurie_mod_interface::{
     register_zurie_mod,
 };
 
+const ENEMY_LAYER: u32 = 2;
+/// Most prefabs the spawn console command spawns at once.
+const MAX_SPAWN_COUNT: i64 = 100;
+
 pub struct Game {
     sound: u64,
     player: Entity,
     pos_component: u64,
     enemy_component: u64,
-    enemy_sprite: u64,
     projectile_component: u64,
     health_component: u64,
     last_shot: Instant,
-    projectile_sprite: u64,
-    direction_component: u64,
+    contact_begin: EventHandle,
+    world_restored: EventHandle,
     next_enemy_wave: Instant,
-    player_sprite: u64,
     timer: Instant,
+    /// Enemies don't hurt the player, set with the god console command.
+    god: bool,
 }
 
 impl Default for Game {
@@ -46,13 +52,12 @@ impl Default for Game {
             enemy_component: 0,
             projectile_component: 0,
             health_component: 0,
-            enemy_sprite: 0,
             last_shot: Instant::now(),
-            projectile_sprite: 0,
-            direction_component: 0,
+            contact_begin: 0,
+            world_restored: 0,
             next_enemy_wave: Instant::now(),
-            player_sprite: 0,
             timer: Instant::now(),
+            god: false,
         }
     }
 }
@@ -68,40 +73,42 @@ impl ZurieMod for Game {
 
     fn init(&mut self) {
         self.sound = load_sound("static/sound.wav");
-        let player_sprite = load_sprite_bin(include_bytes!("../../../static/player.aseprite"));
-        let enemy_sprite = load_sprite_bin(include_bytes!("../../../static/enemy.aseprite"));
-        self.projectile_sprite =
-            load_sprite_bin(include_bytes!("../../../static/projectile.aseprite"));
 
-        let player_ent = Entity::spawn();
+        // Players, enemies and projectiles are prefabs in static/prefabs
+        let player_ent = spawn_prefab("player", Vec2::ZERO);
         let pos_component = register_component("position");
         let enemy_component = register_component("enemy");
         let projectile_component = register_component("projectile");
         let health_component = register_component("health");
-        let direction_component = register_component("direction");
-
-        player_ent.set_component(pos_component, ComponentData::Vec2(Vec2::ZERO.into()));
-        player_ent.set_component(health_component, ComponentData::I32(100));
-        player_ent.set_sprite(player_sprite);
 
         self.player = player_ent;
-        self.player_sprite = player_sprite;
         self.pos_component = pos_component;
         self.enemy_component = enemy_component;
         self.projectile_component = projectile_component;
         self.health_component = health_component;
         self.last_shot = Instant::now();
-        self.direction_component = direction_component;
-        self.enemy_sprite = enemy_sprite;
-
-        spawn_enemy_wave(
-            enemy_component,
-            pos_component,
-            health_component,
-            enemy_sprite,
-        );
+        self.contact_begin = subscribe_by_name("contact-begin");
+        self.world_restored = subscribe_by_name("world-restored");
+
+        spawn_enemy_wave();
 
         set_zoom(10.0);
+        follow_entity(player_ent.0, 4.0, Vec2::new(1.0, 1.0).into());
+
+        let prefab = CommandArg::new("prefab", ArgKind::Text).choices(&[
+            "enemy",
+            "elite_enemy",
+            "projectile",
+        ]);
+        let count = CommandArg::new("count", ArgKind::Integer).optional();
+        register_command(
+            "spawn",
+            "Spawns prefabs around the player",
+            vec![prefab, count],
+        )
+        .unwrap();
+        let on = CommandArg::new("on", ArgKind::Boolean).optional();
+        register_command("god", "Enemies don't hurt the player", vec![on]).unwrap();
     }
 
     fn update(&mut self) {
@@ -117,10 +124,8 @@ impl ZurieMod for Game {
                     }
                     self.timer = Instant::now();
 
-                    let player_ent = Entity::spawn()
-                        .set_component(self.pos_component, ComponentData::Vec2(Vec2::ZERO.into()))
-                        .set_component(self.health_component, ComponentData::I32(100))
-                        .set_sprite(self.player_sprite);
+                    let player_ent = spawn_prefab("player", Vec2::ZERO);
+                    follow_entity(player_ent.0, 4.0, Vec2::new(1.0, 1.0).into());
                     self.player = player_ent
                 }
             }
@@ -149,81 +154,111 @@ impl ZurieMod for Game {
         move_enemies(self.pos_component, self.enemy_component, self.player);
 
         if self.last_shot.elapsed() > Duration::from_secs_f32(0.5) {
-            fire_projectile(
-                self.player,
-                self.pos_component,
-                self.projectile_component,
-                self.projectile_sprite,
-                self.enemy_component,
-                self.direction_component,
-            );
+            fire_projectile(self.player, self.pos_component, self.enemy_component);
             self.last_shot = Instant::now();
         }
 
         if self.next_enemy_wave.elapsed() > Duration::from_secs_f32(5.0) {
-            spawn_enemy_wave(
-                self.enemy_component,
-                self.pos_component,
-                self.health_component,
-                self.enemy_sprite,
-            );
+            spawn_enemy_wave();
             self.next_enemy_wave = Instant::now();
         }
 
-        update_projectiles(
-            self.projectile_component,
-            self.pos_component,
-            self.direction_component,
-        );
+        if !self.god {
+            check_player_collision(self.player, self.pos_component, self.health_component);
+        }
+    }
 
-        check_projectile_collision(
-            self.projectile_component,
+    fn event(&mut self, handle: EventHandle, data: EventData) {
+        // The editor stopped playing, the player may be back with a new id
+        if handle == self.world_restored {
+            let EventData::Raw(bytes) = data else {
+                return;
+            };
+            for pair in bytes.chunks_exact(16) {
+                let old = u64::from_le_bytes(pair[..8].try_into().unwrap());
+                if old == self.player.0 {
+                    self.player = Entity(u64::from_le_bytes(pair[8..].try_into().unwrap()));
+                }
+            }
+            if self.player.exits() {
+                follow_entity(self.player.0, 4.0, Vec2::new(1.0, 1.0).into());
+            }
+            return;
+        }
+        if handle != self.contact_begin {
+            return;
+        }
+        let EventData::Contact(contact) = data else {
+            return;
+        };
+        let (a, b) = (Entity(contact.a), Entity(contact.b));
+        let is_projectile =
+            |entity: Entity| entity.get_component(self.projectile_component).is_some();
+        let (projectile, enemy) = match (is_projectile(a), is_projectile(b)) {
+            (true, false) => (a, b),
+            (false, true) => (b, a),
+            _ => return,
+        };
+        if enemy.get_component(self.enemy_component).is_none() {
+            return;
+        }
+        hit_enemy(
+            projectile,
+            enemy,
             self.pos_component,
-            self.direction_component,
-            self.enemy_component,
             self.health_component,
             20,
+            self.sound,
         );
+    }
 
-        check_player_collision(
-            self.player,
-            self.pos_component,
-            self.enemy_component,
-            self.health_component,
-        );
+    fn console_command(&mut self, name: &str, args: Vec<ArgValue>) -> Result<String, String> {
+        match (name, args.as_slice()) {
+            ("spawn", [ArgValue::Text(prefab), rest @ ..]) => {
+                let count = match rest {
+                    [ArgValue::Integer(count)] => (*count).clamp(1, MAX_SPAWN_COUNT),
+                    _ => 1,
+                };
+                let center: Vec2 = match self.player.get_component(self.pos_component) {
+                    Some(ComponentData::Vec2(pos)) => pos.into(),
+                    _ => Vec2::ZERO,
+                };
+                // A ring around the player, far enough not to hit them right away
+                for i in 0..count {
+                    let angle = i as f32 / count as f32 * std::f32::consts::TAU;
+                    let overrides = [(
+                        "position".into(),
+                        ComponentData::Vec2((center + Vec2::from_angle(angle) * 6.0).into()),
+                    )];
+                    Entity::spawn_prefab(prefab, &overrides)?;
+                }
+                Ok(format!("Spawned {count} {prefab}"))
+            }
+            ("god", args) => {
+                self.god = match args {
+                    [ArgValue::Boolean(on)] => *on,
+                    _ => !self.god,
+                };
+                Ok(format!("God mode {}", if self.god { "on" } else { "off" }))
+            }
+            _ => Err(format!("{name} isn't a command of this mod")),
+        }
     }
 }
 
-fn spawn_enemy_wave(
-    enemy_component: ComponentId,
-    pos_component: ComponentId,
-    health_component: ComponentId,
-    sprite: SpriteHandle,
-) {
-    for i in -2..=2 {
-        let enemy_pos = Vec2::new(i as f32 * 2.0, -5.0);
-        spawn_enemy(
-            enemy_component,
-            pos_component,
-            health_component,
-            enemy_pos,
-            sprite,
-        );
-    }
+/// Spawns a prefab at `position`, nothing can be played without them so a missing or broken
+/// one is fatal.
+fn spawn_prefab(name: &str, position: Vec2) -> Entity {
+    let overrides = [("position".into(), ComponentData::Vec2(position.into()))];
+    Entity::spawn_prefab(name, &overrides).unwrap_or_else(|err| panic!("{err}"))
 }
 
-fn spawn_enemy(
-    enemy_component: ComponentId,
-    pos_component: ComponentId,
-    health_component: ComponentId,
-    pos: Vec2,
-    sprite: SpriteHandle,
-) {
-    Entity::spawn()
-        .set_component(pos_component, ComponentData::Vec2(pos.into()))
-        .set_component(health_component, ComponentData::I32(100))
-        .set_component(enemy_component, ComponentData::None)
-        .set_sprite(sprite);
+/// The middle enemy of every wave is an elite, a variant of the enemy prefab.
+fn spawn_enemy_wave() {
+    for i in -2..=2 {
+        let prefab = if i == 0 { "elite_enemy" } else { "enemy" };
+        spawn_prefab(prefab, Vec2::new(i as f32 * 2.0, -5.0));
+    }
 }
 
 fn move_enemies(pos_component: ComponentId, enemy_component: ComponentId, player: Entity) {
@@ -231,118 +266,86 @@ fn move_enemies(pos_component: ComponentId, enemy_component: ComponentId, player
     if let Some(ComponentData::Vec2(player_pos)) = player.get_component(pos_component) {
         for enemy in enemies.iter() {
             if let Some(ComponentData::Vec2(enemy_pos)) = enemy.get_component(pos_component) {
-                let new_pos: Vec2 = Into::<Vec2>::into(enemy_pos)
-                    + (Into::<Vec2>::into(player_pos) - Into::<Vec2>::into(enemy_pos))
-                        .normalize_or_zero()
-                        * 0.05;
-                enemy.set_component(pos_component, ComponentData::Vec2(new_pos.into()));
+                let velocity: Vec2 = (Into::<Vec2>::into(player_pos)
+                    - Into::<Vec2>::into(enemy_pos))
+                .normalize_or_zero()
+                    * 3.0;
+                set_velocity(enemy.0, velocity.into());
             }
         }
     }
 }
 
-fn fire_projectile(
-    player: Entity,
-    pos_component: ComponentId,
-    projectile_component: ComponentId,
-    projectile_sprite: u64,
-    enemy_component: ComponentId,
-    direction_component: ComponentId,
-) {
+fn fire_projectile(player: Entity, pos_component: ComponentId, enemy_component: ComponentId) {
     if let Some(ComponentData::Vec2(player_pos)) = player.get_component(pos_component) {
         let nearest_enemy_pos: Option<Vec2> =
             get_nearest_enemy_to(pos_component, enemy_component, player_pos.into());
         if let Some(enemy_pos) = nearest_enemy_pos {
-            Entity::spawn()
-                .set_component(pos_component, ComponentData::Vec2(player_pos.into()))
-                .set_component(
-                    direction_component,
-                    ComponentData::Vec2(
-                        vector_between_coordinates(player_pos.into(), enemy_pos)
-                            .normalize()
-                            .into(),
-                    ),
-                )
-                .set_component(projectile_component, ComponentData::None)
-                .set_sprite(projectile_sprite);
+            let projectile = spawn_prefab("projectile", player_pos.into());
+            let direction = vector_between_coordinates(player_pos.into(), enemy_pos).normalize();
+            set_velocity(projectile.0, (direction * 6.0).into());
         }
     }
 }
 
-fn update_projectiles(
-    projectile_component: ComponentId,
+fn hit_enemy(
+    mut projectile: Entity,
+    mut enemy: Entity,
     pos_component: ComponentId,
-    direction_component: ComponentId,
+    health_component: ComponentId,
+    damage: i32,
+    death_sound: u64,
 ) {
-    let projectiles = get_entities_with_component(projectile_component);
-    for projectile in projectiles.iter() {
-        if let (Some(ComponentData::Vec2(proj_pos)), Some(ComponentData::Vec2(proj_dir))) = (
-            projectile.get_component(pos_component),
-            projectile.get_component(direction_component),
-        ) {
-            let new_pos: Vec2 = Into::<Vec2>::into(proj_pos) + Into::<Vec2>::into(proj_dir) * 0.1;
-            projectile.set_component(pos_component, ComponentData::Vec2(new_pos.into()));
+    // A projectile touching two enemies in one step only hits the first
+    if !projectile.exits() || !enemy.exits() {
+        return;
+    }
+    projectile.despawn();
+    if let (Some(ComponentData::I32(health)), Some(ComponentData::Vec2(enemy_pos))) = (
+        enemy.get_component(health_component),
+        enemy.get_component(pos_component),
+    ) {
+        let new_health = health - damage;
+        enemy.set_component(health_component, ComponentData::I32(new_health));
+        if new_health <= 0 {
+            play_death_sound(death_sound, enemy_pos.into());
+            enemy.despawn();
         }
     }
 }
 
-fn check_projectile_collision(
-    projectile_component: ComponentId,
-    pos_component: ComponentId,
-    direction_component: ComponentId,
-    enemy_component: ComponentId,
-    health_component: ComponentId,
-    damage: i32,
-) {
-    let mut projectiles = get_entities_with_component(projectile_component);
-    let mut enemies = get_entities_with_component(enemy_component);
-
-    projectiles.iter_mut().for_each(|projectile| {
-        if let Some(ComponentData::Vec2(proj_pos)) = projectile.get_component(pos_component) {
-            let proj_pos: Vec2 = proj_pos.into();
-            let mut collided = false;
-            enemies.iter_mut().for_each(|enemy| {
-                if let Some(ComponentData::Vec2(enemy_pos)) = enemy.get_component(pos_component) {
-                    let enemy_pos: Vec2 = enemy_pos.into();
-
-                    if proj_pos.distance(enemy_pos) < 0.5 {
-                        collided = true;
-                        enemy.get_component(health_component).map(|health| {
-                            if let ComponentData::I32(health_value) = health {
-                                let new_health = health_value - damage;
-                                enemy.set_component(
-                                    health_component,
-                                    ComponentData::I32(new_health),
-                                );
-
-                                if new_health <= 0 {
-                                    enemy.despawn();
-                                }
-                            }
-                        });
-                    }
-                }
-            });
-            if collided {
-                projectile.despawn();
-            }
-        }
-    });
+/// Enemies further away from the camera sound quieter and are panned to their side.
+fn play_death_sound(sound: u64, position: Vec2) {
+    let settings = PlaySettings {
+        volume: 0.6,
+        pitch: 1.0,
+        panning: 0.0,
+        loop_region: None,
+        fade_in: 0.0,
+        bus: Some("sfx".into()),
+    };
+    play_sound_at_position(sound, position.into(), &settings);
 }
 
 fn check_player_collision(
     mut player: Entity,
     pos_component: ComponentId,
-    enemy_component: ComponentId,
     health_component: ComponentId,
 ) {
     if let (Some(ComponentData::Vec2(pos)), Some(ComponentData::I32(health))) = (
         player.get_component(pos_component),
         player.get_component(health_component),
     ) {
-        let enemies = get_enemies_with_distance_to(pos_component, enemy_component, pos.into(), 1.0)
-            .len() as i32;
+        let filter = QueryFilter {
+            mask: ENEMY_LAYER,
+            exclude: vec![player.0],
+            include_sensors: false,
+        };
+        let enemies = overlap(Shape::Circle(1.0), pos, &filter).len() as i32;
         let new_health = health - 5 * enemies;
+        if enemies > 0 {
+            add_trauma(0.3);
+        }
         if health - 5 * enemies < 0 {
             player.despawn()
         } else {
@@ -351,54 +354,20 @@ fn check_player_collision(
     }
 }
 
-fn get_enemies_with_distance_to(
-    pos_component: ComponentId,
-    enemy_component: ComponentId,
-    to: Vec2,
-    distance: f32,
-) -> Vec<Entity> {
-    get_entities_with_components(&[pos_component, enemy_component])
-        .iter()
-        .filter(|ent| {
-            if let ComponentData::Vec2(pos) = ent
-                .get_component(pos_component)
-                .unwrap_or(engine::ecs::ComponentData::Vec2(Vec2::ZERO.into()))
-            {
-                if Into::<Vec2>::into(pos).distance(to) < distance {
-                    true
-                } else {
-                    false
-                }
-            } else {
-                false
-            }
-        })
-        .copied()
-        .collect()
-}
-
 fn get_nearest_enemy_to(
     pos_component: ComponentId,
     enemy_component: ComponentId,
     to: Vec2,
 ) -> Option<Vec2> {
-    get_entities_with_components(&[pos_component, enemy_component])
-        .iter()
-        .map(|ent| {
-            if let ComponentData::Vec2(pos) = ent
-                .get_component(pos_component)
-                .unwrap_or(engine::ecs::ComponentData::Vec2(Vec2::ZERO.into()))
-            {
-                pos.into()
-            } else {
-                Vec2::ZERO
-            }
-        })
-        .min_by(|a, b| {
-            a.distance(to)
-                .partial_cmp(&b.distance(to))
-                .unwrap_or(std::cmp::Ordering::Equal)
-        })
+    let filter = ComponentFilter {
+        required: vec![enemy_component],
+        excluded: vec![],
+    };
+    let nearest = nearest_k(to.into(), 1, None, &filter).into_iter().next()?;
+    match nearest.get_component(pos_component) {
+        Some(ComponentData::Vec2(pos)) => Some(pos.into()),
+        _ => None,
+    }
 }
 
 fn vector_between_coordinates(from: Vec2, to: Vec2) -> Vec2 {
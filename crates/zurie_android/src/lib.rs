@@ -17,9 +17,12 @@ mod lib {
 
     #[no_mangle]
     fn android_main(app: AndroidApp) {
-        android_logger::init_once(
-            android_logger::Config::default().with_max_level(log::LevelFilter::Info),
+        let level = log::LevelFilter::Info;
+        let logger = android_logger::AndroidLogger::new(
+            android_logger::Config::default().with_max_level(level),
         );
+        // The activity can be started again in the same process, the logger stays set
+        let _ = zurie_core::console::init(Box::new(logger), level);
         info!("Android main called! 1 time");
         let event_loop = EventLoopBuilder::with_android_app(&mut EventLoopBuilder::default(), app)
             .build()
@@ -0,0 +1,402 @@
+use anyhow::{Context, anyhow, bail, ensure};
+use hashbrown::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+pub const ARCHIVE_MAGIC: &[u8; 4] = b"ZPAK";
+const ARCHIVE_VERSION: u32 = 1;
+/// Archive the engine mounts on startup when it exists.
+pub const DEFAULT_ARCHIVE: &str = "assets.zpak";
+
+const FLAG_LZ4: u8 = 1;
+/// LZ4 can't shrink data by more than this, larger sizes in an index are corrupted.
+const MAX_LZ4_RATIO: u64 = 255;
+
+static MOUNTED: RwLock<Option<Arc<Archive>>> = RwLock::new(None);
+
+/// Where a file lives inside the data section of an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    pub offset: u64,
+    /// Size in the archive, smaller than `size` when compressed.
+    pub stored_size: u64,
+    pub size: u64,
+    pub compressed: bool,
+    /// Blake3 hash of the uncompressed content.
+    pub hash: [u8; 32],
+}
+
+/// Builds an archive in memory. Layout is the magic, version and entry count, then the
+/// index and the data of every file.
+#[derive(Default)]
+pub struct ArchiveWriter {
+    entries: Vec<(String, ArchiveEntry)>,
+    data: Vec<u8>,
+}
+
+impl ArchiveWriter {
+    /// Adds a file, compression is only kept when it makes the file smaller.
+    pub fn add(
+        &mut self,
+        path: impl AsRef<Path>,
+        bytes: &[u8],
+        compress: bool,
+    ) -> anyhow::Result<()> {
+        let path = archive_path(path.as_ref());
+        ensure!(
+            !self.entries.iter().any(|(name, _)| *name == path),
+            "{path} is already in the archive"
+        );
+        let compressed = compress
+            .then(|| lz4_flex::compress(bytes))
+            .filter(|compressed| compressed.len() < bytes.len());
+        let stored = compressed.as_deref().unwrap_or(bytes);
+        self.entries.push((
+            path,
+            ArchiveEntry {
+                offset: self.data.len() as u64,
+                stored_size: stored.len() as u64,
+                size: bytes.len() as u64,
+                compressed: compressed.is_some(),
+                hash: *blake3::hash(bytes).as_bytes(),
+            },
+        ));
+        self.data.extend_from_slice(stored);
+        Ok(())
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &ArchiveEntry)> {
+        self.entries
+            .iter()
+            .map(|(path, entry)| (path.as_str(), entry))
+    }
+
+    pub fn write(&self, out: &mut impl Write) -> anyhow::Result<()> {
+        out.write_all(ARCHIVE_MAGIC)?;
+        out.write_all(&ARCHIVE_VERSION.to_le_bytes())?;
+        out.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+        for (path, entry) in self.entries.iter() {
+            out.write_all(&(path.len() as u32).to_le_bytes())?;
+            out.write_all(path.as_bytes())?;
+            out.write_all(&entry.offset.to_le_bytes())?;
+            out.write_all(&entry.stored_size.to_le_bytes())?;
+            out.write_all(&entry.size.to_le_bytes())?;
+            out.write_all(&[if entry.compressed { FLAG_LZ4 } else { 0 }])?;
+            out.write_all(&entry.hash)?;
+        }
+        out.write_all(&self.data)?;
+        Ok(())
+    }
+}
+
+enum ArchiveSource {
+    File(PathBuf),
+    Memory(Vec<u8>),
+}
+
+/// Packed assets, only the index is kept in memory for archives opened from a file.
+pub struct Archive {
+    index: HashMap<String, ArchiveEntry>,
+    data_start: u64,
+    source: ArchiveSource,
+}
+
+impl Archive {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).with_context(|| format!("Failed to open {path:?}"))?;
+        let len = file.metadata()?.len();
+        let (index, data_start) = read_index(&mut BufReader::new(file))
+            .and_then(|(index, data_start)| {
+                check_entries(&index, data_start, len)?;
+                Ok((index, data_start))
+            })
+            .with_context(|| format!("Failed to read archive index of {path:?}"))?;
+        Ok(Self {
+            index,
+            data_start,
+            source: ArchiveSource::File(path.to_path_buf()),
+        })
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> anyhow::Result<Self> {
+        let (index, data_start) = read_index(&mut bytes.as_slice())?;
+        check_entries(&index, data_start, bytes.len() as u64)?;
+        Ok(Self {
+            index,
+            data_start,
+            source: ArchiveSource::Memory(bytes),
+        })
+    }
+
+    pub fn contains(&self, path: impl AsRef<Path>) -> bool {
+        self.index.contains_key(&archive_path(path.as_ref()))
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &ArchiveEntry)> {
+        self.index
+            .iter()
+            .map(|(path, entry)| (path.as_str(), entry))
+    }
+
+    /// Content of `path`, `None` when the archive doesn't have it.
+    pub fn read(&self, path: impl AsRef<Path>) -> Option<anyhow::Result<Vec<u8>>> {
+        let name = archive_path(path.as_ref());
+        let entry = self.index.get(&name)?;
+        Some(
+            self.read_entry(entry)
+                .with_context(|| format!("Failed to read {name} from archive")),
+        )
+    }
+
+    /// Entries were checked against the archive length when it was opened.
+    fn read_entry(&self, entry: &ArchiveEntry) -> anyhow::Result<Vec<u8>> {
+        let start = self.data_start + entry.offset;
+        let stored = match &self.source {
+            ArchiveSource::File(path) => {
+                let mut file = File::open(path)?;
+                file.seek(SeekFrom::Start(start))?;
+                let mut stored = vec![0; entry.stored_size as usize];
+                file.read_exact(&mut stored)?;
+                stored
+            }
+            ArchiveSource::Memory(bytes) => bytes
+                .get(start as usize..(start + entry.stored_size) as usize)
+                .ok_or_else(|| anyhow!("Entry is outside of the archive"))?
+                .to_vec(),
+        };
+        let data = if entry.compressed {
+            lz4_flex::decompress(&stored, entry.size as usize)?
+        } else {
+            stored
+        };
+        if blake3::hash(&data).as_bytes() != &entry.hash {
+            bail!("Content hash does not match, the archive is corrupted");
+        }
+        Ok(data)
+    }
+}
+
+fn read_index(reader: &mut impl Read) -> anyhow::Result<(HashMap<String, ArchiveEntry>, u64)> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    ensure!(&magic == ARCHIVE_MAGIC, "Not a zurie archive");
+    let version = read_u32(reader)?;
+    ensure!(
+        version == ARCHIVE_VERSION,
+        "Unsupported archive version {version}"
+    );
+    let count = read_u32(reader)?;
+    // The count isn't trusted with an allocation, a corrupted one would reserve gigabytes
+    let mut index = HashMap::with_capacity(count.min(1024) as usize);
+    let mut data_start = 12;
+    for _ in 0..count {
+        let path_len = read_u32(reader)?;
+        let mut path = Vec::new();
+        reader.take(path_len as u64).read_to_end(&mut path)?;
+        ensure!(path.len() == path_len as usize, "Archive index ends early");
+        let offset = read_u64(reader)?;
+        let stored_size = read_u64(reader)?;
+        let size = read_u64(reader)?;
+        let mut flags = [0; 1];
+        reader.read_exact(&mut flags)?;
+        let mut hash = [0; 32];
+        reader.read_exact(&mut hash)?;
+        data_start += 4 + path_len as u64 + 8 * 3 + 1 + 32;
+        index.insert(
+            String::from_utf8(path)?,
+            ArchiveEntry {
+                offset,
+                stored_size,
+                size,
+                compressed: flags[0] & FLAG_LZ4 != 0,
+                hash,
+            },
+        );
+    }
+    Ok((index, data_start))
+}
+
+/// Makes sure every entry lies within an archive of `len` bytes and its size can come out of
+/// what is stored, before any of them is read.
+fn check_entries(
+    index: &HashMap<String, ArchiveEntry>,
+    data_start: u64,
+    len: u64,
+) -> anyhow::Result<()> {
+    for (path, entry) in index.iter() {
+        let end = data_start
+            .checked_add(entry.offset)
+            .and_then(|start| start.checked_add(entry.stored_size));
+        ensure!(
+            end.is_some_and(|end| end <= len),
+            "{path} is outside of the archive"
+        );
+        let max_size = if entry.compressed {
+            entry.stored_size.saturating_mul(MAX_LZ4_RATIO)
+        } else {
+            entry.stored_size
+        };
+        ensure!(
+            entry.size <= max_size && (entry.compressed || entry.size == entry.stored_size),
+            "{path} can't be {} bytes with {} stored",
+            entry.size,
+            entry.stored_size
+        );
+    }
+    Ok(())
+}
+
+fn read_u32(reader: &mut impl Read) -> anyhow::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> anyhow::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Name of a file inside an archive, relative with `/` separators so `./static/a.wav` and
+/// `static\a.wav` find the same entry.
+pub fn archive_path(path: &Path) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::ParentDir => {
+                parts.pop();
+            }
+            _ => {}
+        }
+    }
+    parts.join("/")
+}
+
+/// Makes `read_asset` look into `archive` before the file system.
+pub fn mount(archive: Archive) {
+    *MOUNTED.write().unwrap() = Some(Arc::new(archive));
+}
+
+pub fn unmount() {
+    *MOUNTED.write().unwrap() = None;
+}
+
+/// Mounts the archive at `path` if there is one, returns whether it did.
+pub fn mount_if_present(path: impl AsRef<Path>) -> anyhow::Result<bool> {
+    let path = path.as_ref();
+    if !path.is_file() {
+        return Ok(false);
+    }
+    mount(Archive::open(path)?);
+    log::info!("Mounted asset archive {path:?}");
+    Ok(true)
+}
+
+/// Whether `path` is read from the mounted archive rather than the file system.
+pub fn is_packed(path: impl AsRef<Path>) -> bool {
+    let archive = MOUNTED.read().unwrap();
+    archive
+        .as_ref()
+        .is_some_and(|archive| archive.contains(path))
+}
+
+/// Reads an asset from the mounted archive, or from disk when it isn't packed.
+pub fn read_asset(path: impl AsRef<Path>) -> anyhow::Result<Vec<u8>> {
+    let path = path.as_ref();
+    let archive = MOUNTED.read().unwrap().clone();
+    if let Some(bytes) = archive.and_then(|archive| archive.read(path)) {
+        return bytes;
+    }
+    std::fs::read(path).with_context(|| format!("Failed to read {path:?}"))
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    fn packed(compress: bool) -> Vec<u8> {
+        let mut writer = ArchiveWriter::default();
+        writer
+            .add("./static/sound.wav", &[7; 1000], compress)
+            .unwrap();
+        writer.add("mods/demo.wasm", b"\0asm", compress).unwrap();
+        let mut bytes = Vec::new();
+        writer.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for compress in [false, true] {
+            let archive = Archive::from_bytes(packed(compress)).unwrap();
+            let sound = archive.read("static/sound.wav").unwrap().unwrap();
+            assert_eq!(sound, vec![7; 1000]);
+            assert_eq!(archive.read("./mods/demo.wasm").unwrap().unwrap(), b"\0asm");
+            assert!(archive.read("static/missing.wav").is_none());
+        }
+    }
+
+    #[test]
+    fn test_compression_is_only_kept_when_smaller() {
+        let archive = Archive::from_bytes(packed(true)).unwrap();
+        let entries: HashMap<&str, &ArchiveEntry> = archive.entries().collect();
+        assert!(entries["static/sound.wav"].compressed);
+        assert!(entries["static/sound.wav"].stored_size < 1000);
+        assert!(!entries["mods/demo.wasm"].compressed);
+    }
+
+    #[test]
+    fn test_corrupted_content_is_detected() {
+        let mut bytes = packed(false);
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        let archive = Archive::from_bytes(bytes).unwrap();
+        assert!(archive.read("static/sound.wav").unwrap().is_ok());
+        assert!(archive.read("mods/demo.wasm").unwrap().is_err());
+        assert!(Archive::from_bytes(b"ZIP!".to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_index_past_the_end_is_rejected() {
+        // Index fields of the first entry, after the header and its 16 byte path
+        let stored_size_at = 12 + 4 + 16 + 8;
+        let size_at = stored_size_at + 8;
+        for (at, value) in [
+            (stored_size_at, u64::MAX),
+            (stored_size_at - 8, u64::MAX - 10),
+            (stored_size_at, 1001),
+            (size_at, 1 << 40),
+        ] {
+            let mut bytes = packed(false);
+            bytes[at..at + 8].copy_from_slice(&value.to_le_bytes());
+            assert!(Archive::from_bytes(bytes).is_err(), "{at} {value}");
+        }
+        let mut bytes = packed(true);
+        bytes[size_at..size_at + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(Archive::from_bytes(bytes).is_err());
+        // A path length larger than the archive
+        let mut bytes = packed(false);
+        bytes[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Archive::from_bytes(bytes).is_err());
+        let mut bytes = packed(false);
+        bytes[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Archive::from_bytes(bytes).is_err());
+    }
+
+    #[test]
+    fn test_archive_paths() {
+        assert_eq!(archive_path(Path::new("./static/a.wav")), "static/a.wav");
+        assert_eq!(
+            archive_path(Path::new("static/../mods/b.wasm")),
+            "mods/b.wasm"
+        );
+        let mut writer = ArchiveWriter::default();
+        writer.add("a/b", b"1", false).unwrap();
+        assert!(writer.add("./a/b", b"2", false).is_err());
+    }
+}
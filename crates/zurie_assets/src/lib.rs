@@ -0,0 +1,223 @@
+use hashbrown::HashMap;
+use log::info;
+use slotmap::{Key, SlotMap};
+use std::path::{Path, PathBuf};
+
+pub mod archive;
+pub mod watcher;
+
+pub use archive::read_asset;
+pub use watcher::AssetWatcher;
+
+/// Result of asking the store for a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Acquired<K> {
+    /// First load of the path, the caller has to load the data.
+    New(K),
+    /// Path is already loaded, its reference count went up.
+    Existing(K),
+}
+
+impl<K: Copy> Acquired<K> {
+    pub fn handle(&self) -> K {
+        match self {
+            Acquired::New(handle) | Acquired::Existing(handle) => *handle,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct AssetEntry {
+    path: Option<PathBuf>,
+    refs: u32,
+}
+
+/// Handles of one kind of asset. Loading the same path twice returns the same handle and an
+/// asset is only unloaded once every load was released. The data itself stays with the
+/// owner of the store, keyed by the handles handed out here.
+pub struct AssetStore<K: Key> {
+    entries: SlotMap<K, AssetEntry>,
+    paths: HashMap<PathBuf, K>,
+    watcher: Option<AssetWatcher>,
+}
+
+impl<K: Key> Default for AssetStore<K> {
+    fn default() -> Self {
+        Self {
+            entries: SlotMap::with_key(),
+            paths: HashMap::new(),
+            watcher: None,
+        }
+    }
+}
+
+impl<K: Key> AssetStore<K> {
+    /// Store that reports changes to files with one of `extensions` through `changed`.
+    pub fn watched(extensions: &[&str]) -> Self {
+        let watcher = AssetWatcher::new(extensions)
+            .inspect_err(|err| log::warn!("Hot reload is disabled: {err}"))
+            .ok();
+        Self {
+            watcher,
+            ..Default::default()
+        }
+    }
+
+    pub fn load_path(&mut self, path: impl AsRef<Path>) -> Acquired<K> {
+        let path = normalize(path.as_ref());
+        if let Some(handle) = self.paths.get(&path).copied()
+            && let Some(entry) = self.entries.get_mut(handle)
+        {
+            entry.refs += 1;
+            return Acquired::Existing(handle);
+        }
+        if let Some(watcher) = self.watcher.as_mut() {
+            watcher.watch(&path);
+        }
+        let handle = self.entries.insert(AssetEntry {
+            path: Some(path.clone()),
+            refs: 1,
+        });
+        self.paths.insert(path, handle);
+        Acquired::New(handle)
+    }
+
+    /// Handle for an asset that doesn't come from a file, like an embedded buffer.
+    pub fn insert(&mut self) -> K {
+        self.entries.insert(AssetEntry {
+            path: None,
+            refs: 1,
+        })
+    }
+
+    pub fn retain(&mut self, handle: K) {
+        if let Some(entry) = self.entries.get_mut(handle) {
+            entry.refs += 1;
+        }
+    }
+
+    /// Drops one reference, returns true when it was the last one and the owner should unload
+    /// the asset.
+    pub fn release(&mut self, handle: K) -> bool {
+        let Some(entry) = self.entries.get_mut(handle) else {
+            return false;
+        };
+        entry.refs -= 1;
+        if entry.refs > 0 {
+            return false;
+        }
+        if let Some(path) = self.entries.remove(handle).and_then(|entry| entry.path) {
+            info!("Unloading asset {path:?}");
+            self.paths.remove(&path);
+            if let Some(watcher) = self.watcher.as_mut() {
+                watcher.unwatch(&path);
+            }
+        }
+        true
+    }
+
+    pub fn contains(&self, handle: K) -> bool {
+        self.entries.contains_key(handle)
+    }
+
+    pub fn refs(&self, handle: K) -> u32 {
+        self.entries.get(handle).map_or(0, |entry| entry.refs)
+    }
+
+    pub fn path(&self, handle: K) -> Option<&Path> {
+        self.entries.get(handle)?.path.as_deref()
+    }
+
+    pub fn handle(&self, path: impl AsRef<Path>) -> Option<K> {
+        self.paths.get(&normalize(path.as_ref())).copied()
+    }
+
+    /// Every asset loaded from a file.
+    pub fn paths(&self) -> impl Iterator<Item = (K, &Path)> {
+        self.paths
+            .iter()
+            .map(|(path, handle)| (*handle, path.as_path()))
+    }
+
+    /// Assets whose file changed on disk since the last call.
+    pub fn changed(&mut self) -> Vec<K> {
+        let Some(watcher) = self.watcher.as_ref() else {
+            return Vec::new();
+        };
+        let changed = watcher.changed_paths();
+        self.handles_of(changed)
+    }
+
+    fn handles_of(&self, paths: Vec<PathBuf>) -> Vec<K> {
+        let mut handles: Vec<K> = Vec::new();
+        for path in paths {
+            if let Some(handle) = self.handle(&path)
+                && !handles.contains(&handle)
+            {
+                handles.push(handle);
+            }
+        }
+        handles
+    }
+}
+
+/// Absolute path without `.` and `..` so different spellings of a file share one handle.
+fn normalize(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use slotmap::new_key_type;
+
+    new_key_type! { struct TestHandle; }
+
+    fn temp_file(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("zurie_assets_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, b"asset").unwrap();
+        path
+    }
+
+    #[test]
+    fn test_same_path_returns_same_handle() {
+        let path = temp_file("dedup.wav");
+        let mut store: AssetStore<TestHandle> = Default::default();
+        let first = store.load_path(&path);
+        assert!(matches!(first, Acquired::New(_)));
+        let dotted = path.parent().unwrap().join(".").join("dedup.wav");
+        assert_eq!(store.load_path(dotted), Acquired::Existing(first.handle()));
+        assert_eq!(store.refs(first.handle()), 2);
+        assert_ne!(store.insert(), first.handle());
+    }
+
+    #[test]
+    fn test_release_unloads_on_last_reference() {
+        let path = temp_file("refcount.aseprite");
+        let mut store: AssetStore<TestHandle> = Default::default();
+        let handle = store.load_path(&path).handle();
+        store.retain(handle);
+        assert!(!store.release(handle));
+        assert!(store.contains(handle));
+        assert!(store.release(handle));
+        assert!(!store.contains(handle));
+        assert_eq!(store.handle(&path), None);
+        assert!(!store.release(handle));
+        assert!(matches!(store.load_path(&path), Acquired::New(new) if new != handle));
+    }
+
+    #[test]
+    fn test_changed_paths_map_to_handles() {
+        let path = temp_file("changed.aseprite");
+        let mut store: AssetStore<TestHandle> = Default::default();
+        let handle = store.load_path(&path).handle();
+        let changed = store.handles_of(vec![
+            path.clone(),
+            path.clone(),
+            temp_file("unrelated.aseprite"),
+        ]);
+        assert_eq!(changed, vec![handle]);
+    }
+}
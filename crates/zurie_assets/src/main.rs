@@ -0,0 +1,97 @@
+use anyhow::{Context, bail};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use zurie_assets::archive::{Archive, ArchiveWriter, DEFAULT_ARCHIVE};
+
+const USAGE: &str = "Usage:
+    zurie_pack [--compress] [-o <archive>] <files or folders>...
+    zurie_pack --list <archive>
+
+Paths are stored relative to the current folder, the engine reads them from `assets.zpak`.";
+
+fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let mut output = PathBuf::from(DEFAULT_ARCHIVE);
+    let mut compress = false;
+    let mut inputs: Vec<PathBuf> = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--compress" | "-c" => compress = true,
+            "-o" | "--output" => {
+                output = args.next().context("-o needs a path")?.into();
+            }
+            "--list" | "-l" => {
+                return list(&args.next().context("--list needs an archive")?);
+            }
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            _ => inputs.push(arg.into()),
+        }
+    }
+    if inputs.is_empty() {
+        bail!("Nothing to pack\n\n{USAGE}");
+    }
+
+    let mut files = Vec::new();
+    for input in inputs.iter() {
+        collect_files(input, &mut files)?;
+    }
+    let mut writer = ArchiveWriter::default();
+    for file in files.iter() {
+        let bytes = std::fs::read(file).with_context(|| format!("Failed to read {file:?}"))?;
+        writer.add(file, &bytes, compress)?;
+    }
+    let mut out = BufWriter::new(
+        File::create(&output).with_context(|| format!("Failed to create {output:?}"))?,
+    );
+    writer.write(&mut out)?;
+
+    let (size, stored) = writer.entries().fold((0, 0), |(size, stored), (_, entry)| {
+        (size + entry.size, stored + entry.stored_size)
+    });
+    println!(
+        "Packed {} files into {output:?}, {size} bytes stored in {stored}",
+        files.len()
+    );
+    Ok(())
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    if path.is_dir() {
+        let mut entries = std::fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Same input gives the same archive
+        entries.sort();
+        for entry in entries {
+            collect_files(&entry, files)?;
+        }
+    } else if path.is_file() {
+        files.push(path.to_path_buf());
+    } else {
+        bail!("{path:?} does not exist");
+    }
+    Ok(())
+}
+
+fn list(path: &str) -> anyhow::Result<()> {
+    let archive = Archive::open(path)?;
+    let mut entries: Vec<_> = archive.entries().collect();
+    entries.sort_by_key(|(name, _)| *name);
+    for (name, entry) in entries {
+        let hash: String = entry.hash[..8]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        println!(
+            "{hash} {:>10} {:>10} {}{name}",
+            entry.size,
+            entry.stored_size,
+            if entry.compressed { "lz4 " } else { "" }
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use hashbrown::HashMap;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, channel};
+
+/// Watches the folders of loaded assets and reports files that changed on disk.
+pub struct AssetWatcher {
+    watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+    extensions: Vec<String>,
+    /// Watched folders and how many assets live in them. Folders are watched instead of the
+    /// files because editors often save by replacing the file.
+    folders: HashMap<PathBuf, u32>,
+}
+
+impl AssetWatcher {
+    /// Only reports files with one of `extensions`, given without the dot.
+    pub fn new(extensions: &[&str]) -> anyhow::Result<Self> {
+        let (sender, events) = channel();
+        Ok(Self {
+            watcher: notify::recommended_watcher(sender)?,
+            events,
+            extensions: extensions.iter().map(|ext| ext.to_lowercase()).collect(),
+            folders: HashMap::new(),
+        })
+    }
+
+    pub fn watch(&mut self, path: &Path) {
+        // Packed assets have no folder to watch
+        let Some(folder) = path.parent().filter(|folder| folder.is_dir()) else {
+            return;
+        };
+        let count = self.folders.entry(folder.to_path_buf()).or_insert(0);
+        if *count == 0
+            && let Err(err) = self.watcher.watch(folder, RecursiveMode::NonRecursive)
+        {
+            log::warn!("Failed to watch {folder:?} for changes: {err}");
+        }
+        *count += 1;
+    }
+
+    pub fn unwatch(&mut self, path: &Path) {
+        let Some(folder) = path.parent() else {
+            return;
+        };
+        if let Some(count) = self.folders.get_mut(folder) {
+            *count -= 1;
+            if *count == 0 {
+                self.folders.remove(folder);
+                let _ = self.watcher.unwatch(folder);
+            }
+        }
+    }
+
+    /// Files that were written or replaced since the last call.
+    pub fn changed_paths(&self) -> Vec<PathBuf> {
+        let mut changed: Vec<PathBuf> = Vec::new();
+        for event in self.events.try_iter() {
+            let event = match event {
+                Ok(event) => event,
+                Err(err) => {
+                    log::warn!("File watcher error: {err}");
+                    continue;
+                }
+            };
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                continue;
+            }
+            for path in event.paths {
+                if self.has_watched_extension(&path) && !changed.contains(&path) {
+                    changed.push(path);
+                }
+            }
+        }
+        changed
+    }
+
+    fn has_watched_extension(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| self.extensions.contains(&ext.to_lowercase()))
+    }
+}
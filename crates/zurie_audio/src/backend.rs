@@ -0,0 +1,49 @@
+use kira::DefaultBackend;
+use kira::backend::Backend;
+use kira::backend::mock::MockBackend;
+use std::time::Duration;
+
+/// Sample rate of the null backend, low since nothing is heard.
+pub const NULL_SAMPLE_RATE: u32 = 8_000;
+/// Set to `null` to run without a sound device.
+pub const AUDIO_BACKEND_ENV: &str = "ZURIE_AUDIO";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AudioBackend {
+    /// The sound device, or the null backend when there is none.
+    #[default]
+    Auto,
+    /// Plays nothing but still tracks instances and their timing, for tests and servers.
+    Null,
+}
+
+impl AudioBackend {
+    /// `Null` when `ZURIE_AUDIO=null` is set.
+    pub fn from_env() -> Self {
+        match std::env::var(AUDIO_BACKEND_ENV) {
+            Ok(value) if value.eq_ignore_ascii_case("null") => AudioBackend::Null,
+            _ => AudioBackend::Auto,
+        }
+    }
+}
+
+/// Kira backend the audio thread runs on.
+pub trait OutputBackend: Backend + Sized {
+    /// Renders audio up to `elapsed` since the backend started, for backends no device
+    /// pulls samples from. `rendered` counts the frames rendered so far.
+    fn render(_manager: &mut kira::AudioManager<Self>, _elapsed: Duration, _rendered: &mut u64) {}
+}
+
+impl OutputBackend for DefaultBackend {}
+
+impl OutputBackend for MockBackend {
+    fn render(manager: &mut kira::AudioManager<Self>, elapsed: Duration, rendered: &mut u64) {
+        let due = (elapsed.as_secs_f64() * NULL_SAMPLE_RATE as f64) as u64;
+        let backend = manager.backend_mut();
+        backend.on_start_processing();
+        while *rendered < due {
+            backend.process();
+            *rendered += 1;
+        }
+    }
+}
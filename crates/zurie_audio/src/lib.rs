@@ -1,94 +1,980 @@
+use anyhow::Context;
+use backend::{AudioBackend, NULL_SAMPLE_RATE, OutputBackend};
 use kira::AudioManagerSettings;
 use kira::DefaultBackend;
 use kira::backend::cpal::CpalBackend;
-use kira::sound::static_sound::StaticSoundData;
+use kira::backend::mock::{MockBackend, MockBackendSettings};
+use kira::effect::filter::{FilterBuilder, FilterHandle};
+use kira::effect::reverb::{ReverbBuilder, ReverbHandle};
+use kira::sound::static_sound::{StaticSoundData, StaticSoundHandle};
+use kira::sound::streaming::StreamingSoundData;
+use kira::track::{TrackBuilder, TrackHandle};
+use kira::{Mix, Tween};
 use log::info;
+use log::trace;
 use log::warn;
-use slotmap::{KeyData, SlotMap, new_key_type};
+use mixer::{BusSettings, MIXER_SETTINGS_PATH, MixerSettings};
+use music::{MUSIC_BUS, MusicCommand, MusicPlayer, MusicTrack, Playlist};
+use playback::{InstanceCommand, PlaySettings, PlaybackState};
+use slotmap::{KeyData, SecondaryMap, SlotMap, new_key_type};
+use spatial::{Emitter, EmitterTarget, Listener, SpatialAudio, SpatialSettings};
 use tracy_client::set_thread_name;
+use zurie_assets::{Acquired, AssetStore};
+use zurie_shared::loader::AssetLoader;
 
 use std::str::MatchIndices;
 use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
 use std::sync::mpsc::Sender;
 use std::sync::mpsc::channel;
+use std::time::{Duration, Instant};
 
+use std::collections::{HashMap, VecDeque};
+use std::io::Cursor;
 use std::path::Path;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
-use zurie_types::SoundHandle;
+use zurie_types::glam::Vec2;
+use zurie_types::{LoadStatus, SoundHandle, SoundInstanceHandle};
+
+pub mod backend;
+pub mod mixer;
+pub mod music;
+pub mod playback;
+pub mod spatial;
 
-#[derive(Debug)]
 pub enum AudioCommand {
-    Play(SoundHandle),
-    Load(String, Sender<SoundHandle>),
+    Play {
+        sound: SoundHandle,
+        instance: SoundInstanceHandle,
+        settings: PlaySettings,
+    },
+    Instance(SoundInstanceHandle, InstanceCommand),
+    /// Sent by a loader worker once the sound is decoded.
+    Loaded(SoundHandle, anyhow::Result<StaticSoundData>),
+    Unload(SoundHandle),
+    /// Mixer settings changed, buses move to them over `fade` seconds.
+    UpdateMixer {
+        fade: f32,
+    },
+    Music(MusicCommand),
     Stop,
 }
 
+/// A sound the audio thread started playing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayedSound {
+    pub sound: SoundHandle,
+    pub instance: SoundInstanceHandle,
+    /// Time since the audio thread started.
+    pub at: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct InstanceStatus {
+    state: PlaybackState,
+    /// Seconds into the sound.
+    position: f64,
+}
+
+/// Handles and load state of every sound, shared between the audio thread and its managers.
+struct SoundStatuses {
+    assets: AssetStore<SoundHandle>,
+    statuses: SecondaryMap<SoundHandle, LoadStatus>,
+    completed: Vec<SoundHandle>,
+    /// Playing sounds, removed by the audio thread once they stop.
+    instances: SlotMap<SoundInstanceHandle, InstanceStatus>,
+    /// Path of the music track playing.
+    music: Option<String>,
+    /// Latest started sounds, oldest first.
+    played: VecDeque<PlayedSound>,
+}
+
+impl Default for SoundStatuses {
+    fn default() -> Self {
+        Self {
+            assets: AssetStore::watched(&["wav", "ogg", "mp3", "flac"]),
+            statuses: SecondaryMap::new(),
+            completed: Vec::new(),
+            instances: SlotMap::with_key(),
+            music: None,
+            played: VecDeque::new(),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct AudioManager {
     manager: Sender<AudioCommand>,
+    loader: AssetLoader,
+    statuses: Arc<Mutex<SoundStatuses>>,
+    mixer: Arc<RwLock<MixerSettings>>,
+    spatial: Arc<Mutex<SpatialAudio>>,
+    /// False when sounds go to the null backend.
+    device: bool,
 }
 
 impl AudioManager {
-    pub fn new() -> AudioManager {
+    /// Positional sounds ease into their new volume and panning over this many seconds.
+    const SPATIAL_FADE: f32 = 0.05;
+
+    /// Plays on the sound device, or the null backend when there is none or `ZURIE_AUDIO=null`
+    /// is set.
+    pub fn new(loader: AssetLoader) -> AudioManager {
+        Self::with_backend(loader, AudioBackend::from_env())
+    }
+
+    pub fn with_backend(loader: AssetLoader, backend: AudioBackend) -> AudioManager {
         let (sender, receiver) = channel();
+        let (ready_sender, ready) = channel();
+        let statuses: Arc<Mutex<SoundStatuses>> = Default::default();
+        let mixer = Arc::new(RwLock::new(MixerSettings::load_or_default(
+            MIXER_SETTINGS_PATH,
+        )));
+        let thread_statuses = statuses.clone();
+        let thread_mixer = mixer.clone();
         thread::spawn(move || {
             set_thread_name!("Audio thread");
-            let mut audio_thread = AudioThread::new();
-            audio_thread.run(receiver);
+            let device = match backend {
+                AudioBackend::Auto => {
+                    kira::AudioManager::<DefaultBackend>::new(AudioManagerSettings::default())
+                        .inspect_err(|err| {
+                            warn!("No sound device, falling back to the null backend: {err}")
+                        })
+                        .ok()
+                }
+                AudioBackend::Null => None,
+            };
+            let _ = ready_sender.send(device.is_some());
+            if let Some(kira_manager) = device {
+                AudioThread::new(kira_manager, thread_statuses, thread_mixer).run(receiver);
+                return;
+            }
+            let settings = AudioManagerSettings {
+                backend_settings: MockBackendSettings {
+                    sample_rate: NULL_SAMPLE_RATE,
+                },
+                ..Default::default()
+            };
+            match kira::AudioManager::<MockBackend>::new(settings) {
+                Ok(kira_manager) => {
+                    AudioThread::new(kira_manager, thread_statuses, thread_mixer).run(receiver)
+                }
+                Err(_) => log::error!("Failed to start the null audio backend"),
+            }
         });
-        AudioManager { manager: sender }
+        AudioManager {
+            manager: sender,
+            loader,
+            statuses,
+            mixer,
+            spatial: Default::default(),
+            device: ready.recv().unwrap_or(false),
+        }
     }
 
+    /// Whether sounds are heard, false on the null backend.
+    pub fn has_device(&self) -> bool {
+        self.device
+    }
+
+    /// Decodes the sound on a loader worker, the handle can be used right away but plays
+    /// nothing until its status is ready. A path that is already loaded returns its handle,
+    /// every load needs an `unload_sound`.
     pub fn load_sound(&self, path: String) -> SoundHandle {
-        let (sender, receiver) = channel();
-        self.manager.send(AudioCommand::Load(path, sender));
-        receiver.recv().unwrap()
+        let handle = {
+            let mut statuses = self.statuses.lock().unwrap();
+            let handle = match statuses.assets.load_path(&path) {
+                Acquired::New(handle) => handle,
+                Acquired::Existing(handle) => return handle,
+            };
+            statuses.statuses.insert(handle, LoadStatus::Pending);
+            handle
+        };
+        info!("Loading sound {path} with handle: {:?}", handle);
+        self.decode_in_background(handle, path);
+        handle
+    }
+
+    /// Decodes the sound on the calling thread and returns why it can't be loaded, for tools
+    /// and tests. The sound can be played right away.
+    pub fn load_sound_blocking(&self, path: String) -> anyhow::Result<SoundHandle> {
+        let sound = decode_sound(&path)?;
+        let handle = {
+            let mut statuses = self.statuses.lock().unwrap();
+            let handle = statuses.assets.load_path(&path).handle();
+            statuses.statuses.insert(handle, LoadStatus::Ready);
+            handle
+        };
+        info!("Loaded sound {path} with handle: {:?}", handle);
+        let _ = self.manager.send(AudioCommand::Loaded(handle, Ok(sound)));
+        Ok(handle)
+    }
+
+    fn decode_in_background(&self, handle: SoundHandle, path: String) {
+        let sender = self.manager.clone();
+        self.loader.spawn(move || {
+            let _ = sender.send(AudioCommand::Loaded(handle, decode_sound(&path)));
+        });
+    }
+
+    /// Drops one load of the sound, its data is freed once nothing uses it.
+    pub fn unload_sound(&self, sound: SoundHandle) {
+        let mut statuses = self.statuses.lock().unwrap();
+        if statuses.assets.release(sound) {
+            statuses.statuses.remove(sound);
+            let _ = self.manager.send(AudioCommand::Unload(sound));
+        }
+    }
+
+    /// Decodes sounds whose file changed on disk again, the old data plays until then.
+    pub fn hot_reload(&self) {
+        let changed: Vec<(SoundHandle, String)> = {
+            let mut statuses = self.statuses.lock().unwrap();
+            let changed = statuses.assets.changed();
+            changed
+                .into_iter()
+                .filter_map(|handle| {
+                    let path = statuses.assets.path(handle)?.to_str()?.to_string();
+                    Some((handle, path))
+                })
+                .collect()
+        };
+        for (handle, path) in changed {
+            info!("Hot reloading sound {path}");
+            self.decode_in_background(handle, path);
+        }
+    }
+
+    pub fn status(&self, sound: SoundHandle) -> LoadStatus {
+        self.statuses
+            .lock()
+            .unwrap()
+            .statuses
+            .get(sound)
+            .cloned()
+            .unwrap_or_else(|| LoadStatus::Failed("Unknown sound handle".into()))
+    }
+
+    /// Sounds that finished loading, successfully or not, since the last call.
+    pub fn take_completed(&self) -> Vec<SoundHandle> {
+        std::mem::take(&mut self.statuses.lock().unwrap().completed)
+    }
+
+    pub fn play(&self, sound: SoundHandle) -> SoundInstanceHandle {
+        self.play_with(sound, PlaySettings::default())
+    }
+
+    /// Starts a new instance of the sound, its handle can be controlled with `control`.
+    pub fn play_with(&self, sound: SoundHandle, settings: PlaySettings) -> SoundInstanceHandle {
+        let instance = self.new_instance();
+        self.start(sound, instance, settings);
+        instance
+    }
+
+    /// Plays a sound heard from `target`, its volume and panning follow the listener.
+    /// `position` is where an entity target is right now, without it the sound stays silent
+    /// until the next `update_spatial`.
+    pub fn play_at(
+        &self,
+        sound: SoundHandle,
+        target: EmitterTarget,
+        position: Option<Vec2>,
+        mut settings: PlaySettings,
+    ) -> SoundInstanceHandle {
+        let instance = self.new_instance();
+        let mix = {
+            let mut spatial = self.spatial.lock().unwrap();
+            let mut emitter = Emitter::new(target, spatial.settings, settings.volume);
+            emitter.position = emitter.position.or(position);
+            spatial.attach(instance, emitter)
+        };
+        settings.volume = mix.volume;
+        settings.panning = mix.panning;
+        self.start(sound, instance, settings);
+        instance
+    }
+
+    fn new_instance(&self) -> SoundInstanceHandle {
+        self.statuses
+            .lock()
+            .unwrap()
+            .instances
+            .insert(InstanceStatus {
+                state: PlaybackState::Playing,
+                position: 0.0,
+            })
+    }
+
+    fn start(&self, sound: SoundHandle, instance: SoundInstanceHandle, settings: PlaySettings) {
+        let _ = self.manager.send(AudioCommand::Play {
+            sound,
+            instance,
+            settings,
+        });
+    }
+
+    pub fn control(&self, instance: SoundInstanceHandle, command: InstanceCommand) {
+        // Positional sounds are attenuated from the volume they were given
+        if let InstanceCommand::SetVolume { volume, .. } = command
+            && let Some(emitter) = self.spatial.lock().unwrap().emitter_mut(instance)
+        {
+            emitter.volume = volume;
+            return;
+        }
+        let _ = self.manager.send(AudioCommand::Instance(instance, command));
+    }
+
+    pub fn listener(&self) -> Listener {
+        self.spatial.lock().unwrap().listener
+    }
+
+    pub fn set_listener(&self, listener: Listener) {
+        self.spatial.lock().unwrap().listener = listener;
+    }
+
+    /// Attenuation and panning positional sounds start with.
+    pub fn spatial_settings(&self) -> SpatialSettings {
+        self.spatial.lock().unwrap().settings
+    }
+
+    pub fn set_spatial_settings(&self, settings: SpatialSettings) {
+        self.spatial.lock().unwrap().settings = settings;
+    }
+
+    /// Changes the attenuation of one positional sound.
+    pub fn set_emitter_settings(&self, instance: SoundInstanceHandle, settings: SpatialSettings) {
+        if let Some(emitter) = self.spatial.lock().unwrap().emitter_mut(instance) {
+            emitter.settings = settings;
+        }
+    }
+
+    /// Moves positional sounds relative to the listener, called once per frame. `resolve`
+    /// returns the position of an entity.
+    pub fn update_spatial(&self, listener: Vec2, resolve: impl Fn(u64) -> Option<Vec2>) {
+        let mut spatial = self.spatial.lock().unwrap();
+        {
+            let statuses = self.statuses.lock().unwrap();
+            spatial.retain(|instance| statuses.instances.contains_key(instance));
+        }
+        for (instance, mix) in spatial.update(listener, resolve) {
+            let fade = Self::SPATIAL_FADE;
+            let commands = [
+                InstanceCommand::SetVolume {
+                    volume: mix.volume,
+                    fade,
+                },
+                InstanceCommand::SetPanning {
+                    panning: mix.panning,
+                    fade,
+                },
+            ];
+            for command in commands {
+                let _ = self.manager.send(AudioCommand::Instance(instance, command));
+            }
+        }
+    }
+
+    /// Streams `path` on repeat, crossfading from the music playing over `crossfade` seconds.
+    pub fn play_music(&self, path: String, crossfade: f32) {
+        self.set_playlist(vec![path], true, crossfade);
+    }
+
+    /// Streams the tracks one after the other with `crossfade` seconds of overlap.
+    pub fn set_playlist(&self, tracks: Vec<String>, looping: bool, crossfade: f32) {
+        self.music(MusicCommand::SetPlaylist {
+            tracks,
+            looping,
+            crossfade,
+        });
+    }
+
+    pub fn skip_track(&self) {
+        self.music(MusicCommand::Skip);
+    }
+
+    pub fn stop_music(&self, fade: f32) {
+        self.music(MusicCommand::Stop { fade });
+    }
+
+    pub fn pause_music(&self, fade: f32) {
+        self.music(MusicCommand::Pause { fade });
+    }
+
+    pub fn resume_music(&self, fade: f32) {
+        self.music(MusicCommand::Resume { fade });
+    }
+
+    pub fn current_music(&self) -> Option<String> {
+        self.statuses.lock().unwrap().music.clone()
+    }
+
+    fn music(&self, command: MusicCommand) {
+        let _ = self.manager.send(AudioCommand::Music(command));
+    }
+
+    pub fn mixer_settings(&self) -> MixerSettings {
+        self.mixer.read().unwrap().clone()
+    }
+
+    pub fn bus_names(&self) -> Vec<String> {
+        self.mixer.read().unwrap().buses.keys().cloned().collect()
+    }
+
+    pub fn set_master_volume(&self, volume: f32, fade: f32) {
+        self.mixer.write().unwrap().master_volume = volume.max(0.0);
+        let _ = self.manager.send(AudioCommand::UpdateMixer { fade });
+    }
+
+    /// Changes a bus over `fade` seconds, buses that don't exist yet are created.
+    pub fn update_bus(&self, bus: &str, fade: f32, update: impl FnOnce(&mut BusSettings)) {
+        update(
+            self.mixer
+                .write()
+                .unwrap()
+                .buses
+                .entry(bus.to_string())
+                .or_default(),
+        );
+        let _ = self.manager.send(AudioCommand::UpdateMixer { fade });
+    }
+
+    pub fn mixer_gui(&self, context: &egui::Context) {
+        let mut settings = self.mixer_settings();
+        egui::Window::new("Mixer").show(context, |ui| {
+            ui.add(egui::Slider::new(&mut settings.master_volume, 0.0..=1.0).text("master"));
+            for (name, bus) in settings.buses.iter_mut() {
+                ui.collapsing(name.as_str(), |ui| {
+                    ui.add(egui::Slider::new(&mut bus.volume, 0.0..=1.0).text("volume"));
+                    ui.add(
+                        egui::Slider::new(&mut bus.filter_cutoff, 20.0..=mixer::FILTER_OPEN)
+                            .logarithmic(true)
+                            .text("low pass"),
+                    );
+                    ui.add(egui::Slider::new(&mut bus.reverb_mix, 0.0..=1.0).text("reverb"));
+                    if let Some(ducker) = bus.ducked_by.as_ref() {
+                        ui.add(
+                            egui::Slider::new(&mut bus.duck_volume, 0.0..=1.0)
+                                .text(format!("ducked by {ducker}")),
+                        );
+                    }
+                });
+            }
+        });
+        let mut current = self.mixer.write().unwrap();
+        if *current != settings {
+            *current = settings;
+            let _ = self.manager.send(AudioCommand::UpdateMixer { fade: 0.0 });
+        }
+    }
+
+    pub fn instance_state(&self, instance: SoundInstanceHandle) -> PlaybackState {
+        self.statuses
+            .lock()
+            .unwrap()
+            .instances
+            .get(instance)
+            .map_or(PlaybackState::Stopped, |status| status.state)
+    }
+
+    /// Seconds into a playing sound, `None` once it stopped.
+    pub fn instance_position(&self, instance: SoundInstanceHandle) -> Option<f64> {
+        let statuses = self.statuses.lock().unwrap();
+        Some(statuses.instances.get(instance)?.position)
     }
 
-    pub fn play(&self, sound: SoundHandle) {
-        self.manager.send(AudioCommand::Play(sound)).unwrap();
+    /// Latest sounds that started playing, oldest first.
+    pub fn played(&self) -> Vec<PlayedSound> {
+        self.statuses
+            .lock()
+            .unwrap()
+            .played
+            .iter()
+            .copied()
+            .collect()
     }
 }
 
-pub struct AudioThread {
-    kira_manager: kira::AudioManager,
-    sound_storage: SlotMap<SoundHandle, StaticSoundData>,
+/// Packed sounds come from the archive, so they are decoded from memory.
+fn decode_sound(path: &str) -> anyhow::Result<StaticSoundData> {
+    zurie_assets::read_asset(path)
+        .and_then(|bytes| {
+            StaticSoundData::from_cursor(Cursor::new(bytes)).map_err(anyhow::Error::from)
+        })
+        .with_context(|| format!("Failed to load sound {path}"))
+}
+
+/// Kira sub track of a mixer bus with its effects.
+struct Bus {
+    track: TrackHandle,
+    filter: FilterHandle,
+    reverb: ReverbHandle,
+    /// Volume the track is at, ducking included.
+    volume: f32,
 }
 
-impl AudioThread {
-    pub fn new() -> Self {
-        AudioThread {
-            kira_manager:
-                kira::AudioManager::<DefaultBackend>::new(AudioManagerSettings::default()).unwrap(),
+pub struct AudioThread<B: OutputBackend = DefaultBackend> {
+    kira_manager: kira::AudioManager<B>,
+    sound_storage: SecondaryMap<SoundHandle, StaticSoundData>,
+    instances: SecondaryMap<SoundInstanceHandle, StaticSoundHandle>,
+    /// Bus every instance plays on, used for ducking.
+    instance_buses: SecondaryMap<SoundInstanceHandle, String>,
+    statuses: Arc<Mutex<SoundStatuses>>,
+    mixer: Arc<RwLock<MixerSettings>>,
+    buses: HashMap<String, Bus>,
+    /// Mixer changes are saved once they settle.
+    save_mixer_at: Option<Instant>,
+    music: MusicPlayer,
+    started: Instant,
+    /// Frames rendered by backends without a device.
+    rendered: u64,
+}
+
+impl<B: OutputBackend> AudioThread<B> {
+    /// Most recent sounds kept in the played history.
+    const PLAYED_HISTORY: usize = 256;
+
+    fn new(
+        kira_manager: kira::AudioManager<B>,
+        statuses: Arc<Mutex<SoundStatuses>>,
+        mixer: Arc<RwLock<MixerSettings>>,
+    ) -> Self {
+        let mut audio_thread = AudioThread {
+            kira_manager,
             sound_storage: Default::default(),
-        }
+            instances: Default::default(),
+            instance_buses: Default::default(),
+            statuses,
+            mixer,
+            buses: HashMap::new(),
+            save_mixer_at: None,
+            music: MusicPlayer::default(),
+            started: Instant::now(),
+            rendered: 0,
+        };
+        audio_thread.apply_mixer(0.0);
+        audio_thread
     }
 
+    /// How often the state of playing instances is synced back to the managers.
+    const INSTANCE_POLL: Duration = Duration::from_millis(50);
+    const DUCK_FADE: f32 = 0.3;
+    const SAVE_MIXER_AFTER: Duration = Duration::from_secs(1);
+
     fn run(&mut self, receiver: Receiver<AudioCommand>) {
-        while let Ok(command) = receiver.recv() {
-            info!("command received, {:?}", command);
-            match command {
-                AudioCommand::Play(sound_handle) => self.play(sound_handle),
-                AudioCommand::Load(path, sender) => self.load_sound(path, sender),
-                AudioCommand::Stop => break,
+        let mut last_poll = Instant::now();
+        loop {
+            match receiver.recv_timeout(Self::INSTANCE_POLL) {
+                Ok(AudioCommand::Play {
+                    sound,
+                    instance,
+                    settings,
+                }) => {
+                    trace!("command received, play {:?}", sound);
+                    self.play(sound, instance, settings)
+                }
+                Ok(AudioCommand::Instance(instance, command)) => self.control(instance, command),
+                Ok(AudioCommand::Loaded(sound_handle, sound)) => self.loaded(sound_handle, sound),
+                Ok(AudioCommand::Unload(sound_handle)) => {
+                    self.sound_storage.remove(sound_handle);
+                }
+                Ok(AudioCommand::UpdateMixer { fade }) => {
+                    self.apply_mixer(fade);
+                    self.save_mixer_at = Some(Instant::now() + Self::SAVE_MIXER_AFTER);
+                }
+                Ok(AudioCommand::Music(command)) => self.music(command),
+                Ok(AudioCommand::Stop) | Err(RecvTimeoutError::Disconnected) => break,
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+            B::render(
+                &mut self.kira_manager,
+                self.started.elapsed(),
+                &mut self.rendered,
+            );
+            if last_poll.elapsed() >= Self::INSTANCE_POLL {
+                self.poll_instances();
+                self.poll_music();
+                self.apply_ducking();
+                self.save_mixer();
+                last_poll = Instant::now();
+            }
+        }
+    }
+
+    /// Creates missing buses and moves every bus to its settings.
+    fn apply_mixer(&mut self, fade: f32) {
+        let settings = self.mixer.read().unwrap().clone();
+        let tween = playback::fade(fade);
+        self.kira_manager.main_track().set_volume(
+            playback::amplitude_to_decibels(settings.master_volume),
+            tween,
+        );
+        let active = self.active_buses();
+        let active: Vec<&str> = active.iter().map(String::as_str).collect();
+        for (name, bus_settings) in settings.buses.iter() {
+            if !self.buses.contains_key(name) {
+                match self.add_bus(bus_settings) {
+                    Ok(bus) => {
+                        self.buses.insert(name.clone(), bus);
+                    }
+                    Err(err) => {
+                        log::error!("Failed to create audio bus {name}: {err:#}");
+                        continue;
+                    }
+                }
+            }
+            let bus = self.buses.get_mut(name).unwrap();
+            let volume = settings.bus_volume(name, &active);
+            bus.track
+                .set_volume(playback::amplitude_to_decibels(volume), tween);
+            bus.volume = volume;
+            bus.filter
+                .set_cutoff(bus_settings.filter_cutoff as f64, tween);
+            bus.reverb.set_mix(Mix(bus_settings.reverb_mix), tween);
+        }
+    }
+
+    fn add_bus(&mut self, settings: &BusSettings) -> anyhow::Result<Bus> {
+        let mut builder = TrackBuilder::new();
+        let filter = builder.add_effect(FilterBuilder::new().cutoff(settings.filter_cutoff as f64));
+        let reverb = builder.add_effect(ReverbBuilder::new().mix(Mix(settings.reverb_mix)));
+        Ok(Bus {
+            track: self.kira_manager.add_sub_track(builder)?,
+            filter,
+            reverb,
+            volume: 1.0,
+        })
+    }
+
+    /// Buses with at least one sound playing on them.
+    fn active_buses(&self) -> Vec<String> {
+        let mut active: Vec<String> = Vec::new();
+        for bus in self.instance_buses.values() {
+            if !active.contains(bus) {
+                active.push(bus.clone());
+            }
+        }
+        active
+    }
+
+    /// Lowers buses whose ducking bus is playing and restores them once it stops.
+    fn apply_ducking(&mut self) {
+        let active = self.active_buses();
+        let active: Vec<&str> = active.iter().map(String::as_str).collect();
+        let settings = self.mixer.read().unwrap();
+        for (name, bus) in self.buses.iter_mut() {
+            let volume = settings.bus_volume(name, &active);
+            if volume != bus.volume {
+                bus.volume = volume;
+                bus.track.set_volume(
+                    playback::amplitude_to_decibels(volume),
+                    playback::fade(Self::DUCK_FADE),
+                );
+            }
+        }
+    }
+
+    fn save_mixer(&mut self) {
+        if self.save_mixer_at.is_some_and(|at| Instant::now() >= at) {
+            self.save_mixer_at = None;
+            if let Err(err) = self.mixer.read().unwrap().save(MIXER_SETTINGS_PATH) {
+                log::error!("{err:#}");
             }
         }
     }
 
-    fn load_sound(&mut self, path: String, sender: Sender<SoundHandle>) {
-        self.sound_storage.insert_with_key(|key| {
-            sender.send(key);
-            info!("Loading sound with handle: {:?}", key);
-            StaticSoundData::from_file(path).expect("error loading file")
+    /// Publishes instance states and forgets instances that stopped.
+    fn poll_instances(&mut self) {
+        let mut statuses = self.statuses.lock().unwrap();
+        self.instances.retain(|instance, handle| {
+            let state = handle.state();
+            let finished = state == kira::sound::PlaybackState::Stopped;
+            if finished {
+                statuses.instances.remove(instance);
+                self.instance_buses.remove(instance);
+            } else if let Some(shared) = statuses.instances.get_mut(instance) {
+                shared.state = state.into();
+                shared.position = handle.position();
+            }
+            !finished
         });
     }
 
-    fn play(&mut self, sound: SoundHandle) {
-        if let Some(sound) = self.sound_storage.get(sound) {
-            self.kira_manager.play(sound.clone());
+    fn music(&mut self, command: MusicCommand) {
+        match command {
+            MusicCommand::SetPlaylist {
+                tracks,
+                looping,
+                crossfade,
+            } => {
+                self.music.playlist = Playlist::new(tracks, looping);
+                self.music.crossfade = crossfade;
+                self.next_track();
+            }
+            MusicCommand::Skip => self.next_track(),
+            MusicCommand::Stop { fade } => {
+                self.music.playlist = Playlist::default();
+                if let Some(mut track) = self.music.current.take() {
+                    track.handle.stop(playback::fade(fade));
+                }
+                self.statuses.lock().unwrap().music = None;
+            }
+            MusicCommand::Pause { fade } => {
+                if let Some(track) = self.music.current.as_mut() {
+                    track.handle.pause(playback::fade(fade));
+                }
+            }
+            MusicCommand::Resume { fade } => {
+                if let Some(track) = self.music.current.as_mut() {
+                    track.handle.resume(playback::fade(fade));
+                }
+            }
+        }
+    }
+
+    /// Fades the current track out while the next one of the playlist fades in. Tracks that
+    /// fail to open are skipped.
+    fn next_track(&mut self) {
+        let crossfade = self.music.crossfade;
+        if let Some(mut track) = self.music.current.take() {
+            track.handle.stop(playback::fade(crossfade));
+        }
+        for _ in 0..self.music.playlist.len() {
+            let Some(path) = self.music.playlist.advance() else {
+                break;
+            };
+            match self.stream_music(path, crossfade) {
+                Ok(track) => {
+                    self.music.current = Some(track);
+                    break;
+                }
+                Err(err) => log::error!("{err:#}"),
+            }
+        }
+        self.statuses.lock().unwrap().music =
+            self.music.current.as_ref().map(|track| track.path.clone());
+    }
+
+    fn stream_music(&mut self, path: String, fade_in: f32) -> anyhow::Result<MusicTrack> {
+        info!("Streaming music {path}");
+        // Packed music streams from the archive bytes, still encoded
+        let data = if zurie_assets::archive::is_packed(&path) {
+            StreamingSoundData::from_cursor(Cursor::new(zurie_assets::read_asset(&path)?))
+        } else {
+            StreamingSoundData::from_file(&path)
+        }
+        .with_context(|| format!("Failed to open music {path}"))?;
+        let duration = data.duration().as_secs_f64();
+        let data = data.volume(if fade_in > 0.0 {
+            kira::Decibels::SILENCE
         } else {
-            warn!("Could't play sound from handle")
+            kira::Decibels::IDENTITY
+        });
+        let mut handle = match self.buses.get_mut(MUSIC_BUS) {
+            Some(bus) => bus.track.play(data)?,
+            None => self.kira_manager.play(data)?,
+        };
+        if fade_in > 0.0 {
+            handle.set_volume(kira::Decibels::IDENTITY, playback::fade(fade_in));
+        }
+        Ok(MusicTrack {
+            path,
+            handle,
+            duration,
+        })
+    }
+
+    /// Starts the next track once the current one gets close to its end.
+    fn poll_music(&mut self) {
+        let Some(track) = self.music.current.as_ref() else {
+            return;
+        };
+        let state = track.handle.state();
+        let ended = state == kira::sound::PlaybackState::Stopped;
+        // The last track of a playlist plays until its end
+        let crossfade = state == kira::sound::PlaybackState::Playing
+            && !self.music.playlist.is_finished()
+            && music::crossfade_due(
+                track.handle.position(),
+                track.duration,
+                self.music.crossfade,
+            );
+        if ended || crossfade {
+            self.next_track();
+        }
+    }
+
+    fn loaded(&mut self, handle: SoundHandle, sound: anyhow::Result<StaticSoundData>) {
+        let mut statuses = self.statuses.lock().unwrap();
+        // Unloaded while it was decoding
+        if !statuses.assets.contains(handle) {
+            return;
+        }
+        let status = match sound {
+            Ok(sound) => {
+                info!("Sound loaded with handle: {:?}", handle);
+                self.sound_storage.insert(handle, sound);
+                LoadStatus::Ready
+            }
+            Err(err) if self.sound_storage.contains_key(handle) => {
+                log::error!("Failed to reload sound, keeping the old one: {err:#}");
+                LoadStatus::Ready
+            }
+            Err(err) => {
+                log::error!("{err:#}");
+                LoadStatus::Failed(format!("{err:#}"))
+            }
+        };
+        statuses.statuses.insert(handle, status);
+        statuses.completed.push(handle);
+    }
+
+    fn play(&mut self, sound: SoundHandle, instance: SoundInstanceHandle, settings: PlaySettings) {
+        let sound_handle = sound;
+        let Some(sound) = self.sound_storage.get(sound) else {
+            warn!("Could't play sound from handle");
+            self.statuses.lock().unwrap().instances.remove(instance);
+            return;
+        };
+        let volume = playback::amplitude_to_decibels(settings.volume);
+        let sound = sound
+            .clone()
+            .volume(if settings.fade_in > 0.0 {
+                kira::Decibels::SILENCE
+            } else {
+                volume
+            })
+            .playback_rate(playback::pitch_to_rate(settings.pitch))
+            .panning(playback::panning(settings.panning))
+            .loop_region(settings.loop_region.map(kira::sound::Region::from));
+        let bus = settings
+            .bus
+            .as_ref()
+            .and_then(|name| self.buses.get_mut(name));
+        if settings.bus.is_some() && bus.is_none() {
+            warn!("Unknown audio bus {:?}, playing on master", settings.bus);
         }
+        let played = match bus {
+            Some(bus) => bus.track.play(sound),
+            None => self.kira_manager.play(sound),
+        };
+        match played {
+            Ok(mut handle) => {
+                if settings.fade_in > 0.0 {
+                    handle.set_volume(volume, playback::fade(settings.fade_in));
+                }
+                self.instances.insert(instance, handle);
+                if let Some(bus) = settings.bus.filter(|bus| self.buses.contains_key(bus)) {
+                    self.instance_buses.insert(instance, bus);
+                }
+                let mut statuses = self.statuses.lock().unwrap();
+                if statuses.played.len() == Self::PLAYED_HISTORY {
+                    statuses.played.pop_front();
+                }
+                statuses.played.push_back(PlayedSound {
+                    sound: sound_handle,
+                    instance,
+                    at: self.started.elapsed(),
+                });
+            }
+            Err(err) => {
+                log::error!("Failed to play sound: {err}");
+                self.statuses.lock().unwrap().instances.remove(instance);
+            }
+        }
+    }
+
+    fn control(&mut self, instance: SoundInstanceHandle, command: InstanceCommand) {
+        let Some(handle) = self.instances.get_mut(instance) else {
+            return;
+        };
+        match command {
+            InstanceCommand::Stop { fade } => handle.stop(playback::fade(fade)),
+            InstanceCommand::Pause { fade } => handle.pause(playback::fade(fade)),
+            InstanceCommand::Resume { fade } => handle.resume(playback::fade(fade)),
+            InstanceCommand::SetVolume { volume, fade } => handle.set_volume(
+                playback::amplitude_to_decibels(volume),
+                playback::fade(fade),
+            ),
+            InstanceCommand::SetPitch { pitch, fade } => {
+                handle.set_playback_rate(playback::pitch_to_rate(pitch), playback::fade(fade))
+            }
+            InstanceCommand::SetPanning { panning, fade } => {
+                handle.set_panning(playback::panning(panning), playback::fade(fade))
+            }
+            InstanceCommand::SetLoopRegion(region) => {
+                handle.set_loop_region(region.map(kira::sound::Region::from))
+            }
+            InstanceCommand::Seek(position) => handle.seek_to(position),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    /// Mono 16 bit wav of silence.
+    fn silent_wav(name: &str, seconds: f32) -> String {
+        let sample_rate: u32 = 8_000;
+        let data_len = (sample_rate as f32 * seconds) as u32 * 2;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_len.to_le_bytes());
+        bytes.resize(bytes.len() + data_len as usize, 0);
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, bytes).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    fn wait_until(condition: impl Fn() -> bool) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(5) {
+            if condition() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    #[test]
+    fn test_null_backend_tracks_instances() {
+        let audio = AudioManager::with_backend(AssetLoader::default(), AudioBackend::Null);
+        assert!(!audio.has_device());
+        let sound = audio
+            .load_sound_blocking(silent_wav("zurie_null_backend.wav", 0.3))
+            .unwrap();
+        let instance = audio.play(sound);
+        let started = || audio.played().iter().any(|played| played.sound == sound);
+        assert!(wait_until(started));
+        assert_eq!(audio.played()[0].instance, instance);
+        assert!(wait_until(|| audio.instance_position(instance) > Some(0.0)));
+        assert!(wait_until(
+            || audio.instance_state(instance) == PlaybackState::Stopped
+        ));
+        assert_eq!(audio.instance_position(instance), None);
+    }
+
+    #[test]
+    fn test_load_errors_are_returned() {
+        let audio = AudioManager::with_backend(AssetLoader::default(), AudioBackend::Null);
+        assert!(audio.load_sound_blocking("missing.wav".into()).is_err());
+        let not_audio = std::env::temp_dir().join("zurie_not_audio.wav");
+        std::fs::write(&not_audio, b"not a wav").unwrap();
+        let err = audio
+            .load_sound_blocking(not_audio.to_str().unwrap().to_string())
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("zurie_not_audio.wav"));
     }
 }
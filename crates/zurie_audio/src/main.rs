@@ -1,9 +1,10 @@
 use std::{error::Error, io::stdin, path::Path};
 use zurie_audio::AudioManager;
+use zurie_shared::loader::AssetLoader;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut manager = AudioManager::new();
-    let sound = manager.load_sound("static/sound.wav".into());
+    let manager = AudioManager::new(AssetLoader::default());
+    let sound = manager.load_sound_blocking("static/sound.wav".into())?;
     loop {
         wait_for_enter_press()?;
         manager.play(sound);
@@ -0,0 +1,136 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+pub const MIXER_SETTINGS_PATH: &str = "mixer.ron";
+/// Cutoff of a filter that lets everything through.
+pub const FILTER_OPEN: f32 = 20_000.0;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct BusSettings {
+    /// Linear amplitude.
+    pub volume: f32,
+    /// Low pass cutoff in Hz.
+    pub filter_cutoff: f32,
+    /// 0 is dry, 1 only reverb.
+    pub reverb_mix: f32,
+    /// Bus that lowers this one while something plays on it, like dialogue over music.
+    pub ducked_by: Option<String>,
+    /// Volume multiplier while ducked.
+    pub duck_volume: f32,
+}
+
+impl Default for BusSettings {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            filter_cutoff: FILTER_OPEN,
+            reverb_mix: 0.0,
+            ducked_by: None,
+            duck_volume: 0.3,
+        }
+    }
+}
+
+/// Volumes and effects of every bus, saved between runs.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct MixerSettings {
+    pub master_volume: f32,
+    pub buses: BTreeMap<String, BusSettings>,
+}
+
+impl Default for MixerSettings {
+    fn default() -> Self {
+        let mut buses = BTreeMap::new();
+        buses.insert(
+            "music".to_string(),
+            BusSettings {
+                ducked_by: Some("dialogue".into()),
+                ..Default::default()
+            },
+        );
+        for bus in ["sfx", "ui", "dialogue"] {
+            buses.insert(bus.to_string(), BusSettings::default());
+        }
+        Self {
+            master_volume: 1.0,
+            buses,
+        }
+    }
+}
+
+impl MixerSettings {
+    /// Saved settings, the defaults if there are none yet.
+    pub fn load_or_default(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let Ok(text) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        ron::from_str(&text).unwrap_or_else(|err| {
+            log::warn!("Ignoring invalid mixer settings in {path:?}: {err}");
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let text = ron::ser::to_string_pretty(self, Default::default())?;
+        std::fs::write(path, text).with_context(|| format!("Failed to save {path:?}"))
+    }
+
+    /// Volume `bus` plays at given which buses have something playing.
+    pub fn bus_volume(&self, bus: &str, active_buses: &[&str]) -> f32 {
+        let Some(settings) = self.buses.get(bus) else {
+            return 1.0;
+        };
+        let ducked = settings
+            .ducked_by
+            .as_deref()
+            .is_some_and(|ducker| active_buses.contains(&ducker));
+        if ducked {
+            settings.volume * settings.duck_volume
+        } else {
+            settings.volume
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn test_dialogue_ducks_music() {
+        let mut settings = MixerSettings::default();
+        settings.buses.get_mut("music").unwrap().volume = 0.5;
+        assert_eq!(settings.bus_volume("music", &["sfx"]), 0.5);
+        assert_eq!(settings.bus_volume("music", &["dialogue"]), 0.15);
+        assert_eq!(settings.bus_volume("dialogue", &["dialogue"]), 1.0);
+        assert_eq!(settings.bus_volume("missing", &[]), 1.0);
+    }
+
+    #[test]
+    fn test_settings_round_trip() {
+        let path = std::env::temp_dir().join("zurie_mixer_test.ron");
+        let mut settings = MixerSettings {
+            master_volume: 0.25,
+            ..Default::default()
+        };
+        settings.buses.get_mut("sfx").unwrap().reverb_mix = 0.4;
+        settings.save(&path).unwrap();
+        assert_eq!(MixerSettings::load_or_default(&path), settings);
+
+        std::fs::write(&path, "(master_volume: 0.5)").unwrap();
+        let partial = MixerSettings::load_or_default(&path);
+        assert_eq!(partial.master_volume, 0.5);
+        assert_eq!(partial.buses, MixerSettings::default().buses);
+        std::fs::write(&path, "not ron").unwrap();
+        assert_eq!(
+            MixerSettings::load_or_default(&path),
+            MixerSettings::default()
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use kira::sound::FromFileError;
+use kira::sound::streaming::StreamingSoundHandle;
+
+/// Bus music plays on when the mixer has it.
+pub const MUSIC_BUS: &str = "music";
+
+/// Music is streamed from disk while it plays instead of being decoded up front.
+pub enum MusicCommand {
+    /// Replaces the playlist, its first track crossfades with the one playing.
+    SetPlaylist {
+        tracks: Vec<String>,
+        looping: bool,
+        crossfade: f32,
+    },
+    /// Crossfades to the next track of the playlist.
+    Skip,
+    Stop {
+        fade: f32,
+    },
+    Pause {
+        fade: f32,
+    },
+    Resume {
+        fade: f32,
+    },
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Playlist {
+    tracks: Vec<String>,
+    next: usize,
+    looping: bool,
+}
+
+impl Playlist {
+    pub fn new(tracks: Vec<String>, looping: bool) -> Self {
+        Self {
+            tracks,
+            next: 0,
+            looping,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    /// No track left to play after the current one.
+    pub fn is_finished(&self) -> bool {
+        self.tracks.is_empty() || (!self.looping && self.next >= self.tracks.len())
+    }
+
+    /// Track to play next, `None` at the end of a playlist that doesn't loop.
+    pub fn advance(&mut self) -> Option<String> {
+        if self.is_finished() {
+            return None;
+        }
+        if self.next >= self.tracks.len() {
+            self.next = 0;
+        }
+        self.next += 1;
+        Some(self.tracks[self.next - 1].clone())
+    }
+}
+
+/// Whether the next track should start fading in, `position` and `duration` are in seconds.
+/// Crossfades take at most half of the track.
+pub fn crossfade_due(position: f64, duration: f64, crossfade: f32) -> bool {
+    position >= duration - (crossfade.max(0.0) as f64).min(duration / 2.0)
+}
+
+pub(crate) struct MusicTrack {
+    pub path: String,
+    pub handle: StreamingSoundHandle<FromFileError>,
+    /// Length in seconds.
+    pub duration: f64,
+}
+
+#[derive(Default)]
+pub(crate) struct MusicPlayer {
+    pub playlist: Playlist,
+    pub crossfade: f32,
+    pub current: Option<MusicTrack>,
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    fn tracks() -> Vec<String> {
+        vec!["a.ogg".into(), "b.ogg".into()]
+    }
+
+    #[test]
+    fn test_playlist_order() {
+        let mut once = Playlist::new(tracks(), false);
+        assert_eq!(once.advance().as_deref(), Some("a.ogg"));
+        assert!(!once.is_finished());
+        assert_eq!(once.advance().as_deref(), Some("b.ogg"));
+        assert!(once.is_finished());
+        assert_eq!(once.advance(), None);
+
+        let mut looping = Playlist::new(tracks(), true);
+        let order: Vec<_> = (0..5).filter_map(|_| looping.advance()).collect();
+        assert_eq!(order, ["a.ogg", "b.ogg", "a.ogg", "b.ogg", "a.ogg"]);
+        assert_eq!(Playlist::new(Vec::new(), true).advance(), None);
+    }
+
+    #[test]
+    fn test_crossfade_timing() {
+        assert!(!crossfade_due(97.0, 100.0, 2.0));
+        assert!(crossfade_due(98.0, 100.0, 2.0));
+        assert!(crossfade_due(100.0, 100.0, 0.0));
+        assert!(!crossfade_due(99.9, 100.0, 0.0));
+        // A crossfade longer than the track
+        assert!(!crossfade_due(4.0, 10.0, 30.0));
+        assert!(crossfade_due(5.0, 10.0, 30.0));
+    }
+}
@@ -0,0 +1,165 @@
+use kira::sound::{EndPosition, PlaybackPosition, PlaybackState as KiraPlaybackState, Region};
+use kira::{Decibels, Panning, PlaybackRate, Tween};
+use std::time::Duration;
+
+/// Part of a sound that repeats, in seconds. No end loops until the end of the sound.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoopRegion {
+    pub start: f64,
+    pub end: Option<f64>,
+}
+
+impl From<LoopRegion> for Region {
+    fn from(region: LoopRegion) -> Self {
+        Region {
+            start: PlaybackPosition::Seconds(region.start.max(0.0)),
+            end: match region.end {
+                Some(end) => EndPosition::Custom(PlaybackPosition::Seconds(end)),
+                None => EndPosition::EndOfAudio,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaySettings {
+    /// Linear amplitude, 1 plays the sound as is.
+    pub volume: f32,
+    /// Playback rate, 2 is an octave up and twice as fast.
+    pub pitch: f32,
+    /// -1 is hard left, 1 hard right.
+    pub panning: f32,
+    pub loop_region: Option<LoopRegion>,
+    /// Seconds to fade in from silence.
+    pub fade_in: f32,
+    /// Mixer bus to play on, the master output if `None`.
+    pub bus: Option<String>,
+}
+
+impl Default for PlaySettings {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            pitch: 1.0,
+            panning: 0.0,
+            loop_region: None,
+            fade_in: 0.0,
+            bus: None,
+        }
+    }
+}
+
+/// Changes to a playing sound, fades are in seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InstanceCommand {
+    Stop {
+        fade: f32,
+    },
+    Pause {
+        fade: f32,
+    },
+    Resume {
+        fade: f32,
+    },
+    SetVolume {
+        volume: f32,
+        fade: f32,
+    },
+    SetPitch {
+        pitch: f32,
+        fade: f32,
+    },
+    SetPanning {
+        panning: f32,
+        fade: f32,
+    },
+    SetLoopRegion(Option<LoopRegion>),
+    /// Jumps to a position in seconds.
+    Seek(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackState {
+    Playing,
+    Paused,
+    /// Finished or stopped, also returned for unknown instances.
+    Stopped,
+}
+
+impl From<KiraPlaybackState> for PlaybackState {
+    fn from(state: KiraPlaybackState) -> Self {
+        match state {
+            KiraPlaybackState::Playing
+            | KiraPlaybackState::Resuming
+            | KiraPlaybackState::WaitingToResume => PlaybackState::Playing,
+            KiraPlaybackState::Pausing | KiraPlaybackState::Paused => PlaybackState::Paused,
+            KiraPlaybackState::Stopping | KiraPlaybackState::Stopped => PlaybackState::Stopped,
+        }
+    }
+}
+
+/// Kira works in decibels, mods in linear amplitude.
+pub fn amplitude_to_decibels(amplitude: f32) -> Decibels {
+    if amplitude <= 0.0 {
+        Decibels::SILENCE
+    } else {
+        Decibels((20.0 * amplitude.log10()).max(Decibels::SILENCE.0))
+    }
+}
+
+pub fn pitch_to_rate(pitch: f32) -> PlaybackRate {
+    PlaybackRate(pitch.max(0.0) as f64)
+}
+
+pub fn panning(panning: f32) -> Panning {
+    Panning(panning.clamp(-1.0, 1.0))
+}
+
+/// Tween over `seconds`. NaN, negative, infinite and too long fades are instant, guests pass
+/// them straight to the audio thread.
+pub fn fade(seconds: f32) -> Tween {
+    let duration = match Duration::try_from_secs_f32(seconds) {
+        Ok(duration) if !seconds.is_nan() => duration,
+        _ => Duration::ZERO,
+    };
+    Tween {
+        duration,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn test_amplitude_to_decibels() {
+        assert_eq!(amplitude_to_decibels(1.0), Decibels(0.0));
+        assert!((amplitude_to_decibels(0.5).0 + 6.0206).abs() < 0.001);
+        assert!((amplitude_to_decibels(2.0).0 - 6.0206).abs() < 0.001);
+        assert_eq!(amplitude_to_decibels(0.0), Decibels::SILENCE);
+        assert_eq!(amplitude_to_decibels(-1.0), Decibels::SILENCE);
+        assert_eq!(amplitude_to_decibels(1e-12), Decibels::SILENCE);
+    }
+
+    #[test]
+    fn test_fade() {
+        assert_eq!(fade(1.5).duration, Duration::from_millis(1500));
+        assert_eq!(fade(0.0).duration, Duration::ZERO);
+        assert_eq!(fade(-1.0).duration, Duration::ZERO);
+        assert_eq!(fade(f32::NAN).duration, Duration::ZERO);
+        assert_eq!(fade(f32::INFINITY).duration, Duration::ZERO);
+        assert_eq!(fade(f32::MAX).duration, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_loop_region() {
+        let region: Region = LoopRegion {
+            start: 1.5,
+            end: None,
+        }
+        .into();
+        assert_eq!(region.start, PlaybackPosition::Seconds(1.5));
+        assert_eq!(region.end, EndPosition::EndOfAudio);
+    }
+}
@@ -0,0 +1,282 @@
+use slotmap::SecondaryMap;
+use zurie_types::SoundInstanceHandle;
+use zurie_types::glam::Vec2;
+
+/// How the volume falls off between the min and max distance of [`SpatialSettings`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttenuationCurve {
+    /// Silent at the max distance.
+    Linear,
+    /// `min / (min + rolloff * (distance - min))`, how point sources sound.
+    Inverse,
+    /// `(distance / min) ^ -rolloff`.
+    Exponential,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpatialSettings {
+    /// Sounds closer than this play at full volume.
+    pub min_distance: f32,
+    /// The volume stops changing past this distance.
+    pub max_distance: f32,
+    pub curve: AttenuationCurve,
+    /// Steepness of the inverse and exponential curves.
+    pub rolloff: f32,
+    /// Horizontal distance at which a sound is panned fully to one side.
+    pub pan_width: f32,
+}
+
+impl Default for SpatialSettings {
+    fn default() -> Self {
+        Self {
+            min_distance: 2.0,
+            max_distance: 20.0,
+            curve: AttenuationCurve::Inverse,
+            rolloff: 1.0,
+            pan_width: 10.0,
+        }
+    }
+}
+
+impl SpatialSettings {
+    /// Volume multiplier at `distance` from the listener.
+    pub fn attenuation(&self, distance: f32) -> f32 {
+        let min = self.min_distance.max(f32::EPSILON);
+        let max = self.max_distance.max(min);
+        let distance = distance.clamp(min, max);
+        let gain = match self.curve {
+            AttenuationCurve::Linear if max == min => 1.0,
+            AttenuationCurve::Linear => 1.0 - (distance - min) / (max - min),
+            AttenuationCurve::Inverse => min / (min + self.rolloff.max(0.0) * (distance - min)),
+            AttenuationCurve::Exponential => (distance / min).powf(-self.rolloff.max(0.0)),
+        };
+        gain.clamp(0.0, 1.0)
+    }
+
+    /// Stereo panning of a sound `offset` away from the listener.
+    pub fn panning(&self, offset: Vec2) -> f32 {
+        if self.pan_width <= 0.0 {
+            return 0.0;
+        }
+        (offset.x / self.pan_width).clamp(-1.0, 1.0)
+    }
+
+    pub fn mix(&self, listener: Vec2, emitter: Vec2) -> SpatialMix {
+        let offset = emitter - listener;
+        SpatialMix {
+            volume: self.attenuation(offset.length()),
+            panning: self.panning(offset),
+        }
+    }
+}
+
+/// Volume multiplier and panning of an emitter for the current listener position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpatialMix {
+    pub volume: f32,
+    pub panning: f32,
+}
+
+/// What the sounds are heard from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Listener {
+    #[default]
+    Camera,
+    /// Raw entity id, falls back to the camera while the entity has no position.
+    Entity(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmitterTarget {
+    /// Raw entity id, the sound follows the entity's position component.
+    Entity(u64),
+    Position(Vec2),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Emitter {
+    pub target: EmitterTarget,
+    pub settings: SpatialSettings,
+    /// Volume of the sound before attenuation.
+    pub volume: f32,
+    /// Where the sound was last heard from, kept when its entity is despawned.
+    pub position: Option<Vec2>,
+    mix: Option<SpatialMix>,
+}
+
+impl Emitter {
+    pub fn new(target: EmitterTarget, settings: SpatialSettings, volume: f32) -> Self {
+        let position = match target {
+            EmitterTarget::Position(position) => Some(position),
+            EmitterTarget::Entity(_) => None,
+        };
+        Self {
+            target,
+            settings,
+            volume,
+            position,
+            mix: None,
+        }
+    }
+
+    /// Moves the emitter and returns its new mix when it changed.
+    fn update(&mut self, listener: Vec2, position: Option<Vec2>) -> Option<SpatialMix> {
+        self.position = position.or(self.position);
+        // Silent until its entity has been seen
+        let mix = match self.position {
+            Some(position) => self.settings.mix(listener, position),
+            None => SpatialMix {
+                volume: 0.0,
+                panning: 0.0,
+            },
+        };
+        let mix = SpatialMix {
+            volume: mix.volume * self.volume,
+            ..mix
+        };
+        if self.mix == Some(mix) {
+            return None;
+        }
+        self.mix = Some(mix);
+        Some(mix)
+    }
+}
+
+/// Emitters of the playing positional sounds and the listener they are heard from.
+#[derive(Default)]
+pub struct SpatialAudio {
+    pub listener: Listener,
+    /// Settings new emitters start with.
+    pub settings: SpatialSettings,
+    listener_position: Vec2,
+    emitters: SecondaryMap<SoundInstanceHandle, Emitter>,
+}
+
+impl SpatialAudio {
+    /// Starts tracking an instance, returns the mix it should start playing with.
+    pub fn attach(&mut self, instance: SoundInstanceHandle, mut emitter: Emitter) -> SpatialMix {
+        let mix = emitter
+            .update(self.listener_position, None)
+            .unwrap_or(SpatialMix {
+                volume: 0.0,
+                panning: 0.0,
+            });
+        self.emitters.insert(instance, emitter);
+        mix
+    }
+
+    pub fn emitter_mut(&mut self, instance: SoundInstanceHandle) -> Option<&mut Emitter> {
+        self.emitters.get_mut(instance)
+    }
+
+    /// Recomputes every emitter against the listener, `resolve` returns the position of an
+    /// entity. Only emitters whose mix changed are returned.
+    pub fn update(
+        &mut self,
+        listener: Vec2,
+        resolve: impl Fn(u64) -> Option<Vec2>,
+    ) -> Vec<(SoundInstanceHandle, SpatialMix)> {
+        self.listener_position = listener;
+        self.emitters
+            .iter_mut()
+            .filter_map(|(instance, emitter)| {
+                let position = match emitter.target {
+                    EmitterTarget::Entity(entity) => resolve(entity),
+                    EmitterTarget::Position(position) => Some(position),
+                };
+                Some((instance, emitter.update(listener, position)?))
+            })
+            .collect()
+    }
+
+    /// Forgets emitters whose sound stopped.
+    pub fn retain(&mut self, playing: impl Fn(SoundInstanceHandle) -> bool) {
+        self.emitters.retain(|instance, _| playing(instance));
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use slotmap::SlotMap;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.0001
+    }
+
+    #[test]
+    fn test_attenuation_curves() {
+        let mut settings = SpatialSettings {
+            min_distance: 2.0,
+            max_distance: 10.0,
+            curve: AttenuationCurve::Linear,
+            rolloff: 1.0,
+            pan_width: 10.0,
+        };
+        assert_eq!(settings.attenuation(0.0), 1.0);
+        assert_eq!(settings.attenuation(2.0), 1.0);
+        assert!(close(settings.attenuation(6.0), 0.5));
+        assert_eq!(settings.attenuation(10.0), 0.0);
+        assert_eq!(settings.attenuation(50.0), 0.0);
+
+        settings.curve = AttenuationCurve::Inverse;
+        assert!(close(settings.attenuation(4.0), 0.5));
+        assert!(close(settings.attenuation(10.0), 0.2));
+        assert!(close(settings.attenuation(50.0), 0.2));
+
+        settings.curve = AttenuationCurve::Exponential;
+        settings.rolloff = 2.0;
+        assert!(close(settings.attenuation(4.0), 0.25));
+        assert_eq!(settings.attenuation(1.0), 1.0);
+    }
+
+    #[test]
+    fn test_panning_follows_horizontal_offset() {
+        let settings = SpatialSettings::default();
+        let mix = settings.mix(Vec2::new(1.0, 0.0), Vec2::new(6.0, 3.0));
+        assert!(close(mix.panning, 0.5));
+        assert_eq!(settings.panning(Vec2::new(-30.0, 0.0)), -1.0);
+        assert_eq!(settings.panning(Vec2::new(0.0, 8.0)), 0.0);
+        let centered = SpatialSettings {
+            pan_width: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(centered.panning(Vec2::new(5.0, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn test_emitters_follow_entities() {
+        let mut instances: SlotMap<SoundInstanceHandle, ()> = SlotMap::with_key();
+        let (follow, fixed) = (instances.insert(()), instances.insert(()));
+        let settings = SpatialSettings {
+            curve: AttenuationCurve::Linear,
+            ..Default::default()
+        };
+        let mut spatial = SpatialAudio::default();
+        let start = spatial.attach(
+            follow,
+            Emitter::new(EmitterTarget::Entity(7), settings, 0.5),
+        );
+        assert_eq!(start.volume, 0.0);
+        let start = spatial.attach(
+            fixed,
+            Emitter::new(EmitterTarget::Position(Vec2::ZERO), settings, 1.0),
+        );
+        assert_eq!(start.volume, 1.0);
+
+        let updates = spatial.update(Vec2::ZERO, |_| Some(Vec2::new(11.0, 0.0)));
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].0, follow);
+        assert!(close(updates[0].1.volume, 0.25));
+        assert!(close(updates[0].1.panning, 1.0));
+
+        // Despawned entities keep their last position
+        assert!(spatial.update(Vec2::ZERO, |_| None).is_empty());
+        let updates = spatial.update(Vec2::new(20.0, 0.0), |_| None);
+        assert_eq!(updates.len(), 2);
+
+        spatial.retain(|instance| instance == fixed);
+        assert!(spatial.emitter_mut(follow).is_none());
+        assert!(spatial.emitter_mut(fixed).is_some());
+    }
+}
@@ -1,15 +1,103 @@
-use log::info;
+use anyhow::Context;
+use log::{error, info};
 use winit::event_loop::EventLoop;
 use zurie_core::app::App;
+use zurie_core::headless::{DEFAULT_MOD, HeadlessEngine};
+use zurie_core::replay::{Replay, ReplayPlayer};
+
+const HEADLESS_USAGE: &str = "Usage: zurie_engine --headless [--frames <count>] [--seed <seed>]
+                    [--record <replay>] [--mod <path>]...
+       zurie_engine --replay <replay>
+
+Runs mods without a window, GPU or sound device. Without --frames it runs at real time speed
+until stopped, like a dedicated server. --replay plays a recording headless and checks the world
+matches it on every tick, the windowed engine records with --record <replay>.";
 
 fn main() {
-    env_logger::init();
+    let logger = env_logger::Builder::from_default_env().build();
+    let level = logger.filter();
+    zurie_core::console::init(Box::new(logger), level).expect("Logger was already set");
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(position) = args.iter().position(|arg| arg == "--replay") {
+        let result = match args.get(position + 1) {
+            Some(path) => play_replay(path),
+            None => Err(anyhow::anyhow!("--replay needs a path\n\n{HEADLESS_USAGE}")),
+        };
+        if let Err(err) = result {
+            error!("{err:#}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.iter().any(|arg| arg == "--headless") {
+        if let Err(err) = run_headless(&args) {
+            error!("{err:#}");
+            std::process::exit(1);
+        }
+        return;
+    }
 
     info!("Creating event loop");
 
     let event_loop = EventLoop::new().unwrap();
 
-    let mut app = App::default();
+    let mut app = match args.iter().position(|arg| arg == "--record") {
+        Some(position) => {
+            let path = args.get(position + 1).expect("--record needs a path");
+            App::recording(path.into())
+        }
+        None => App::default(),
+    };
 
     event_loop.run_app(&mut app).unwrap();
 }
+
+fn run_headless(args: &[String]) -> anyhow::Result<()> {
+    let mut frames: Option<u64> = None;
+    let mut seed: u64 = fastrand::u64(..);
+    let mut record: Option<String> = None;
+    let mut mods: Vec<String> = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--headless" => {}
+            "--frames" => {
+                let count = args.next().context("--frames needs a count")?;
+                frames = Some(count.parse().context("--frames needs a number")?);
+            }
+            "--seed" => {
+                let value = args.next().context("--seed needs a number")?;
+                seed = value.parse().context("--seed needs a number")?;
+            }
+            "--record" => record = Some(args.next().context("--record needs a path")?.clone()),
+            "--mod" => mods.push(args.next().context("--mod needs a path")?.clone()),
+            _ => anyhow::bail!("Unknown argument {arg}\n\n{HEADLESS_USAGE}"),
+        }
+    }
+    if mods.is_empty() {
+        mods.push(DEFAULT_MOD.into());
+    }
+    let mut engine = HeadlessEngine::with_options(&mods, seed, HeadlessEngine::SCREEN_SIZE)?;
+    info!("Running {} mods headless with seed {seed}", mods.len());
+    if record.is_some() {
+        engine.start_recording();
+    }
+    match frames {
+        Some(frames) => engine.run_frames(frames)?,
+        None => engine.run_realtime(|_| false)?,
+    }
+    if let (Some(path), Some(replay)) = (record, engine.finish_recording()) {
+        replay.save(path)?;
+    }
+    Ok(())
+}
+
+fn play_replay(path: &str) -> anyhow::Result<()> {
+    let replay = Replay::load(path)?;
+    let ticks = replay.ticks.len();
+    let mut player = ReplayPlayer::new(replay)?;
+    player.run()?;
+    info!("{path} replayed {ticks} ticks without diverging");
+    Ok(())
+}
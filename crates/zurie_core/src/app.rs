@@ -1,4 +1,5 @@
-use log::info;
+use log::{error, info};
+use std::path::PathBuf;
 use std::{sync::Arc, time::Instant};
 use tracy_client::{Client, set_thread_name};
 
@@ -19,6 +20,7 @@ pub struct App {
     state: Option<State>,
     delta_time: Instant,
     tracy_client: Client,
+    record_path: Option<PathBuf>,
 }
 
 impl Default for App {
@@ -30,6 +32,30 @@ impl Default for App {
             window: Default::default(),
             state: None,
             tracy_client,
+            record_path: None,
+        }
+    }
+}
+
+impl App {
+    /// App that records a replay of the session and saves it to `path` on exit.
+    pub fn recording(path: PathBuf) -> Self {
+        Self {
+            record_path: Some(path),
+            ..Default::default()
+        }
+    }
+
+    fn save_recording(&mut self) {
+        let (Some(path), Some(state)) = (self.record_path.as_ref(), self.state.as_mut()) else {
+            return;
+        };
+        let Some(replay) = state.finish_recording() else {
+            return;
+        };
+        match replay.save(path) {
+            Ok(()) => info!("Saved replay of {} ticks to {path:?}", replay.ticks.len()),
+            Err(err) => error!("{err:#}"),
         }
     }
 }
@@ -43,7 +69,10 @@ impl ApplicationHandler for App {
             let window = Arc::new(event_loop.create_window(window_attributes).unwrap());
             self.window = Some(window.clone());
 
-            let state = pollster::block_on(State::new(window.clone(), event_loop));
+            let mut state = pollster::block_on(State::new(window.clone(), event_loop));
+            if self.record_path.is_some() {
+                state.start_recording();
+            }
             self.state = Some(state);
         }
     }
@@ -65,7 +94,10 @@ impl ApplicationHandler for App {
                         ..
                     },
                 ..
-            } => event_loop.exit(),
+            } => {
+                self.save_recording();
+                event_loop.exit()
+            }
             WindowEvent::Resized(size) => self
                 .state
                 .as_mut()
@@ -0,0 +1,433 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use egui::text::{CCursor, CCursorRange};
+use egui::{Color32, ComboBox, Context, Id, Key, Modifiers, RichText, ScrollArea, TextEdit};
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+use zurie_scripting::mod_manager::ModManager;
+use zurie_types::console::{ArgValue, Command, common_prefix, complete, split_words};
+
+/// Lines the console keeps, older ones are dropped.
+const MAX_LINES: usize = 2000;
+/// Most verbose level the console keeps, like `debug`. Without it the console keeps what the
+/// platform logger shows.
+pub const CAPTURE_LEVEL_ENV: &str = "ZURIE_CONSOLE_LEVEL";
+/// Target of the lines the console prints itself.
+const CONSOLE_TARGET: &str = "console";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLine {
+    pub level: Level,
+    /// Module the record came from, mods log with their own module paths.
+    pub target: String,
+    pub text: String,
+}
+
+/// Records logged since the console last took them.
+static CAPTURED: Mutex<VecDeque<LogLine>> = Mutex::new(VecDeque::new());
+
+fn push_line(lines: &mut VecDeque<LogLine>, line: LogLine) {
+    if lines.len() >= MAX_LINES {
+        lines.pop_front();
+    }
+    lines.push_back(line);
+}
+
+/// Keeps records for the console and passes them on to the platform logger.
+pub struct ConsoleLogger {
+    inner: Box<dyn Log>,
+    capture_level: LevelFilter,
+}
+
+impl Log for ConsoleLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.capture_level || self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = LogLine {
+            level: record.level(),
+            target: record.target().to_owned(),
+            text: record.args().to_string(),
+        };
+        push_line(&mut CAPTURED.lock().unwrap(), line);
+        if self.inner.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Installs the logger the console reads from in front of `inner`, the platform logger.
+/// `inner_level` is the most verbose level `inner` shows, the console keeps the same unless
+/// [`CAPTURE_LEVEL_ENV`] is set.
+pub fn init(inner: Box<dyn Log>, inner_level: LevelFilter) -> Result<(), SetLoggerError> {
+    let capture = std::env::var(CAPTURE_LEVEL_ENV).map(|level| level.parse::<LevelFilter>());
+    let capture_level = match &capture {
+        Ok(Ok(level)) => *level,
+        _ => inner_level,
+    };
+    log::set_boxed_logger(Box::new(ConsoleLogger {
+        inner,
+        capture_level,
+    }))?;
+    log::set_max_level(inner_level.max(capture_level));
+    if let Ok(Err(_)) = capture {
+        log::error!("{CAPTURE_LEVEL_ENV} is not a log level, keeping what the logger shows");
+    }
+    Ok(())
+}
+
+/// In-game log viewer and command line, F12 shows and hides it.
+pub struct Console {
+    open: bool,
+    lines: VecDeque<LogLine>,
+    /// Most verbose level shown.
+    level: LevelFilter,
+    /// Only lines whose target contains this are shown.
+    module: String,
+    input: String,
+    /// Entered lines, Up and Down go through them.
+    entered: Vec<String>,
+    browsing: Option<usize>,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self {
+            open: true,
+            lines: VecDeque::new(),
+            level: LevelFilter::Info,
+            module: String::new(),
+            input: String::new(),
+            entered: Vec::new(),
+            browsing: None,
+        }
+    }
+}
+
+impl Console {
+    /// Commands of the console itself, mods can't register these names.
+    fn builtin_commands() -> Vec<Command> {
+        let command = |name: &str, description: &str| Command {
+            name: name.into(),
+            description: description.into(),
+            args: Vec::new(),
+        };
+        vec![
+            command("help", "Lists the commands"),
+            command("clear", "Clears the console"),
+        ]
+    }
+
+    fn print(&mut self, level: Level, text: impl Into<String>) {
+        let line = LogLine {
+            level,
+            target: CONSOLE_TARGET.into(),
+            text: text.into(),
+        };
+        push_line(&mut self.lines, line);
+    }
+
+    /// Lines that pass the level and module filters.
+    pub fn visible(&self) -> impl Iterator<Item = &LogLine> {
+        let module = self.module.to_lowercase();
+        self.lines.iter().filter(move |line| {
+            line.level <= self.level && line.target.to_lowercase().contains(&module)
+        })
+    }
+
+    /// Runs a command line, `run` runs the commands of mods. `commands` are the mods'
+    /// commands.
+    pub fn execute(
+        &mut self,
+        line: &str,
+        commands: &[Command],
+        run: impl FnOnce(&str, &[ArgValue]) -> Option<Result<String, String>>,
+    ) {
+        self.print(Level::Info, format!("> {line}"));
+        if self.entered.last().is_none_or(|last| last != line) {
+            self.entered.push(line.to_owned());
+        }
+        let words = match split_words(line) {
+            Ok(words) => words,
+            Err(err) => return self.print(Level::Error, err),
+        };
+        let Some((name, words)) = words.split_first() else {
+            return;
+        };
+        match name.as_str() {
+            "help" => {
+                for command in Self::builtin_commands().iter().chain(commands) {
+                    let help = format!("{} - {}", command.usage(), command.description);
+                    self.print(Level::Info, help);
+                }
+                return;
+            }
+            "clear" => {
+                self.lines.clear();
+                return;
+            }
+            _ => {}
+        }
+        let Some(command) = commands.iter().find(|command| command.name == *name) else {
+            let text = format!("Unknown command {name}, help lists the commands");
+            return self.print(Level::Error, text);
+        };
+        let result = command
+            .parse(words)
+            .and_then(|args| run(name, &args).unwrap_or_else(|| Err(format!("{name} is gone"))));
+        match result {
+            Ok(text) if text.is_empty() => {}
+            Ok(text) => self.print(Level::Info, text),
+            Err(err) => self.print(Level::Error, err),
+        }
+    }
+
+    /// Tab completion of the input, a single match is filled in and more are listed.
+    fn complete(&mut self, commands: &[Command]) {
+        let commands: Vec<Command> = Self::builtin_commands()
+            .into_iter()
+            .chain(commands.iter().cloned())
+            .collect();
+        let completions = complete(&commands, &self.input);
+        match completions.as_slice() {
+            [] => {}
+            [completion] => self.input = format!("{completion} "),
+            _ => {
+                let words: Vec<&str> = completions
+                    .iter()
+                    .filter_map(|completion| completion.rsplit(' ').next())
+                    .collect();
+                self.print(Level::Info, words.join("  "));
+                self.input = common_prefix(&completions);
+            }
+        }
+    }
+
+    /// Steps through the entered lines, `back` towards older ones.
+    fn browse(&mut self, back: bool) {
+        let index = match (self.browsing, back) {
+            (None, true) => self.entered.len().checked_sub(1),
+            (None, false) => None,
+            (Some(index), true) => Some(index.saturating_sub(1)),
+            (Some(index), false) => Some(index + 1).filter(|index| *index < self.entered.len()),
+        };
+        self.browsing = index;
+        self.input = index.map_or(String::new(), |index| self.entered[index].clone());
+    }
+
+    pub fn show(&mut self, context: &Context, mod_manager: &ModManager) {
+        self.lines.extend(CAPTURED.lock().unwrap().drain(..));
+        while self.lines.len() > MAX_LINES {
+            self.lines.pop_front();
+        }
+        if context.input_mut(|input| input.consume_key(Modifiers::NONE, Key::F12)) {
+            self.open = !self.open;
+        }
+        let mut open = self.open;
+        egui::Window::new("Console")
+            .open(&mut open)
+            .default_width(500.0)
+            .show(context, |ui| {
+                ui.horizontal(|ui| {
+                    ComboBox::from_id_salt("console level")
+                        .selected_text(self.level.to_string())
+                        .show_ui(ui, |ui| {
+                            for level in LevelFilter::iter().skip(1) {
+                                ui.selectable_value(&mut self.level, level, level.to_string());
+                            }
+                        });
+                    ui.add(TextEdit::singleline(&mut self.module).hint_text("Module"));
+                    if ui.button("Clear").clicked() {
+                        self.lines.clear();
+                    }
+                });
+                let lines: Vec<&LogLine> = self.visible().collect();
+                let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+                ScrollArea::vertical()
+                    .max_height(300.0)
+                    .stick_to_bottom(true)
+                    .auto_shrink([false, true])
+                    .show_rows(ui, row_height, lines.len(), |ui, rows| {
+                        for line in lines[rows].iter() {
+                            ui.label(line_text(line));
+                        }
+                    });
+                self.input_line(ui, mod_manager);
+            });
+        self.open = open;
+    }
+
+    fn input_line(&mut self, ui: &mut egui::Ui, mod_manager: &ModManager) {
+        let id = Id::new("console input");
+        if ui.memory(|memory| memory.has_focus(id)) {
+            let key = |key| ui.input_mut(|input| input.consume_key(Modifiers::NONE, key));
+            let mut moved = true;
+            if key(Key::Tab) {
+                self.complete(&mod_manager.console_commands());
+            } else if key(Key::ArrowUp) {
+                self.browse(true);
+            } else if key(Key::ArrowDown) {
+                self.browse(false);
+            } else {
+                moved = false;
+            }
+            // Changed text would keep the cursor where it was
+            if moved {
+                let mut state = egui::TextEdit::load_state(ui.ctx(), id).unwrap_or_default();
+                let end = CCursor::new(self.input.chars().count());
+                state.cursor.set_char_range(Some(CCursorRange::one(end)));
+                state.store(ui.ctx(), id);
+            }
+        }
+        let response = ui.add(
+            TextEdit::singleline(&mut self.input)
+                .id(id)
+                .lock_focus(true)
+                .desired_width(f32::INFINITY)
+                .hint_text("Command, Tab completes")
+                .font(egui::TextStyle::Monospace),
+        );
+        if response.lost_focus() && ui.input(|input| input.key_pressed(Key::Enter)) {
+            let line = std::mem::take(&mut self.input);
+            self.browsing = None;
+            if !line.trim().is_empty() {
+                let commands = mod_manager.console_commands();
+                self.execute(&line, &commands, |name, args| {
+                    mod_manager.run_console_command(name, args)
+                });
+            }
+            response.request_focus();
+        }
+    }
+}
+
+fn line_text(line: &LogLine) -> RichText {
+    let color = match line.level {
+        Level::Error => Color32::LIGHT_RED,
+        Level::Warn => Color32::YELLOW,
+        Level::Info => Color32::LIGHT_GRAY,
+        Level::Debug | Level::Trace => Color32::GRAY,
+    };
+    RichText::new(format!("[{} {}] {}", line.level, line.target, line.text))
+        .monospace()
+        .color(color)
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use zurie_types::console::{ArgKind, CommandArg};
+
+    fn commands() -> Vec<Command> {
+        vec![Command {
+            name: "spawn".into(),
+            description: "Spawns prefabs".into(),
+            args: vec![
+                CommandArg {
+                    name: "prefab".into(),
+                    kind: ArgKind::Text,
+                    optional: false,
+                    choices: vec!["enemy".into(), "elite_enemy".into()],
+                },
+                CommandArg {
+                    name: "count".into(),
+                    kind: ArgKind::Integer,
+                    optional: true,
+                    choices: Vec::new(),
+                },
+            ],
+        }]
+    }
+
+    fn texts(console: &Console) -> Vec<&str> {
+        console.visible().map(|line| line.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_execute() {
+        let mut console = Console::default();
+        let mut ran = None;
+        console.execute("spawn enemy 3", &commands(), |name, args| {
+            ran = Some((name.to_owned(), args.to_vec()));
+            Some(Ok("Spawned 3".into()))
+        });
+        let args = vec![ArgValue::Text("enemy".into()), ArgValue::Integer(3)];
+        assert_eq!(ran, Some(("spawn".into(), args)));
+        assert_eq!(texts(&console), vec!["> spawn enemy 3", "Spawned 3"]);
+
+        console.execute("spawn", &commands(), |_, _| panic!("Arguments are missing"));
+        console.execute("fly", &commands(), |_, _| panic!("Unknown command"));
+        let errors = console
+            .lines
+            .iter()
+            .filter(|line| line.level == Level::Error);
+        assert_eq!(errors.count(), 2);
+
+        console.execute("help", &commands(), |_, _| None);
+        assert!(texts(&console).contains(&"spawn <prefab> [count] - Spawns prefabs"));
+        console.execute("clear", &commands(), |_, _| None);
+        assert!(texts(&console).is_empty());
+        assert_eq!(console.entered.len(), 5);
+    }
+
+    #[test]
+    fn test_complete_and_browse() {
+        let mut console = Console {
+            input: "sp".into(),
+            ..Default::default()
+        };
+        console.complete(&commands());
+        assert_eq!(console.input, "spawn ");
+        console.complete(&commands());
+        assert_eq!(console.input, "spawn e");
+        assert_eq!(texts(&console), vec!["enemy  elite_enemy"]);
+        console.input.push('l');
+        console.complete(&commands());
+        assert_eq!(console.input, "spawn elite_enemy ");
+
+        console.entered = vec!["help".into(), "clear".into()];
+        console.browse(true);
+        assert_eq!(console.input, "clear");
+        console.browse(true);
+        console.browse(true);
+        assert_eq!(console.input, "help");
+        console.browse(false);
+        console.browse(false);
+        assert_eq!(console.input, "");
+    }
+
+    #[test]
+    fn test_filters() {
+        let mut console = Console::default();
+        let line = |level, target: &str| LogLine {
+            level,
+            target: target.into(),
+            text: String::new(),
+        };
+        console
+            .lines
+            .push_back(line(Level::Info, "vampire_like_demo"));
+        console
+            .lines
+            .push_back(line(Level::Debug, "vampire_like_demo::enemy"));
+        console.lines.push_back(line(Level::Warn, "zurie_render"));
+        assert_eq!(console.visible().count(), 2);
+        console.level = LevelFilter::Trace;
+        console.module = "Vampire".into();
+        assert_eq!(console.visible().count(), 2);
+        console.level = LevelFilter::Warn;
+        assert_eq!(console.visible().count(), 0);
+        for _ in 0..MAX_LINES {
+            push_line(&mut console.lines, line(Level::Info, ""));
+        }
+        assert_eq!(console.lines.len(), MAX_LINES);
+    }
+}
@@ -0,0 +1,315 @@
+use egui::{Context, RawInput};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use zurie_audio::AudioManager;
+use zurie_audio::backend::AudioBackend;
+use zurie_ecs::{ComponentID, World};
+use zurie_input::{InputEvent, InputState};
+use zurie_physics::Physics;
+use zurie_render::sprite::SpriteManager;
+use zurie_scripting::mod_manager::ModManager;
+use zurie_shared::{DELTA_TIME, loader::AssetLoader};
+use zurie_types::{
+    KeyCode,
+    camera::{Camera, Cameras},
+    glam::Vec2,
+};
+
+use crate::replay::{Replay, ReplayTick};
+use crate::state::{ENGINE_COMPONENTS, SPATIAL_CELL_SIZE, update_cameras, update_spatial_audio};
+
+pub use zurie_scripting::mod_manager::DEFAULT_MOD;
+
+/// Runs mods without a window, renderer or sound device, for integration tests and dedicated
+/// servers. Time only moves when the engine is stepped and sprite loads are no-ops.
+pub struct HeadlessEngine {
+    mod_manager: ModManager,
+    world: Arc<RwLock<World>>,
+    cameras: Arc<RwLock<Cameras>>,
+    input: InputState,
+    gui_context: Context,
+    pos_component: ComponentID,
+    delta_time: f32,
+    frame: u64,
+    recording: Option<Replay>,
+}
+
+impl HeadlessEngine {
+    pub const DEFAULT_DELTA_TIME: f32 = 1.0 / 60.0;
+    /// Screen the cameras are set up for by default.
+    pub const SCREEN_SIZE: Vec2 = Vec2::new(1280.0, 720.0);
+
+    /// Loads the mods at `mod_paths` in order and runs their init.
+    pub fn new(mod_paths: &[String]) -> anyhow::Result<Self> {
+        Self::with_options(mod_paths, fastrand::u64(..), Self::SCREEN_SIZE)
+    }
+
+    /// Like `new` with the seed of the mods' random generators and the screen size the cameras
+    /// are set up for.
+    pub fn with_options(mod_paths: &[String], seed: u64, screen: Vec2) -> anyhow::Result<Self> {
+        let gui_context = Context::default();
+        let loader = AssetLoader::default();
+        let input = InputState::default();
+        let mut world = World::default();
+        // Same components in the same order as the windowed engine, so world hashes match
+        let [pos_component, ..] =
+            ENGINE_COMPONENTS.map(|name| world.register_component(name.into()));
+        world.index_component(pos_component, SPATIAL_CELL_SIZE);
+        let world = Arc::new(RwLock::new(world));
+        let cameras = Arc::new(RwLock::new(Cameras::new(
+            Camera::create_camera_from_screen_size(screen.x, screen.y, 0.1, 100.0, 1.0, Vec2::ZERO),
+            screen.x,
+            screen.y,
+        )));
+        let sprite_manager = Arc::new(RwLock::new(SpriteManager::headless(
+            gui_context.clone(),
+            loader.clone(),
+        )));
+        let mod_manager = ModManager::with_mods(
+            mod_paths,
+            seed,
+            gui_context.clone(),
+            input.clone(),
+            world.clone(),
+            cameras.clone(),
+            sprite_manager,
+            AudioManager::with_backend(loader, AudioBackend::Null),
+        )?;
+        Ok(Self {
+            mod_manager,
+            world,
+            cameras,
+            input,
+            gui_context,
+            pos_component,
+            delta_time: Self::DEFAULT_DELTA_TIME,
+            frame: 0,
+            recording: None,
+        })
+    }
+
+    pub fn world(&self) -> &Arc<RwLock<World>> {
+        &self.world
+    }
+
+    pub fn cameras(&self) -> &Arc<RwLock<Cameras>> {
+        &self.cameras
+    }
+
+    pub fn input(&self) -> &InputState {
+        &self.input
+    }
+
+    pub fn audio_manager(&self) -> &AudioManager {
+        self.mod_manager.audio_manager()
+    }
+
+    pub fn physics(&self) -> &Arc<RwLock<Physics>> {
+        self.mod_manager.physics()
+    }
+
+    pub fn mod_manager(&mut self) -> &mut ModManager {
+        &mut self.mod_manager
+    }
+
+    /// Frames stepped so far.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Seconds every step advances the engine by.
+    pub fn set_delta_time(&mut self, delta_time: f32) {
+        self.delta_time = delta_time;
+    }
+
+    /// Applies input for the next step and tells the mods handling it.
+    pub fn input_event(&mut self, event: InputEvent) -> anyhow::Result<()> {
+        self.input.apply(event);
+        self.mod_manager.input_event(event)
+    }
+
+    /// Presses a key for the next step and tells the mods subscribed to it.
+    pub fn press_key(&mut self, key: KeyCode) -> anyhow::Result<()> {
+        self.input_event(InputEvent::Key { key, pressed: true })
+    }
+
+    pub fn world_hash(&self) -> u64 {
+        self.world.read().unwrap().state_hash()
+    }
+
+    /// Records input and world hashes of every following step.
+    pub fn start_recording(&mut self) {
+        self.input.start_recording();
+        self.recording = Some(Replay::new(
+            self.mod_manager.seed(),
+            self.mod_manager.mod_paths(),
+            self.cameras.read().unwrap().screen_size(),
+            self.world_hash(),
+        ));
+    }
+
+    pub fn finish_recording(&mut self) -> Option<Replay> {
+        self.input.stop_recording();
+        self.recording.take()
+    }
+
+    /// Runs one frame: mods update, physics steps, cameras and positional sounds follow their
+    /// entities and the input of the frame is cleared.
+    pub fn step(&mut self) -> anyhow::Result<()> {
+        unsafe {
+            DELTA_TIME = self.delta_time;
+        }
+        self.mod_manager.advance_clock(self.delta_time);
+        let events = self.input.take_recorded();
+        let input = RawInput {
+            predicted_dt: self.delta_time,
+            ..Default::default()
+        };
+        let mut result = Ok(());
+        let gui_context = self.gui_context.clone();
+        // Mods build their windows during the update, so it runs inside a gui pass
+        let _ = gui_context.run(input, |_| result = self.mod_manager.update());
+        result?;
+        self.mod_manager.update_physics(self.delta_time);
+        {
+            let world = self.world.read().unwrap();
+            update_cameras(
+                &world,
+                &mut self.cameras.write().unwrap(),
+                self.pos_component,
+                self.delta_time,
+            );
+            update_spatial_audio(
+                &world,
+                &self.cameras.read().unwrap(),
+                self.pos_component,
+                self.mod_manager.audio_manager(),
+            );
+        }
+        if let Some(recording) = self.recording.as_mut() {
+            recording.ticks.push(ReplayTick {
+                delta_time: self.delta_time,
+                events,
+                world_hash: self.world.read().unwrap().state_hash(),
+            });
+        }
+        self.input.after_update();
+        self.frame += 1;
+        Ok(())
+    }
+
+    pub fn run_frames(&mut self, frames: u64) -> anyhow::Result<()> {
+        for _ in 0..frames {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    /// Steps until `exit` returns true, at most `max_frames` times. Returns whether `exit` was
+    /// reached.
+    pub fn run_until(
+        &mut self,
+        max_frames: u64,
+        mut exit: impl FnMut(&Self) -> bool,
+    ) -> anyhow::Result<bool> {
+        for _ in 0..max_frames {
+            if exit(self) {
+                return Ok(true);
+            }
+            self.step()?;
+        }
+        Ok(exit(self))
+    }
+
+    /// Steps at real time speed until `exit` returns true, for dedicated servers.
+    pub fn run_realtime(&mut self, mut exit: impl FnMut(&Self) -> bool) -> anyhow::Result<()> {
+        let frame_time = Duration::from_secs_f32(self.delta_time);
+        let mut next_frame = Instant::now();
+        while !exit(self) {
+            self.step()?;
+            next_frame += frame_time;
+            if let Some(wait) = next_frame.checked_duration_since(Instant::now()) {
+                thread::sleep(wait);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use std::path::Path;
+    use zurie_types::ComponentData;
+    use zurie_types::physics::{Collider, RigidBody, Shape};
+
+    #[test]
+    fn test_runs_without_mods() {
+        let mut engine = HeadlessEngine::new(&[]).unwrap();
+        engine.run_frames(3).unwrap();
+        assert_eq!(engine.frame(), 3);
+        let reached = engine
+            .run_until(100, |engine| engine.frame() == 10)
+            .unwrap();
+        assert!(reached);
+        assert_eq!(engine.frame(), 10);
+        assert!(!engine.run_until(5, |_| false).unwrap());
+    }
+
+    #[test]
+    fn test_physics_steps_with_frames() {
+        let mut engine = HeadlessEngine::new(&[]).unwrap();
+        let components = engine.physics().read().unwrap().components();
+        engine.physics().write().unwrap().gravity = Vec2::new(0.0, -10.0);
+        let ball = {
+            let mut world = engine.world().write().unwrap();
+            let floor = world.spawn_entity();
+            let shape = Shape::Box {
+                half_extents: Vec2::new(5.0, 0.5),
+            };
+            let collider = ComponentData::Collider(Collider {
+                shape,
+                ..Default::default()
+            });
+            let origin = ComponentData::Vector(Vec2::ZERO);
+            world.set_component(floor, (components.position, origin));
+            world.set_component(floor, (components.collider, collider));
+            let ball = world.spawn_entity();
+            let position = ComponentData::Vector(Vec2::new(0.0, 3.0));
+            let body = ComponentData::RigidBody(RigidBody::default());
+            let collider = ComponentData::Collider(Collider::default());
+            world.set_component(ball, (components.position, position));
+            world.set_component(ball, (components.rigid_body, body));
+            world.set_component(ball, (components.collider, collider));
+            ball
+        };
+        engine.run_frames(120).unwrap();
+        let world = engine.world().read().unwrap();
+        let Some(ComponentData::Vector(position)) = world.get_component(ball, components.position)
+        else {
+            panic!("ball lost its position");
+        };
+        assert!((position.y - 1.0).abs() < 0.05);
+        assert_eq!(engine.physics().read().unwrap().contacts().count(), 1);
+    }
+
+    /// Run with `cargo test -- --ignored` after `just build_mods`.
+    #[test]
+    #[ignore = "requires built demo mod"]
+    fn test_demo_spawns_player_and_enemies() {
+        let demo = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../..")
+            .join(DEFAULT_MOD);
+        let demo = demo.to_str().unwrap().to_string();
+        let mut engine = HeadlessEngine::new(&[demo]).unwrap();
+        engine.run_frames(60).unwrap();
+        let world = engine.world().read().unwrap();
+        let positioned = world.get_entities_with_component(engine.pos_component);
+        assert!(positioned.len() > 1);
+        assert!(positioned.iter().all(|entity| matches!(
+            world.get_component(*entity, engine.pos_component),
+            Some(ComponentData::Vector(_))
+        )));
+    }
+}
@@ -1,3 +1,7 @@
 pub mod app;
+pub mod console;
 
+#[cfg(not(target_os = "android"))]
+pub mod headless;
+pub mod replay;
 pub mod state;
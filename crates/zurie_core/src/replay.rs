@@ -0,0 +1,196 @@
+use anyhow::{Context, bail, ensure};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use zurie_input::InputEvent;
+use zurie_types::glam::Vec2;
+
+#[cfg(not(target_os = "android"))]
+use crate::headless::HeadlessEngine;
+
+pub const REPLAY_VERSION: u32 = 1;
+
+/// Everything needed to run a session again: the seed the mods' random generators start
+/// from, the mods in load order and the input and length of every tick.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    pub mods: Vec<String>,
+    pub screen_size: Vec2,
+    /// World hash once the mods ran their init.
+    pub initial_hash: u64,
+    pub ticks: Vec<ReplayTick>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ReplayTick {
+    pub delta_time: f32,
+    /// Input applied before the tick ran.
+    pub events: Vec<InputEvent>,
+    /// World hash after the tick.
+    pub world_hash: u64,
+}
+
+impl Replay {
+    pub fn new(seed: u64, mods: Vec<String>, screen_size: Vec2, initial_hash: u64) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            seed,
+            mods,
+            screen_size,
+            initial_hash,
+            ticks: Vec::new(),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {path:?}"))?;
+        let replay: Self =
+            ron::from_str(&text).with_context(|| format!("{path:?} is not a replay"))?;
+        ensure!(
+            replay.version == REPLAY_VERSION,
+            "Unsupported replay version {}",
+            replay.version
+        );
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let text = ron::ser::to_string(self)?;
+        std::fs::write(path, text).with_context(|| format!("Failed to save {path:?}"))
+    }
+}
+
+/// Runs a replay through a headless engine and checks the world matches the recording after
+/// every tick.
+#[cfg(not(target_os = "android"))]
+pub struct ReplayPlayer {
+    engine: HeadlessEngine,
+    replay: Replay,
+    tick: usize,
+}
+
+#[cfg(not(target_os = "android"))]
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> anyhow::Result<Self> {
+        let engine = HeadlessEngine::with_options(&replay.mods, replay.seed, replay.screen_size)?;
+        let hash = engine.world_hash();
+        if hash != replay.initial_hash {
+            bail!(
+                "World after init is {hash:016x}, the recording has {:016x}",
+                replay.initial_hash
+            );
+        }
+        Ok(Self {
+            engine,
+            replay,
+            tick: 0,
+        })
+    }
+
+    pub fn engine(&self) -> &HeadlessEngine {
+        &self.engine
+    }
+
+    /// Ticks played so far.
+    pub fn tick(&self) -> usize {
+        self.tick
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.tick >= self.replay.ticks.len()
+    }
+
+    /// Plays the next tick, false once the replay is over. Fails when the world diverged.
+    pub fn step(&mut self) -> anyhow::Result<bool> {
+        let Some(tick) = self.replay.ticks.get(self.tick) else {
+            return Ok(false);
+        };
+        for event in tick.events.iter() {
+            self.engine.input_event(*event)?;
+        }
+        self.engine.set_delta_time(tick.delta_time);
+        self.engine.step()?;
+        let hash = self.engine.world_hash();
+        if hash != tick.world_hash {
+            bail!(
+                "World diverged at tick {}: {hash:016x}, the recording has {:016x}",
+                self.tick,
+                tick.world_hash
+            );
+        }
+        self.tick += 1;
+        Ok(true)
+    }
+
+    /// Plays every remaining tick.
+    pub fn run(&mut self) -> anyhow::Result<()> {
+        while self.step()? {}
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::headless::DEFAULT_MOD;
+    use zurie_types::KeyCode;
+
+    fn record(mods: &[String], seed: u64) -> Replay {
+        let mut engine =
+            HeadlessEngine::with_options(mods, seed, HeadlessEngine::SCREEN_SIZE).unwrap();
+        engine.start_recording();
+        for frame in 0..30 {
+            if frame % 10 == 0 {
+                engine.press_key(KeyCode::KeyD).unwrap();
+            }
+            engine.input().set_mouse_pos(Vec2::splat(frame as f32));
+            engine.step().unwrap();
+        }
+        engine.finish_recording().unwrap()
+    }
+
+    #[test]
+    fn test_record_and_play() {
+        let replay = record(&[], 3);
+        assert_eq!(replay.ticks.len(), 30);
+        assert_eq!(replay.ticks[0].events.len(), 2);
+        assert_eq!(replay.ticks[1].events.len(), 1);
+
+        let path = std::env::temp_dir().join("zurie_replay_test.ron");
+        replay.save(&path).unwrap();
+        let loaded = Replay::load(&path).unwrap();
+        assert_eq!(loaded, replay);
+
+        let mut player = ReplayPlayer::new(loaded).unwrap();
+        player.run().unwrap();
+        assert!(player.is_finished());
+        assert_eq!(player.engine().frame(), 30);
+        assert_eq!(player.engine().input().get_mouse_pos(), Vec2::splat(29.0));
+    }
+
+    #[test]
+    fn test_divergence_is_detected() {
+        let mut replay = record(&[], 3);
+        replay.ticks[5].world_hash ^= 1;
+        let mut player = ReplayPlayer::new(replay).unwrap();
+        let err = player.run().unwrap_err();
+        assert!(err.to_string().contains("tick 5"));
+        assert_eq!(player.tick(), 5);
+    }
+
+    /// Run with `cargo test -- --ignored` after `just build_mods`.
+    #[test]
+    #[ignore = "requires built demo mod"]
+    fn test_demo_replays() {
+        let demo = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../..")
+            .join(DEFAULT_MOD);
+        let demo = demo.to_str().unwrap().to_string();
+        let replay = record(&[demo], 42);
+        ReplayPlayer::new(replay).unwrap().run().unwrap();
+    }
+}
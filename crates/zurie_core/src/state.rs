@@ -1,23 +1,60 @@
+pub mod editor;
 pub mod gui;
+pub mod profiler;
 
+use crate::console::Console;
+use crate::replay::{Replay, ReplayTick};
 use ecolor::hex_color;
+use editor::{Editor, EditorMode};
 use egui::Context;
+use log::{error, info, warn};
+use profiler::ProfilerWindow;
 use zurie_input::InputState;
 
 use std::sync::{Arc, RwLock};
 #[cfg(target_os = "android")]
 use winit::platform::android::ActiveEventLoopExtAndroid;
 use winit::{event::WindowEvent, event_loop::ActiveEventLoop, window::Window};
+use zurie_assets::archive::DEFAULT_ARCHIVE;
+use zurie_audio::AudioManager;
+use zurie_audio::spatial::Listener;
+use zurie_ecs::history::History;
+use zurie_ecs::inspector::Inspector;
 use zurie_ecs::{Architype, ComponentID, World};
+use zurie_render::object_draw::tiles::TilemapDraw;
 use zurie_render::{compute_sand::CellType, render_state::RenderState};
 use zurie_scripting::mod_manager::ModManager;
-use zurie_types::{ComponentData, Object, camera::Camera, glam::Vec2};
+use zurie_shared::{
+    DELTA_TIME,
+    loader::AssetLoader,
+    profiler::Stage,
+    slotmap::{Key, KeyData},
+};
+use zurie_types::{
+    ComponentData, Object,
+    camera::{Camera, Cameras},
+    glam::Vec2,
+};
+
+/// Components the engine registers before any mod, in registration order.
+pub(crate) const ENGINE_COMPONENTS: [&str; 7] = [
+    "position",
+    "scale",
+    "color",
+    "sprite",
+    "layer",
+    "animation",
+    "tilemap",
+];
+
+/// Cell size of the spatial index over positions, in world units.
+pub(crate) const SPATIAL_CELL_SIZE: f32 = 4.0;
 
 pub struct State {
     input: InputState,
     selected_cell_type: CellType,
     background_color: [f32; 4],
-    camera: Arc<RwLock<Camera>>,
+    cameras: Arc<RwLock<Cameras>>,
     mod_manager: ModManager,
     world: Arc<RwLock<World>>,
     render_state: RenderState,
@@ -25,56 +62,76 @@ pub struct State {
     scale_component: ComponentID,
     color_component: ComponentID,
     sprite_component: ComponentID,
+    layer_component: ComponentID,
+    animation_component: ComponentID,
+    tilemap_component: ComponentID,
     gui_context: Context,
+    inspector: Inspector,
+    editor: Editor,
+    /// Undo and redo of the edits made in the inspector and the editor.
+    history: History,
+    console: Console,
+    profiler_window: ProfilerWindow,
+    recording: Option<Replay>,
+    /// Replays only hold ticks of the game running untouched, the recording ends at the first
+    /// paused tick or edit.
+    recording_stopped: bool,
 }
 
 impl State {
     pub async fn new(window: Arc<Window>, event_loop: &ActiveEventLoop) -> State {
-        let render_state =
-            RenderState::new(window, event_loop).expect("error creating render state");
+        match mount_archive(event_loop) {
+            Ok(true) => {}
+            Ok(false) => info!("No {DEFAULT_ARCHIVE} found, loading loose asset files"),
+            Err(err) => error!("Failed to mount {DEFAULT_ARCHIVE}: {err:#}"),
+        }
+        let loader = AssetLoader::default();
+        let render_state = RenderState::new(window, event_loop, loader.clone())
+            .expect("error creating render state");
         let gui_context = render_state.gui.gui.context();
         gui_context.set_style(gruvbox_egui::gruvbox_dark_theme());
 
         let size = render_state.renderer.window_size();
-        let camera = Arc::new(RwLock::new(Camera::create_camera_from_screen_size(
+        let cameras = Arc::new(RwLock::new(Cameras::new(
+            Camera::create_camera_from_screen_size(
+                size[0] as f32,
+                size[1] as f32,
+                0.1,
+                100.0,
+                1.0,
+                Vec2::ZERO,
+            ),
             size[0] as f32,
             size[1] as f32,
-            0.1,
-            100.0,
-            1.0,
-            Vec2::ZERO,
         )));
         let input = InputState::default();
-        let (world, pos_component, scale_component, color_component, sprite_component) = {
-            let mut world: World = Default::default();
-            let pos_component = world.register_component("position".into());
-            let scale_component = world.register_component("scale".into());
-            let color_component = world.register_component("color".into());
-            let sprite_component = world.register_component("sprite".into());
-            (
-                Arc::new(RwLock::new(world)),
-                pos_component,
-                scale_component,
-                color_component,
-                sprite_component,
-            )
-        };
+        let mut world: World = Default::default();
+        let [
+            pos_component,
+            scale_component,
+            color_component,
+            sprite_component,
+            layer_component,
+            animation_component,
+            tilemap_component,
+        ] = ENGINE_COMPONENTS.map(|name| world.register_component(name.into()));
+        world.index_component(pos_component, SPATIAL_CELL_SIZE);
+        let world = Arc::new(RwLock::new(world));
 
         let mod_manager = ModManager::new(
             gui_context.clone(),
             input.clone(),
             world.clone(),
-            camera.clone(),
+            cameras.clone(),
             render_state.sprite_manager.clone(),
-            #[cfg(target_os = "android")]
-            event_loop.android_app().clone(),
+            loader,
         );
 
         State {
             input,
             selected_cell_type: CellType::Sand,
             background_color: hex_color!("#8FA3B3").to_normalized_gamma_f32(),
-            camera,
+            cameras,
             mod_manager,
             world,
             render_state,
@@ -82,23 +139,128 @@ impl State {
             scale_component,
             color_component,
             sprite_component,
+            layer_component,
+            animation_component,
+            tilemap_component,
             gui_context,
+            inspector: Inspector::default(),
+            editor: Editor::new(
+                pos_component,
+                scale_component,
+                sprite_component,
+                tilemap_component,
+            ),
+            history: History::default(),
+            console: Console::default(),
+            profiler_window: ProfilerWindow::default(),
+            recording: None,
+            recording_stopped: false,
+        }
+    }
+
+    /// Records input and world hashes of every following frame, to be played headless.
+    pub fn start_recording(&mut self) {
+        self.input.start_recording();
+        self.recording = Some(Replay::new(
+            self.mod_manager.seed(),
+            self.mod_manager.mod_paths(),
+            self.cameras.read().unwrap().screen_size(),
+            self.world.read().unwrap().state_hash(),
+        ));
+    }
+
+    pub fn finish_recording(&mut self) -> Option<Replay> {
+        self.input.stop_recording();
+        self.recording_stopped = false;
+        self.recording.take()
+    }
+
+    /// Ends the recording early, `finish_recording` still returns the ticks before this.
+    fn stop_recording(&mut self, reason: &str) {
+        if self.recording.is_none() || self.recording_stopped {
+            return;
         }
+        warn!("Recording stopped, {reason}");
+        self.input.stop_recording();
+        self.recording_stopped = true;
     }
 
     pub fn render(&mut self) -> anyhow::Result<()> {
         self.render_state.gui.start_gui();
+        let delta_time = unsafe { DELTA_TIME };
+        // While editing the game stands still: mods aren't updated and events sent to them wait
+        // in their queues, only the engine's mod and mixer windows are shown
+        let runs_game = self.editor.runs_game();
+        if runs_game {
+            self.mod_manager.advance_clock(delta_time);
+        }
+        let events = self.input.take_recorded();
+        self.mod_manager.set_paused(!runs_game);
         self.mod_manager.update()?;
-        self.world
-            .write()
-            .unwrap()
-            .inspector(self.gui_context.clone());
-        let mut objects: Vec<Object> = self
-            .world
-            .read()
-            .unwrap()
+        if runs_game {
+            self.mod_manager.update_physics(delta_time);
+        }
+        if self.editor.mode() != EditorMode::Off {
+            self.stop_recording("the editor was opened");
+        }
+        if let Some(recording) = self.recording.as_mut().filter(|_| !self.recording_stopped) {
+            recording.ticks.push(ReplayTick {
+                delta_time,
+                events,
+                world_hash: self.world.read().unwrap().state_hash(),
+            });
+        }
+        {
+            let _scope = self.mod_manager.profiler().scope(Stage::Tools);
+            let mut world = self.world.write().unwrap();
+            self.inspector
+                .show(&mut world, &mut self.history, &self.gui_context);
+            self.editor.show(
+                &mut world,
+                &mut self.cameras.write().unwrap(),
+                &self.gui_context,
+                &self.mod_manager,
+                &mut self.inspector,
+                &mut self.history,
+            );
+            self.console.show(&self.gui_context, &self.mod_manager);
+            self.profiler_window
+                .show(&self.gui_context, self.mod_manager.profiler());
+            // The editor picks entities itself
+            let editing = self.editor.mode() == EditorMode::Editing;
+            if let Some(point) = self.viewport_click().filter(|_| !editing) {
+                self.inspector.select(self.editor.entity_at(&world, point));
+            }
+        }
+        let edited = self
+            .recording
+            .as_ref()
+            .filter(|_| !self.recording_stopped)
+            .is_some_and(|recording| {
+                let hash = recording
+                    .ticks
+                    .last()
+                    .map_or(recording.initial_hash, |tick| tick.world_hash);
+                hash != self.world.read().unwrap().state_hash()
+            });
+        if edited {
+            self.stop_recording("the world was changed by the inspector, editor or console");
+        }
+        if runs_game {
+            self.update_cameras();
+        }
+        self.update_spatial_audio();
+        self.update_animations();
+        let world = self.world.read().unwrap();
+        let mut objects: Vec<Object> = world
             .get_entities_data_with_components(vec![self.pos_component])
             .iter()
+            // Tilemaps are drawn by chunk instead
+            .filter(|(entity, _)| {
+                world
+                    .get_component(*entity, self.tilemap_component)
+                    .is_none()
+            })
             .map(|(_, entity_data)| {
                 let mut obj = Object::default();
                 for (component_id, component_data) in entity_data.data.iter() {
@@ -122,6 +284,16 @@ impl State {
                             ComponentData::Sprite(handle) => *handle,
                             _ => 0,
                         };
+                    } else if *component_id == self.layer_component {
+                        obj.layer = match component_data {
+                            ComponentData::I32(layer) => *layer as u32,
+                            _ => 0,
+                        };
+                    } else if *component_id == self.animation_component {
+                        obj.frame = match component_data {
+                            ComponentData::Animation(animation) => animation.frame,
+                            _ => 0,
+                        };
                     }
                 }
                 obj
@@ -131,20 +303,121 @@ impl State {
             objects.push(Object::default())
         };
         let objects = Arc::new(RwLock::new(objects));
+        let tilemaps = self.tilemaps(&world);
 
-        self.render_state
-            .render(self.background_color, &self.camera.read().unwrap(), objects)?;
+        self.render_state.render(
+            self.background_color,
+            &self.cameras.read().unwrap(),
+            objects,
+            &tilemaps,
+            self.mod_manager.profiler(),
+        )?;
         self.input.after_update();
+        self.mod_manager.profiler().end_frame();
 
         anyhow::Ok(())
     }
 
+    /// Tilemap entities with a position, in entity order.
+    fn tilemaps<'a>(&self, world: &'a World) -> Vec<TilemapDraw<'a>> {
+        world
+            .get_entities_data_with_components(vec![self.pos_component, self.tilemap_component])
+            .into_iter()
+            .filter_map(|(entity, entity_data)| {
+                let mut position = Vec2::ZERO;
+                let mut color = [1.0, 1.0, 1.0, 1.0];
+                let mut layer = 0;
+                let mut tilemap = None;
+                for (component_id, component_data) in entity_data.data.iter() {
+                    match component_data {
+                        ComponentData::Vector(v) if *component_id == self.pos_component => {
+                            position = *v
+                        }
+                        ComponentData::Color(c) if *component_id == self.color_component => {
+                            color = *c
+                        }
+                        ComponentData::I32(l) if *component_id == self.layer_component => {
+                            layer = *l as u32
+                        }
+                        ComponentData::Tilemap(t) if *component_id == self.tilemap_component => {
+                            tilemap = Some(t.as_ref())
+                        }
+                        _ => {}
+                    }
+                }
+                Some(TilemapDraw {
+                    entity: KeyData::as_ffi(entity.data()),
+                    position,
+                    color,
+                    layer,
+                    tilemap: tilemap?,
+                })
+            })
+            .collect()
+    }
+
+    /// World position of a click this frame that no window took.
+    fn viewport_click(&self) -> Option<Vec2> {
+        let context = &self.gui_context;
+        if !context.input(|input| input.pointer.primary_clicked()) || context.is_pointer_over_area()
+        {
+            return None;
+        }
+        let pointer = context.input(|input| input.pointer.interact_pos())?;
+        let pixel = Vec2::new(pointer.x, pointer.y) * context.pixels_per_point();
+        let cameras = self.cameras.read().unwrap();
+        Some(
+            cameras
+                .main_camera()
+                .screen_to_world(pixel, cameras.screen_size()),
+        )
+    }
+
+    fn update_cameras(&mut self) {
+        update_cameras(
+            &self.world.read().unwrap(),
+            &mut self.cameras.write().unwrap(),
+            self.pos_component,
+            unsafe { DELTA_TIME },
+        );
+    }
+
+    fn update_spatial_audio(&self) {
+        update_spatial_audio(
+            &self.world.read().unwrap(),
+            &self.cameras.read().unwrap(),
+            self.pos_component,
+            self.mod_manager.audio_manager(),
+        );
+    }
+
+    fn update_animations(&mut self) {
+        let sprite_manager = self.render_state.sprite_manager.read().unwrap();
+        let mut world = self.world.write().unwrap();
+        for entity in world
+            .get_entities_with_components(vec![self.animation_component, self.sprite_component])
+        {
+            let sprite = match world.get_component(entity, self.sprite_component) {
+                Some(ComponentData::Sprite(handle)) => KeyData::from_ffi(*handle).into(),
+                _ => continue,
+            };
+            let Some(sheet) = sprite_manager.get_sheet(sprite) else {
+                continue;
+            };
+            if let Some(ComponentData::Animation(animation)) =
+                world.get_component_mut(entity, self.animation_component)
+            {
+                animation.advance(sheet, unsafe { DELTA_TIME } * 1000.0);
+            }
+        }
+    }
+
     pub fn resize(&mut self, size: [u32; 2]) {
         self.render_state.resize(size);
-        self.camera
+        self.cameras
             .write()
             .unwrap()
-            .update_matrix_from_screen_size(size[0] as f32, size[1] as f32);
+            .resize(size[0] as f32, size[1] as f32);
     }
 
     pub fn event(&mut self, ev: WindowEvent) -> anyhow::Result<()> {
@@ -155,3 +428,64 @@ impl State {
         Ok(())
     }
 }
+
+#[cfg(not(target_os = "android"))]
+fn mount_archive(_event_loop: &ActiveEventLoop) -> anyhow::Result<bool> {
+    zurie_assets::archive::mount_if_present(DEFAULT_ARCHIVE)
+}
+
+/// There is no working directory on Android, the archive is one of the APK assets.
+#[cfg(target_os = "android")]
+fn mount_archive(event_loop: &ActiveEventLoop) -> anyhow::Result<bool> {
+    use std::ffi::CString;
+    use std::io::Read;
+    use zurie_assets::archive::Archive;
+
+    let name = CString::new(DEFAULT_ARCHIVE)?;
+    let Some(mut asset) = event_loop.android_app().asset_manager().open(&name) else {
+        return Ok(false);
+    };
+    let mut bytes = Vec::new();
+    asset.read_to_end(&mut bytes)?;
+    zurie_assets::archive::mount(Archive::from_bytes(bytes)?);
+    info!("Mounted asset archive {DEFAULT_ARCHIVE} from the APK");
+    Ok(true)
+}
+
+fn entity_position(world: &World, pos_component: ComponentID, entity: u64) -> Option<Vec2> {
+    match world.get_component(KeyData::from_ffi(entity).into(), pos_component) {
+        Some(ComponentData::Vector(position)) => Some(*position),
+        _ => None,
+    }
+}
+
+/// Moves every camera towards the entity it follows.
+pub(crate) fn update_cameras(
+    world: &World,
+    cameras: &mut Cameras,
+    pos_component: ComponentID,
+    delta_time: f32,
+) {
+    for (_, camera) in cameras.iter_mut() {
+        let follow_target = camera
+            .follow
+            .and_then(|follow| entity_position(world, pos_component, follow.target));
+        camera.update(delta_time, follow_target);
+    }
+}
+
+/// Moves positional sounds to where their entities and the listener are this frame.
+pub(crate) fn update_spatial_audio(
+    world: &World,
+    cameras: &Cameras,
+    pos_component: ComponentID,
+    audio_manager: &AudioManager,
+) {
+    let position = |entity: u64| entity_position(world, pos_component, entity);
+    let listener = match audio_manager.listener() {
+        Listener::Entity(entity) => position(entity),
+        Listener::Camera => None,
+    }
+    .unwrap_or_else(|| cameras.main_camera().position);
+    audio_manager.update_spatial(listener, position);
+}
@@ -0,0 +1,734 @@
+use hashbrown::{HashMap, HashSet};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Context as _;
+use egui::{
+    Color32, Context, DragValue, Id, Key, LayerId, Painter, PointerButton, Pos2, Rect, Stroke,
+};
+use log::{error, info};
+use zurie_ecs::history::History;
+use zurie_ecs::inspector::Inspector;
+use zurie_ecs::{ComponentID, Entity, World, WorldSnapshot};
+use zurie_physics::Physics;
+use zurie_render::sprite::{LoadSpriteInfo, SpriteManager};
+use zurie_scripting::mod_manager::ModManager;
+use zurie_shared::slotmap::{Key as _, KeyData};
+use zurie_types::ComponentData;
+use zurie_types::camera::{Camera, Cameras};
+use zurie_types::glam::Vec2;
+
+/// Scene the editor saves and loads unless another path is typed in.
+pub const DEFAULT_SCENE: &str = "static/scenes/scene.ron";
+/// Length of the gizmo axes, in points.
+const GIZMO_LENGTH: f32 = 60.0;
+/// How far from a gizmo handle a press still grabs it, in points.
+const HANDLE_RADIUS: f32 = 8.0;
+/// Grids with more lines than this across the screen aren't drawn.
+const MAX_GRID_LINES: f32 = 200.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EditorMode {
+    Off,
+    /// Mods and physics are paused while entities are edited.
+    Editing,
+    /// The game runs from the edited world, which comes back on stop.
+    Playing,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Tool {
+    Move,
+    Scale,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Axis {
+    Both,
+    X,
+    Y,
+}
+
+struct Drag {
+    axis: Axis,
+    /// World position the pointer was pressed at.
+    start: Vec2,
+    /// Position or scale of every selected entity when the drag started.
+    values: Vec<(Entity, Vec2)>,
+}
+
+/// Level editor over the world, toggled with F1.
+pub struct Editor {
+    mode: EditorMode,
+    /// The world and its history as they were when play was pressed.
+    before_play: Option<(WorldSnapshot, History)>,
+    /// The last selected entity is the one in the inspector and the one with the gizmo.
+    selection: Vec<Entity>,
+    tool: Tool,
+    drag: Option<Drag>,
+    show_grid: bool,
+    snap: bool,
+    grid_size: f32,
+    scene_path: String,
+    /// Palette of prefab names, read when the palette is first opened.
+    prefabs: Option<Vec<String>>,
+    pos_component: ComponentID,
+    scale_component: ComponentID,
+    sprite_component: ComponentID,
+    tilemap_component: ComponentID,
+}
+
+impl Editor {
+    pub fn new(
+        pos_component: ComponentID,
+        scale_component: ComponentID,
+        sprite_component: ComponentID,
+        tilemap_component: ComponentID,
+    ) -> Self {
+        Self {
+            mode: EditorMode::Off,
+            before_play: None,
+            selection: Vec::new(),
+            tool: Tool::Move,
+            drag: None,
+            show_grid: true,
+            snap: true,
+            grid_size: 1.0,
+            scene_path: DEFAULT_SCENE.into(),
+            prefabs: None,
+            pos_component,
+            scale_component,
+            sprite_component,
+            tilemap_component,
+        }
+    }
+
+    pub fn mode(&self) -> EditorMode {
+        self.mode
+    }
+
+    /// Whether mods, physics and camera follow run this frame.
+    pub fn runs_game(&self) -> bool {
+        self.mode != EditorMode::Editing
+    }
+
+    fn toggle(&mut self) {
+        self.mode = match self.mode {
+            EditorMode::Off => EditorMode::Editing,
+            // Leaving while playing keeps the played world
+            EditorMode::Editing | EditorMode::Playing => EditorMode::Off,
+        };
+        self.before_play = None;
+        self.drag = None;
+        info!("Editor mode: {:?}", self.mode);
+    }
+
+    /// Runs the game from the edited world until `stop`. Edits made while playing get a
+    /// history of their own.
+    pub fn play(&mut self, world: &World, history: &mut History) {
+        self.before_play = Some((world.snapshot(), std::mem::take(history)));
+        self.mode = EditorMode::Playing;
+        self.drag = None;
+    }
+
+    /// Puts the world and its history back to how they were when play was pressed. Entities
+    /// despawned while playing come back with new ids, returned by their old ones.
+    pub fn stop(
+        &mut self,
+        world: &mut World,
+        history: &mut History,
+        physics: &mut Physics,
+    ) -> HashMap<Entity, Entity> {
+        let mut respawned = HashMap::new();
+        if let Some((snapshot, edited)) = self.before_play.take() {
+            respawned = world.restore(&snapshot);
+            *history = edited;
+            history.remap_entities(&respawned);
+            for entity in self.selection.iter_mut() {
+                if let Some(new) = respawned.get(entity) {
+                    *entity = *new;
+                }
+            }
+            physics.reset();
+        }
+        self.mode = EditorMode::Editing;
+        respawned
+    }
+
+    /// Saves the world as a snapshot. Sprite handles only mean something to this run, so the
+    /// scene names sprite files instead.
+    pub fn save_scene(
+        &self,
+        world: &World,
+        sprite_manager: &SpriteManager,
+        path: impl AsRef<Path>,
+    ) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let mut snapshot = world.snapshot();
+        for (_, entity_data) in snapshot.entities.iter_mut() {
+            for (component, data) in entity_data.data.iter_mut() {
+                let file = match data {
+                    ComponentData::Sprite(handle) if *component == self.sprite_component => {
+                        sprite_manager.path_of(KeyData::from_ffi(*handle).into())
+                    }
+                    _ => None,
+                };
+                if let Some(file) = file {
+                    *data = ComponentData::String(file.to_string_lossy().into_owned());
+                }
+            }
+        }
+        if let Some(folder) = path.parent() {
+            std::fs::create_dir_all(folder)?;
+        }
+        let text = ron::ser::to_string_pretty(&snapshot, Default::default())?;
+        std::fs::write(path, text).with_context(|| format!("Failed to save {path:?}"))
+    }
+
+    /// Replaces the world with a scene from `save_scene`.
+    pub fn load_scene(
+        &mut self,
+        world: &mut World,
+        sprite_manager: &mut SpriteManager,
+        path: impl AsRef<Path>,
+    ) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let bytes = zurie_assets::read_asset(path)?;
+        let mut snapshot: WorldSnapshot =
+            ron::de::from_bytes(&bytes).with_context(|| format!("{path:?} is not a scene"))?;
+        let sprite_component = snapshot
+            .components
+            .iter()
+            .find(|(_, name)| name == "sprite")
+            .map(|(component, _)| *component);
+        for (_, entity_data) in snapshot.entities.iter_mut() {
+            for (component, data) in entity_data.data.iter_mut() {
+                let ComponentData::String(file) = data else {
+                    continue;
+                };
+                if Some(*component) != sprite_component {
+                    continue;
+                }
+                let handle = sprite_manager.handle_of(&*file).unwrap_or_else(|| {
+                    sprite_manager.push_to_load_queue(LoadSpriteInfo::Path(Box::from(Path::new(
+                        file.as_str(),
+                    ))))
+                });
+                *data = ComponentData::Sprite(KeyData::as_ffi(handle.data()));
+            }
+        }
+        world.restore(&snapshot);
+        self.selection.clear();
+        self.drag = None;
+        Ok(())
+    }
+
+    /// Entity whose quad contains `point`, the last drawn one when they overlap. Tilemaps
+    /// aren't drawn as quads and can't be picked.
+    pub fn entity_at(&self, world: &World, point: Vec2) -> Option<Entity> {
+        world
+            .get_entities_with_component(self.pos_component)
+            .into_iter()
+            .rev()
+            .find(|entity| {
+                let position = vector(world, *entity, self.pos_component, Vec2::ZERO);
+                let half_scale =
+                    vector(world, *entity, self.scale_component, Vec2::ONE).abs() / 2.0;
+                (point - position).abs().cmple(half_scale).all()
+                    && world
+                        .get_component(*entity, self.tilemap_component)
+                        .is_none()
+            })
+    }
+
+    /// Draws the editor and handles its input, F1 turns it on and off.
+    pub fn show(
+        &mut self,
+        world: &mut World,
+        cameras: &mut Cameras,
+        context: &Context,
+        mod_manager: &ModManager,
+        inspector: &mut Inspector,
+        history: &mut History,
+    ) {
+        if context.input(|input| input.key_pressed(Key::F1)) {
+            self.toggle();
+        }
+        if self.mode == EditorMode::Off {
+            return;
+        }
+        self.selection
+            .retain(|entity| world.get_entity_data(*entity).is_some());
+        // Entities picked in the inspector list
+        match inspector.selected() {
+            Some(entity) if self.selection.last() != Some(&entity) => self.selection = vec![entity],
+            _ => {}
+        }
+        self.window(world, history, cameras.main_camera(), context, mod_manager);
+        if self.mode == EditorMode::Editing {
+            self.viewport(world, history, cameras, context);
+        }
+        self.draw(world, cameras, context);
+        inspector.select(self.selection.last().copied());
+    }
+
+    fn window(
+        &mut self,
+        world: &mut World,
+        history: &mut History,
+        camera: &Camera,
+        context: &Context,
+        mod_manager: &ModManager,
+    ) {
+        egui::Window::new("Editor").show(context, |ui| {
+            ui.horizontal(|ui| {
+                if self.mode == EditorMode::Playing {
+                    if ui.button("Stop").clicked() {
+                        let mut physics = mod_manager.physics().write().unwrap();
+                        let respawned = self.stop(world, history, &mut physics);
+                        drop(physics);
+                        // Mods keep the ids they looked up while playing
+                        mod_manager.world_restored(&respawned);
+                    }
+                } else if ui.button("Play").clicked() {
+                    self.play(world, history);
+                }
+                ui.label(format!("{} selected", self.selection.len()));
+            });
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.tool, Tool::Move, "Move");
+                ui.selectable_value(&mut self.tool, Tool::Scale, "Scale");
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.show_grid, "Grid");
+                ui.checkbox(&mut self.snap, "Snap");
+                ui.add(
+                    DragValue::new(&mut self.grid_size)
+                        .speed(0.05)
+                        .range(0.1..=100.0),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.scene_path);
+                if ui.button("Save").clicked() {
+                    let sprite_manager = mod_manager.sprite_manager().read().unwrap();
+                    match self.save_scene(world, &sprite_manager, &self.scene_path) {
+                        Ok(()) => info!("Saved scene {}", self.scene_path),
+                        Err(err) => error!("{err:#}"),
+                    }
+                }
+                if ui.button("Load").clicked() {
+                    let mut sprite_manager = mod_manager.sprite_manager().write().unwrap();
+                    let path = self.scene_path.clone();
+                    match self.load_scene(world, &mut sprite_manager, path) {
+                        // Recorded edits and contacts were of another world
+                        Ok(()) => {
+                            history.clear();
+                            mod_manager.physics().write().unwrap().reset();
+                        }
+                        Err(err) => error!("{err:#}"),
+                    }
+                }
+            });
+            egui::CollapsingHeader::new("Prefabs").show(ui, |ui| {
+                if ui.button("Refresh").clicked() {
+                    self.prefabs = None;
+                }
+                let prefabs = self
+                    .prefabs
+                    .get_or_insert_with(|| mod_manager.prefab_names())
+                    .clone();
+                for name in prefabs {
+                    if ui.button(&name).clicked() {
+                        self.spawn_prefab(world, history, camera, mod_manager, &name);
+                    }
+                }
+            });
+        });
+    }
+
+    /// Spawns a prefab in the middle of the view and selects it.
+    fn spawn_prefab(
+        &mut self,
+        world: &mut World,
+        history: &mut History,
+        camera: &Camera,
+        mod_manager: &ModManager,
+        name: &str,
+    ) {
+        let mut position = camera.position;
+        if self.snap {
+            position = snap(position, self.grid_size, Axis::Both);
+        }
+        let overrides = BTreeMap::from([("position".into(), ComponentData::Vector(position))]);
+        let before: HashSet<Entity> = world
+            .get_all_entities()
+            .into_iter()
+            .map(|(entity, _)| entity)
+            .collect();
+        match mod_manager.spawn_prefab(world, name, &overrides) {
+            Ok(entity) => self.selection = vec![entity],
+            Err(err) => error!("{err:#}"),
+        }
+        // The prefab spawns its children too, they go in the same transaction
+        history.begin(format!("Spawn {name}"));
+        for (entity, _) in world.get_all_entities() {
+            if !before.contains(&entity) {
+                history.spawned(world, entity);
+            }
+        }
+        history.commit();
+    }
+
+    /// Selection, gizmo drags, panning with the middle button and deleting.
+    fn viewport(
+        &mut self,
+        world: &mut World,
+        history: &mut History,
+        cameras: &mut Cameras,
+        context: &Context,
+    ) {
+        let screen_size = cameras.screen_size();
+        let camera = cameras.main_camera_mut();
+        let pixels_per_point = context.pixels_per_point();
+        if context.input(|input| input.pointer.button_down(PointerButton::Middle)) {
+            let delta = context.input(|input| input.pointer.delta()) * pixels_per_point;
+            let size = (camera.viewport.size * screen_size).max(Vec2::ONE);
+            camera.position -= Vec2::new(delta.x, delta.y) / size * 2.0 * camera.half_extent();
+        }
+        if !context.wants_keyboard_input() && context.input(|input| input.key_pressed(Key::Delete))
+        {
+            history.begin("Delete");
+            for entity in self.selection.drain(..) {
+                history.despawn(world, entity);
+            }
+            history.commit();
+        }
+
+        let Some(pointer) = context.input(|input| input.pointer.hover_pos()) else {
+            return;
+        };
+        let point = camera.screen_to_world(
+            Vec2::new(pointer.x, pointer.y) * pixels_per_point,
+            screen_size,
+        );
+        if self.drag.is_some() {
+            if context.input(|input| input.pointer.primary_down()) {
+                self.drag_to(world, history, point);
+            } else {
+                self.drag = None;
+                history.seal();
+            }
+            return;
+        }
+        if !context.input(|input| input.pointer.primary_pressed()) || context.is_pointer_over_area()
+        {
+            return;
+        }
+        let gizmo = self.gizmo(world, camera, screen_size, pixels_per_point);
+        let handle = gizmo.and_then(|center| handle_at(center, pointer));
+        let axis = match (handle, self.entity_at(world, point)) {
+            (Some(axis), _) => axis,
+            (None, Some(entity)) if context.input(|input| input.modifiers.shift) => {
+                match self
+                    .selection
+                    .iter()
+                    .position(|selected| *selected == entity)
+                {
+                    Some(index) => {
+                        self.selection.remove(index);
+                    }
+                    None => self.selection.push(entity),
+                }
+                return;
+            }
+            (None, Some(entity)) => {
+                if !self.selection.contains(&entity) {
+                    self.selection = vec![entity];
+                }
+                Axis::Both
+            }
+            (None, None) => {
+                if !context.input(|input| input.modifiers.shift) {
+                    self.selection.clear();
+                }
+                return;
+            }
+        };
+        let (component, default) = self.dragged_component();
+        self.drag = Some(Drag {
+            axis,
+            start: point,
+            values: self
+                .selection
+                .iter()
+                .map(|entity| (*entity, vector(world, *entity, component, default)))
+                .collect(),
+        });
+    }
+
+    /// Component the tool changes and its value on entities without it.
+    fn dragged_component(&self) -> (ComponentID, Vec2) {
+        match self.tool {
+            Tool::Move => (self.pos_component, Vec2::ZERO),
+            Tool::Scale => (self.scale_component, Vec2::ONE),
+        }
+    }
+
+    /// Every frame of a drag merges into one transaction, sealed on release.
+    fn drag_to(&self, world: &mut World, history: &mut History, point: Vec2) {
+        let Some(drag) = self.drag.as_ref() else {
+            return;
+        };
+        let (component, _) = self.dragged_component();
+        let delta = constrain(point - drag.start, drag.axis);
+        let name = match self.tool {
+            Tool::Move => "Move",
+            Tool::Scale => "Scale",
+        };
+        history.begin_merged(name, Id::new("editor drag").value());
+        for (entity, start) in drag.values.iter() {
+            let mut value = *start + delta;
+            if self.snap {
+                value = snap(value, self.grid_size, drag.axis);
+            }
+            history.set_component(world, *entity, (component, ComponentData::Vector(value)));
+        }
+        history.commit();
+    }
+
+    /// Screen position of the gizmo on the last selected entity, in points.
+    fn gizmo(
+        &self,
+        world: &World,
+        camera: &Camera,
+        screen_size: Vec2,
+        pixels_per_point: f32,
+    ) -> Option<Pos2> {
+        let entity = *self.selection.last()?;
+        let position = vector(world, entity, self.pos_component, Vec2::ZERO);
+        Some(to_screen(camera, screen_size, pixels_per_point, position))
+    }
+
+    fn draw(&self, world: &World, cameras: &Cameras, context: &Context) {
+        let painter = context.layer_painter(LayerId::background());
+        let camera = cameras.main_camera();
+        let screen_size = cameras.screen_size();
+        let pixels_per_point = context.pixels_per_point();
+        let to_screen = |point| to_screen(camera, screen_size, pixels_per_point, point);
+        if self.show_grid {
+            self.draw_grid(&painter, camera, &to_screen);
+        }
+        for entity in self.selection.iter() {
+            let position = vector(world, *entity, self.pos_component, Vec2::ZERO);
+            let half_scale = vector(world, *entity, self.scale_component, Vec2::ONE).abs() / 2.0;
+            let bounds = Rect::from_two_pos(
+                to_screen(position - half_scale),
+                to_screen(position + half_scale),
+            );
+            painter.rect_stroke(bounds, 0.0, Stroke::new(1.5, Color32::YELLOW));
+        }
+        if self.mode != EditorMode::Editing {
+            return;
+        }
+        let Some(center) = self.gizmo(world, camera, screen_size, pixels_per_point) else {
+            return;
+        };
+        for (axis, color) in [(Axis::X, Color32::RED), (Axis::Y, Color32::GREEN)] {
+            let end = handle_position(center, axis);
+            painter.line_segment([center, end], Stroke::new(2.0, color));
+            match self.tool {
+                Tool::Move => {
+                    painter.circle_filled(end, 5.0, color);
+                }
+                Tool::Scale => {
+                    let square = Rect::from_center_size(end, egui::vec2(10.0, 10.0));
+                    painter.rect_filled(square, 0.0, color);
+                }
+            }
+        }
+        let free = Rect::from_center_size(center, egui::vec2(12.0, 12.0));
+        painter.rect_stroke(free, 0.0, Stroke::new(2.0, Color32::WHITE));
+    }
+
+    fn draw_grid(&self, painter: &Painter, camera: &Camera, to_screen: &impl Fn(Vec2) -> Pos2) {
+        let grid_size = self.grid_size.max(0.1);
+        let min = camera.view_position() - camera.half_extent();
+        let max = camera.view_position() + camera.half_extent();
+        if (max - min).max_element() / grid_size > MAX_GRID_LINES {
+            return;
+        }
+        let stroke = Stroke::new(1.0, Color32::from_white_alpha(30));
+        let first = (min / grid_size).floor() * grid_size;
+        let mut x = first.x;
+        while x <= max.x {
+            painter.line_segment(
+                [
+                    to_screen(Vec2::new(x, min.y)),
+                    to_screen(Vec2::new(x, max.y)),
+                ],
+                stroke,
+            );
+            x += grid_size;
+        }
+        let mut y = first.y;
+        while y <= max.y {
+            painter.line_segment(
+                [
+                    to_screen(Vec2::new(min.x, y)),
+                    to_screen(Vec2::new(max.x, y)),
+                ],
+                stroke,
+            );
+            y += grid_size;
+        }
+    }
+}
+
+fn vector(world: &World, entity: Entity, component: ComponentID, default: Vec2) -> Vec2 {
+    match world.get_component(entity, component) {
+        Some(ComponentData::Vector(vector)) => *vector,
+        _ => default,
+    }
+}
+
+fn to_screen(camera: &Camera, screen_size: Vec2, pixels_per_point: f32, point: Vec2) -> Pos2 {
+    let pixel = camera.world_to_screen(point, screen_size) / pixels_per_point;
+    Pos2::new(pixel.x, pixel.y)
+}
+
+fn handle_position(center: Pos2, axis: Axis) -> Pos2 {
+    match axis {
+        Axis::Both => center,
+        Axis::X => center + egui::vec2(GIZMO_LENGTH, 0.0),
+        Axis::Y => center + egui::vec2(0.0, GIZMO_LENGTH),
+    }
+}
+
+/// Gizmo handle under `pointer`, the axis ends win over the middle.
+fn handle_at(center: Pos2, pointer: Pos2) -> Option<Axis> {
+    [Axis::X, Axis::Y, Axis::Both]
+        .into_iter()
+        .find(|axis| handle_position(center, *axis).distance(pointer) <= HANDLE_RADIUS)
+}
+
+fn constrain(delta: Vec2, axis: Axis) -> Vec2 {
+    match axis {
+        Axis::Both => delta,
+        Axis::X => Vec2::new(delta.x, 0.0),
+        Axis::Y => Vec2::new(0.0, delta.y),
+    }
+}
+
+/// Rounds the coordinates on `axis` to the grid, the others stay as they are.
+fn snap(value: Vec2, grid_size: f32, axis: Axis) -> Vec2 {
+    let snapped = (value / grid_size).round() * grid_size;
+    match axis {
+        Axis::Both => snapped,
+        Axis::X => Vec2::new(snapped.x, value.y),
+        Axis::Y => Vec2::new(value.x, snapped.y),
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use zurie_shared::loader::AssetLoader;
+
+    fn editor(world: &mut World) -> Editor {
+        let [position, scale, sprite, tilemap] = ["position", "scale", "sprite", "tilemap"]
+            .map(|name| world.register_component(name.into()));
+        Editor::new(position, scale, sprite, tilemap)
+    }
+
+    #[test]
+    fn test_snap_only_dragged_axis() {
+        let start = Vec2::new(0.3, 0.7);
+        let delta = constrain(Vec2::new(1.4, 5.0), Axis::X);
+        assert_eq!(snap(start + delta, 0.5, Axis::X), Vec2::new(1.5, 0.7));
+        assert_eq!(snap(start, 0.5, Axis::Both), Vec2::new(0.5, 0.5));
+    }
+
+    #[test]
+    fn test_handles() {
+        let center = Pos2::new(100.0, 100.0);
+        assert_eq!(handle_at(center, Pos2::new(158.0, 102.0)), Some(Axis::X));
+        assert_eq!(handle_at(center, Pos2::new(100.0, 165.0)), Some(Axis::Y));
+        assert_eq!(handle_at(center, Pos2::new(96.0, 100.0)), Some(Axis::Both));
+        assert_eq!(handle_at(center, Pos2::new(130.0, 130.0)), None);
+    }
+
+    #[test]
+    fn test_play_and_stop_restore_world() {
+        let mut world = World::default();
+        let mut editor = editor(&mut world);
+        let mut physics = Physics::new(&mut world);
+        let entity = world.spawn_entity();
+        world.set_component(
+            entity,
+            (editor.pos_component, ComponentData::Vector(Vec2::ONE)),
+        );
+        let data = world.get_entity_data(entity).cloned();
+        assert_eq!(editor.entity_at(&world, Vec2::new(1.4, 0.6)), Some(entity));
+        assert_eq!(editor.entity_at(&world, Vec2::new(1.6, 1.0)), None);
+
+        editor.toggle();
+        assert!(!editor.runs_game());
+        let mut history = History::default();
+        history.set_component(
+            &mut world,
+            entity,
+            (editor.scale_component, ComponentData::I32(1)),
+        );
+        editor.play(&world, &mut history);
+        assert!(editor.runs_game());
+        assert!(!history.can_undo());
+        history.despawn(&mut world, entity);
+        let spawned = world.spawn_entity();
+        let respawned = editor.stop(&mut world, &mut history, &mut physics);
+        assert_eq!(editor.mode(), EditorMode::Editing);
+        // Despawned while playing, so it's back with a new id and the id spawned while
+        // playing stays dead
+        let (restored, _) = world.get_all_entities()[0];
+        assert_ne!(restored, entity);
+        assert_eq!(respawned.get(&entity), Some(&restored));
+        assert!(world.get_entity_data(spawned).is_none());
+        assert_ne!(world.spawn_entity(), spawned);
+        assert_eq!(history.undo_name(), Some("Set component"));
+        history.undo(&mut world);
+        assert_eq!(world.get_entity_data(restored).cloned(), data);
+    }
+
+    #[test]
+    fn test_scene_names_sprite_files() {
+        let mut sprite_manager =
+            SpriteManager::headless(Context::default(), AssetLoader::default());
+        let file = LoadSpriteInfo::Path(Box::from(Path::new("static/player.aseprite")));
+        let sprite = ComponentData::Sprite(KeyData::as_ffi(
+            sprite_manager.push_to_load_queue(file).data(),
+        ));
+        let mut world = World::default();
+        let editor = editor(&mut world);
+        let entity = world.spawn_entity();
+        world.set_component(entity, (editor.sprite_component, sprite.clone()));
+        let path = std::env::temp_dir().join("zurie_scene_test.ron");
+        editor.save_scene(&world, &sprite_manager, &path).unwrap();
+        assert!(
+            std::fs::read_to_string(&path)
+                .unwrap()
+                .contains("player.aseprite")
+        );
+
+        // Another run registers its components in another order
+        let mut other = World::default();
+        other.register_component("health".into());
+        let mut other_editor = self::editor(&mut other);
+        other_editor
+            .load_scene(&mut other, &mut sprite_manager, &path)
+            .unwrap();
+        let sprite_component = other_editor.sprite_component;
+        assert_ne!(sprite_component, editor.sprite_component);
+        let (loaded, _) = other.get_all_entities()[0];
+        assert_eq!(other.get_component(loaded, sprite_component), Some(&sprite));
+    }
+}
@@ -0,0 +1,309 @@
+use std::time::Duration;
+
+use egui::{
+    Align2, CollapsingHeader, Color32, Context, FontId, Grid, Key, Modifiers, Pos2, Sense, Shape,
+    Stroke, Ui, Vec2,
+};
+use zurie_shared::profiler::{FrameProfile, HISTORY, HostCategory, ModFrame, Profiler, Stage};
+
+const GRAPH_HEIGHT: f32 = 80.0;
+/// Colors of the graph lines, used in turn.
+const COLORS: [Color32; 8] = [
+    Color32::from_rgb(251, 73, 52),
+    Color32::from_rgb(184, 187, 38),
+    Color32::from_rgb(250, 189, 47),
+    Color32::from_rgb(131, 165, 152),
+    Color32::from_rgb(211, 134, 155),
+    Color32::from_rgb(142, 192, 124),
+    Color32::from_rgb(254, 128, 25),
+    Color32::from_rgb(235, 219, 178),
+];
+
+/// A graph line, its values are per frame, oldest first.
+struct Series {
+    name: String,
+    values: Vec<f32>,
+}
+
+/// Frame time broken down by engine stage and mod, F3 shows and hides it.
+#[derive(Default)]
+pub struct ProfilerWindow {
+    open: bool,
+    /// Frames shown while paused.
+    paused: Option<Vec<FrameProfile>>,
+}
+
+impl ProfilerWindow {
+    pub fn show(&mut self, context: &Context, profiler: &Profiler) {
+        if context.input_mut(|input| input.consume_key(Modifiers::NONE, Key::F3)) {
+            self.open = !self.open;
+        }
+        if !self.open {
+            return;
+        }
+        let frames: Vec<FrameProfile> = match &self.paused {
+            Some(frames) => frames.clone(),
+            None => profiler.frames().iter().cloned().collect(),
+        };
+        let mut open = self.open;
+        egui::Window::new("Profiler")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(context, |ui| {
+                ui.horizontal(|ui| {
+                    let (average, max) = stats(&frames, |frame| millis(frame.time));
+                    let fps = if average > 0.0 { 1000.0 / average } else { 0.0 };
+                    ui.label(format!(
+                        "Frame {average:.2} ms, slowest {max:.2} ms, {fps:.0} fps"
+                    ));
+                    let mut paused = self.paused.is_some();
+                    if ui.checkbox(&mut paused, "Pause").changed() {
+                        self.paused = paused.then(|| frames.clone());
+                    }
+                });
+                CollapsingHeader::new("Frame")
+                    .default_open(true)
+                    .show(ui, |ui| frame_section(ui, &frames));
+                CollapsingHeader::new("Mods")
+                    .default_open(true)
+                    .show(ui, |ui| mods_section(ui, &frames));
+            });
+        self.open = open;
+    }
+}
+
+fn frame_section(ui: &mut Ui, frames: &[FrameProfile]) {
+    let mut series = vec![Series {
+        name: "frame".into(),
+        values: frames.iter().map(|frame| millis(frame.time)).collect(),
+    }];
+    series.extend(Stage::ALL.iter().map(|stage| {
+        Series {
+            name: stage.name().into(),
+            values: frames
+                .iter()
+                .map(|frame| millis(frame.stage(*stage)))
+                .collect(),
+        }
+    }));
+    graph(ui, &series, "ms");
+    Grid::new("profiler stages").striped(true).show(ui, |ui| {
+        ui.strong("Stage");
+        ui.strong("Last ms");
+        ui.strong("Average ms");
+        ui.strong("Slowest ms");
+        ui.end_row();
+        for Series { name, values } in series.iter() {
+            let (average, max) = average_and_max(values);
+            ui.label(name);
+            ui.label(format!("{:.2}", values.last().copied().unwrap_or_default()));
+            ui.label(format!("{average:.2}"));
+            ui.label(format!("{max:.2}"));
+            ui.end_row();
+        }
+    });
+}
+
+fn mods_section(ui: &mut Ui, frames: &[FrameProfile]) {
+    let names = mod_names(frames);
+    if names.is_empty() {
+        ui.label("No mods ran");
+        return;
+    }
+    let series: Vec<Series> = names
+        .iter()
+        .map(|name| Series {
+            name: name.clone(),
+            values: mod_values(frames, name, |frame| millis(frame.time())),
+        })
+        .collect();
+    graph(ui, &series, "ms");
+    // Averages per frame, calls are host calls
+    Grid::new("profiler mods").striped(true).show(ui, |ui| {
+        for heading in [
+            "Mod",
+            "Update ms",
+            "Events ms",
+            "Keys ms",
+            "Calls",
+            "Host ms",
+        ] {
+            ui.strong(heading);
+        }
+        ui.end_row();
+        for name in names.iter() {
+            let average =
+                |value: fn(&ModFrame) -> f32| average_and_max(&mod_values(frames, name, value)).0;
+            ui.label(name);
+            ui.label(format!("{:.2}", average(|frame| millis(frame.update.time))));
+            ui.label(format!("{:.2}", average(|frame| millis(frame.event.time))));
+            ui.label(format!(
+                "{:.2}",
+                average(|frame| millis(frame.key_event.time))
+            ));
+            ui.label(format!("{:.0}", average(|frame| frame.host_calls() as f32)));
+            ui.label(format!("{:.2}", average(|frame| millis(frame.host_time()))));
+            ui.end_row();
+        }
+    });
+    for name in names.iter() {
+        CollapsingHeader::new(format!("{name} host calls"))
+            .id_salt(("profiler host calls", name))
+            .show(ui, |ui| host_section(ui, frames, name));
+    }
+}
+
+/// Host calls of the mod named `name` by interface, the busiest first.
+fn host_section(ui: &mut Ui, frames: &[FrameProfile], name: &str) {
+    let mut categories: Vec<(HostCategory, f32, f32)> = HostCategory::ALL
+        .iter()
+        .map(|category| {
+            let calls = mod_values(frames, name, |frame| frame.host(*category).calls as f32);
+            let time = mod_values(frames, name, |frame| millis(frame.host(*category).time));
+            let (calls, time) = (average_and_max(&calls).0, average_and_max(&time).0);
+            (*category, calls, time)
+        })
+        .filter(|(_, calls, _)| *calls > 0.0)
+        .collect();
+    categories.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let series: Vec<Series> = categories
+        .iter()
+        .map(|(category, _, _)| Series {
+            name: category.name().into(),
+            values: mod_values(frames, name, |frame| frame.host(*category).calls as f32),
+        })
+        .collect();
+    graph(ui, &series, "calls");
+    Grid::new(("profiler host", name))
+        .striped(true)
+        .show(ui, |ui| {
+            ui.strong("Interface");
+            ui.strong("Calls per frame");
+            ui.strong("ms per frame");
+            ui.end_row();
+            for (category, calls, time) in categories {
+                ui.label(category.name());
+                ui.label(format!("{calls:.1}"));
+                ui.label(format!("{time:.3}"));
+                ui.end_row();
+            }
+        });
+}
+
+/// Lines of the series over the last `HISTORY` frames, scaled to the largest value.
+fn graph(ui: &mut Ui, series: &[Series], unit: &str) {
+    let size = Vec2::new(ui.available_width(), GRAPH_HEIGHT);
+    let (response, painter) = ui.allocate_painter(size, Sense::hover());
+    let rect = response.rect;
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    let max = series
+        .iter()
+        .flat_map(|series| series.values.iter().copied())
+        .fold(0.0, f32::max);
+    let step = rect.width() / (HISTORY - 1) as f32;
+    for (series, color) in series.iter().zip(COLORS.iter().cycle()) {
+        // The newest frame is at the right edge
+        let start = rect.right() - step * (series.values.len().max(1) - 1) as f32;
+        let points: Vec<Pos2> = series
+            .values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| {
+                let height = if max > 0.0 { value / max } else { 0.0 };
+                Pos2::new(
+                    start + step * index as f32,
+                    rect.bottom() - rect.height() * height,
+                )
+            })
+            .collect();
+        painter.add(Shape::line(points, Stroke::new(1.5, *color)));
+    }
+    painter.text(
+        rect.left_top() + Vec2::splat(4.0),
+        Align2::LEFT_TOP,
+        format!("{max:.2} {unit}"),
+        FontId::monospace(10.0),
+        ui.visuals().text_color(),
+    );
+    ui.horizontal_wrapped(|ui| {
+        for (series, color) in series.iter().zip(COLORS.iter().cycle()) {
+            ui.colored_label(*color, &series.name);
+        }
+    });
+}
+
+fn millis(time: Duration) -> f32 {
+    time.as_secs_f32() * 1000.0
+}
+
+fn average_and_max(values: &[f32]) -> (f32, f32) {
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+    let average = values.iter().sum::<f32>() / values.len() as f32;
+    (average, values.iter().copied().fold(0.0, f32::max))
+}
+
+fn stats(frames: &[FrameProfile], value: impl Fn(&FrameProfile) -> f32) -> (f32, f32) {
+    average_and_max(&frames.iter().map(value).collect::<Vec<f32>>())
+}
+
+/// Mods that ran in any of the frames, in the order they first ran.
+fn mod_names(frames: &[FrameProfile]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for (name, _) in frames.iter().flat_map(|frame| frame.mods.iter()) {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+    names
+}
+
+/// `value` of the mod named `name` in every frame, 0 in frames it didn't run in.
+fn mod_values(frames: &[FrameProfile], name: &str, value: impl Fn(&ModFrame) -> f32) -> Vec<f32> {
+    frames
+        .iter()
+        .map(|frame| frame.mod_frame(name).map_or(0.0, &value))
+        .collect()
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    fn frame(mods: &[(&str, u32)]) -> FrameProfile {
+        let mut frame = FrameProfile {
+            time: Duration::from_millis(16),
+            ..Default::default()
+        };
+        for (name, ecs_calls) in mods {
+            let mut mod_frame = ModFrame::default();
+            for _ in 0..*ecs_calls {
+                mod_frame
+                    .host_mut(HostCategory::Ecs)
+                    .add(Duration::from_micros(5));
+            }
+            frame.mods.push((name.to_string(), mod_frame));
+        }
+        frame
+    }
+
+    #[test]
+    fn test_mod_values() {
+        let frames = vec![
+            frame(&[("demo", 30)]),
+            frame(&[("demo", 10), ("late", 4)]),
+            frame(&[]),
+        ];
+        assert_eq!(mod_names(&frames), vec!["demo", "late"]);
+        let calls = mod_values(&frames, "demo", |frame| frame.host_calls() as f32);
+        assert_eq!(calls, vec![30.0, 10.0, 0.0]);
+        let (average, max) = average_and_max(&calls);
+        assert!((average - 40.0 / 3.0).abs() < 1e-4);
+        assert_eq!(max, 30.0);
+        let late = mod_values(&frames, "late", |frame| frame.host_calls() as f32);
+        assert_eq!(late, vec![0.0, 4.0, 0.0]);
+        assert_eq!(stats(&frames, |frame| millis(frame.time)), (16.0, 16.0));
+        assert_eq!(average_and_max(&[]), (0.0, 0.0));
+    }
+}
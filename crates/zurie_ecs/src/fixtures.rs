@@ -0,0 +1,9 @@
+use crate::{ComponentID, World};
+
+/// World with a position and a health component registered, in that order.
+pub fn world() -> (World, ComponentID, ComponentID) {
+    let mut world = World::default();
+    let position = world.register_component("position".into());
+    let health = world.register_component("health".into());
+    (world, position, health)
+}
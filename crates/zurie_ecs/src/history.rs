@@ -0,0 +1,780 @@
+use hashbrown::HashMap;
+use zurie_types::ComponentData;
+
+use crate::{ComponentID, Entity, EntityData, World};
+
+/// Transactions `History::default` keeps to undo, older ones are dropped.
+pub const DEFAULT_LIMIT: usize = 256;
+
+/// A change to a `World` with what it takes to revert it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Spawn {
+        entity: Entity,
+        data: EntityData,
+    },
+    Despawn {
+        entity: Entity,
+        data: EntityData,
+    },
+    SetComponent {
+        entity: Entity,
+        component: ComponentID,
+        data: ComponentData,
+        previous: Option<ComponentData>,
+    },
+    RemoveComponent {
+        entity: Entity,
+        component: ComponentID,
+        previous: ComponentData,
+        /// Where it was in the entity data, undo puts it back there.
+        index: usize,
+    },
+}
+
+impl Command {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Spawn { .. } => "Spawn",
+            Command::Despawn { .. } => "Despawn",
+            Command::SetComponent { .. } => "Set component",
+            Command::RemoveComponent { .. } => "Remove component",
+        }
+    }
+
+    pub fn entity(&self) -> Entity {
+        match self {
+            Command::Spawn { entity, .. }
+            | Command::Despawn { entity, .. }
+            | Command::SetComponent { entity, .. }
+            | Command::RemoveComponent { entity, .. } => *entity,
+        }
+    }
+
+    fn entity_mut(&mut self) -> &mut Entity {
+        match self {
+            Command::Spawn { entity, .. }
+            | Command::Despawn { entity, .. }
+            | Command::SetComponent { entity, .. }
+            | Command::RemoveComponent { entity, .. } => entity,
+        }
+    }
+
+    /// Whether the command changes `component` of `entity`, spawns and despawns change all.
+    fn touches(&self, entity: Entity, component: ComponentID) -> bool {
+        match self {
+            Command::Spawn { .. } | Command::Despawn { .. } => self.entity() == entity,
+            Command::SetComponent {
+                entity: touched,
+                component: changed,
+                ..
+            }
+            | Command::RemoveComponent {
+                entity: touched,
+                component: changed,
+                ..
+            } => *touched == entity && *changed == component,
+        }
+    }
+
+    /// Does the command again, returning the new id of an entity it had to spawn.
+    fn apply(&self, world: &mut World) -> Option<Entity> {
+        match self {
+            Command::Spawn { data, .. } => return Some(world.spawn_entity_with_data(data.clone())),
+            Command::Despawn { entity, .. } => world.despawn(*entity),
+            Command::SetComponent {
+                entity,
+                component,
+                data,
+                ..
+            } => world.set_component(*entity, (*component, data.clone())),
+            Command::RemoveComponent {
+                entity, component, ..
+            } => world.remove_component(*entity, *component),
+        }
+        None
+    }
+
+    /// Undoes the command, returning the new id of an entity it had to spawn.
+    fn revert(&self, world: &mut World) -> Option<Entity> {
+        match self {
+            Command::Spawn { entity, .. } => world.despawn(*entity),
+            Command::Despawn { data, .. } => {
+                return Some(world.spawn_entity_with_data(data.clone()));
+            }
+            Command::SetComponent {
+                entity,
+                component,
+                previous: Some(previous),
+                ..
+            } => world.set_component(*entity, (*component, previous.clone())),
+            Command::SetComponent {
+                entity,
+                component,
+                previous: None,
+                ..
+            } => world.remove_component(*entity, *component),
+            Command::RemoveComponent {
+                entity,
+                component,
+                previous,
+                index,
+            } => {
+                if let Some(mut entity_data) = world.get_entity_data(*entity).cloned() {
+                    let index = (*index).min(entity_data.data.len());
+                    entity_data
+                        .data
+                        .insert(index, (*component, previous.clone()));
+                    world.modify_entity(*entity, entity_data);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Commands undone and redone together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transaction {
+    pub name: String,
+    pub commands: Vec<Command>,
+    merge_key: Option<u64>,
+}
+
+impl Transaction {
+    /// Takes the commands of a later transaction in. A component set again only keeps its
+    /// newest value, the previous one is still the one from before both.
+    fn merge(&mut self, later: Transaction) {
+        for command in later.commands {
+            if let Command::SetComponent {
+                entity,
+                component,
+                data,
+                ..
+            } = &command
+            {
+                let last = self
+                    .commands
+                    .iter_mut()
+                    .rev()
+                    .find(|earlier| earlier.touches(*entity, *component));
+                if let Some(Command::SetComponent { data: earlier, .. }) = last {
+                    *earlier = data.clone();
+                    continue;
+                }
+            }
+            self.commands.push(command);
+        }
+    }
+}
+
+/// Undo and redo stacks of world edits. Edits made through it are recorded as commands in
+/// transactions, an edit outside of `begin` and `commit` is a transaction of its own.
+///
+/// Undoing a despawn or redoing a spawn brings the entity back with a new id, recorded
+/// commands follow it. Ids held elsewhere, like in component data, aren't updated.
+pub struct History {
+    undo: Vec<Transaction>,
+    redo: Vec<Transaction>,
+    open: Option<Transaction>,
+    /// Nested `begin` calls, the transaction is recorded when the outermost commits.
+    depth: usize,
+    /// The next transaction doesn't merge with the last one.
+    sealed: bool,
+    limit: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_LIMIT)
+    }
+}
+
+impl History {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            open: None,
+            depth: 0,
+            sealed: false,
+            limit: limit.max(1),
+        }
+    }
+
+    /// Groups the following edits into one transaction until `commit`. Nested calls join
+    /// the outer transaction.
+    pub fn begin(&mut self, name: impl Into<String>) {
+        self.open_transaction(name.into(), None);
+    }
+
+    /// Like `begin`, but the transaction merges into the last one when that has the same
+    /// `key` and nothing was sealed, undone or recorded in between. Meant for edits that
+    /// continue over frames, like drags.
+    pub fn begin_merged(&mut self, name: impl Into<String>, key: u64) {
+        self.open_transaction(name.into(), Some(key));
+    }
+
+    fn open_transaction(&mut self, name: String, merge_key: Option<u64>) {
+        self.depth += 1;
+        if self.open.is_none() {
+            self.open = Some(Transaction {
+                name,
+                commands: Vec::new(),
+                merge_key,
+            });
+        }
+    }
+
+    pub fn commit(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+        if self.depth == 0 {
+            self.finish();
+        }
+    }
+
+    /// Ends merging, the next transaction gets undone on its own.
+    pub fn seal(&mut self) {
+        self.sealed = true;
+    }
+
+    /// Records the open transaction, whatever the nesting.
+    fn finish(&mut self) {
+        self.depth = 0;
+        let Some(transaction) = self.open.take() else {
+            return;
+        };
+        if transaction.commands.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        let sealed = std::mem::take(&mut self.sealed);
+        match self.undo.last_mut() {
+            Some(last)
+                if !sealed
+                    && transaction.merge_key.is_some()
+                    && last.merge_key == transaction.merge_key =>
+            {
+                last.merge(transaction)
+            }
+            _ => {
+                self.undo.push(transaction);
+                if self.undo.len() > self.limit {
+                    self.undo.remove(0);
+                }
+            }
+        }
+    }
+
+    fn record(&mut self, command: Command) {
+        match self.open.as_mut() {
+            Some(transaction) => transaction.commands.push(command),
+            None => {
+                self.open = Some(Transaction {
+                    name: command.name().into(),
+                    commands: vec![command],
+                    merge_key: None,
+                });
+                self.finish();
+            }
+        }
+    }
+
+    pub fn spawn(&mut self, world: &mut World, data: EntityData) -> Entity {
+        let entity = world.spawn_entity_with_data(data.clone());
+        self.record(Command::Spawn { entity, data });
+        entity
+    }
+
+    /// Records an entity spawned without the history, like a prefab, as it is now.
+    pub fn spawned(&mut self, world: &World, entity: Entity) {
+        if let Some(data) = world.get_entity_data(entity) {
+            let data = data.clone();
+            self.record(Command::Spawn { entity, data });
+        }
+    }
+
+    pub fn despawn(&mut self, world: &mut World, entity: Entity) {
+        let Some(data) = world.get_entity_data(entity).cloned() else {
+            return;
+        };
+        world.despawn(entity);
+        self.record(Command::Despawn { entity, data });
+    }
+
+    /// Sets a component, setting the value it already has records nothing.
+    pub fn set_component(
+        &mut self,
+        world: &mut World,
+        entity: Entity,
+        new_component: (ComponentID, ComponentData),
+    ) {
+        if world.get_entity_data(entity).is_none() {
+            return;
+        }
+        let (component, data) = new_component;
+        let previous = world.get_component(entity, component).cloned();
+        if previous.as_ref() == Some(&data) {
+            return;
+        }
+        world.set_component(entity, (component, data.clone()));
+        self.record(Command::SetComponent {
+            entity,
+            component,
+            data,
+            previous,
+        });
+    }
+
+    pub fn remove_component(&mut self, world: &mut World, entity: Entity, component: ComponentID) {
+        let Some((index, previous)) = world.get_entity_data(entity).and_then(|entity_data| {
+            let index = entity_data
+                .data
+                .iter()
+                .position(|(id, _)| *id == component)?;
+            Some((index, entity_data.data[index].1.clone()))
+        }) else {
+            return;
+        };
+        world.remove_component(entity, component);
+        self.record(Command::RemoveComponent {
+            entity,
+            component,
+            previous,
+            index,
+        });
+    }
+
+    /// Reverts the last transaction, an open one is recorded first. False when there was
+    /// nothing to undo.
+    pub fn undo(&mut self, world: &mut World) -> bool {
+        self.finish();
+        let Some(mut transaction) = self.undo.pop() else {
+            return false;
+        };
+        for index in (0..transaction.commands.len()).rev() {
+            if let Some(entity) = transaction.commands[index].revert(world) {
+                self.remap(&mut transaction, index, entity);
+            }
+        }
+        self.redo.push(transaction);
+        self.sealed = true;
+        true
+    }
+
+    /// Applies the last undone transaction again. False when there was nothing to redo.
+    pub fn redo(&mut self, world: &mut World) -> bool {
+        self.finish();
+        let Some(mut transaction) = self.redo.pop() else {
+            return false;
+        };
+        for index in 0..transaction.commands.len() {
+            if let Some(entity) = transaction.commands[index].apply(world) {
+                self.remap(&mut transaction, index, entity);
+            }
+        }
+        self.undo.push(transaction);
+        self.sealed = true;
+        true
+    }
+
+    /// Points the recorded commands on entities that came back with a new id, like after
+    /// `World::restore`, to that id.
+    pub fn remap_entities(&mut self, ids: &HashMap<Entity, Entity>) {
+        let stacks = self.undo.iter_mut().chain(self.redo.iter_mut());
+        for command in stacks
+            .chain(self.open.iter_mut())
+            .flat_map(|transaction| transaction.commands.iter_mut())
+        {
+            if let Some(entity) = ids.get(&command.entity()) {
+                *command.entity_mut() = *entity;
+            }
+        }
+    }
+
+    /// Points every command on the entity of `transaction.commands[index]` to its new id.
+    fn remap(&mut self, transaction: &mut Transaction, index: usize, entity: Entity) {
+        let old = transaction.commands[index].entity();
+        let stacks = self.undo.iter_mut().chain(self.redo.iter_mut());
+        for command in stacks
+            .chain(std::iter::once(transaction))
+            .flat_map(|transaction| transaction.commands.iter_mut())
+        {
+            if command.entity() == old {
+                *command.entity_mut() = entity;
+            }
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+            || self
+                .open
+                .as_ref()
+                .is_some_and(|open| !open.commands.is_empty())
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Name of the transaction `undo` would revert.
+    pub fn undo_name(&self) -> Option<&str> {
+        self.open
+            .as_ref()
+            .filter(|open| !open.commands.is_empty())
+            .or(self.undo.last())
+            .map(|transaction| transaction.name.as_str())
+    }
+
+    pub fn redo_name(&self) -> Option<&str> {
+        self.redo
+            .last()
+            .map(|transaction| transaction.name.as_str())
+    }
+
+    /// Forgets every transaction, for when the world was replaced.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.open = None;
+        self.depth = 0;
+        self.sealed = false;
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::fixtures::world;
+    use zurie_types::glam::Vec2;
+
+    fn entity_data(position: ComponentID, health: ComponentID) -> EntityData {
+        EntityData {
+            data: vec![
+                (position, ComponentData::Vector(Vec2::ONE)),
+                (health, ComponentData::I32(100)),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_set_component_undo_redo() {
+        let (mut world, position, health) = world();
+        let mut history = History::default();
+        let entity = world.spawn_entity_with_data(entity_data(position, health));
+        history.set_component(&mut world, entity, (health, ComponentData::I32(50)));
+        let new = world.register_component("new".into());
+        history.set_component(&mut world, entity, (new, ComponentData::None));
+        assert_eq!(history.undo_name(), Some("Set component"));
+
+        assert!(history.undo(&mut world));
+        assert_eq!(world.get_component(entity, new), None);
+        assert!(history.undo(&mut world));
+        assert_eq!(
+            world.get_entity_data(entity),
+            Some(&entity_data(position, health))
+        );
+        assert!(!history.undo(&mut world));
+        assert!(!history.can_undo());
+
+        assert!(history.redo(&mut world));
+        assert!(history.redo(&mut world));
+        assert!(!history.redo(&mut world));
+        assert_eq!(
+            world.get_component(entity, health),
+            Some(&ComponentData::I32(50))
+        );
+        assert_eq!(world.get_component(entity, new), Some(&ComponentData::None));
+    }
+
+    #[test]
+    fn test_unchanged_and_missing_record_nothing() {
+        let (mut world, position, health) = world();
+        let mut history = History::default();
+        let entity = world.spawn_entity_with_data(entity_data(position, health));
+        history.set_component(&mut world, entity, (health, ComponentData::I32(100)));
+        let new = world.register_component("new".into());
+        history.remove_component(&mut world, entity, new);
+        world.despawn(entity);
+        history.set_component(&mut world, entity, (health, ComponentData::I32(1)));
+        history.despawn(&mut world, entity);
+        history.begin("Nothing");
+        history.commit();
+        assert!(!history.can_undo());
+        assert!(!history.undo(&mut world));
+    }
+
+    #[test]
+    fn test_remove_component_keeps_order() {
+        let (mut world, position, health) = world();
+        let mut history = History::default();
+        let entity = world.spawn_entity_with_data(entity_data(position, health));
+        history.remove_component(&mut world, entity, position);
+        assert_eq!(world.get_component(entity, position), None);
+
+        history.undo(&mut world);
+        assert_eq!(
+            world.get_entity_data(entity),
+            Some(&entity_data(position, health))
+        );
+        history.redo(&mut world);
+        assert_eq!(world.get_component(entity, position), None);
+        assert_eq!(
+            world.get_component(entity, health),
+            Some(&ComponentData::I32(100))
+        );
+    }
+
+    #[test]
+    fn test_despawn_undo_follows_new_id() {
+        let (mut world, position, health) = world();
+        let mut history = History::default();
+        let entity = history.spawn(&mut world, entity_data(position, health));
+        history.set_component(&mut world, entity, (health, ComponentData::I32(10)));
+        history.despawn(&mut world, entity);
+        assert_eq!(world.get_all_entities().len(), 0);
+
+        history.undo(&mut world);
+        let (back, data) = world.get_all_entities()[0];
+        assert_ne!(back, entity);
+        assert_eq!(data.data[1], (health, ComponentData::I32(10)));
+        // The set is undone on the entity that came back
+        history.undo(&mut world);
+        assert_eq!(
+            world.get_component(back, health),
+            Some(&ComponentData::I32(100))
+        );
+        history.undo(&mut world);
+        assert!(world.get_entity_data(back).is_none());
+        assert_eq!(world.get_all_entities().len(), 0);
+
+        for _ in 0..3 {
+            history.redo(&mut world);
+        }
+        assert_eq!(world.get_all_entities().len(), 0);
+        history.undo(&mut world);
+        let (again, data) = world.get_all_entities()[0];
+        assert_ne!(again, back);
+        assert_eq!(data.data[1], (health, ComponentData::I32(10)));
+    }
+
+    #[test]
+    fn test_spawn_redo_follows_new_id() {
+        let (mut world, position, health) = world();
+        let mut history = History::default();
+        history.begin("Spawn player");
+        let entity = history.spawn(&mut world, EntityData::default());
+        history.set_component(
+            &mut world,
+            entity,
+            (position, ComponentData::Vector(Vec2::X)),
+        );
+        history.commit();
+        history.set_component(&mut world, entity, (health, ComponentData::I32(3)));
+
+        history.undo(&mut world);
+        history.undo(&mut world);
+        assert_eq!(world.get_all_entities().len(), 0);
+        history.redo(&mut world);
+        history.redo(&mut world);
+        let (respawned, data) = world.get_all_entities()[0];
+        assert_ne!(respawned, entity);
+        assert_eq!(
+            data.data,
+            vec![
+                (position, ComponentData::Vector(Vec2::X)),
+                (health, ComponentData::I32(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_restore_follows_new_id() {
+        let (mut world, position, health) = world();
+        let mut history = History::default();
+        let entity = world.spawn_entity_with_data(entity_data(position, health));
+        let snapshot = world.snapshot();
+        history.set_component(&mut world, entity, (health, ComponentData::I32(5)));
+        world.despawn(entity);
+
+        let respawned = world.restore(&snapshot);
+        history.remap_entities(&respawned);
+        let entity = respawned[&entity];
+        assert!(history.undo(&mut world));
+        assert_eq!(
+            world.get_component(entity, health),
+            Some(&ComponentData::I32(100))
+        );
+        assert!(history.redo(&mut world));
+        assert_eq!(
+            world.get_component(entity, health),
+            Some(&ComponentData::I32(5))
+        );
+    }
+
+    #[test]
+    fn test_transactions_undo_together() {
+        let (mut world, position, health) = world();
+        let mut history = History::default();
+        let first = world.spawn_entity_with_data(entity_data(position, health));
+        let second = world.spawn_entity_with_data(entity_data(position, health));
+        history.begin("Damage all");
+        for entity in [first, second] {
+            history.begin("Damage");
+            history.set_component(&mut world, entity, (health, ComponentData::I32(0)));
+            history.commit();
+        }
+        assert!(!history.undo.iter().any(|t| t.name == "Damage"));
+        history.commit();
+        assert_eq!(history.undo_name(), Some("Damage all"));
+
+        history.undo(&mut world);
+        for entity in [first, second] {
+            assert_eq!(
+                world.get_component(entity, health),
+                Some(&ComponentData::I32(100))
+            );
+        }
+        assert_eq!(history.redo_name(), Some("Damage all"));
+        history.redo(&mut world);
+        for entity in [first, second] {
+            assert_eq!(
+                world.get_component(entity, health),
+                Some(&ComponentData::I32(0))
+            );
+        }
+    }
+
+    #[test]
+    fn test_drag_merges_until_sealed() {
+        let (mut world, position, health) = world();
+        let mut history = History::default();
+        let entity = world.spawn_entity_with_data(entity_data(position, health));
+        let drag = |history: &mut History, world: &mut World, key, x| {
+            history.begin_merged("Move", key);
+            let data = ComponentData::Vector(Vec2::new(x, 1.0));
+            history.set_component(world, entity, (position, data));
+            history.commit();
+        };
+        for x in 2..10 {
+            drag(&mut history, &mut world, 1, x as f32);
+        }
+        assert_eq!(history.undo.len(), 1);
+        assert_eq!(history.undo[0].commands.len(), 1);
+        history.seal();
+        drag(&mut history, &mut world, 1, 20.0);
+        drag(&mut history, &mut world, 2, 30.0);
+        assert_eq!(history.undo.len(), 3);
+
+        history.undo(&mut world);
+        history.undo(&mut world);
+        assert_eq!(
+            world.get_component(entity, position),
+            Some(&ComponentData::Vector(Vec2::new(9.0, 1.0)))
+        );
+        // Undo seals, so the next drag doesn't join the first
+        drag(&mut history, &mut world, 1, 40.0);
+        assert_eq!(history.undo.len(), 2);
+        history.undo(&mut world);
+        history.undo(&mut world);
+        assert_eq!(
+            world.get_entity_data(entity),
+            Some(&entity_data(position, health))
+        );
+    }
+
+    #[test]
+    fn test_merge_keeps_other_commands() {
+        let (mut world, position, health) = world();
+        let mut history = History::default();
+        let entity = world.spawn_entity_with_data(entity_data(position, health));
+        history.begin_merged("Edit", 7);
+        history.set_component(&mut world, entity, (health, ComponentData::I32(1)));
+        history.commit();
+        history.begin_merged("Edit", 7);
+        history.remove_component(&mut world, entity, health);
+        history.set_component(&mut world, entity, (health, ComponentData::I32(2)));
+        history.commit();
+        assert_eq!(history.undo[0].commands.len(), 3);
+
+        history.undo(&mut world);
+        assert_eq!(
+            world.get_entity_data(entity),
+            Some(&entity_data(position, health))
+        );
+    }
+
+    #[test]
+    fn test_new_edit_clears_redo() {
+        let (mut world, position, health) = world();
+        let mut history = History::default();
+        let entity = world.spawn_entity_with_data(entity_data(position, health));
+        history.set_component(&mut world, entity, (health, ComponentData::I32(1)));
+        history.undo(&mut world);
+        assert!(history.can_redo());
+        history.set_component(&mut world, entity, (health, ComponentData::I32(2)));
+        assert!(!history.can_redo());
+        assert!(!history.redo(&mut world));
+        assert_eq!(
+            world.get_component(entity, health),
+            Some(&ComponentData::I32(2))
+        );
+    }
+
+    #[test]
+    fn test_limit_drops_oldest() {
+        let (mut world, position, health) = world();
+        let mut history = History::new(3);
+        let entity = world.spawn_entity_with_data(entity_data(position, health));
+        for value in 0..5 {
+            history.set_component(&mut world, entity, (health, ComponentData::I32(value)));
+        }
+        let mut undone = 0;
+        while history.undo(&mut world) {
+            undone += 1;
+        }
+        assert_eq!(undone, 3);
+        assert_eq!(
+            world.get_component(entity, health),
+            Some(&ComponentData::I32(1))
+        );
+    }
+
+    #[test]
+    fn test_undo_records_open_transaction() {
+        let (mut world, position, health) = world();
+        let mut history = History::default();
+        let entity = world.spawn_entity_with_data(entity_data(position, health));
+        history.begin("Unfinished");
+        history.set_component(&mut world, entity, (health, ComponentData::I32(1)));
+        assert_eq!(history.undo_name(), Some("Unfinished"));
+        assert!(history.undo(&mut world));
+        assert_eq!(
+            world.get_component(entity, health),
+            Some(&ComponentData::I32(100))
+        );
+        // The commit that was still to come changes nothing
+        history.commit();
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn test_undo_updates_spatial_index() {
+        let (mut world, position, health) = world();
+        world.index_component(position, 4.0);
+        let mut history = History::default();
+        let entity = history.spawn(&mut world, entity_data(position, health));
+        let far = ComponentData::Vector(Vec2::splat(50.0));
+        history.set_component(&mut world, entity, (position, far));
+        let filter = Default::default();
+        assert!(world.query_radius(Vec2::ONE, 0.5, &filter).is_empty());
+
+        history.undo(&mut world);
+        assert_eq!(world.query_radius(Vec2::ONE, 0.5, &filter), vec![entity]);
+        history.despawn(&mut world, entity);
+        assert!(world.query_radius(Vec2::ONE, 0.5, &filter).is_empty());
+        history.undo(&mut world);
+        assert_eq!(world.query_radius(Vec2::ONE, 0.5, &filter).len(), 1);
+    }
+}
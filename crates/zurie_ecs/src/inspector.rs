@@ -0,0 +1,506 @@
+use egui::{Button, ComboBox, Context, DragValue, Id, Key, KeyboardShortcut, Modifiers};
+use egui::{ScrollArea, TextEdit, Ui};
+use zurie_types::ComponentData;
+use zurie_types::glam::Vec2;
+use zurie_types::physics::{BodyKind, Shape};
+
+use crate::history::History;
+use crate::{ComponentID, Entity, EntityData, World};
+
+/// Kinds of data a component can be added with from the inspector.
+const NEW_KINDS: [&str; 6] = ["I32", "I64", "String", "Vector", "Color", "None"];
+
+/// Data a component of `NEW_KINDS[kind]` starts with.
+fn new_data(kind: usize) -> ComponentData {
+    match kind {
+        0 => ComponentData::I32(0),
+        1 => ComponentData::I64(0),
+        2 => ComponentData::String(String::new()),
+        3 => ComponentData::Vector(Vec2::ZERO),
+        4 => ComponentData::Color([1.0; 4]),
+        _ => ComponentData::None,
+    }
+}
+
+/// Editor window over a `World`. Every edit goes through a `History`, so it can be undone,
+/// and through the `World` API, so the spatial index stays up to date.
+#[derive(Default)]
+pub struct Inspector {
+    selected: Option<Entity>,
+    search: String,
+    filter: Option<ComponentID>,
+    new_component: Option<ComponentID>,
+    new_kind: usize,
+}
+
+impl Inspector {
+    pub fn selected(&self) -> Option<Entity> {
+        self.selected
+    }
+
+    pub fn select(&mut self, entity: Option<Entity>) {
+        self.selected = entity;
+    }
+
+    /// Spawns an empty entity and selects it.
+    pub fn spawn(&mut self, world: &mut World, history: &mut History) -> Entity {
+        let entity = history.spawn(world, EntityData::default());
+        self.selected = Some(entity);
+        entity
+    }
+
+    /// Spawns a copy of the selected entity and selects the copy.
+    pub fn duplicate(&mut self, world: &mut World, history: &mut History) -> Option<Entity> {
+        let data = world.get_entity_data(self.selected?)?.clone();
+        let entity = history.spawn(world, data);
+        self.selected = Some(entity);
+        Some(entity)
+    }
+
+    pub fn despawn(&mut self, world: &mut World, history: &mut History) {
+        if let Some(entity) = self.selected.take() {
+            history.despawn(world, entity);
+        }
+    }
+
+    /// Whether `entity` has the filter component and its id or one of its component names
+    /// contains the search text, ignoring case.
+    pub fn matches(&self, world: &World, entity: Entity) -> bool {
+        let Some(entity_data) = world.get_entity_data(entity) else {
+            return false;
+        };
+        let has = |component| entity_data.data.iter().any(|(id, _)| *id == component);
+        if self.filter.is_some_and(|filter| !has(filter)) {
+            return false;
+        }
+        let search = self.search.trim().to_lowercase();
+        search.is_empty()
+            || entity.to_string().contains(&search)
+            || entity_data.data.iter().any(|(component, _)| {
+                world
+                    .component_name(*component)
+                    .is_some_and(|name| name.to_lowercase().contains(&search))
+            })
+    }
+
+    pub fn show(&mut self, world: &mut World, history: &mut History, context: &Context) {
+        // An edit goes on while the pointer is held or a text field has focus
+        if !context.input(|input| input.pointer.any_down())
+            && context.memory(|memory| memory.focused().is_none())
+        {
+            history.seal();
+            undo_shortcuts(world, history, context);
+        }
+        // Mods may have despawned it since the last frame
+        if self
+            .selected
+            .is_some_and(|entity| world.get_entity_data(entity).is_none())
+        {
+            self.selected = None;
+        }
+        egui::Window::new("Inspector").show(context, |ui| {
+            egui::CollapsingHeader::new("Registered Components").show(ui, |ui| {
+                ScrollArea::vertical()
+                    .id_salt("components")
+                    .max_height(120.0)
+                    .show(ui, |ui| {
+                        for (component, name) in world.registered_components() {
+                            ui.label(format!("id: {}, name: {}", component, name));
+                        }
+                    });
+            });
+            ui.label(format!(
+                "Entities count: {}",
+                world.get_all_entities().len()
+            ));
+            ui.horizontal(|ui| {
+                let undo = ui.add_enabled(history.can_undo(), Button::new("Undo"));
+                if undo
+                    .on_hover_text(history.undo_name().unwrap_or(""))
+                    .clicked()
+                {
+                    history.undo(world);
+                }
+                let redo = ui.add_enabled(history.can_redo(), Button::new("Redo"));
+                if redo
+                    .on_hover_text(history.redo_name().unwrap_or(""))
+                    .clicked()
+                {
+                    history.redo(world);
+                }
+            });
+            self.entity_list(world, history, ui);
+            ui.separator();
+            self.entity_editor(world, history, ui);
+        });
+    }
+
+    fn entity_list(&mut self, world: &mut World, history: &mut History, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.add(TextEdit::singleline(&mut self.search).hint_text("Search"));
+            let filter = self
+                .filter
+                .and_then(|component| world.component_name(component))
+                .unwrap_or("Any component")
+                .to_owned();
+            ComboBox::from_id_salt("filter")
+                .selected_text(filter)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.filter, None, "Any component");
+                    for (component, name) in world.registered_components() {
+                        ui.selectable_value(&mut self.filter, Some(component), name);
+                    }
+                });
+            if ui.button("Spawn").clicked() {
+                self.spawn(world, history);
+            }
+        });
+        let entities: Vec<(Entity, String)> = world
+            .get_all_entities()
+            .into_iter()
+            .filter(|(entity, _)| self.matches(world, *entity))
+            .map(|(entity, entity_data)| {
+                let names: Vec<&str> = entity_data
+                    .data
+                    .iter()
+                    .map(|(component, _)| world.component_name(*component).unwrap_or("Unknown"))
+                    .collect();
+                (entity, format!("Entity {} ({})", entity, names.join(", ")))
+            })
+            .collect();
+        ScrollArea::vertical()
+            .id_salt("entities")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                for (entity, label) in entities {
+                    if ui
+                        .selectable_label(self.selected == Some(entity), label)
+                        .clicked()
+                    {
+                        self.selected = Some(entity);
+                    }
+                }
+            });
+    }
+
+    fn entity_editor(&mut self, world: &mut World, history: &mut History, ui: &mut Ui) {
+        let Some(entity) = self.selected else {
+            ui.label("Select an entity in the list or click it in the viewport");
+            return;
+        };
+        ui.horizontal(|ui| {
+            ui.strong(format!("Entity {}", entity));
+            if ui.button("Duplicate").clicked() {
+                self.duplicate(world, history);
+            }
+            if ui.button("Despawn").clicked() {
+                self.despawn(world, history);
+            }
+        });
+        // The buttons move the selection
+        let Some(entity) = self.selected else {
+            return;
+        };
+        let Some(entity_data) = world.get_entity_data(entity) else {
+            return;
+        };
+        for (component, mut data) in entity_data.data.clone() {
+            let name = world
+                .component_name(component)
+                .unwrap_or("Unknown")
+                .to_owned();
+            ui.push_id(component, |ui| {
+                let removed = ui
+                    .horizontal(|ui| {
+                        ui.label(format!("{} ({})", name, component));
+                        ui.small_button("Remove").clicked()
+                    })
+                    .inner;
+                if removed {
+                    history.remove_component(world, entity, component);
+                } else if ui
+                    .horizontal_wrapped(|ui| edit_component(ui, &mut data))
+                    .inner
+                {
+                    // A drag or typing changes the component every frame, it's one edit
+                    history
+                        .begin_merged(format!("Edit {name}"), Id::new((entity, component)).value());
+                    history.set_component(world, entity, (component, data));
+                    history.commit();
+                }
+            });
+        }
+        ui.separator();
+        self.add_component(world, history, entity, ui);
+    }
+
+    fn add_component(
+        &mut self,
+        world: &mut World,
+        history: &mut History,
+        entity: Entity,
+        ui: &mut Ui,
+    ) {
+        let missing: Vec<(ComponentID, String)> = world
+            .registered_components()
+            .filter(|(component, _)| world.get_component(entity, *component).is_none())
+            .map(|(component, name)| (component, name.to_owned()))
+            .collect();
+        if self
+            .new_component
+            .is_none_or(|new| !missing.iter().any(|(component, _)| *component == new))
+        {
+            self.new_component = missing.first().map(|(component, _)| *component);
+        }
+        ui.horizontal(|ui| {
+            let selected = missing
+                .iter()
+                .find(|(component, _)| Some(*component) == self.new_component)
+                .map_or("", |(_, name)| name.as_str());
+            ComboBox::from_id_salt("new component")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for (component, name) in missing.iter() {
+                        ui.selectable_value(&mut self.new_component, Some(*component), name);
+                    }
+                });
+            ComboBox::from_id_salt("new kind").show_index(
+                ui,
+                &mut self.new_kind,
+                NEW_KINDS.len(),
+                |kind| NEW_KINDS[kind],
+            );
+            let add = ui.add_enabled(self.new_component.is_some(), Button::new("Add"));
+            if let (true, Some(component)) = (add.clicked(), self.new_component) {
+                history.set_component(world, entity, (component, new_data(self.new_kind)));
+            }
+        });
+    }
+}
+
+/// Ctrl+Z undoes, Ctrl+Shift+Z and Ctrl+Y redo.
+fn undo_shortcuts(world: &mut World, history: &mut History, context: &Context) {
+    let shortcut = |modifiers, key| {
+        context.input_mut(|input| input.consume_shortcut(&KeyboardShortcut::new(modifiers, key)))
+    };
+    // Checked first, Ctrl+Z would also take it
+    if shortcut(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z)
+        || shortcut(Modifiers::COMMAND, Key::Y)
+    {
+        history.redo(world);
+    } else if shortcut(Modifiers::COMMAND, Key::Z) {
+        history.undo(world);
+    }
+}
+
+/// Typed widgets for `data`, true when they changed it.
+fn edit_component(ui: &mut Ui, data: &mut ComponentData) -> bool {
+    match data {
+        ComponentData::I32(value) => ui.add(DragValue::new(value)).changed(),
+        ComponentData::I64(value) => ui.add(DragValue::new(value)).changed(),
+        ComponentData::String(text) => ui.text_edit_singleline(text).changed(),
+        ComponentData::Vector(vector) => edit_vec2(ui, vector),
+        ComponentData::Color(color) => ui.color_edit_button_rgba_unmultiplied(color).changed(),
+        ComponentData::Raw(bytes) => {
+            ui.label(format!("Raw: {} bytes", bytes.len()));
+            false
+        }
+        ComponentData::Sprite(handle) => {
+            ui.label(format!("Sprite: {handle}"));
+            false
+        }
+        ComponentData::Animation(animation) => {
+            ui.label(format!(
+                "Animation: {:?}, frame: {}",
+                animation.tag, animation.frame
+            ));
+            let mut changed = ui.checkbox(&mut animation.playing, "Playing").changed();
+            changed |= ui.checkbox(&mut animation.looping, "Looping").changed();
+            changed | drag(ui, &mut animation.speed, "speed: ")
+        }
+        ComponentData::RigidBody(body) => {
+            let mut changed = false;
+            ComboBox::from_id_salt("body kind")
+                .selected_text(format!("{:?}", body.kind))
+                .show_ui(ui, |ui| {
+                    for kind in [BodyKind::Dynamic, BodyKind::Kinematic, BodyKind::Static] {
+                        changed |= ui
+                            .selectable_value(&mut body.kind, kind, format!("{kind:?}"))
+                            .changed();
+                    }
+                });
+            changed |= drag(ui, &mut body.mass, "mass: ");
+            changed |= drag(ui, &mut body.gravity_scale, "gravity scale: ");
+            changed | drag(ui, &mut body.linear_damping, "damping: ")
+        }
+        ComponentData::Collider(collider) => {
+            let mut changed = match &mut collider.shape {
+                Shape::Circle { radius } => drag(ui, radius, "radius: "),
+                Shape::Box { half_extents } => {
+                    ui.label("half extents:");
+                    edit_vec2(ui, half_extents)
+                }
+                Shape::Capsule {
+                    half_height,
+                    radius,
+                } => drag(ui, half_height, "half height: ") | drag(ui, radius, "radius: "),
+            };
+            ui.label("offset:");
+            changed |= edit_vec2(ui, &mut collider.offset);
+            changed |= ui.checkbox(&mut collider.sensor, "Sensor").changed();
+            changed |= drag(ui, &mut collider.restitution, "restitution: ");
+            changed | drag(ui, &mut collider.friction, "friction: ")
+        }
+        ComponentData::Tilemap(tilemap) => {
+            ui.label(format!(
+                "Tilemap: sprite {}, chunks: {}",
+                tilemap.tileset.sprite,
+                tilemap.chunks().count()
+            ));
+            false
+        }
+        ComponentData::None => {
+            ui.label("None");
+            false
+        }
+    }
+}
+
+fn edit_vec2(ui: &mut Ui, vector: &mut Vec2) -> bool {
+    drag(ui, &mut vector.x, "x: ") | drag(ui, &mut vector.y, "y: ")
+}
+
+fn drag(ui: &mut Ui, value: &mut f32, prefix: &str) -> bool {
+    ui.add(DragValue::new(value).speed(0.1).prefix(prefix))
+        .changed()
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::fixtures::world;
+    use zurie_types::physics::{Collider, RigidBody};
+
+    #[test]
+    fn test_duplicate_and_despawn_selected() {
+        let (mut world, position, _) = world();
+        world.index_component(position, 4.0);
+        let mut history = History::default();
+        let mut inspector = Inspector::default();
+        let original = inspector.spawn(&mut world, &mut history);
+        world.set_component(original, (position, ComponentData::Vector(Vec2::ONE)));
+
+        let copy = inspector.duplicate(&mut world, &mut history).unwrap();
+        assert_ne!(copy, original);
+        assert_eq!(inspector.selected(), Some(copy));
+        assert_eq!(
+            world.get_component(copy, position),
+            Some(&ComponentData::Vector(Vec2::ONE))
+        );
+        let filter = Default::default();
+        assert_eq!(
+            world.query_radius(Vec2::ONE, 0.1, &filter),
+            vec![original, copy]
+        );
+
+        inspector.despawn(&mut world, &mut history);
+        assert_eq!(inspector.selected(), None);
+        assert!(world.get_entity_data(copy).is_none());
+        assert_eq!(world.query_radius(Vec2::ONE, 0.1, &filter), vec![original]);
+        assert_eq!(inspector.duplicate(&mut world, &mut history), None);
+
+        assert!(history.undo(&mut world));
+        assert_eq!(world.query_radius(Vec2::ONE, 0.1, &filter).len(), 2);
+        while history.undo(&mut world) {}
+        assert!(world.get_all_entities().is_empty());
+    }
+
+    #[test]
+    fn test_search_and_filter() {
+        let (mut world, position, health) = world();
+        let player = world.spawn_entity_with_data(EntityData {
+            data: vec![
+                (position, ComponentData::Vector(Vec2::ZERO)),
+                (health, ComponentData::I32(100)),
+            ],
+        });
+        let marker = world.spawn_entity_with_data(EntityData {
+            data: vec![(position, ComponentData::Vector(Vec2::ZERO))],
+        });
+        let mut inspector = Inspector::default();
+        assert!(inspector.matches(&world, player) && inspector.matches(&world, marker));
+
+        inspector.search = " HEAL".into();
+        assert!(inspector.matches(&world, player) && !inspector.matches(&world, marker));
+        inspector.search = marker.to_string();
+        assert!(!inspector.matches(&world, player) && inspector.matches(&world, marker));
+
+        inspector.search.clear();
+        inspector.filter = Some(health);
+        assert!(inspector.matches(&world, player) && !inspector.matches(&world, marker));
+    }
+
+    #[test]
+    fn test_show_without_input_changes_nothing() {
+        let (mut world, position, health) = world();
+        let body = world.register_component("body".into());
+        let collider = world.register_component("collider".into());
+        let entity = world.spawn_entity_with_data(EntityData {
+            data: vec![
+                (position, ComponentData::Vector(Vec2::ONE)),
+                (health, ComponentData::I32(100)),
+                (body, ComponentData::RigidBody(RigidBody::default())),
+                (collider, ComponentData::Collider(Collider::default())),
+            ],
+        });
+        let hash = world.state_hash();
+        let mut history = History::default();
+        let mut inspector = Inspector::default();
+        inspector.select(Some(entity));
+        let context = Context::default();
+        for _ in 0..2 {
+            let _ = context.run(Default::default(), |context| {
+                inspector.show(&mut world, &mut history, context)
+            });
+        }
+        assert_eq!(world.state_hash(), hash);
+        assert_eq!(inspector.selected(), Some(entity));
+    }
+
+    #[test]
+    fn test_undo_shortcuts() {
+        let (mut world, _, health) = world();
+        let mut history = History::default();
+        let mut inspector = Inspector::default();
+        let entity = inspector.spawn(&mut world, &mut history);
+        history.set_component(&mut world, entity, (health, ComponentData::I32(1)));
+        let context = Context::default();
+        let mut press = |history: &mut History, modifiers, key| {
+            let event = egui::Event::Key {
+                key,
+                physical_key: None,
+                pressed: true,
+                repeat: false,
+                modifiers,
+            };
+            let input = egui::RawInput {
+                modifiers,
+                events: vec![event],
+                ..Default::default()
+            };
+            let _ = context.run(input, |context| {
+                inspector.show(&mut world, history, context)
+            });
+        };
+        press(&mut history, Modifiers::COMMAND, Key::Z);
+        press(&mut history, Modifiers::COMMAND, Key::Z);
+        assert!(!history.can_undo());
+        press(&mut history, Modifiers::COMMAND | Modifiers::SHIFT, Key::Z);
+        assert_eq!(history.redo_name(), Some("Set component"));
+        press(&mut history, Modifiers::COMMAND, Key::Y);
+        assert!(!history.can_redo());
+        // Redone spawns come back with a new id
+        let (_, entity_data) = world.get_all_entities()[0];
+        assert_eq!(entity_data.data, vec![(health, ComponentData::I32(1))]);
+    }
+}
@@ -1,11 +1,19 @@
 #![feature(extract_if)]
 
-use egui::{Context, Label};
-use hashbrown::HashSet;
-use log::info;
+use hashbrown::{HashMap, HashSet};
+use log::trace;
 use serde::{Deserialize, Serialize};
+use spatial::{ComponentFilter, SpatialHash};
+use std::hash::{Hash, Hasher};
 use zurie_shared::slotmap::{Key, KeyData, SlotMap, new_key_type};
 use zurie_types::ComponentData;
+use zurie_types::glam::Vec2;
+
+#[cfg(test)]
+mod fixtures;
+pub mod history;
+pub mod inspector;
+pub mod spatial;
 
 new_key_type! { pub struct Entity; }
 new_key_type! { pub struct ComponentID; }
@@ -28,7 +36,7 @@ pub struct Architype {
     pub optional: Vec<ComponentID>,
 }
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EntityData {
     pub data: Vec<(ComponentID, ComponentData)>,
 }
@@ -44,7 +52,7 @@ impl EntityStorage {
     }
 
     pub fn spawn_entity_with_data(&mut self, data: EntityData) -> Entity {
-        info!(
+        trace!(
             "Ent spawned. Ent count: {}, component_count, {}",
             self.entities.len(),
             data.data.len()
@@ -91,7 +99,7 @@ impl EntityStorage {
 
         let required_component_ids: HashSet<ComponentID> = components.iter().copied().collect();
 
-        info!(
+        trace!(
             "requested find entities with: {:?}",
             &components
                 .iter()
@@ -115,7 +123,7 @@ impl EntityStorage {
     pub fn get_entities_with_component(&self, component: ComponentID) -> Vec<Entity> {
         let mut entities = Vec::with_capacity(self.entities.len() / 2);
 
-        info!(
+        trace!(
             "requested find entities with: {:?}",
             KeyData::as_ffi(component.data())
         );
@@ -144,7 +152,7 @@ impl EntityStorage {
                     return;
                 }
             }
-            info!("setting component: {:?}", &new_component);
+            trace!("setting component: {:?}", &new_component);
             entity_data.data.push(new_component);
         }
     }
@@ -194,10 +202,18 @@ impl EntityStorage {
     }
 }
 
+/// Entities and registered components of a `World`, see `World::snapshot`.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct WorldSnapshot {
+    pub components: Vec<(ComponentID, String)>,
+    pub entities: SlotMap<Entity, EntityData>,
+}
+
 #[derive(Default)]
 pub struct World {
     storage: EntityStorage,
     registered_components: SlotMap<ComponentID, String>,
+    spatial: Option<SpatialHash>,
 }
 
 impl World {
@@ -210,12 +226,26 @@ impl World {
         self.registered_components.insert(name)
     }
 
+    pub fn registered_components(&self) -> impl Iterator<Item = (ComponentID, &str)> {
+        self.registered_components
+            .iter()
+            .map(|(component, name)| (component, name.as_str()))
+    }
+
+    pub fn component_name(&self, component: ComponentID) -> Option<&str> {
+        self.registered_components
+            .get(component)
+            .map(String::as_str)
+    }
+
     pub fn spawn_entity(&mut self) -> Entity {
         self.storage.spawn_entity()
     }
 
     pub fn spawn_entity_with_data(&mut self, data: EntityData) -> Entity {
-        self.storage.spawn_entity_with_data(data)
+        let entity = self.storage.spawn_entity_with_data(data);
+        self.reindex(entity);
+        entity
     }
 
     pub fn get_entity_data(&self, entity: Entity) -> Option<&EntityData> {
@@ -242,18 +272,29 @@ impl World {
 
     pub fn modify_entity(&mut self, entity: Entity, new_data: EntityData) {
         self.storage.modify_entity(entity, new_data);
+        self.reindex(entity);
     }
 
     pub fn despawn(&mut self, entity: Entity) {
         self.storage.despawn(entity);
+        if let Some(spatial) = self.spatial.as_mut() {
+            spatial.remove(entity);
+        }
     }
 
     pub fn set_component(&mut self, entity: Entity, new_component: (ComponentID, ComponentData)) {
-        self.storage.set_component(entity, new_component)
+        let component = new_component.0;
+        self.storage.set_component(entity, new_component);
+        if self.is_indexed(component) {
+            self.reindex(entity);
+        }
     }
 
     pub fn remove_component(&mut self, entity: Entity, component: ComponentID) {
-        self.storage.remove_component(entity, component)
+        self.storage.remove_component(entity, component);
+        if self.is_indexed(component) {
+            self.reindex(entity);
+        }
     }
 
     pub fn get_component(&self, entity: Entity, component: ComponentID) -> Option<&ComponentData> {
@@ -264,60 +305,227 @@ impl World {
         entity: Entity,
         component: ComponentID,
     ) -> Option<&mut ComponentData> {
+        if self.is_indexed(component) {
+            self.spatial.as_mut().unwrap().mark_dirty(entity);
+        }
         self.storage.get_component_mut(entity, component)
     }
 
-    pub fn inspector(&mut self, context: Context) {
-        let window = egui::Window::new("Inspector");
-        window.show(&context, |ui| {
-            // Make components list collapsible
-            egui::CollapsingHeader::new("Registered Components")
-                .default_open(true) // Optional: starts expanded
-                .show(ui, |ui| {
-                    egui::ScrollArea::vertical().show(ui, |ui| {
-                        for component in self.registered_components.iter() {
-                            ui.add(Label::new(format!(
-                                "id: {}, name: {}",
-                                component.0, component.1
-                            )));
-                        }
-                    });
+    /// Keeps a spatial index of the `Vector` values of `component`, used by the proximity
+    /// queries. Replaces any earlier index.
+    pub fn index_component(&mut self, component: ComponentID, cell_size: f32) {
+        self.spatial = Some(SpatialHash::new(component, cell_size));
+        for entity in self.storage.entities.keys().collect::<Vec<_>>() {
+            self.reindex(entity);
+        }
+    }
+
+    pub fn spatial_index(&self) -> Option<&SpatialHash> {
+        self.spatial.as_ref()
+    }
+
+    fn is_indexed(&self, component: ComponentID) -> bool {
+        self.spatial
+            .as_ref()
+            .is_some_and(|spatial| spatial.component() == component)
+    }
+
+    fn reindex(&mut self, entity: Entity) {
+        let Some(spatial) = self.spatial.as_mut() else {
+            return;
+        };
+        match self.storage.get_component(entity, spatial.component()) {
+            Some(ComponentData::Vector(position)) => spatial.insert(entity, *position),
+            _ => spatial.remove(entity),
+        }
+    }
+
+    /// Indexed entities and their positions inside the box, unsorted.
+    fn positions_in(&self, min: Vec2, max: Vec2) -> Vec<(Entity, Vec2)> {
+        let Some(spatial) = self.spatial.as_ref() else {
+            return Vec::new();
+        };
+        let mut found = spatial.candidates(min, max);
+        // Changed through get_component_mut since they were last indexed
+        found.extend(spatial.dirty().filter_map(|entity| {
+            match self.storage.get_component(entity, spatial.component()) {
+                Some(ComponentData::Vector(position)) => Some((entity, *position)),
+                _ => None,
+            }
+        }));
+        found.retain(|(_, position)| position.cmpge(min).all() && position.cmple(max).all());
+        found
+    }
+
+    fn accepts(&self, entity: Entity, filter: &ComponentFilter) -> bool {
+        self.storage
+            .get_entity_data(entity)
+            .is_some_and(|data| filter.accepts(data))
+    }
+
+    /// Entities within `radius` of `center`, in entity order. Needs `index_component`.
+    pub fn query_radius(&self, center: Vec2, radius: f32, filter: &ComponentFilter) -> Vec<Entity> {
+        let reach = Vec2::splat(radius);
+        let mut found: Vec<Entity> = self
+            .positions_in(center - reach, center + reach)
+            .into_iter()
+            .filter(|(entity, position)| {
+                position.distance(center) <= radius && self.accepts(*entity, filter)
+            })
+            .map(|(entity, _)| entity)
+            .collect();
+        found.sort();
+        found
+    }
+
+    /// Entities inside the box, edges included, in entity order. Needs `index_component`.
+    pub fn query_aabb(&self, min: Vec2, max: Vec2, filter: &ComponentFilter) -> Vec<Entity> {
+        let mut found: Vec<Entity> = self
+            .positions_in(min, max)
+            .into_iter()
+            .filter(|(entity, _)| self.accepts(*entity, filter))
+            .map(|(entity, _)| entity)
+            .collect();
+        found.sort();
+        found
+    }
+
+    /// Up to `k` entities closest to `point`, nearest first. Needs `index_component`.
+    pub fn nearest_k(
+        &self,
+        point: Vec2,
+        k: usize,
+        max_distance: Option<f32>,
+        filter: &ComponentFilter,
+    ) -> Vec<Entity> {
+        let Some(spatial) = self.spatial.as_ref() else {
+            return Vec::new();
+        };
+        // The search below never covers a point that isn't finite
+        if k == 0 || !point.is_finite() {
+            return Vec::new();
+        }
+        let limit = max_distance.unwrap_or(f32::INFINITY);
+        // Grow the search until it holds k entities or can't find any more
+        let mut radius = spatial.cell_size().min(limit);
+        loop {
+            let reach = Vec2::splat(radius);
+            let mut found: Vec<(Entity, f32)> = self
+                .positions_in(point - reach, point + reach)
+                .into_iter()
+                .map(|(entity, position)| (entity, position.distance(point)))
+                .filter(|(entity, distance)| *distance <= radius && self.accepts(*entity, filter))
+                .collect();
+            let covers_all = spatial.bounds().is_none_or(|(min, max)| {
+                let farthest = (point - min).abs().max((max - point).abs());
+                farthest.length() <= radius
+            }) && spatial.dirty().all(|entity| {
+                match self.storage.get_component(entity, spatial.component()) {
+                    Some(ComponentData::Vector(position)) => position.distance(point) <= radius,
+                    _ => true,
+                }
+            });
+            if found.len() >= k || radius >= limit || covers_all {
+                found.sort_by(|(a, a_distance), (b, b_distance)| {
+                    a_distance.total_cmp(b_distance).then(a.cmp(b))
                 });
-            ui.label(format!("Entities count: {}", self.storage.entities.len()));
-            egui::CollapsingHeader::new("Entities")
-                .default_open(true)
-                .show(ui, |ui| {
-                    egui::ScrollArea::vertical().show(ui, |ui| {
-                        for (entity, components) in self.storage.entities.iter() {
-                            egui::CollapsingHeader::new(format!("Entity {}", entity))
-                                .id_salt(entity.0) // Unique ID for each entity header
-                                .show(ui, |ui| {
-                                    for (component_id, component) in components.data.iter() {
-                                        let component_name =
-                                            match self.registered_components.get(*component_id) {
-                                                Some(name) => name,
-                                                None => "Unknown",
-                                            };
-                                        let text = match component {
-                                            ComponentData::String(s) => format!("String: {}", s),
-                                            ComponentData::Vector(v) => format!("Vector: {:?}", v),
-                                            ComponentData::Color(c) => format!("Color: {:?}", c),
-                                            ComponentData::Raw(r) => format!("Raw: {:?}", r),
-                                            ComponentData::None => "None".to_string(),
-                                            ComponentData::Sprite(h) => format!("Sprite: {h}"),
-                                            ComponentData::I32(i) => format!("I32: {i}"),
-                                            ComponentData::I64(i) => format!("I64: {i}"),
-                                        };
-                                        ui.label(format!(
-                                            "Component {} ({}): {}",
-                                            component_id, component_name, text
-                                        ));
-                                    }
-                                });
-                        }
-                    });
+                return found
+                    .into_iter()
+                    .take(k)
+                    .map(|(entity, _)| entity)
+                    .collect();
+            }
+            radius = (radius * 2.0).min(limit);
+        }
+    }
+
+    pub fn snapshot(&self) -> WorldSnapshot {
+        WorldSnapshot {
+            components: self
+                .registered_components
+                .iter()
+                .map(|(component, name)| (component, name.clone()))
+                .collect(),
+            entities: self.storage.entities.clone(),
+        }
+    }
+
+    /// Replaces every entity with the ones in `snapshot`. Components are matched by name, so
+    /// snapshots saved by another run still load.
+    ///
+    /// Entities alive in both keep their ids. The others are despawned or spawned again, so
+    /// ids handed out since the snapshot never point to another entity. Returns the new ids of
+    /// the snapshot entities that had to be spawned again.
+    pub fn restore(&mut self, snapshot: &WorldSnapshot) -> HashMap<Entity, Entity> {
+        let components: HashMap<ComponentID, ComponentID> = snapshot
+            .components
+            .iter()
+            .map(|(component, name)| (*component, self.register_component(name.clone())))
+            .collect();
+        let entities = &mut self.storage.entities;
+        entities.retain(|entity, _| snapshot.entities.contains_key(entity));
+        let mut respawned = HashMap::new();
+        for (entity, entity_data) in snapshot.entities.iter() {
+            let mut entity_data = entity_data.clone();
+            entity_data
+                .data
+                .retain_mut(|(component, _)| match components.get(component) {
+                    Some(registered) => {
+                        *component = *registered;
+                        true
+                    }
+                    None => false,
                 });
-        });
+            match entities.get_mut(entity) {
+                Some(current) => *current = entity_data,
+                None => {
+                    respawned.insert(entity, entities.insert(entity_data));
+                }
+            }
+        }
+        if let Some(spatial) = self.spatial.as_ref() {
+            self.index_component(spatial.component(), spatial.cell_size());
+        }
+        respawned
+    }
+
+    /// Hash of every entity and component, equal worlds give the same hash on every run.
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = StableHasher::default();
+        for (component, name) in self.registered_components.iter() {
+            KeyData::as_ffi(component.data()).hash(&mut hasher);
+            name.hash(&mut hasher);
+        }
+        for (entity, entity_data) in self.storage.entities.iter() {
+            KeyData::as_ffi(entity.data()).hash(&mut hasher);
+            for (component, data) in entity_data.data.iter() {
+                KeyData::as_ffi(component.data()).hash(&mut hasher);
+                data.hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+}
+
+/// FNV-1a, unlike the std hasher its output is fixed across runs and builds.
+pub struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
     }
 }
 
@@ -499,4 +707,73 @@ pub mod test {
 
         assert!(world.get_entity_data(entity).is_none());
     }
+
+    #[test]
+    fn test_state_hash() {
+        let build = |value: f32| {
+            let mut world = World::default();
+            let position = world.register_component("position".into());
+            let entity = world.spawn_entity();
+            world.set_component(
+                entity,
+                (position, ComponentData::Vector(Vec2::splat(value))),
+            );
+            world
+        };
+        assert_eq!(build(1.0).state_hash(), build(1.0).state_hash());
+        assert_ne!(build(1.0).state_hash(), build(2.0).state_hash());
+
+        let mut world = build(1.0);
+        let hash = world.state_hash();
+        let entity = world.spawn_entity();
+        assert_ne!(world.state_hash(), hash);
+        world.despawn(entity);
+        assert_eq!(world.state_hash(), hash);
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut world = World::default();
+        let position = world.register_component("position".into());
+        world.index_component(position, 4.0);
+        let kept = world.spawn_entity_with_data(EntityData {
+            data: vec![(position, ComponentData::Vector(Vec2::ONE))],
+        });
+        let snapshot = world.snapshot();
+        let hash = world.state_hash();
+
+        world.set_component(kept, (position, ComponentData::Vector(Vec2::ZERO)));
+        let spawned = world.spawn_entity();
+        assert!(world.restore(&snapshot).is_empty());
+        assert_eq!(world.state_hash(), hash);
+        assert!(world.get_entity_data(spawned).is_none());
+        let filter = Default::default();
+        assert_eq!(world.query_radius(Vec2::ONE, 0.1, &filter), vec![kept]);
+        // Ids from before the restore stay dead
+        let reused = world.spawn_entity();
+        assert_ne!(reused, spawned);
+        assert!(world.get_entity_data(spawned).is_none());
+
+        // A despawned entity comes back with a new id
+        world.despawn(kept);
+        let squatter = world.spawn_entity();
+        let respawned = world.restore(&snapshot);
+        let restored = respawned[&kept];
+        assert_ne!(restored, kept);
+        assert_ne!(restored, squatter);
+        assert!(world.get_entity_data(kept).is_none());
+        assert!(world.get_entity_data(squatter).is_none());
+        assert_eq!(world.query_radius(Vec2::ONE, 0.1, &filter), vec![restored]);
+
+        // Another run registers its components in another order
+        let mut other = World::default();
+        let health = other.register_component("health".into());
+        let loaded = other.restore(&snapshot)[&kept];
+        let position = other.register_component("position".into());
+        assert_ne!(position, health);
+        assert_eq!(
+            other.get_component(loaded, position),
+            Some(&ComponentData::Vector(Vec2::ONE))
+        );
+    }
 }
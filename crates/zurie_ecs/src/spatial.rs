@@ -0,0 +1,378 @@
+use crate::{ComponentID, Entity, EntityData};
+use hashbrown::{HashMap, HashSet};
+use zurie_types::glam::Vec2;
+
+/// Components an entity needs for a query to return it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ComponentFilter {
+    /// Every one of these.
+    pub required: Vec<ComponentID>,
+    /// None of these.
+    pub excluded: Vec<ComponentID>,
+}
+
+impl ComponentFilter {
+    pub fn accepts(&self, data: &EntityData) -> bool {
+        let has = |component: &ComponentID| data.data.iter().any(|(id, _)| id == component);
+        self.required.iter().all(has) && !self.excluded.iter().any(has)
+    }
+}
+
+/// Grid of square cells, each holding the entities positioned inside it.
+pub struct SpatialHash {
+    component: ComponentID,
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<Entity>>,
+    /// Lowest and highest cell used since the index was last empty, queries are clamped to it.
+    occupied: Option<((i32, i32), (i32, i32))>,
+    positions: HashMap<Entity, Vec2>,
+    /// Borrowed mutably through `get_component_mut`, their stored position can't be trusted.
+    dirty: HashSet<Entity>,
+}
+
+impl SpatialHash {
+    pub fn new(component: ComponentID, cell_size: f32) -> Self {
+        Self {
+            component,
+            cell_size: cell_size.max(f32::EPSILON),
+            cells: HashMap::new(),
+            occupied: None,
+            positions: HashMap::new(),
+            dirty: HashSet::new(),
+        }
+    }
+
+    /// Component holding the positions that are indexed.
+    pub fn component(&self) -> ComponentID {
+        self.component
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    fn cell(&self, position: Vec2) -> (i32, i32) {
+        let cell = (position / self.cell_size).floor();
+        (cell.x as i32, cell.y as i32)
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Vec2) {
+        self.dirty.remove(&entity);
+        if let Some(old) = self.positions.insert(entity, position) {
+            let (old_cell, new_cell) = (self.cell(old), self.cell(position));
+            if old_cell == new_cell {
+                return;
+            }
+            self.remove_from_cell(entity, old_cell);
+        }
+        let cell = self.cell(position);
+        self.cells.entry(cell).or_default().push(entity);
+        let (low, high) = self.occupied.unwrap_or((cell, cell));
+        self.occupied = Some((
+            (low.0.min(cell.0), low.1.min(cell.1)),
+            (high.0.max(cell.0), high.1.max(cell.1)),
+        ));
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        self.dirty.remove(&entity);
+        if let Some(position) = self.positions.remove(&entity) {
+            self.remove_from_cell(entity, self.cell(position));
+        }
+    }
+
+    fn remove_from_cell(&mut self, entity: Entity, cell: (i32, i32)) {
+        if let Some(entities) = self.cells.get_mut(&cell) {
+            entities.retain(|other| *other != entity);
+            if entities.is_empty() {
+                self.cells.remove(&cell);
+            }
+            if self.cells.is_empty() {
+                self.occupied = None;
+            }
+        }
+    }
+
+    pub fn mark_dirty(&mut self, entity: Entity) {
+        if self.positions.contains_key(&entity) {
+            self.dirty.insert(entity);
+        }
+    }
+
+    pub fn position(&self, entity: Entity) -> Option<Vec2> {
+        self.positions.get(&entity).copied()
+    }
+
+    pub fn dirty(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.dirty.iter().copied()
+    }
+
+    /// Entities in the cells touching the box, maybe outside it. Dirty entities are left out.
+    pub fn candidates(&self, min: Vec2, max: Vec2) -> Vec<(Entity, Vec2)> {
+        let Some((occupied_low, occupied_high)) = self.occupied else {
+            return Vec::new();
+        };
+        let low = self.cell(min);
+        let low = (low.0.max(occupied_low.0), low.1.max(occupied_low.1));
+        let high = self.cell(max);
+        let high = (high.0.min(occupied_high.0), high.1.min(occupied_high.1));
+        if low.0 > high.0 || low.1 > high.1 {
+            return Vec::new();
+        }
+        let cell_count = (high.0 as i64 - low.0 as i64 + 1)
+            .checked_mul(high.1 as i64 - low.1 as i64 + 1)
+            .unwrap_or(i64::MAX);
+        let mut found = Vec::new();
+        // A box spanning more cells than there are occupied ones is faster to scan whole
+        if cell_count > self.cells.len() as i64 {
+            for (cell, entities) in self.cells.iter() {
+                if (low.0..=high.0).contains(&cell.0) && (low.1..=high.1).contains(&cell.1) {
+                    found.extend(entities.iter().copied());
+                }
+            }
+        } else {
+            for x in low.0..=high.0 {
+                for y in low.1..=high.1 {
+                    if let Some(entities) = self.cells.get(&(x, y)) {
+                        found.extend(entities.iter().copied());
+                    }
+                }
+            }
+        }
+        found
+            .into_iter()
+            .filter(|entity| !self.dirty.contains(entity))
+            .map(|entity| (entity, self.positions[&entity]))
+            .collect()
+    }
+
+    /// Smallest box holding every indexed position.
+    pub fn bounds(&self) -> Option<(Vec2, Vec2)> {
+        let mut cells = self.cells.keys();
+        let first = *cells.next()?;
+        let (low, high) = cells.fold((first, first), |(low, high), cell| {
+            (
+                (low.0.min(cell.0), low.1.min(cell.1)),
+                (high.0.max(cell.0), high.1.max(cell.1)),
+            )
+        });
+        let min = Vec2::new(low.0 as f32, low.1 as f32) * self.cell_size;
+        let max = Vec2::new(high.0 as f32 + 1.0, high.1 as f32 + 1.0) * self.cell_size;
+        Some((min, max))
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::World;
+    use zurie_types::ComponentData;
+
+    /// xorshift, enough to scatter entities the same way every run.
+    struct Scatter(u64);
+
+    impl Scatter {
+        fn next(&mut self) -> f32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 >> 40) as f32 / (1u64 << 24) as f32
+        }
+
+        fn point(&mut self, range: f32) -> Vec2 {
+            Vec2::new(self.next() - 0.5, self.next() - 0.5) * range * 2.0
+        }
+    }
+
+    fn positioned(
+        world: &World,
+        position: ComponentID,
+        filter: &ComponentFilter,
+    ) -> Vec<(Entity, Vec2)> {
+        world
+            .get_all_entities()
+            .into_iter()
+            .filter(|(_, data)| filter.accepts(data))
+            .filter_map(|(entity, data)| {
+                data.data.iter().find_map(|(component, value)| match value {
+                    ComponentData::Vector(v) if *component == position => Some((entity, *v)),
+                    _ => None,
+                })
+            })
+            .collect()
+    }
+
+    fn check_queries(
+        world: &World,
+        position: ComponentID,
+        filter: &ComponentFilter,
+        scatter: &mut Scatter,
+    ) {
+        let all = positioned(world, position, filter);
+        for _ in 0..20 {
+            let center = scatter.point(60.0);
+            let radius = scatter.next() * 20.0;
+            let mut expected: Vec<Entity> = all
+                .iter()
+                .filter(|(_, p)| p.distance(center) <= radius)
+                .map(|(entity, _)| *entity)
+                .collect();
+            expected.sort();
+            assert_eq!(world.query_radius(center, radius, filter), expected);
+
+            let (a, b) = (scatter.point(60.0), scatter.point(60.0));
+            let (min, max) = (a.min(b), a.max(b));
+            let mut expected: Vec<Entity> = all
+                .iter()
+                .filter(|(_, p)| p.cmpge(min).all() && p.cmple(max).all())
+                .map(|(entity, _)| *entity)
+                .collect();
+            expected.sort();
+            assert_eq!(world.query_aabb(min, max, filter), expected);
+
+            let k = (scatter.next() * 12.0) as usize;
+            let mut by_distance = all.clone();
+            by_distance.sort_by(|(a, p), (b, q)| {
+                p.distance(center)
+                    .total_cmp(&q.distance(center))
+                    .then(a.cmp(b))
+            });
+            let expected: Vec<Entity> = by_distance.iter().take(k).map(|(e, _)| *e).collect();
+            assert_eq!(world.nearest_k(center, k, None, filter), expected);
+            let expected: Vec<Entity> = by_distance
+                .iter()
+                .filter(|(_, p)| p.distance(center) <= radius)
+                .take(k)
+                .map(|(e, _)| *e)
+                .collect();
+            assert_eq!(world.nearest_k(center, k, Some(radius), filter), expected);
+        }
+    }
+
+    #[test]
+    fn test_queries_match_brute_force() {
+        let mut scatter = Scatter(0x2545_f491_4f6c_dd1d);
+        let mut world = World::default();
+        let position = world.register_component("position".into());
+        let enemy = world.register_component("enemy".into());
+        let dead = world.register_component("dead".into());
+        world.index_component(position, 4.0);
+
+        let mut entities = Vec::new();
+        for i in 0..300 {
+            let entity = world.spawn_entity();
+            world.set_component(
+                entity,
+                (position, ComponentData::Vector(scatter.point(50.0))),
+            );
+            if i % 3 == 0 {
+                world.set_component(entity, (enemy, ComponentData::None));
+            }
+            if i % 7 == 0 {
+                world.set_component(entity, (dead, ComponentData::None));
+            }
+            entities.push(entity);
+        }
+        // Entities without a position are never returned
+        world.spawn_entity_with_data(EntityData {
+            data: vec![(enemy, ComponentData::None)],
+        });
+
+        let filters = [
+            ComponentFilter::default(),
+            ComponentFilter {
+                required: vec![enemy],
+                excluded: vec![dead],
+            },
+        ];
+        for filter in filters.iter() {
+            check_queries(&world, position, filter, &mut scatter);
+        }
+
+        // Moves, despawns, removed positions and positions changed in place
+        for (i, entity) in entities.iter().enumerate() {
+            match i % 5 {
+                0 => world.set_component(
+                    *entity,
+                    (position, ComponentData::Vector(scatter.point(50.0))),
+                ),
+                1 => world.despawn(*entity),
+                2 => world.remove_component(*entity, position),
+                3 => {
+                    if let Some(ComponentData::Vector(p)) =
+                        world.get_component_mut(*entity, position)
+                    {
+                        *p += Vec2::splat(30.0);
+                    }
+                }
+                _ => {}
+            }
+        }
+        for filter in filters.iter() {
+            check_queries(&world, position, filter, &mut scatter);
+        }
+    }
+
+    #[test]
+    fn test_unbounded_queries() {
+        let mut world = World::default();
+        let position = world.register_component("position".into());
+        world.index_component(position, 1.0);
+        let filter = ComponentFilter::default();
+        // Far apart so the occupied cells span most of the i32 range
+        let mut entities: Vec<Entity> = [Vec2::splat(-1e9), Vec2::ZERO, Vec2::splat(1e9)]
+            .into_iter()
+            .map(|point| {
+                world.spawn_entity_with_data(EntityData {
+                    data: vec![(position, ComponentData::Vector(point))],
+                })
+            })
+            .collect();
+        entities.sort();
+
+        assert_eq!(
+            world.query_radius(Vec2::ZERO, f32::INFINITY, &filter),
+            entities
+        );
+        assert_eq!(
+            world.query_aabb(Vec2::splat(-1e30), Vec2::splat(1e30), &filter),
+            entities
+        );
+        assert_eq!(
+            world.query_aabb(Vec2::splat(f32::MIN), Vec2::splat(f32::MAX), &filter),
+            entities
+        );
+        assert!(
+            world
+                .query_aabb(Vec2::splat(2e9), Vec2::splat(1e30), &filter)
+                .is_empty()
+        );
+        assert!(world.query_radius(Vec2::NAN, 1.0, &filter).is_empty());
+        assert_eq!(world.nearest_k(Vec2::ZERO, 3, None, &filter).len(), 3);
+        assert!(world.nearest_k(Vec2::NAN, 3, None, &filter).is_empty());
+    }
+
+    #[test]
+    fn test_index_after_the_fact() {
+        let mut world = World::default();
+        let position = world.register_component("position".into());
+        let entity = world.spawn_entity_with_data(EntityData {
+            data: vec![(position, ComponentData::Vector(Vec2::new(1.0, 1.0)))],
+        });
+        let filter = ComponentFilter::default();
+        assert!(world.query_radius(Vec2::ZERO, 2.0, &filter).is_empty());
+        world.index_component(position, 1.0);
+        assert_eq!(world.query_radius(Vec2::ZERO, 2.0, &filter), vec![entity]);
+        world.modify_entity(entity, EntityData::default());
+        assert!(world.query_radius(Vec2::ZERO, 2.0, &filter).is_empty());
+        assert_eq!(world.spatial_index().unwrap().len(), 0);
+    }
+}
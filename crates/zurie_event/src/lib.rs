@@ -5,6 +5,20 @@ use std::sync::{Arc, RwLock};
 use zurie_types::{ModHandle, glam::Vec2};
 new_key_type! { pub struct EventHandle; }
 
+/// Emitted by the engine with the sprite handle as `I64` once it finished loading.
+pub const SPRITE_LOADED_EVENT: &str = "sprite-loaded";
+/// Emitted by the engine with the sound handle as `I64` once it finished loading.
+pub const SOUND_LOADED_EVENT: &str = "sound-loaded";
+/// Emitted by the engine with the prefab name as `String` once its file was read again.
+pub const PREFAB_RELOADED_EVENT: &str = "prefab-reloaded";
+/// Emitted by the engine with a `Contact` when two colliders start touching.
+pub const CONTACT_BEGIN_EVENT: &str = "contact-begin";
+/// Emitted by the engine with a `Contact` when two colliders stop touching.
+pub const CONTACT_END_EVENT: &str = "contact-end";
+/// Emitted by the engine when the editor stops playing and puts the world back. `Raw` holds
+/// pairs of little endian ffi handles, an entity despawned while playing and its new id.
+pub const WORLD_RESTORED_EVENT: &str = "world-restored";
+
 #[derive(Clone)]
 pub struct Event {
     pub handle: EventHandle,
@@ -51,6 +65,13 @@ pub enum EventData {
     Vector(Vec2),
     Color([f32; 4]),
     Raw(Vec<u8>),
+    /// Entities as ffi handles, the normal points from `a` to `b`.
+    Contact {
+        a: u64,
+        b: u64,
+        normal: Vec2,
+        point: Vec2,
+    },
     None,
 }
 
@@ -69,13 +90,16 @@ impl EventManager {
             }
         }
     }
-    pub fn subscribe_by_name(&mut self, name: String, mod_handle: ModHandle) -> EventHandle {
-        let event_handle = self
-            .event_storage
+    pub fn event_handle(&mut self, name: &str) -> EventHandle {
+        self.event_storage
             .iter()
             .find(|(_, event_name)| name == **event_name)
             .map(|(key, _)| key)
-            .unwrap_or_else(|| self.event_storage.insert(name.clone()));
+            .unwrap_or_else(|| self.event_storage.insert(name.to_string()))
+    }
+
+    pub fn subscribe_by_name(&mut self, name: String, mod_handle: ModHandle) -> EventHandle {
+        let event_handle = self.event_handle(&name);
         self.subscribe_by_handle(event_handle, mod_handle);
         info!("Event registered: {}", name);
         event_handle
@@ -95,6 +119,12 @@ impl EventManager {
         }
     }
 
+    /// Emits an event from the engine itself, every subscribed mod receives it.
+    pub fn emit_engine_event(&mut self, name: &str, data: EventData) {
+        let handle = self.event_handle(name);
+        self.emit(&ModHandle::null(), Event { handle, data });
+    }
+
     pub fn mod_subscribe(&mut self, queue: ModEventQueue, handle: ModHandle) {
         self.event_queue.insert(handle, queue);
     }
@@ -1,11 +1,75 @@
 #![feature(fn_traits)]
 
 use hashbrown::HashSet;
-use log::info;
+use log::trace;
+use serde::{Deserialize, Serialize};
 use std::sync::{Arc, RwLock};
-use winit::event::{ElementState, MouseButton, WindowEvent};
+use winit::dpi::PhysicalPosition;
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
 use zurie_types::{KeyCode, glam::Vec2};
 
+/// Input the engine reacts to, kept apart from winit so it can be recorded and replayed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+    Key {
+        key: KeyCode,
+        pressed: bool,
+    },
+    MouseMoved(Vec2),
+    MouseButton {
+        button: PointerButton,
+        pressed: bool,
+    },
+    Scroll(f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PointerButton {
+    Left,
+    Right,
+}
+
+impl InputEvent {
+    /// `None` for events that aren't input or that the engine ignores.
+    pub fn from_window_event(ev: &WindowEvent) -> Option<Self> {
+        match ev {
+            WindowEvent::KeyboardInput { event, .. } => match event.physical_key {
+                winit::keyboard::PhysicalKey::Code(key_code) => Some(InputEvent::Key {
+                    key: KeyCode::try_from(key_code as u32).ok()?,
+                    pressed: event.state == ElementState::Pressed,
+                }),
+                winit::keyboard::PhysicalKey::Unidentified(_) => None,
+            },
+            WindowEvent::MouseInput { state, button, .. } => Some(InputEvent::MouseButton {
+                button: match button {
+                    MouseButton::Left => PointerButton::Left,
+                    MouseButton::Right => PointerButton::Right,
+                    _ => return None,
+                },
+                pressed: *state == ElementState::Pressed,
+            }),
+            WindowEvent::CursorMoved { position, .. } => Some(InputEvent::MouseMoved(Vec2::new(
+                position.x as f32,
+                position.y as f32,
+            ))),
+            WindowEvent::MouseWheel { delta, .. } => {
+                let scroll_amount: f32 = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(pos) => {
+                        if *pos == PhysicalPosition::new(-0.0, -0.0) {
+                            0.0
+                        } else {
+                            -(pos.y as f32)
+                        }
+                    }
+                };
+                (scroll_amount != 0.0).then_some(InputEvent::Scroll(scroll_amount))
+            }
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Default)]
 pub struct InputState {
     state: Arc<RwLock<InputStateInner>>,
@@ -13,7 +77,28 @@ pub struct InputState {
 
 impl InputState {
     pub fn event(&self, ev: WindowEvent) {
-        self.get_inner_mut(|mut state| state.event(ev));
+        if let Some(event) = InputEvent::from_window_event(&ev) {
+            self.apply(event);
+        }
+    }
+
+    pub fn apply(&self, event: InputEvent) {
+        self.get_inner_mut(|state| state.apply(event));
+    }
+
+    /// Keeps every applied event until it is taken with `take_recorded`.
+    pub fn start_recording(&self) {
+        self.get_inner_mut(|state| state.recorded = Some(Vec::new()));
+    }
+
+    pub fn stop_recording(&self) {
+        self.get_inner_mut(|state| state.recorded = None);
+    }
+
+    /// Events applied since the last call, empty when not recording.
+    pub fn take_recorded(&self) -> Vec<InputEvent> {
+        self.get_inner_mut(|state| state.recorded.as_mut().map(std::mem::take))
+            .unwrap_or_default()
     }
 
     pub fn get_mouse_pos(&self) -> Vec2 {
@@ -36,6 +121,31 @@ impl InputState {
         self.get_inner(|state| state.pressed_keys_buffer.contains(key))
     }
 
+    /// Input without a window, for headless runs and tests.
+    pub fn press_key(&self, key: KeyCode) {
+        self.apply(InputEvent::Key { key, pressed: true });
+    }
+
+    pub fn release_key(&self, key: KeyCode) {
+        self.apply(InputEvent::Key {
+            key,
+            pressed: false,
+        });
+    }
+
+    pub fn set_mouse_pos(&self, position: Vec2) {
+        self.apply(InputEvent::MouseMoved(position));
+    }
+
+    pub fn set_mouse_buttons(&self, left_pressed: bool, right_pressed: bool) {
+        for (button, pressed) in [
+            (PointerButton::Left, left_pressed),
+            (PointerButton::Right, right_pressed),
+        ] {
+            self.apply(InputEvent::MouseButton { button, pressed });
+        }
+    }
+
     fn get_inner_mut<R>(&self, writer: impl FnOnce(&mut InputStateInner) -> R) -> R {
         writer.call_once((&mut self.state.write().unwrap(),))
     }
@@ -54,33 +164,36 @@ pub struct InputStateInner {
     pub mouse: MouseState,
     pub keyboard: KeyboardState,
     pub pressed_keys_buffer: HashSet<KeyCode>,
+    pub recorded: Option<Vec<InputEvent>>,
 }
 
 impl InputStateInner {
     pub fn event(&mut self, ev: WindowEvent) {
-        if let WindowEvent::KeyboardInput { event, .. } = ev.clone() {
-            match event.physical_key {
-                winit::keyboard::PhysicalKey::Code(key_code) => {
-                    let key_code = key_code as u32;
-                    if let Ok(key_code) = KeyCode::try_from(key_code) {
-                        match event.state {
-                            ElementState::Pressed => {
-                                self.pressed_keys_buffer.insert(key_code);
-                            }
-                            ElementState::Released => {
-                                self.pressed_keys_buffer.remove(&key_code);
-                            }
-                        }
-                    }
-                }
-                winit::keyboard::PhysicalKey::Unidentified(_) => {}
+        if let Some(event) = InputEvent::from_window_event(&ev) {
+            self.apply(event);
+        }
+    }
+
+    pub fn apply(&mut self, event: InputEvent) {
+        if let Some(recorded) = self.recorded.as_mut() {
+            recorded.push(event);
+        }
+        match event {
+            InputEvent::Key { key, pressed: true } => {
+                self.pressed_keys_buffer.insert(key);
+            }
+            InputEvent::Key {
+                key,
+                pressed: false,
+            } => {
+                self.pressed_keys_buffer.remove(&key);
             }
+            _ => self.mouse.apply(event),
         }
-        self.mouse.event(ev);
     }
 
     pub fn after_update(&mut self) {
-        info!("{:?}", self.pressed_keys_buffer);
+        trace!("{:?}", self.pressed_keys_buffer);
         let mut keys_lock = self.pressed_keys_buffer.clear();
     }
 }
@@ -98,31 +211,60 @@ pub struct MouseState {
 
 impl MouseState {
     pub fn event(&mut self, ev: WindowEvent) {
-        match ev {
-            WindowEvent::MouseInput { state, button, .. } => match (state, button) {
-                (ElementState::Pressed, MouseButton::Left) => {
-                    self.left_pressed = true;
-                    info!("mouse pressed");
-                }
-                (ElementState::Released, MouseButton::Left) => {
-                    self.left_pressed = false;
-                    info!("mouse released");
-                }
-                (ElementState::Pressed, MouseButton::Right) => {
-                    self.right_pressed = true;
-                    info!("mouse pressed");
-                }
-                (ElementState::Released, MouseButton::Right) => {
-                    self.right_pressed = false;
-                    info!("mouse released");
-                }
-                _ => {}
-            },
+        if let Some(event) = InputEvent::from_window_event(&ev) {
+            self.apply(event);
+        }
+    }
 
-            WindowEvent::CursorMoved { position, .. } => {
-                self.position = Vec2::new(position.x as f32, position.y as f32);
+    pub fn apply(&mut self, event: InputEvent) {
+        match event {
+            InputEvent::MouseButton { button, pressed } => {
+                match button {
+                    PointerButton::Left => self.left_pressed = pressed,
+                    PointerButton::Right => self.right_pressed = pressed,
+                }
+                trace!("mouse {}", if pressed { "pressed" } else { "released" });
             }
+            InputEvent::MouseMoved(position) => self.position = position,
             _ => {}
         }
     }
 }
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn test_recording() {
+        let input = InputState::default();
+        input.press_key(KeyCode::KeyA);
+        assert!(input.take_recorded().is_empty());
+
+        input.start_recording();
+        input.set_mouse_pos(Vec2::new(3.0, 4.0));
+        input.apply(InputEvent::MouseButton {
+            button: PointerButton::Right,
+            pressed: true,
+        });
+        input.release_key(KeyCode::KeyA);
+        assert_eq!(
+            input.take_recorded(),
+            vec![
+                InputEvent::MouseMoved(Vec2::new(3.0, 4.0)),
+                InputEvent::MouseButton {
+                    button: PointerButton::Right,
+                    pressed: true
+                },
+                InputEvent::Key {
+                    key: KeyCode::KeyA,
+                    pressed: false
+                },
+            ]
+        );
+        assert!(input.take_recorded().is_empty());
+        assert!(input.right_mouse_button_pressed());
+        assert!(!input.is_key_pressed(&KeyCode::KeyA));
+        assert_eq!(input.get_mouse_pos(), Vec2::new(3.0, 4.0));
+    }
+}
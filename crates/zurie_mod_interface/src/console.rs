@@ -0,0 +1,39 @@
+use crate::engine::console::{self, ArgKind, Command, CommandArg};
+
+pub use crate::engine::console::ArgValue;
+
+impl CommandArg {
+    pub fn new(name: &str, kind: ArgKind) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            optional: false,
+            choices: Vec::new(),
+        }
+    }
+
+    /// Lets the argument be left out, optional arguments come last.
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+
+    /// Values tab completion offers.
+    pub fn choices(mut self, choices: &[&str]) -> Self {
+        self.choices = choices.iter().map(|choice| choice.to_string()).collect();
+        self
+    }
+}
+
+/// Adds a command to the developer console, typing it calls `ZurieMod::console_command`.
+pub fn register_command(
+    name: &str,
+    description: &str,
+    args: Vec<CommandArg>,
+) -> Result<(), String> {
+    console::register_command(&Command {
+        name: name.into(),
+        description: description.into(),
+        args,
+    })
+}
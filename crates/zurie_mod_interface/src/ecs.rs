@@ -1,6 +1,7 @@
+use crate::engine::core::Vec2;
 use crate::engine::ecs::{self, entity_exits, spawn_entity};
-use crate::engine::ecs::{ComponentData, despawn_entity};
-use crate::engine::sprite;
+use crate::engine::ecs::{ComponentData, ComponentFilter, despawn_entity};
+use crate::engine::{prefab, sprite};
 
 #[derive(Clone, Copy, Default, Debug)]
 pub struct Entity(pub u64);
@@ -29,6 +30,11 @@ impl Entity {
         Entity(spawn_entity())
     }
 
+    /// Spawns the prefab called `name`, `overrides` replace its components by name.
+    pub fn spawn_prefab(name: &str, overrides: &[(String, ComponentData)]) -> Result<Self, String> {
+        prefab::spawn_prefab(name, overrides).map(Entity)
+    }
+
     pub fn despawn(&mut self) {
         despawn_entity(self.0);
     }
@@ -53,3 +59,29 @@ pub fn get_entities_with_components(components: &[u64]) -> Vec<Entity> {
         .map(|ent: &u64| Entity(*ent))
         .collect()
 }
+
+pub fn query_radius(center: Vec2, radius: f32, filter: &ComponentFilter) -> Vec<Entity> {
+    ecs::query_radius(center, radius, filter)
+        .into_iter()
+        .map(Entity)
+        .collect()
+}
+
+pub fn query_aabb(min: Vec2, max: Vec2, filter: &ComponentFilter) -> Vec<Entity> {
+    ecs::query_aabb(min, max, filter)
+        .into_iter()
+        .map(Entity)
+        .collect()
+}
+
+pub fn nearest_k(
+    point: Vec2,
+    k: u32,
+    max_distance: Option<f32>,
+    filter: &ComponentFilter,
+) -> Vec<Entity> {
+    ecs::nearest_k(point, k, max_distance, filter)
+        .into_iter()
+        .map(Entity)
+        .collect()
+}
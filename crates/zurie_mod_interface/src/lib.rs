@@ -1,3 +1,4 @@
+pub mod console;
 pub mod ecs;
 pub mod input;
 
@@ -73,6 +74,10 @@ impl Guest for APIWraper {
     fn event(handle: EventHandle, data: EventData) {
         zurie_mod().event(handle, data);
     }
+
+    fn console_command(name: String, args: Vec<ArgValue>) -> Result<String, String> {
+        zurie_mod().console_command(&name, args)
+    }
 }
 fn zurie_mod() -> impl DerefMut<Target = Box<dyn ZurieMod>> {
     ZURIE_MOD.get().unwrap().lock().unwrap()
@@ -120,6 +125,11 @@ pub trait ZurieMod: Send + Sync {
     fn event(&mut self, handle: EventHandle, data: EventData) {
         warn!("Generic event handler is't implamented")
     }
+    /// Runs a command registered with `console::register_command`, Ok is printed to the
+    /// console.
+    fn console_command(&mut self, name: &str, args: Vec<ArgValue>) -> Result<String, String> {
+        Err(format!("{name} isn't implemented"))
+    }
     fn get_mod_name(&self) -> String;
 }
 
@@ -0,0 +1,965 @@
+pub mod shapes;
+
+use shapes::{Contact, RayCast, RoundedBox};
+use std::collections::{BTreeMap, BTreeSet};
+use zurie_ecs::{ComponentID, Entity, World};
+use zurie_types::ComponentData;
+use zurie_types::glam::Vec2;
+use zurie_types::physics::{BodyKind, Collider, RigidBody, Shape};
+use zurie_types::tilemap::Tilemap;
+
+/// Components the physics reads and writes, registered by `Physics::new`.
+pub const RIGID_BODY_COMPONENT: &str = "rigid-body";
+pub const COLLIDER_COMPONENT: &str = "collider";
+pub const VELOCITY_COMPONENT: &str = "velocity";
+
+/// Penetration left alone so resting bodies keep touching instead of jittering.
+const SLOP: f32 = 0.01;
+/// Fraction of the remaining penetration corrected every step.
+const CORRECTION: f32 = 0.8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PhysicsComponents {
+    pub position: ComponentID,
+    pub velocity: ComponentID,
+    pub rigid_body: ComponentID,
+    pub collider: ComponentID,
+    pub tilemap: ComponentID,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContactPhase {
+    Begin,
+    End,
+}
+
+/// Two colliders started or stopped touching. `a` is always the lower entity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ContactEvent {
+    pub phase: ContactPhase,
+    pub a: Entity,
+    pub b: Entity,
+    /// Where they touch, for `End` where they last touched.
+    pub contact: Contact,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+    pub entity: Entity,
+    pub distance: f32,
+    pub point: Vec2,
+    pub normal: Vec2,
+}
+
+/// Which colliders queries see.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryFilter {
+    /// Layers to look at.
+    pub mask: u32,
+    pub exclude: Vec<Entity>,
+    pub include_sensors: bool,
+}
+
+impl Default for QueryFilter {
+    fn default() -> Self {
+        Self {
+            mask: u32::MAX,
+            exclude: Vec::new(),
+            include_sensors: false,
+        }
+    }
+}
+
+impl QueryFilter {
+    fn accepts(&self, entity: Entity, collider: &Collider) -> bool {
+        collider.layer & self.mask != 0
+            && (self.include_sensors || !collider.sensor)
+            && !self.exclude.contains(&entity)
+    }
+}
+
+struct Body {
+    entity: Entity,
+    position: Vec2,
+    velocity: Vec2,
+    body: Option<RigidBody>,
+    collider: Option<Collider>,
+}
+
+impl Body {
+    fn inverse_mass(&self) -> f32 {
+        self.body.map_or(0.0, |body| body.inverse_mass())
+    }
+
+    fn moves(&self) -> bool {
+        self.body.is_some_and(|body| body.kind != BodyKind::Static)
+    }
+
+    fn shape(&self) -> Option<RoundedBox> {
+        let collider = self.collider?;
+        Some(RoundedBox::new(
+            &collider.shape,
+            self.position + collider.offset,
+        ))
+    }
+}
+
+struct Manifold {
+    a: usize,
+    b: usize,
+    contact: Contact,
+    solid: bool,
+    restitution: f32,
+    friction: f32,
+    /// Normal velocity the solver aims for, set up from the velocity before solving.
+    target: f32,
+    normal_impulse: f32,
+    tangent_impulse: f32,
+}
+
+/// Simulates entities with a position and a rigid body or collider. Steps at a fixed rate no
+/// matter how long frames take, so the same input always gives the same world.
+pub struct Physics {
+    /// Zero by default, the engine doesn't know which way is down.
+    pub gravity: Vec2,
+    pub fixed_delta_time: f32,
+    /// Steps a single update may run, time past that is dropped instead of catching up.
+    pub max_steps: u32,
+    /// Solver passes per step, more keeps stacks steadier.
+    pub iterations: u32,
+    accumulator: f32,
+    contacts: BTreeMap<(Entity, Entity), Contact>,
+    components: PhysicsComponents,
+}
+
+impl Physics {
+    pub fn new(world: &mut World) -> Self {
+        let components = PhysicsComponents {
+            position: world.register_component("position".into()),
+            velocity: world.register_component(VELOCITY_COMPONENT.into()),
+            rigid_body: world.register_component(RIGID_BODY_COMPONENT.into()),
+            collider: world.register_component(COLLIDER_COMPONENT.into()),
+            tilemap: world.register_component("tilemap".into()),
+        };
+        Self {
+            gravity: Vec2::ZERO,
+            fixed_delta_time: 1.0 / 60.0,
+            max_steps: 5,
+            iterations: 8,
+            accumulator: 0.0,
+            contacts: BTreeMap::new(),
+            components,
+        }
+    }
+
+    pub fn components(&self) -> PhysicsComponents {
+        self.components
+    }
+
+    /// Colliders touching since the last step.
+    pub fn contacts(&self) -> impl Iterator<Item = (Entity, Entity, &Contact)> {
+        self.contacts
+            .iter()
+            .map(|((a, b), contact)| (*a, *b, contact))
+    }
+
+    /// Forgets the contacts and the time left over from the last update, for when the world
+    /// was replaced.
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+        self.contacts.clear();
+    }
+
+    /// Runs as many fixed steps as fit in the time passed since the last update.
+    pub fn update(&mut self, world: &mut World, delta_time: f32) -> Vec<ContactEvent> {
+        self.accumulator += delta_time.max(0.0);
+        let mut events = Vec::new();
+        let mut steps = 0;
+        // Frames a hair shorter than a step still run one, timers are never exact
+        while self.accumulator >= self.fixed_delta_time - 1e-6 {
+            if steps == self.max_steps {
+                self.accumulator = 0.0;
+                break;
+            }
+            self.accumulator -= self.fixed_delta_time;
+            events.extend(self.step(world));
+            steps += 1;
+        }
+        self.accumulator = self.accumulator.max(0.0);
+        events
+    }
+
+    /// Advances one fixed step and returns the contacts that began and ended.
+    pub fn step(&mut self, world: &mut World) -> Vec<ContactEvent> {
+        let dt = self.fixed_delta_time;
+        let mut bodies = self.bodies(world);
+        for body in bodies.iter_mut() {
+            let Some(rigid_body) = body.body else {
+                continue;
+            };
+            match rigid_body.kind {
+                BodyKind::Dynamic => {
+                    body.velocity += self.gravity * rigid_body.gravity_scale * dt;
+                    body.velocity /= 1.0 + rigid_body.linear_damping.max(0.0) * dt;
+                    body.position += body.velocity * dt;
+                }
+                BodyKind::Kinematic => body.position += body.velocity * dt,
+                BodyKind::Static => {}
+            }
+        }
+
+        // Solid tiles are static bodies, only those something could touch. Sorted in with the
+        // rest so contacts keep the lower entity first
+        let areas: Vec<(Vec2, Vec2)> = bodies
+            .iter()
+            .filter(|body| body.moves())
+            .filter_map(|body| body.shape())
+            .map(|shape| (shape.min(), shape.max()))
+            .collect();
+        bodies.extend(self.tile_bodies(world, &areas));
+        bodies.sort_by_key(|body| body.entity);
+
+        let mut manifolds = self.find_contacts(&bodies);
+        self.solve(&mut bodies, &mut manifolds);
+        self.write_back(world, &bodies);
+
+        let contacts: BTreeMap<(Entity, Entity), Contact> = manifolds
+            .iter()
+            .map(|manifold| {
+                let (a, b) = (bodies[manifold.a].entity, bodies[manifold.b].entity);
+                ((a, b), manifold.contact)
+            })
+            .collect();
+        let mut events = Vec::new();
+        for ((a, b), contact) in self.contacts.iter() {
+            if !contacts.contains_key(&(*a, *b)) {
+                events.push(ContactEvent {
+                    phase: ContactPhase::End,
+                    a: *a,
+                    b: *b,
+                    contact: *contact,
+                });
+            }
+        }
+        for ((a, b), contact) in contacts.iter() {
+            if !self.contacts.contains_key(&(*a, *b)) {
+                events.push(ContactEvent {
+                    phase: ContactPhase::Begin,
+                    a: *a,
+                    b: *b,
+                    contact: *contact,
+                });
+            }
+        }
+        self.contacts = contacts;
+        events
+    }
+
+    /// Entities with a position and a rigid body or collider, in entity order.
+    fn bodies(&self, world: &World) -> Vec<Body> {
+        let components = self.components;
+        let mut bodies: Vec<Body> = world
+            .get_entities_data_with_components(vec![components.position])
+            .into_iter()
+            .filter_map(|(entity, data)| {
+                let mut body = Body {
+                    entity,
+                    position: Vec2::ZERO,
+                    velocity: Vec2::ZERO,
+                    body: None,
+                    collider: None,
+                };
+                for (component, value) in data.data.iter() {
+                    match value {
+                        ComponentData::Vector(v) if *component == components.position => {
+                            body.position = *v
+                        }
+                        ComponentData::Vector(v) if *component == components.velocity => {
+                            body.velocity = *v
+                        }
+                        ComponentData::RigidBody(b) if *component == components.rigid_body => {
+                            body.body = Some(*b)
+                        }
+                        ComponentData::Collider(c) if *component == components.collider => {
+                            body.collider = Some(*c)
+                        }
+                        _ => {}
+                    }
+                }
+                (body.body.is_some() || body.collider.is_some()).then_some(body)
+            })
+            .collect();
+        bodies.sort_by_key(|body| body.entity);
+        bodies
+    }
+
+    /// A static box for every run of solid tiles touching one of the areas.
+    fn tile_bodies(&self, world: &World, areas: &[(Vec2, Vec2)]) -> Vec<Body> {
+        let components = self.components;
+        let mut seen = BTreeSet::new();
+        let mut bodies = Vec::new();
+        for (entity, data) in
+            world.get_entities_data_with_components(vec![components.position, components.tilemap])
+        {
+            let (mut position, mut tilemap): (Vec2, Option<&Tilemap>) = (Vec2::ZERO, None);
+            for (component, value) in data.data.iter() {
+                match value {
+                    ComponentData::Vector(v) if *component == components.position => position = *v,
+                    ComponentData::Tilemap(t) if *component == components.tilemap => {
+                        tilemap = Some(t)
+                    }
+                    _ => {}
+                }
+            }
+            let Some(tilemap) = tilemap.filter(|tilemap| tilemap.collision_layer != 0) else {
+                continue;
+            };
+            for (min, max) in areas.iter() {
+                let (first, last) = (
+                    tilemap.tile_at(*min - position),
+                    tilemap.tile_at(*max - position),
+                );
+                for (start, length) in tilemap.solid_runs(first, last) {
+                    if !seen.insert((entity, start.y, start.x)) {
+                        continue;
+                    }
+                    let size = tilemap.tile_size * Vec2::new(length as f32, 1.0);
+                    let collider = Collider {
+                        shape: Shape::Box {
+                            half_extents: size / 2.0,
+                        },
+                        layer: tilemap.collision_layer,
+                        ..Default::default()
+                    };
+                    bodies.push(Body {
+                        entity,
+                        position: position + start.as_vec2() * tilemap.tile_size + size / 2.0,
+                        velocity: Vec2::ZERO,
+                        body: None,
+                        collider: Some(collider),
+                    });
+                }
+            }
+        }
+        bodies
+    }
+
+    /// Sorts bounding boxes along x and only tests pairs that overlap there.
+    fn find_contacts(&self, bodies: &[Body]) -> Vec<Manifold> {
+        let mut shapes: Vec<(usize, RoundedBox)> = bodies
+            .iter()
+            .enumerate()
+            .filter_map(|(index, body)| Some((index, body.shape()?)))
+            .collect();
+        shapes.sort_by(|a, b| a.1.min().x.total_cmp(&b.1.min().x).then(a.0.cmp(&b.0)));
+
+        let mut manifolds = Vec::new();
+        for (i, (first, first_shape)) in shapes.iter().enumerate() {
+            for (second, second_shape) in shapes[i + 1..].iter() {
+                if second_shape.min().x > first_shape.max().x {
+                    break;
+                }
+                let (a, b, shape_a, shape_b) = if first < second {
+                    (*first, *second, first_shape, second_shape)
+                } else {
+                    (*second, *first, second_shape, first_shape)
+                };
+                let (body_a, body_b) = (&bodies[a], &bodies[b]);
+                if !body_a.moves() && !body_b.moves() {
+                    continue;
+                }
+                let (Some(collider_a), Some(collider_b)) = (body_a.collider, body_b.collider)
+                else {
+                    continue;
+                };
+                if !collider_a.interacts_with(&collider_b) {
+                    continue;
+                }
+                let Some(contact) = shape_a.contact(shape_b) else {
+                    continue;
+                };
+                let solid = !collider_a.sensor
+                    && !collider_b.sensor
+                    && body_a.inverse_mass() + body_b.inverse_mass() > 0.0;
+                manifolds.push(Manifold {
+                    a,
+                    b,
+                    contact,
+                    solid,
+                    restitution: collider_a.restitution.max(collider_b.restitution),
+                    friction: (collider_a.friction * collider_b.friction).max(0.0).sqrt(),
+                    target: 0.0,
+                    normal_impulse: 0.0,
+                    tangent_impulse: 0.0,
+                });
+            }
+        }
+        manifolds.sort_by_key(|manifold| (bodies[manifold.a].entity, bodies[manifold.b].entity));
+        manifolds
+    }
+
+    /// Sequential impulses on the velocities, then pushes overlapping bodies apart.
+    fn solve(&self, bodies: &mut [Body], manifolds: &mut [Manifold]) {
+        for manifold in manifolds.iter_mut().filter(|manifold| manifold.solid) {
+            let relative = bodies[manifold.b].velocity - bodies[manifold.a].velocity;
+            let approaching = relative.dot(manifold.contact.normal);
+            manifold.target = if approaching < 0.0 {
+                -approaching * manifold.restitution
+            } else {
+                0.0
+            };
+        }
+        for _ in 0..self.iterations.max(1) {
+            for manifold in manifolds.iter_mut().filter(|manifold| manifold.solid) {
+                let (inverse_a, inverse_b) = (
+                    bodies[manifold.a].inverse_mass(),
+                    bodies[manifold.b].inverse_mass(),
+                );
+                let total = inverse_a + inverse_b;
+                let normal = manifold.contact.normal;
+                let relative = bodies[manifold.b].velocity - bodies[manifold.a].velocity;
+
+                let impulse = (manifold.target - relative.dot(normal)) / total;
+                let accumulated = (manifold.normal_impulse + impulse).max(0.0);
+                let impulse = accumulated - manifold.normal_impulse;
+                manifold.normal_impulse = accumulated;
+                bodies[manifold.a].velocity -= normal * impulse * inverse_a;
+                bodies[manifold.b].velocity += normal * impulse * inverse_b;
+
+                let relative = bodies[manifold.b].velocity - bodies[manifold.a].velocity;
+                let tangent = Vec2::new(-normal.y, normal.x);
+                let limit = manifold.normal_impulse * manifold.friction;
+                let impulse = -relative.dot(tangent) / total;
+                let accumulated = (manifold.tangent_impulse + impulse).clamp(-limit, limit);
+                let impulse = accumulated - manifold.tangent_impulse;
+                manifold.tangent_impulse = accumulated;
+                bodies[manifold.a].velocity -= tangent * impulse * inverse_a;
+                bodies[manifold.b].velocity += tangent * impulse * inverse_b;
+            }
+        }
+        for manifold in manifolds.iter().filter(|manifold| manifold.solid) {
+            let (inverse_a, inverse_b) = (
+                bodies[manifold.a].inverse_mass(),
+                bodies[manifold.b].inverse_mass(),
+            );
+            let correction = manifold.contact.normal * (manifold.contact.depth - SLOP).max(0.0)
+                / (inverse_a + inverse_b)
+                * CORRECTION;
+            bodies[manifold.a].position -= correction * inverse_a;
+            bodies[manifold.b].position += correction * inverse_b;
+        }
+    }
+
+    fn write_back(&self, world: &mut World, bodies: &[Body]) {
+        for body in bodies.iter().filter(|body| body.moves()) {
+            world.set_component(
+                body.entity,
+                (
+                    self.components.position,
+                    ComponentData::Vector(body.position),
+                ),
+            );
+            world.set_component(
+                body.entity,
+                (
+                    self.components.velocity,
+                    ComponentData::Vector(body.velocity),
+                ),
+            );
+        }
+    }
+
+    /// Colliders the filter accepts with their shapes, in entity order. Of the tiles only those
+    /// between `min` and `max` are included.
+    fn colliders(
+        &self,
+        world: &World,
+        filter: &QueryFilter,
+        min: Vec2,
+        max: Vec2,
+    ) -> Vec<(Entity, RoundedBox)> {
+        let mut bodies = self.bodies(world);
+        bodies.extend(self.tile_bodies(world, &[(min, max)]));
+        bodies
+            .into_iter()
+            .filter(|body| {
+                body.collider
+                    .is_some_and(|collider| filter.accepts(body.entity, &collider))
+            })
+            .filter_map(|body| Some((body.entity, body.shape()?)))
+            .collect()
+    }
+
+    /// Closest collider along a ray, at most `max_distance` away.
+    pub fn raycast(
+        &self,
+        world: &World,
+        origin: Vec2,
+        direction: Vec2,
+        max_distance: f32,
+        filter: &QueryFilter,
+    ) -> Option<Hit> {
+        self.cast(world, None, origin, direction, max_distance, filter)
+    }
+
+    /// First collider a shape moving from `origin` along `direction` runs into.
+    pub fn shape_cast(
+        &self,
+        world: &World,
+        shape: &Shape,
+        origin: Vec2,
+        direction: Vec2,
+        max_distance: f32,
+        filter: &QueryFilter,
+    ) -> Option<Hit> {
+        let moving = RoundedBox::new(shape, origin);
+        self.cast(world, Some(moving), origin, direction, max_distance, filter)
+    }
+
+    fn cast(
+        &self,
+        world: &World,
+        moving: Option<RoundedBox>,
+        origin: Vec2,
+        direction: Vec2,
+        max_distance: f32,
+        filter: &QueryFilter,
+    ) -> Option<Hit> {
+        let direction = direction.try_normalize()?;
+        let end = origin + direction * max_distance.min(f32::MAX);
+        let reach = moving.map_or(Vec2::ZERO, |moving| moving.extents());
+        let (min, max) = (origin.min(end) - reach, origin.max(end) + reach);
+        let mut closest: Option<Hit> = None;
+        for (entity, shape) in self.colliders(world, filter, min, max) {
+            let swept = moving.map_or(shape, |moving| shape.minkowski_sum(&moving));
+            let Some(RayCast {
+                distance,
+                point,
+                normal,
+            }) = swept.raycast(origin, direction, max_distance)
+            else {
+                continue;
+            };
+            if closest.is_none_or(|closest| distance < closest.distance) {
+                // The ray hit where the cast shape's center stops, it touches the target itself
+                let point = match moving {
+                    Some(_) => {
+                        point.clamp(shape.center - shape.half, shape.center + shape.half)
+                            + normal * shape.radius
+                    }
+                    None => point,
+                };
+                closest = Some(Hit {
+                    entity,
+                    distance,
+                    point,
+                    normal,
+                });
+            }
+        }
+        closest
+    }
+
+    /// Every collider a shape placed at `position` overlaps.
+    pub fn overlap(
+        &self,
+        world: &World,
+        shape: &Shape,
+        position: Vec2,
+        filter: &QueryFilter,
+    ) -> Vec<Entity> {
+        let query = RoundedBox::new(shape, position);
+        let mut entities: Vec<Entity> = self
+            .colliders(world, filter, query.min(), query.max())
+            .into_iter()
+            .filter(|(_, shape)| query.contact(shape).is_some())
+            .map(|(entity, _)| entity)
+            .collect();
+        // A tilemap may overlap with several runs of tiles
+        entities.sort();
+        entities.dedup();
+        entities
+    }
+
+    pub fn velocity(&self, world: &World, entity: Entity) -> Vec2 {
+        match world.get_component(entity, self.components.velocity) {
+            Some(ComponentData::Vector(velocity)) => *velocity,
+            _ => Vec2::ZERO,
+        }
+    }
+
+    pub fn set_velocity(&self, world: &mut World, entity: Entity, velocity: Vec2) {
+        world.set_component(
+            entity,
+            (self.components.velocity, ComponentData::Vector(velocity)),
+        );
+    }
+
+    /// Changes the velocity of a dynamic body by `impulse` divided by its mass.
+    pub fn apply_impulse(&self, world: &mut World, entity: Entity, impulse: Vec2) {
+        let inverse_mass = match world.get_component(entity, self.components.rigid_body) {
+            Some(ComponentData::RigidBody(body)) => body.inverse_mass(),
+            _ => return,
+        };
+        let velocity = self.velocity(world, entity) + impulse * inverse_mass;
+        self.set_velocity(world, entity, velocity);
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use zurie_types::glam::{IVec2, UVec2};
+    use zurie_types::tilemap::Tileset;
+
+    fn spawn(
+        world: &mut World,
+        physics: &Physics,
+        position: Vec2,
+        body: Option<RigidBody>,
+        collider: Collider,
+    ) -> Entity {
+        let components = physics.components();
+        let entity = world.spawn_entity();
+        world.set_component(
+            entity,
+            (components.position, ComponentData::Vector(position)),
+        );
+        if let Some(body) = body {
+            world.set_component(
+                entity,
+                (components.rigid_body, ComponentData::RigidBody(body)),
+            );
+        }
+        world.set_component(
+            entity,
+            (components.collider, ComponentData::Collider(collider)),
+        );
+        entity
+    }
+
+    fn position(world: &World, physics: &Physics, entity: Entity) -> Vec2 {
+        match world.get_component(entity, physics.components().position) {
+            Some(ComponentData::Vector(position)) => *position,
+            _ => panic!("no position"),
+        }
+    }
+
+    fn ground(world: &mut World, physics: &Physics) -> Entity {
+        let collider = Collider {
+            shape: Shape::Box {
+                half_extents: Vec2::new(10.0, 0.5),
+            },
+            ..Default::default()
+        };
+        spawn(world, physics, Vec2::ZERO, None, collider)
+    }
+
+    #[test]
+    fn test_fixed_steps() {
+        let mut world = World::default();
+        let mut physics = Physics::new(&mut world);
+        let body = RigidBody {
+            kind: BodyKind::Kinematic,
+            ..Default::default()
+        };
+        let entity = spawn(
+            &mut world,
+            &physics,
+            Vec2::ZERO,
+            Some(body),
+            Collider::default(),
+        );
+        physics.set_velocity(&mut world, entity, Vec2::new(60.0, 0.0));
+        // Uneven frames add up to the same steps as even ones
+        for delta_time in [0.01, 0.03, 0.005, 0.0217] {
+            physics.update(&mut world, delta_time);
+        }
+        assert!((position(&world, &physics, entity).x - 4.0).abs() < 1e-4);
+        // A long stall runs at most `max_steps`
+        physics.update(&mut world, 10.0);
+        assert!((position(&world, &physics, entity).x - 9.0).abs() < 1e-4);
+        // Time left over from before a reset doesn't add up to a step
+        physics.update(&mut world, 0.01);
+        physics.reset();
+        physics.update(&mut world, 0.01);
+        assert!((position(&world, &physics, entity).x - 9.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_body_rests_on_ground() {
+        let mut world = World::default();
+        let mut physics = Physics::new(&mut world);
+        physics.gravity = Vec2::new(0.0, -10.0);
+        let floor = ground(&mut world, &physics);
+        let ball = spawn(
+            &mut world,
+            &physics,
+            Vec2::new(0.0, 3.0),
+            Some(RigidBody::default()),
+            Collider::default(),
+        );
+        let mut events = Vec::new();
+        for _ in 0..180 {
+            events.extend(physics.step(&mut world));
+        }
+        let resting = position(&world, &physics, ball);
+        assert!((resting.y - 1.0).abs() < 0.05, "{resting}");
+        assert!(physics.velocity(&world, ball).length() < 0.1);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].phase, ContactPhase::Begin);
+        assert_eq!((events[0].a, events[0].b), (floor, ball));
+        assert_eq!(events[0].contact.normal, Vec2::Y);
+
+        physics.reset();
+        assert_eq!(physics.contacts().count(), 0);
+        let events = physics.step(&mut world);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].phase, ContactPhase::Begin);
+    }
+
+    #[test]
+    fn test_bounce_and_momentum() {
+        let mut world = World::default();
+        let mut physics = Physics::new(&mut world);
+        let bouncy = Collider {
+            restitution: 1.0,
+            friction: 0.0,
+            ..Default::default()
+        };
+        let left = spawn(
+            &mut world,
+            &physics,
+            Vec2::new(-2.0, 0.0),
+            Some(RigidBody::default()),
+            bouncy,
+        );
+        let heavy = RigidBody {
+            mass: 3.0,
+            ..Default::default()
+        };
+        let right = spawn(
+            &mut world,
+            &physics,
+            Vec2::new(2.0, 0.0),
+            Some(heavy),
+            bouncy,
+        );
+        physics.set_velocity(&mut world, left, Vec2::new(4.0, 0.0));
+        for _ in 0..120 {
+            physics.step(&mut world);
+        }
+        let (left_velocity, right_velocity) = (
+            physics.velocity(&world, left),
+            physics.velocity(&world, right),
+        );
+        let momentum = left_velocity.x + 3.0 * right_velocity.x;
+        assert!((momentum - 4.0).abs() < 1e-3, "{momentum}");
+        // Elastic, so the energy stays too
+        let energy = left_velocity.x.powi(2) + 3.0 * right_velocity.x.powi(2);
+        assert!((energy - 16.0).abs() < 1e-2, "{energy}");
+        assert!(left_velocity.x < 0.0 && right_velocity.x > 0.0);
+    }
+
+    #[test]
+    fn test_sensors_and_layers() {
+        let mut world = World::default();
+        let mut physics = Physics::new(&mut world);
+        let sensor = Collider {
+            sensor: true,
+            ..Default::default()
+        };
+        let kinematic = RigidBody {
+            kind: BodyKind::Kinematic,
+            ..Default::default()
+        };
+        let projectile = spawn(&mut world, &physics, Vec2::ZERO, Some(kinematic), sensor);
+        physics.set_velocity(&mut world, projectile, Vec2::new(30.0, 0.0));
+        let target = spawn(
+            &mut world,
+            &physics,
+            Vec2::new(3.0, 0.0),
+            Some(RigidBody::default()),
+            Collider::default(),
+        );
+        let ghost = Collider {
+            layer: 2,
+            mask: 2,
+            ..Default::default()
+        };
+        spawn(
+            &mut world,
+            &physics,
+            Vec2::new(3.0, 0.0),
+            Some(RigidBody::default()),
+            ghost,
+        );
+
+        let mut events = Vec::new();
+        for _ in 0..30 {
+            events.extend(physics.step(&mut world));
+        }
+        let phases: Vec<(ContactPhase, Entity, Entity)> = events
+            .iter()
+            .map(|event| (event.phase, event.a, event.b))
+            .collect();
+        assert_eq!(
+            phases,
+            vec![
+                (ContactPhase::Begin, projectile, target),
+                (ContactPhase::End, projectile, target)
+            ]
+        );
+        // The sensor passed through without pushing anything
+        assert_eq!(position(&world, &physics, target), Vec2::new(3.0, 0.0));
+    }
+
+    #[test]
+    fn test_queries() {
+        let mut world = World::default();
+        let physics = Physics::new(&mut world);
+        let floor = ground(&mut world, &physics);
+        let ball = spawn(
+            &mut world,
+            &physics,
+            Vec2::new(3.0, 4.0),
+            None,
+            Collider::default(),
+        );
+        let sensor = Collider {
+            sensor: true,
+            ..Default::default()
+        };
+        let trigger = spawn(&mut world, &physics, Vec2::new(-3.0, 4.0), None, sensor);
+
+        let filter = QueryFilter::default();
+        let hit = physics
+            .raycast(&world, Vec2::new(3.0, 10.0), Vec2::NEG_Y, 100.0, &filter)
+            .unwrap();
+        assert_eq!(hit.entity, ball);
+        assert!((hit.distance - 5.5).abs() < 1e-5);
+        assert_eq!(hit.normal, Vec2::Y);
+
+        let skip_ball = QueryFilter {
+            exclude: vec![ball],
+            ..Default::default()
+        };
+        let hit = physics
+            .raycast(&world, Vec2::new(3.0, 10.0), Vec2::NEG_Y, 100.0, &skip_ball)
+            .unwrap();
+        assert_eq!((hit.entity, hit.point), (floor, Vec2::new(3.0, 0.5)));
+        assert!(
+            physics
+                .raycast(&world, Vec2::new(-3.0, 10.0), Vec2::NEG_Y, 5.0, &filter)
+                .is_none()
+        );
+
+        let square = Shape::Box {
+            half_extents: Vec2::splat(0.5),
+        };
+        let hit = physics
+            .shape_cast(
+                &world,
+                &square,
+                Vec2::new(8.0, 4.0),
+                Vec2::NEG_X,
+                10.0,
+                &filter,
+            )
+            .unwrap();
+        assert_eq!(hit.entity, ball);
+        assert!((hit.distance - 4.0).abs() < 1e-5);
+        assert!((hit.point - Vec2::new(3.5, 4.0)).length() < 1e-5);
+
+        let wide = Shape::Circle { radius: 4.0 };
+        let everything = QueryFilter {
+            include_sensors: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            physics.overlap(&world, &wide, Vec2::new(0.0, 3.0), &filter),
+            vec![floor, ball]
+        );
+        assert_eq!(
+            physics.overlap(&world, &wide, Vec2::new(0.0, 3.0), &everything),
+            vec![floor, ball, trigger]
+        );
+    }
+
+    #[test]
+    fn test_tilemap_collision() {
+        let mut world = World::default();
+        let mut physics = Physics::new(&mut world);
+        physics.gravity = Vec2::new(0.0, -10.0);
+        let tileset = Tileset {
+            sprite: 0,
+            columns: 2,
+            rows: 1,
+            solid: vec![1],
+        };
+        let mut tilemap = Tilemap::new(tileset, Vec2::ONE);
+        // A solid floor from x -20 to 20 with its top at y 0, plus a tile that doesn't collide
+        tilemap.fill_rect(IVec2::new(-20, -1), UVec2::new(40, 1), 1);
+        tilemap.set(IVec2::new(5, 0), 2);
+        let map = world.spawn_entity();
+        world.set_component(
+            map,
+            (
+                physics.components().position,
+                ComponentData::Vector(Vec2::ZERO),
+            ),
+        );
+        world.set_component(
+            map,
+            (
+                physics.components().tilemap,
+                ComponentData::Tilemap(Box::new(tilemap)),
+            ),
+        );
+        let slippery = Collider {
+            friction: 0.0,
+            ..Default::default()
+        };
+        let ball = spawn(
+            &mut world,
+            &physics,
+            Vec2::new(0.0, 2.0),
+            Some(RigidBody::default()),
+            slippery,
+        );
+        let mut events = Vec::new();
+        for _ in 0..120 {
+            events.extend(physics.step(&mut world));
+        }
+        assert!((position(&world, &physics, ball).y - 0.5).abs() < 0.05);
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].a, events[0].b), (map, ball));
+
+        // Sliding across the tile seams keeps its speed
+        physics.set_velocity(&mut world, ball, Vec2::new(4.0, 0.0));
+        for _ in 0..120 {
+            physics.step(&mut world);
+        }
+        assert!((physics.velocity(&world, ball).x - 4.0).abs() < 1e-3);
+
+        let filter = QueryFilter {
+            exclude: vec![ball],
+            ..Default::default()
+        };
+        let hit = physics
+            .raycast(&world, Vec2::new(5.5, 3.0), Vec2::NEG_Y, 10.0, &filter)
+            .unwrap();
+        assert_eq!(hit.entity, map);
+        assert!((hit.point - Vec2::new(5.5, 0.0)).length() < 1e-5);
+        assert!(
+            physics
+                .raycast(&world, Vec2::new(30.0, 3.0), Vec2::NEG_Y, 10.0, &filter)
+                .is_none()
+        );
+        let wide = Shape::Circle { radius: 3.0 };
+        assert_eq!(
+            physics.overlap(&world, &wide, Vec2::new(-10.0, 1.0), &filter),
+            vec![map]
+        );
+    }
+}
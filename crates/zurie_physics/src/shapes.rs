@@ -0,0 +1,289 @@
+use zurie_types::glam::Vec2;
+use zurie_types::physics::Shape;
+
+/// Axis aligned box grown by a radius. Circles, boxes and capsules are all rounded boxes, which
+/// keeps every shape pair on one code path.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RoundedBox {
+    pub center: Vec2,
+    pub half: Vec2,
+    pub radius: f32,
+}
+
+/// Where two shapes touch. The normal points from the first shape to the second.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contact {
+    pub normal: Vec2,
+    pub depth: f32,
+    pub point: Vec2,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayCast {
+    pub distance: f32,
+    pub point: Vec2,
+    pub normal: Vec2,
+}
+
+impl RoundedBox {
+    pub fn new(shape: &Shape, center: Vec2) -> Self {
+        let (half, radius) = match *shape {
+            Shape::Circle { radius } => (Vec2::ZERO, radius),
+            Shape::Box { half_extents } => (half_extents, 0.0),
+            Shape::Capsule {
+                half_height,
+                radius,
+            } => (Vec2::new(0.0, half_height), radius),
+        };
+        Self {
+            center,
+            half: half.abs(),
+            radius: radius.abs(),
+        }
+    }
+
+    /// Half size of the bounding box.
+    pub fn extents(&self) -> Vec2 {
+        self.half + Vec2::splat(self.radius)
+    }
+
+    pub fn min(&self) -> Vec2 {
+        self.center - self.extents()
+    }
+
+    pub fn max(&self) -> Vec2 {
+        self.center + self.extents()
+    }
+
+    /// Signed distance from the surface, negative inside.
+    pub fn distance(&self, point: Vec2) -> f32 {
+        let q = (point - self.center).abs() - self.half;
+        q.max(Vec2::ZERO).length() + q.x.max(q.y).min(0.0) - self.radius
+    }
+
+    /// Shape swept along all of `other`, casting a ray against it is casting `other`.
+    pub fn minkowski_sum(&self, other: &RoundedBox) -> Self {
+        Self {
+            center: self.center,
+            half: self.half + other.half,
+            radius: self.radius + other.radius,
+        }
+    }
+
+    pub fn contact(&self, other: &RoundedBox) -> Option<Contact> {
+        let delta = other.center - self.center;
+        let gap = delta.abs() - self.half - other.half;
+        let sign = Vec2::new(sign(delta.x), sign(delta.y));
+        let (distance, normal) = if gap.x > 0.0 && gap.y > 0.0 {
+            (gap.length(), (gap * sign).normalize())
+        } else if gap.x > gap.y {
+            (gap.x, Vec2::new(sign.x, 0.0))
+        } else {
+            (gap.y, Vec2::new(0.0, sign.y))
+        };
+        let depth = self.radius + other.radius - distance;
+        if depth <= 0.0 {
+            return None;
+        }
+        let closest = other
+            .center
+            .clamp(self.center - self.half, self.center + self.half);
+        Some(Contact {
+            normal,
+            depth,
+            point: closest + normal * (self.radius - depth * 0.5),
+        })
+    }
+
+    /// First hit of a ray along the unit vector `direction`, at most `max_distance` away. A ray
+    /// starting inside hits at distance 0.
+    pub fn raycast(&self, origin: Vec2, direction: Vec2, max_distance: f32) -> Option<RayCast> {
+        if self.distance(origin) <= 0.0 {
+            return Some(RayCast {
+                distance: 0.0,
+                point: origin,
+                normal: -direction,
+            });
+        }
+        let (distance, normal) = ray_aabb(origin, direction, self.min(), self.max())?;
+        if distance > max_distance {
+            return None;
+        }
+        let point = origin + direction * distance;
+        let local = point - self.center;
+        let in_corner = local.x.abs() > self.half.x && local.y.abs() > self.half.y;
+        if self.radius == 0.0 || !in_corner {
+            return Some(RayCast {
+                distance,
+                point,
+                normal,
+            });
+        }
+        // Missing the corner circle means leaving the box through the same corner
+        let corner = self.center + self.half * Vec2::new(sign(local.x), sign(local.y));
+        let distance = ray_circle(origin, direction, corner, self.radius)?;
+        if distance > max_distance {
+            return None;
+        }
+        let point = origin + direction * distance;
+        Some(RayCast {
+            distance,
+            point,
+            normal: (point - corner).normalize_or_zero(),
+        })
+    }
+}
+
+fn sign(value: f32) -> f32 {
+    if value < 0.0 { -1.0 } else { 1.0 }
+}
+
+/// Entry distance and normal of a ray starting outside the box.
+fn ray_aabb(origin: Vec2, direction: Vec2, min: Vec2, max: Vec2) -> Option<(f32, Vec2)> {
+    let (mut enter, mut exit) = (0.0f32, f32::INFINITY);
+    let mut normal = Vec2::ZERO;
+    for axis in 0..2 {
+        if direction[axis] == 0.0 {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let near = (min[axis] - origin[axis]) / direction[axis];
+        let far = (max[axis] - origin[axis]) / direction[axis];
+        let (near, far) = (near.min(far), near.max(far));
+        if near > enter {
+            enter = near;
+            normal = Vec2::ZERO;
+            normal[axis] = -sign(direction[axis]);
+        }
+        exit = exit.min(far);
+    }
+    (enter <= exit).then_some((enter, normal))
+}
+
+fn ray_circle(origin: Vec2, direction: Vec2, center: Vec2, radius: f32) -> Option<f32> {
+    let to_origin = origin - center;
+    let b = to_origin.dot(direction);
+    let c = to_origin.length_squared() - radius * radius;
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let distance = -b - discriminant.sqrt();
+    (distance >= 0.0).then_some(distance)
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    fn circle(x: f32, y: f32, radius: f32) -> RoundedBox {
+        RoundedBox::new(&Shape::Circle { radius }, Vec2::new(x, y))
+    }
+
+    fn square(x: f32, y: f32, half: f32) -> RoundedBox {
+        RoundedBox::new(
+            &Shape::Box {
+                half_extents: Vec2::splat(half),
+            },
+            Vec2::new(x, y),
+        )
+    }
+
+    #[test]
+    fn test_contacts() {
+        let contact = circle(0.0, 0.0, 1.0)
+            .contact(&circle(1.5, 0.0, 1.0))
+            .unwrap();
+        assert_eq!(contact.normal, Vec2::X);
+        assert!((contact.depth - 0.5).abs() < 1e-6);
+        assert!((contact.point - Vec2::new(0.75, 0.0)).length() < 1e-6);
+        assert!(
+            circle(0.0, 0.0, 1.0)
+                .contact(&circle(2.1, 0.0, 1.0))
+                .is_none()
+        );
+
+        let contact = square(0.0, 0.0, 1.0)
+            .contact(&square(0.5, -1.8, 1.0))
+            .unwrap();
+        assert_eq!(contact.normal, Vec2::NEG_Y);
+        assert!((contact.depth - 0.2).abs() < 1e-6);
+
+        // Diagonal from a box corner, the circle is outside the corner's reach
+        assert!(
+            square(0.0, 0.0, 1.0)
+                .contact(&circle(1.8, 1.8, 1.0))
+                .is_none()
+        );
+        let contact = square(0.0, 0.0, 1.0)
+            .contact(&circle(1.5, 1.5, 1.0))
+            .unwrap();
+        assert!((contact.normal - Vec2::ONE.normalize()).length() < 1e-6);
+
+        let capsule = RoundedBox::new(
+            &Shape::Capsule {
+                half_height: 1.0,
+                radius: 0.5,
+            },
+            Vec2::ZERO,
+        );
+        assert!(capsule.contact(&circle(0.0, 1.9, 0.5)).is_some());
+        assert!(capsule.contact(&circle(1.1, 0.0, 0.5)).is_none());
+    }
+
+    #[test]
+    fn test_raycast() {
+        let hit = square(5.0, 0.0, 1.0)
+            .raycast(Vec2::ZERO, Vec2::X, 10.0)
+            .unwrap();
+        assert_eq!(hit.distance, 4.0);
+        assert_eq!(hit.normal, Vec2::NEG_X);
+        assert!(
+            square(5.0, 0.0, 1.0)
+                .raycast(Vec2::ZERO, Vec2::X, 3.0)
+                .is_none()
+        );
+        assert!(
+            square(5.0, 0.0, 1.0)
+                .raycast(Vec2::ZERO, Vec2::Y, 10.0)
+                .is_none()
+        );
+
+        let hit = circle(0.0, 5.0, 1.0)
+            .raycast(Vec2::ZERO, Vec2::Y, 10.0)
+            .unwrap();
+        assert!((hit.distance - 4.0).abs() < 1e-6);
+        // Passes the bounding box corner but not the circle
+        let origin = Vec2::new(-3.0, -1.4);
+        assert!(
+            circle(0.0, 0.0, 1.0)
+                .raycast(origin, Vec2::ONE.normalize(), 10.0)
+                .is_none()
+        );
+
+        let inside = circle(0.0, 0.0, 1.0)
+            .raycast(Vec2::ZERO, Vec2::X, 1.0)
+            .unwrap();
+        assert_eq!(inside.distance, 0.0);
+    }
+
+    #[test]
+    fn test_shape_cast_is_raycast_against_sum() {
+        let moving = circle(0.0, 0.0, 0.5);
+        let target = square(4.0, 0.0, 1.0);
+        let hit = target
+            .minkowski_sum(&moving)
+            .raycast(moving.center, Vec2::X, 10.0)
+            .unwrap();
+        assert!((hit.distance - 2.5).abs() < 1e-6);
+        let moved = circle(hit.distance, 0.0, 0.5);
+        assert!(moved.contact(&target).is_none());
+        assert!(
+            circle(hit.distance + 0.01, 0.0, 0.5)
+                .contact(&target)
+                .is_some()
+        );
+    }
+}
@@ -0,0 +1,422 @@
+//! Prefab files declaring entities by the names and data of their components.
+
+use anyhow::{Context, bail};
+use hashbrown::HashMap;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use zurie_assets::AssetWatcher;
+use zurie_assets::archive::{archive_path, is_packed};
+use zurie_ecs::{Entity, EntityData, World};
+use zurie_shared::slotmap::{Key, KeyData};
+use zurie_types::ComponentData;
+use zurie_types::glam::Vec2;
+
+/// Folder prefab names are looked up in.
+pub const PREFAB_DIRECTORY: &str = "static/prefabs";
+/// Component of spawned children holding their parent entity as `I64`.
+pub const PARENT_COMPONENT: &str = "parent";
+/// Children are positioned relative to their parent through this component.
+pub const POSITION_COMPONENT: &str = "position";
+const EXTENSIONS: [&str; 2] = ["ron", "json"];
+
+/// Components are kept sorted by name, so a prefab always spawns the same way.
+pub type Components = BTreeMap<String, ComponentData>;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Prefab {
+    /// Prefab this one is a variant of, it starts from its components and children.
+    pub base: Option<String>,
+    /// Base components the variant leaves out.
+    pub remove: Vec<String>,
+    pub components: Components,
+    pub children: Vec<ChildPrefab>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChildPrefab {
+    pub prefab: String,
+    #[serde(default)]
+    pub overrides: Components,
+}
+
+/// Prefab with its bases and overrides applied.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResolvedPrefab {
+    pub components: Components,
+    pub children: Vec<ResolvedPrefab>,
+}
+
+/// Reads a prefab from RON, or JSON for .json files.
+pub fn parse(path: &Path, bytes: &[u8]) -> anyhow::Result<Prefab> {
+    let text = std::str::from_utf8(bytes)?;
+    if path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        Ok(serde_json::from_str(text)?)
+    } else {
+        Ok(ron::from_str(text)?)
+    }
+}
+
+/// Prefabs read so far by name, like `enemies/bat` for `<directory>/enemies/bat.ron`.
+pub struct Prefabs {
+    directory: PathBuf,
+    loaded: HashMap<String, (PathBuf, Prefab)>,
+    watcher: Option<AssetWatcher>,
+}
+
+impl Prefabs {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            loaded: HashMap::new(),
+            watcher: None,
+        }
+    }
+
+    /// Prefabs that are read again once their file changes, see `reload_changed`.
+    pub fn watched(directory: impl Into<PathBuf>) -> Self {
+        let watcher = AssetWatcher::new(&EXTENSIONS)
+            .inspect_err(|err| warn!("Prefab hot reload is disabled: {err}"))
+            .ok();
+        Self {
+            watcher,
+            ..Self::new(directory)
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Names of the prefab files in the directory and its folders, sorted. Only loose files
+    /// are listed, packed prefabs can still be spawned by name.
+    pub fn names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut folders = vec![self.directory.clone()];
+        while let Some(folder) = folders.pop() {
+            let Ok(entries) = std::fs::read_dir(&folder) else {
+                continue;
+            };
+            for path in entries.flatten().map(|entry| entry.path()) {
+                if path.is_dir() {
+                    folders.push(path);
+                    continue;
+                }
+                let extension = path.extension().and_then(|extension| extension.to_str());
+                if !extension.is_some_and(|extension| EXTENSIONS.contains(&extension)) {
+                    continue;
+                }
+                if let Ok(name) = path.with_extension("").strip_prefix(&self.directory) {
+                    names.push(archive_path(name));
+                }
+            }
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// File of the prefab called `name`, the RON one if there are both.
+    fn path(&self, name: &str) -> anyhow::Result<PathBuf> {
+        EXTENSIONS
+            .iter()
+            .map(|extension| self.directory.join(format!("{name}.{extension}")))
+            .find(|path| path.is_file() || is_packed(path))
+            .with_context(|| format!("No prefab called {name} in {:?}", self.directory))
+    }
+
+    fn read(&self, name: &str) -> anyhow::Result<(PathBuf, Prefab)> {
+        let path = self.path(name)?;
+        let prefab = zurie_assets::read_asset(&path)
+            .and_then(|bytes| parse(&path, &bytes))
+            .with_context(|| format!("Failed to read prefab {path:?}"))?;
+        Ok((path, prefab))
+    }
+
+    pub fn get(&mut self, name: &str) -> anyhow::Result<&Prefab> {
+        if !self.loaded.contains_key(name) {
+            let (path, prefab) = self.read(name)?;
+            if let Some(watcher) = self.watcher.as_mut() {
+                watcher.watch(&path);
+            }
+            self.loaded.insert(name.to_string(), (path, prefab));
+        }
+        Ok(&self.loaded[name].1)
+    }
+
+    /// Reads the prefab again, the old one stays when the file is broken.
+    pub fn reload(&mut self, name: &str) -> anyhow::Result<()> {
+        let (path, prefab) = self.read(name)?;
+        self.loaded.insert(name.to_string(), (path, prefab));
+        Ok(())
+    }
+
+    /// Rereads the loaded prefabs whose file changed since the last call and returns their
+    /// names. Prefabs spawned before keep their components.
+    pub fn reload_changed(&mut self) -> Vec<String> {
+        let Some(watcher) = self.watcher.as_ref() else {
+            return Vec::new();
+        };
+        let changed: Vec<PathBuf> = watcher
+            .changed_paths()
+            .iter()
+            .map(|path| canonical(path))
+            .collect();
+        if changed.is_empty() {
+            return Vec::new();
+        }
+        let mut names: Vec<String> = self
+            .loaded
+            .iter()
+            .filter(|(_, (path, _))| changed.contains(&canonical(path)))
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names.retain(|name| match self.reload(name) {
+            Ok(()) => {
+                info!("Reloaded prefab {name}");
+                true
+            }
+            Err(err) => {
+                warn!("Keeping the previous prefab {name}: {err:#}");
+                false
+            }
+        });
+        names
+    }
+
+    /// `name` with its bases and children, `overrides` replace components of the result.
+    pub fn resolve(
+        &mut self,
+        name: &str,
+        overrides: &Components,
+    ) -> anyhow::Result<ResolvedPrefab> {
+        self.resolve_nested(name, overrides, &mut Vec::new())
+    }
+
+    /// `chain` holds the prefabs being resolved, meeting one of them again would never end.
+    fn resolve_nested(
+        &mut self,
+        name: &str,
+        overrides: &Components,
+        chain: &mut Vec<String>,
+    ) -> anyhow::Result<ResolvedPrefab> {
+        if chain.iter().any(|outer| outer == name) {
+            bail!("Prefab {name} contains itself through {}", chain.join(", "));
+        }
+        chain.push(name.to_string());
+        let prefab = self.get(name)?.clone();
+        let mut resolved = match prefab.base.as_deref() {
+            Some(base) => self.resolve_nested(base, &Components::new(), chain)?,
+            None => ResolvedPrefab::default(),
+        };
+        for component in prefab.remove.iter() {
+            resolved.components.remove(component);
+        }
+        resolved.components.extend(prefab.components);
+        for child in prefab.children.iter() {
+            let child = self.resolve_nested(&child.prefab, &child.overrides, chain)?;
+            resolved.children.push(child);
+        }
+        chain.pop();
+        resolved.components.extend(
+            overrides
+                .iter()
+                .map(|(name, data)| (name.clone(), data.clone())),
+        );
+        Ok(resolved)
+    }
+}
+
+impl ResolvedPrefab {
+    /// Spawns the entity and its children, registering the components by name. `convert`
+    /// changes component data before it is set, like turning sprite paths into handles.
+    pub fn spawn(
+        &self,
+        world: &mut World,
+        convert: &mut impl FnMut(&str, ComponentData) -> ComponentData,
+    ) -> Entity {
+        self.spawn_under(world, None, convert)
+    }
+
+    fn spawn_under(
+        &self,
+        world: &mut World,
+        parent: Option<(Entity, Vec2)>,
+        convert: &mut impl FnMut(&str, ComponentData) -> ComponentData,
+    ) -> Entity {
+        let mut components = self.components.clone();
+        if let Some((parent, _)) = parent {
+            let parent = ComponentData::I64(KeyData::as_ffi(parent.data()) as i64);
+            components.insert(PARENT_COMPONENT.to_string(), parent);
+        }
+        let origin = parent.map_or(Vec2::ZERO, |(_, origin)| origin);
+        let mut position = origin;
+        let data = components
+            .into_iter()
+            .map(|(name, data)| {
+                let mut data = convert(&name, data);
+                if let (POSITION_COMPONENT, ComponentData::Vector(offset)) =
+                    (name.as_str(), &mut data)
+                {
+                    *offset += origin;
+                    position = *offset;
+                }
+                (world.register_component(name), data)
+            })
+            .collect();
+        let entity = world.spawn_entity_with_data(EntityData { data });
+        for child in self.children.iter() {
+            child.spawn_under(world, Some((entity, position)), convert);
+        }
+        entity
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use zurie_types::physics::{Collider, Shape};
+
+    fn prefab_directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = std::env::temp_dir().join("zurie_prefab_test").join(name);
+        for (file, text) in files {
+            let path = directory.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, text).unwrap();
+        }
+        directory
+    }
+
+    const ENEMY: &str = r#"(
+    components: {
+        "health": I32(100),
+        "enemy": None,
+        "sprite": String("static/enemy.aseprite"),
+        "collider": Collider((
+            shape: Circle(radius: 0.4),
+            offset: (0.0, 0.0),
+            sensor: false,
+            restitution: 0.0,
+            friction: 0.0,
+            layer: 2,
+            mask: 7,
+        )),
+    },
+    children: [(prefab: "orb", overrides: {"position": Vector((1.0, 0.0))})],
+)"#;
+
+    #[test]
+    fn test_variants_and_children() {
+        let directory = prefab_directory(
+            "variants",
+            &[
+                ("enemy.ron", ENEMY),
+                (
+                    "orb.ron",
+                    r#"(components: {"position": Vector((0.0, 0.0))})"#,
+                ),
+                (
+                    "enemies/elite.json",
+                    r#"{"base": "enemy", "remove": ["enemy"], "components": {"health": {"I32": 300}}}"#,
+                ),
+            ],
+        );
+        let mut prefabs = Prefabs::new(&directory);
+        assert_eq!(prefabs.names(), vec!["enemies/elite", "enemy", "orb"]);
+        let overrides = Components::from([("position".into(), ComponentData::Vector(Vec2::ONE))]);
+        let elite = prefabs.resolve("enemies/elite", &overrides).unwrap();
+        assert_eq!(
+            elite.components.keys().collect::<Vec<_>>(),
+            vec!["collider", "health", "position", "sprite"]
+        );
+        assert_eq!(elite.components["health"], ComponentData::I32(300));
+        assert_eq!(
+            elite.components["collider"],
+            ComponentData::Collider(Collider {
+                shape: Shape::Circle { radius: 0.4 },
+                offset: Vec2::ZERO,
+                sensor: false,
+                restitution: 0.0,
+                friction: 0.0,
+                layer: 2,
+                mask: 7,
+            })
+        );
+        assert_eq!(elite.children.len(), 1);
+
+        let mut world = World::default();
+        let entity = elite.spawn(&mut world, &mut |name, data| match (name, data) {
+            ("sprite", ComponentData::String(_)) => ComponentData::Sprite(9),
+            (_, data) => data,
+        });
+        let component = |world: &mut World, entity: Entity, name: &str| {
+            let id = world.register_component(name.into());
+            world.get_component(entity, id).cloned()
+        };
+        assert_eq!(
+            component(&mut world, entity, "sprite"),
+            Some(ComponentData::Sprite(9))
+        );
+        let parent = world.register_component(PARENT_COMPONENT.into());
+        let children = world.get_entities_with_component(parent);
+        assert_eq!(children.len(), 1);
+        assert_eq!(
+            component(&mut world, children[0], PARENT_COMPONENT),
+            Some(ComponentData::I64(KeyData::as_ffi(entity.data()) as i64))
+        );
+        // Children are placed relative to their parent
+        assert_eq!(
+            component(&mut world, children[0], POSITION_COMPONENT),
+            Some(ComponentData::Vector(Vec2::new(2.0, 1.0)))
+        );
+    }
+
+    #[test]
+    fn test_errors_and_reload() {
+        let directory = prefab_directory(
+            "errors",
+            &[
+                ("a.ron", r#"(base: Some("b"))"#),
+                ("b.ron", r#"(children: [(prefab: "a")])"#),
+                ("broken.ron", "(components: {"),
+                ("health.ron", r#"(components: {"health": I32(1)})"#),
+            ],
+        );
+        let mut prefabs = Prefabs::new(&directory);
+        let error = prefabs.resolve("a", &Components::new()).unwrap_err();
+        assert_eq!(error.to_string(), "Prefab a contains itself through a, b");
+        assert!(prefabs.resolve("broken", &Components::new()).is_err());
+        assert!(prefabs.resolve("missing", &Components::new()).is_err());
+
+        let health = |prefabs: &mut Prefabs| {
+            prefabs
+                .resolve("health", &Components::new())
+                .unwrap()
+                .components["health"]
+                .clone()
+        };
+        assert_eq!(health(&mut prefabs), ComponentData::I32(1));
+        std::fs::write(
+            directory.join("health.ron"),
+            r#"(components: {"health": I32(2)})"#,
+        )
+        .unwrap();
+        // Cached until reloaded, a broken file keeps the previous prefab
+        assert_eq!(health(&mut prefabs), ComponentData::I32(1));
+        prefabs.reload("health").unwrap();
+        assert_eq!(health(&mut prefabs), ComponentData::I32(2));
+        std::fs::write(directory.join("health.ron"), "(").unwrap();
+        assert!(prefabs.reload("health").is_err());
+        assert_eq!(health(&mut prefabs), ComponentData::I32(2));
+    }
+}
@@ -5,11 +5,13 @@ layout(location = 0) in vec2 vert_position;
 layout(location = 1) in vec2 position;
 layout(location = 2) in vec2 scale;
 layout(location = 3) in vec4 color;
+layout(location = 4) in vec2 uv_offset;
+layout(location = 5) in vec2 uv_scale;
 
 layout(location = 0) out vec4 frag_color;
 layout(location = 1) out vec2 frag_tex_coord;
 
-layout(set = 0, binding = 0) uniform Camera {
+layout(push_constant) uniform Camera {
     mat4 proj_mat;
     vec2 cam_pos;
 };
@@ -23,4 +25,6 @@ void main() {
         vert_position.x + 0.5,  // Convert from [-0.5, 0.5] to [0, 1]
         vert_position.y + 0.5  // Flip Y and convert to [0, 1]
     );
+    // Select the current frame of the sprite sheet
+    frag_tex_coord = uv_offset + frag_tex_coord * uv_scale;
 }
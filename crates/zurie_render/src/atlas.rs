@@ -0,0 +1,307 @@
+/// Pixel rectangle inside an atlas page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AtlasRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl AtlasRect {
+    pub fn overlaps(&self, other: &AtlasRect) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+
+    /// Column `index` of a rect split into `count` equal columns, used for sprite frames.
+    pub fn column(&self, index: u32, count: u32) -> AtlasRect {
+        let count = count.max(1);
+        let width = self.width / count;
+        AtlasRect {
+            x: self.x + (index % count) * width,
+            width,
+            ..*self
+        }
+    }
+
+    /// UV offset and scale of the rect in a square page of `page_size` pixels.
+    pub fn uv(&self, page_size: u32) -> ([f32; 2], [f32; 2]) {
+        let page_size = page_size as f32;
+        (
+            [self.x as f32 / page_size, self.y as f32 / page_size],
+            [
+                self.width as f32 / page_size,
+                self.height as f32 / page_size,
+            ],
+        )
+    }
+}
+
+/// Where a packed image ended up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AtlasAllocation {
+    pub page: usize,
+    pub rect: AtlasRect,
+}
+
+#[derive(Debug)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    used_width: u32,
+}
+
+/// Shelf packer for a single square page.
+#[derive(Debug)]
+pub struct PagePacker {
+    size: u32,
+    padding: u32,
+    shelves: Vec<Shelf>,
+    /// Space given back by `free`, reused before opening new shelves.
+    free_rects: Vec<AtlasRect>,
+    /// Page holds a single image that nothing else can share it with.
+    reserved: bool,
+}
+
+impl PagePacker {
+    pub fn new(size: u32, padding: u32) -> Self {
+        Self {
+            size,
+            padding,
+            shelves: Vec::new(),
+            free_rects: Vec::new(),
+            reserved: false,
+        }
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn pack(&mut self, width: u32, height: u32) -> Option<AtlasRect> {
+        let padded_width = width + self.padding;
+        let padded_height = height + self.padding;
+        if self.reserved || padded_width > self.size || padded_height > self.size {
+            return None;
+        }
+
+        // Smallest freed slot that fits
+        if let Some((index, _)) = self
+            .free_rects
+            .iter()
+            .enumerate()
+            .filter(|(_, rect)| rect.width >= padded_width && rect.height >= padded_height)
+            .min_by_key(|(_, rect)| rect.width * rect.height)
+        {
+            let slot = self.free_rects.swap_remove(index);
+            return Some(AtlasRect {
+                x: slot.x,
+                y: slot.y,
+                width,
+                height,
+            });
+        }
+
+        // Lowest shelf that is tall enough and has room left
+        let shelf = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| {
+                shelf.height >= padded_height && self.size - shelf.used_width >= padded_width
+            })
+            .min_by_key(|shelf| shelf.height);
+        let shelf = match shelf {
+            Some(shelf) => shelf,
+            None => {
+                let y = self
+                    .shelves
+                    .last()
+                    .map_or(0, |shelf| shelf.y + shelf.height);
+                if self.size - y < padded_height {
+                    return None;
+                }
+                self.shelves.push(Shelf {
+                    y,
+                    height: padded_height,
+                    used_width: 0,
+                });
+                self.shelves.last_mut().unwrap()
+            }
+        };
+        let rect = AtlasRect {
+            x: shelf.used_width,
+            y: shelf.y,
+            width,
+            height,
+        };
+        shelf.used_width += padded_width;
+        Some(rect)
+    }
+
+    pub fn free(&mut self, rect: AtlasRect) {
+        if self.reserved {
+            return;
+        }
+        self.free_rects.push(AtlasRect {
+            width: rect.width + self.padding,
+            height: rect.height + self.padding,
+            ..rect
+        });
+    }
+}
+
+/// Packs images into as many pages as needed. Images bigger than a page get a page of their own.
+#[derive(Debug)]
+pub struct AtlasPacker {
+    page_size: u32,
+    padding: u32,
+    pages: Vec<PagePacker>,
+}
+
+impl AtlasPacker {
+    pub fn new(page_size: u32, padding: u32) -> Self {
+        Self {
+            page_size,
+            padding,
+            pages: Vec::new(),
+        }
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn page_size(&self, page: usize) -> Option<u32> {
+        self.pages.get(page).map(|page| page.size())
+    }
+
+    pub fn pack(&mut self, width: u32, height: u32) -> AtlasAllocation {
+        for (page, packer) in self.pages.iter_mut().enumerate() {
+            if let Some(rect) = packer.pack(width, height) {
+                return AtlasAllocation { page, rect };
+            }
+        }
+
+        let size = self
+            .page_size
+            .max((width.max(height) + self.padding).next_power_of_two());
+        let mut packer = PagePacker::new(size, self.padding);
+        let rect = packer
+            .pack(width, height)
+            .expect("Image should fit in an empty page");
+        self.pages.push(packer);
+        AtlasAllocation {
+            page: self.pages.len() - 1,
+            rect,
+        }
+    }
+
+    /// Adds a page holding only one image, used for data that can't share a page like
+    /// block compressed textures. The page is at least 4 pixels to fit a block.
+    pub fn reserve_page(&mut self, width: u32, height: u32) -> AtlasAllocation {
+        let size = width.max(height).next_power_of_two().max(4);
+        let mut packer = PagePacker::new(size, 0);
+        let rect = packer
+            .pack(width, height)
+            .expect("Image should fit in an empty page");
+        packer.reserved = true;
+        self.pages.push(packer);
+        AtlasAllocation {
+            page: self.pages.len() - 1,
+            rect,
+        }
+    }
+
+    pub fn free(&mut self, allocation: AtlasAllocation) {
+        if let Some(page) = self.pages.get_mut(allocation.page) {
+            page.free(allocation.rect);
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    fn assert_valid(packer: &AtlasPacker, allocations: &[AtlasAllocation]) {
+        for (i, a) in allocations.iter().enumerate() {
+            let size = packer.page_size(a.page).unwrap();
+            assert!(a.rect.x + a.rect.width <= size && a.rect.y + a.rect.height <= size);
+            for b in allocations[i + 1..].iter() {
+                assert!(
+                    a.page != b.page || !a.rect.overlaps(&b.rect),
+                    "{a:?} overlaps {b:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_random_rects_do_not_overlap() {
+        let mut rng = fastrand::Rng::with_seed(7);
+        let mut packer = AtlasPacker::new(256, 1);
+        let allocations: Vec<AtlasAllocation> = (0..300)
+            .map(|_| packer.pack(rng.u32(1..64), rng.u32(1..64)))
+            .collect();
+        assert!(packer.page_count() > 1);
+        assert_valid(&packer, &allocations);
+    }
+
+    #[test]
+    fn test_rects_share_shelves() {
+        let mut packer = AtlasPacker::new(64, 0);
+        let allocations: Vec<AtlasAllocation> = (0..16).map(|_| packer.pack(16, 16)).collect();
+        assert_eq!(packer.page_count(), 1);
+        assert_eq!(allocations[4].rect, AtlasRect {
+            x: 0,
+            y: 16,
+            width: 16,
+            height: 16,
+        });
+        assert_eq!(packer.pack(1, 1).page, 1);
+    }
+
+    #[test]
+    fn test_oversized_image_gets_own_page() {
+        let mut packer = AtlasPacker::new(64, 1);
+        packer.pack(8, 8);
+        let big = packer.pack(100, 20);
+        assert_eq!(big.page, 1);
+        assert_eq!(packer.page_size(1), Some(128));
+    }
+
+    #[test]
+    fn test_freed_space_is_reused() {
+        let mut packer = AtlasPacker::new(32, 0);
+        let first = packer.pack(32, 16);
+        let second = packer.pack(32, 16);
+        packer.free(first);
+        let third = packer.pack(16, 16);
+        assert_eq!(third.page, 0);
+        assert_eq!(third.rect.y, first.rect.y);
+        assert_valid(&packer, &[second, third]);
+    }
+
+    #[test]
+    fn test_reserved_page_is_not_shared() {
+        let mut packer = AtlasPacker::new(64, 0);
+        let reserved = packer.reserve_page(10, 3);
+        assert_eq!(packer.page_size(reserved.page), Some(16));
+        packer.free(reserved);
+        assert_eq!(packer.pack(2, 2).page, 1);
+    }
+
+    #[test]
+    fn test_frame_uvs() {
+        let strip = AtlasRect {
+            x: 32,
+            y: 64,
+            width: 48,
+            height: 16,
+        };
+        assert_eq!(strip.column(1, 3).uv(128), ([0.375, 0.5], [0.125, 0.125]));
+        assert_eq!(strip.column(4, 3), strip.column(1, 3));
+    }
+}
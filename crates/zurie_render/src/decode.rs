@@ -0,0 +1,234 @@
+use anyhow::{Context, anyhow, bail};
+use asefile::AsepriteFile;
+use image::ImageFormat;
+use vulkano::format::Format;
+use zurie_types::animation::{AnimationDirection, AnimationTag, SpriteSheet};
+
+const PNG_MAGIC: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+const QOI_MAGIC: &[u8] = b"qoif";
+const KTX2_MAGIC: &[u8] = &[
+    0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+/// Aseprite files store this after the file size.
+const ASEPRITE_MAGIC: &[u8] = &[0xE0, 0xA5];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpriteFormat {
+    Aseprite,
+    Png,
+    Qoi,
+    Ktx2,
+}
+
+impl SpriteFormat {
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(PNG_MAGIC) {
+            Some(Self::Png)
+        } else if bytes.starts_with(QOI_MAGIC) {
+            Some(Self::Qoi)
+        } else if bytes.starts_with(KTX2_MAGIC) {
+            Some(Self::Ktx2)
+        } else if bytes.get(4..6) == Some(ASEPRITE_MAGIC) {
+            Some(Self::Aseprite)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SpritePixels {
+    /// Tightly packed 8 bit RGBA, goes into the atlas.
+    Rgba(Vec<u8>),
+    /// Block compressed data of the first mip level, gets a page of its own.
+    Compressed { format: Format, data: Vec<u8> },
+}
+
+/// Sprite decoded on the CPU, frames are laid out side by side.
+#[derive(Debug)]
+pub struct DecodedSprite {
+    pub pixels: SpritePixels,
+    pub width: u32,
+    pub height: u32,
+    pub sheet: SpriteSheet,
+}
+
+impl DecodedSprite {
+    pub fn decode(bytes: &[u8]) -> anyhow::Result<Self> {
+        match SpriteFormat::detect(bytes) {
+            Some(SpriteFormat::Aseprite) => decode_aseprite(bytes),
+            Some(SpriteFormat::Png) => decode_image(bytes, ImageFormat::Png),
+            Some(SpriteFormat::Qoi) => decode_image(bytes, ImageFormat::Qoi),
+            Some(SpriteFormat::Ktx2) => decode_ktx2(bytes),
+            None => bail!("Unknown image format, expected Aseprite, PNG, QOI or KTX2"),
+        }
+    }
+
+    pub fn frame_width(&self) -> u32 {
+        self.width / self.sheet.frame_count()
+    }
+}
+
+fn decode_aseprite(bytes: &[u8]) -> anyhow::Result<DecodedSprite> {
+    let ase = AsepriteFile::read(bytes).context("Failed to decode Aseprite file")?;
+    let width = ase.width() as u32;
+    let height = ase.height() as u32;
+    let frame_count = ase.num_frames().max(1);
+    let frames: Vec<_> = (0..frame_count)
+        .map(|frame| ase.frame(frame).image())
+        .collect();
+    let mut rgba_data = Vec::with_capacity((width * frame_count * height * 4) as usize);
+    for y in 0..height {
+        for frame in &frames {
+            for x in 0..width {
+                rgba_data.extend_from_slice(&frame.get_pixel(x, y).0);
+            }
+        }
+    }
+    Ok(DecodedSprite {
+        pixels: SpritePixels::Rgba(rgba_data),
+        width: width * frame_count,
+        height,
+        sheet: sheet_from_ase(&ase),
+    })
+}
+
+fn sheet_from_ase(ase: &AsepriteFile) -> SpriteSheet {
+    SpriteSheet {
+        frame_durations: (0..ase.num_frames())
+            .map(|frame| ase.frame(frame).duration())
+            .collect(),
+        tags: (0..ase.num_tags())
+            .map(|tag| {
+                let tag = ase.tag(tag);
+                AnimationTag {
+                    name: tag.name().to_string(),
+                    from: tag.from_frame(),
+                    to: tag.to_frame(),
+                    direction: match tag.animation_direction() {
+                        asefile::AnimationDirection::Reverse => AnimationDirection::Reverse,
+                        asefile::AnimationDirection::PingPong => AnimationDirection::PingPong,
+                        _ => AnimationDirection::Forward,
+                    },
+                }
+            })
+            .collect(),
+    }
+}
+
+fn decode_image(bytes: &[u8], format: ImageFormat) -> anyhow::Result<DecodedSprite> {
+    let image = image::load_from_memory_with_format(bytes, format)
+        .with_context(|| format!("Failed to decode {format:?} image"))?
+        .into_rgba8();
+    Ok(DecodedSprite {
+        width: image.width(),
+        height: image.height(),
+        pixels: SpritePixels::Rgba(image.into_raw()),
+        sheet: Default::default(),
+    })
+}
+
+fn decode_ktx2(bytes: &[u8]) -> anyhow::Result<DecodedSprite> {
+    let reader = ktx2::Reader::new(bytes).map_err(|err| anyhow!("Invalid KTX2 file: {err}"))?;
+    let header = reader.header();
+    if let Some(scheme) = header.supercompression_scheme {
+        bail!("Supercompressed KTX2 files are not supported ({scheme:?})");
+    }
+    let width = header.pixel_width;
+    let height = header.pixel_height.max(1);
+    let data = reader
+        .levels()
+        .next()
+        .ok_or_else(|| anyhow!("KTX2 file has no mip levels"))?
+        .to_vec();
+
+    // Pages are sampled without sRGB decoding, so sRGB variants use the UNORM formats.
+    let pixels = match header.format {
+        Some(ktx2::Format::R8G8B8A8_UNORM | ktx2::Format::R8G8B8A8_SRGB) => {
+            if data.len() != (width * height * 4) as usize {
+                bail!("KTX2 level size does not match {width}x{height} RGBA");
+            }
+            SpritePixels::Rgba(data)
+        }
+        Some(ktx2::Format::BC7_UNORM_BLOCK | ktx2::Format::BC7_SRGB_BLOCK) => {
+            SpritePixels::Compressed {
+                format: Format::BC7_UNORM_BLOCK,
+                data,
+            }
+        }
+        Some(ktx2::Format::ETC2_R8G8B8A8_UNORM_BLOCK | ktx2::Format::ETC2_R8G8B8A8_SRGB_BLOCK) => {
+            SpritePixels::Compressed {
+                format: Format::ETC2_R8G8B8A8_UNORM_BLOCK,
+                data,
+            }
+        }
+        Some(ktx2::Format::ETC2_R8G8B8_UNORM_BLOCK | ktx2::Format::ETC2_R8G8B8_SRGB_BLOCK) => {
+            SpritePixels::Compressed {
+                format: Format::ETC2_R8G8B8_UNORM_BLOCK,
+                data,
+            }
+        }
+        format => bail!("Unsupported KTX2 format {format:?}"),
+    };
+    Ok(DecodedSprite {
+        pixels,
+        width,
+        height,
+        sheet: Default::default(),
+    })
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+    use std::io::Cursor;
+
+    fn encode(format: ImageFormat) -> Vec<u8> {
+        let image = RgbaImage::from_fn(3, 2, |x, y| Rgba([x as u8, y as u8, 7, 255]));
+        let mut bytes = Cursor::new(Vec::new());
+        image.write_to(&mut bytes, format).unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(
+            SpriteFormat::detect(&encode(ImageFormat::Png)),
+            Some(SpriteFormat::Png)
+        );
+        assert_eq!(
+            SpriteFormat::detect(&encode(ImageFormat::Qoi)),
+            Some(SpriteFormat::Qoi)
+        );
+        assert_eq!(
+            SpriteFormat::detect(include_bytes!("../../../static/error.aseprite")),
+            Some(SpriteFormat::Aseprite)
+        );
+        assert_eq!(SpriteFormat::detect(KTX2_MAGIC), Some(SpriteFormat::Ktx2));
+        assert_eq!(SpriteFormat::detect(b"GIF89a"), None);
+        assert_eq!(SpriteFormat::detect(&[]), None);
+    }
+
+    #[test]
+    fn test_decode_png_and_qoi() {
+        for format in [ImageFormat::Png, ImageFormat::Qoi] {
+            let sprite = DecodedSprite::decode(&encode(format)).unwrap();
+            assert_eq!((sprite.width, sprite.height), (3, 2));
+            assert_eq!(sprite.frame_width(), 3);
+            match sprite.pixels {
+                SpritePixels::Rgba(pixels) => assert_eq!(&pixels[20..24], &[2, 1, 7, 255]),
+                pixels => panic!("expected rgba pixels, got {pixels:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let mut png = encode(ImageFormat::Png);
+        png.truncate(20);
+        assert!(DecodedSprite::decode(&png).is_err());
+        assert!(DecodedSprite::decode(KTX2_MAGIC).is_err());
+        assert!(DecodedSprite::decode(b"not an image").is_err());
+    }
+}
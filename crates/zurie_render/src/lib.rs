@@ -1,4 +1,6 @@
+pub mod atlas;
 pub mod compute_sand;
+pub mod decode;
 pub mod gui;
 pub mod object_draw;
 pub mod pixels_draw;
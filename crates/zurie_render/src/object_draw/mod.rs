@@ -1,2 +1,3 @@
 pub mod pipeline;
 pub mod render_pass;
+pub mod tiles;
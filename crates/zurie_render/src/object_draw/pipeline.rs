@@ -1,21 +1,23 @@
 use crate::{render::Renderer, sprite::SpriteManager};
 
+use super::tiles::{ChunkKey, TilemapDraw};
 use slotmap::KeyData;
-use std::{
-    collections::HashMap,
-    sync::{Arc, RwLock},
-};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 use vulkano::{
     buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
     command_buffer::{
-        AutoCommandBufferBuilder, CommandBufferInheritanceInfo, CommandBufferUsage,
-        SecondaryAutoCommandBuffer, allocator::StandardCommandBufferAllocator,
+        AutoCommandBufferBuilder, ClearAttachment, ClearRect, CommandBufferInheritanceInfo,
+        CommandBufferUsage, SecondaryAutoCommandBuffer, allocator::StandardCommandBufferAllocator,
     },
     descriptor_set::{
         PersistentDescriptorSet, WriteDescriptorSet, allocator::StandardDescriptorSetAllocator,
     },
     device::Queue,
-    image::sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerMipmapMode},
+    image::{
+        sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerMipmapMode},
+        view::ImageView,
+    },
     memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
     pipeline::{
         DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineLayout,
@@ -34,7 +36,7 @@ use vulkano::{
     render_pass::Subpass,
 };
 use zurie_types::Object;
-use zurie_types::SpriteHandle;
+use zurie_types::glam::Vec2;
 
 #[derive(BufferContents, Vertex)]
 #[repr(C)]
@@ -53,6 +55,10 @@ pub struct InstanceData {
     scale: [f32; 2],
     #[format(R32G32B32A32_SFLOAT)]
     color: [f32; 4],
+    #[format(R32G32_SFLOAT)]
+    uv_offset: [f32; 2],
+    #[format(R32G32_SFLOAT)]
+    uv_scale: [f32; 2],
 }
 
 pub fn textured_quad() -> Vec<TriangleVertex> {
@@ -72,6 +78,26 @@ pub fn textured_quad() -> Vec<TriangleVertex> {
     ]
 }
 
+/// What the instances of a chunk batch were built from besides the tiles.
+#[derive(PartialEq)]
+struct BatchSource {
+    position: Vec2,
+    color: [f32; 4],
+    tile_size: Vec2,
+    sprite: u64,
+    columns: u32,
+    rows: u32,
+    sprite_uv: Option<(usize, [f32; 2], [f32; 2])>,
+}
+
+/// The tiles of a chunk, uploaded once and drawn every frame until they change.
+struct ChunkBatch {
+    revision: u64,
+    source: BatchSource,
+    page: usize,
+    instances: Option<Subbuffer<[InstanceData]>>,
+}
+
 /// A subpass pipeline that fills a quad over the frame.
 pub struct ObjectDrawPipeline {
     gfx_queue: Arc<Queue>,
@@ -87,6 +113,10 @@ pub struct ObjectDrawPipeline {
     vertices: Subbuffer<[TriangleVertex]>,
     sprite_manager: Arc<RwLock<SpriteManager>>,
     indices: Subbuffer<[u32]>,
+    sampler: Arc<Sampler>,
+    /// One descriptor set per atlas page, pages are never replaced so these live forever.
+    page_descriptors: Vec<Arc<PersistentDescriptorSet>>,
+    chunk_batches: HashMap<ChunkKey, ChunkBatch>,
 }
 
 impl ObjectDrawPipeline {
@@ -195,6 +225,13 @@ impl ObjectDrawPipeline {
         };
 
         let gfx_queue = app.gfx_queue();
+        let sampler = Sampler::new(app.device.clone(), SamplerCreateInfo {
+            mag_filter: Filter::Nearest,
+            min_filter: Filter::Nearest,
+            address_mode: [SamplerAddressMode::ClampToEdge; 3],
+            mipmap_mode: SamplerMipmapMode::Nearest,
+            ..Default::default()
+        })?;
 
         Ok(Self {
             gfx_queue,
@@ -207,33 +244,40 @@ impl ObjectDrawPipeline {
 
             sprite_manager,
             indices,
+            sampler,
+            page_descriptors: Vec::new(),
+            chunk_batches: HashMap::new(),
         })
     }
 
-    fn create_descriptor(
-        &self,
-        camera: vs::Camera,
-        sprite: SpriteHandle,
-    ) -> Arc<PersistentDescriptorSet> {
+    fn update_page_descriptors(&mut self, pages: &[Arc<ImageView>]) {
         let layout = self
             .pipeline
             .layout()
             .set_layouts()
             .first()
             .expect("No set layout found");
-        let sampler = Sampler::new(self.gfx_queue.device().clone(), SamplerCreateInfo {
-            mag_filter: Filter::Nearest,
-            min_filter: Filter::Nearest,
-            address_mode: [SamplerAddressMode::Repeat; 3],
-            mipmap_mode: SamplerMipmapMode::Nearest,
-            ..Default::default()
-        })
-        .unwrap();
+        for page in pages.iter().skip(self.page_descriptors.len()) {
+            self.page_descriptors.push(
+                PersistentDescriptorSet::new(
+                    &self.descriptor_set_allocator,
+                    layout.clone(),
+                    [
+                        WriteDescriptorSet::sampler(0, self.sampler.clone()),
+                        WriteDescriptorSet::image_view(1, page.clone()),
+                    ],
+                    [],
+                )
+                .expect("Failed to create descriptor set"),
+            );
+        }
+    }
 
-        let camera_buffer = Buffer::from_data(
+    fn instance_buffer(&self, instances: Vec<InstanceData>) -> Subbuffer<[InstanceData]> {
+        Buffer::from_iter(
             self.memory_allocator.clone(),
             BufferCreateInfo {
-                usage: BufferUsage::UNIFORM_BUFFER,
+                usage: BufferUsage::VERTEX_BUFFER,
                 ..Default::default()
             },
             AllocationCreateInfo {
@@ -241,36 +285,80 @@ impl ObjectDrawPipeline {
                     | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
                 ..Default::default()
             },
-            camera,
+            instances,
         )
-        .expect("Failed to create camera buffer");
+        .unwrap()
+    }
 
-        PersistentDescriptorSet::new(
-            &self.descriptor_set_allocator,
-            layout.clone(),
-            [
-                WriteDescriptorSet::buffer(0, camera_buffer),
-                WriteDescriptorSet::sampler(1, sampler),
-                WriteDescriptorSet::image_view(
-                    2,
-                    self.sprite_manager
-                        .write()
-                        .expect("Failed to acquire sprite manager lock")
-                        .get_texture(sprite)
-                        .expect("Failed to get sprite texture"),
-                ),
-            ],
-            [],
-        )
-        .expect("Failed to create descriptor set")
+    /// Rebuilds the batches of chunks that changed and drops those of chunks that are gone.
+    pub fn update_tilemaps(&mut self, tilemaps: &[TilemapDraw]) {
+        let sprite_manager = self.sprite_manager.clone();
+        let sprite_manager = sprite_manager
+            .read()
+            .expect("Failed to acquire sprite manager lock");
+        let mut batches = HashMap::with_capacity(self.chunk_batches.len());
+        for draw in tilemaps.iter() {
+            let tileset = &draw.tilemap.tileset;
+            // Moves when the sprite finishes loading or is reloaded
+            let sprite_uv = sprite_manager.frame_uv(KeyData::from_ffi(tileset.sprite).into(), 0);
+            for (chunk, tiles) in draw.tilemap.chunks() {
+                let key = (draw.entity, chunk);
+                let source = BatchSource {
+                    position: draw.position,
+                    color: draw.color,
+                    tile_size: draw.tilemap.tile_size,
+                    sprite: tileset.sprite,
+                    columns: tileset.columns,
+                    rows: tileset.rows,
+                    sprite_uv,
+                };
+                match self.chunk_batches.remove(&key) {
+                    Some(batch) if batch.revision == tiles.revision() && batch.source == source => {
+                        batches.insert(key, batch);
+                        continue;
+                    }
+                    _ => {}
+                }
+                let Some((page, offset, scale)) = sprite_uv else {
+                    continue;
+                };
+                let instances: Vec<InstanceData> = draw
+                    .chunk_tiles(chunk, tiles)
+                    .filter_map(|(position, tile)| {
+                        let (uv_offset, uv_scale) = tileset.tile_uv(tile, offset, scale)?;
+                        Some(InstanceData {
+                            position: position.into(),
+                            scale: draw.tilemap.tile_size.into(),
+                            color: draw.color,
+                            uv_offset,
+                            uv_scale,
+                        })
+                    })
+                    .collect();
+                let instances = (!instances.is_empty()).then(|| self.instance_buffer(instances));
+                batches.insert(
+                    key,
+                    ChunkBatch {
+                        revision: tiles.revision(),
+                        source,
+                        page,
+                        instances,
+                    },
+                );
+            }
+        }
+        self.chunk_batches = batches;
     }
 
-    /// Draws input `image` over a quad of size -1.0 to 1.0.
+    /// Draws the `chunks` batched by `update_tilemaps` and then `objects` as textured quads
+    /// into `viewport`, clearing it first if `clear_color` is set.
     pub fn draw(
-        &self,
-        viewport_dimensions: [u32; 2],
+        &mut self,
+        viewport: Viewport,
+        clear_color: Option<[f32; 4]>,
         camera: vs::Camera,
-        objects: Arc<RwLock<Vec<Object>>>,
+        chunks: &[ChunkKey],
+        objects: &[Object],
     ) -> Arc<SecondaryAutoCommandBuffer> {
         let mut builder = AutoCommandBufferBuilder::secondary(
             self.command_buffer_allocator.as_ref(),
@@ -282,62 +370,71 @@ impl ObjectDrawPipeline {
             },
         )
         .unwrap();
-        let mut objects_by_texture: HashMap<SpriteHandle, Vec<InstanceData>> = Default::default();
-        for obj in objects.read().unwrap().iter() {
-            objects_by_texture
-                .entry(KeyData::from_ffi(obj.sprite).into())
-                .or_default()
-                .push(InstanceData {
+        if let Some(clear_color) = clear_color {
+            builder
+                .clear_attachments(
+                    [ClearAttachment::Color {
+                        color_attachment: 0,
+                        clear_value: clear_color.into(),
+                    }]
+                    .into_iter()
+                    .collect(),
+                    [ClearRect {
+                        offset: [viewport.offset[0] as u32, viewport.offset[1] as u32],
+                        extent: [viewport.extent[0] as u32, viewport.extent[1] as u32],
+                        array_layers: 0..1,
+                    }]
+                    .into_iter()
+                    .collect(),
+                )
+                .unwrap();
+        }
+        // Objects come sorted by z index, a new draw starts whenever the atlas page changes
+        let object_batches = {
+            let sprite_manager = self.sprite_manager.clone();
+            let sprite_manager = sprite_manager
+                .read()
+                .expect("Failed to acquire sprite manager lock");
+            self.update_page_descriptors(sprite_manager.pages());
+            let instances = objects.iter().filter_map(|obj| {
+                let (page, uv_offset, uv_scale) =
+                    sprite_manager.frame_uv(KeyData::from_ffi(obj.sprite).into(), obj.frame)?;
+                let instance = InstanceData {
                     position: obj.position.into(),
                     scale: obj.scale,
                     color: obj.color,
-                });
+                    uv_offset,
+                    uv_scale,
+                };
+                Some((page, instance))
+            });
+            page_runs(instances)
+        };
+        // Tiles go under the objects, one draw per chunk
+        let mut draws: Vec<(usize, Subbuffer<[InstanceData]>)> = chunks
+            .iter()
+            .filter_map(|key| {
+                let batch = self.chunk_batches.get(key)?;
+                Some((batch.page, batch.instances.clone()?))
+            })
+            .collect();
+        for (page, instance_data) in object_batches {
+            draws.push((page, self.instance_buffer(instance_data)));
         }
-        for (sprite, objects) in objects_by_texture {
-            let desc_set = self.create_descriptor(camera, sprite);
-            let instance_data: Vec<InstanceData> = objects
-                .iter()
-                .map(|obj| InstanceData {
-                    position: obj.position,
-                    scale: obj.scale,
-                    color: obj.color,
-                })
-                .collect();
-
-            let instance_buffer = Buffer::from_iter(
-                self.memory_allocator.clone(),
-                BufferCreateInfo {
-                    usage: BufferUsage::VERTEX_BUFFER,
-                    ..Default::default()
-                },
-                AllocationCreateInfo {
-                    memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
-                        | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
-                    ..Default::default()
-                },
-                instance_data,
-            )
-            .unwrap();
+        for (page, instance_buffer) in draws {
             let instance_buffer_len = instance_buffer.len();
             builder
-                .set_viewport(
-                    0,
-                    [Viewport {
-                        offset: [0.0, 0.0],
-                        extent: [viewport_dimensions[0] as f32, viewport_dimensions[1] as f32],
-                        depth_range: 0.0..=1.0,
-                    }]
-                    .into_iter()
-                    .collect(),
-                )
+                .set_viewport(0, [viewport.clone()].into_iter().collect())
                 .unwrap()
                 .bind_pipeline_graphics(self.pipeline.clone())
                 .unwrap()
+                .push_constants(self.pipeline.layout().clone(), 0, camera)
+                .unwrap()
                 .bind_descriptor_sets(
                     PipelineBindPoint::Graphics,
                     self.pipeline.layout().clone(),
                     0,
-                    desc_set,
+                    self.page_descriptors[page].clone(),
                 )
                 .unwrap()
                 .bind_vertex_buffers(0, (self.vertices.clone(), instance_buffer))
@@ -351,6 +448,18 @@ impl ObjectDrawPipeline {
     }
 }
 
+/// Splits `items` into runs on the same page, keeping their order.
+fn page_runs<T>(items: impl IntoIterator<Item = (usize, T)>) -> Vec<(usize, Vec<T>)> {
+    let mut runs: Vec<(usize, Vec<T>)> = Vec::new();
+    for (page, item) in items {
+        match runs.last_mut() {
+            Some((last, run)) if *last == page => run.push(item),
+            _ => runs.push((page, vec![item])),
+        }
+    }
+    runs
+}
+
 pub(crate) mod vs {
     vulkano_shaders::shader! {
         ty: "vertex",
@@ -364,3 +473,18 @@ mod fs {
         path: "shaders/object_draw/fs.glsl"
     }
 }
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn test_page_runs_keep_order() {
+        let items = [(0, 'a'), (0, 'b'), (1, 'c'), (0, 'd')];
+        assert_eq!(
+            page_runs(items),
+            vec![(0, vec!['a', 'b']), (1, vec!['c']), (0, vec!['d'])]
+        );
+        assert!(page_runs(Vec::<(usize, char)>::new()).is_empty());
+    }
+}
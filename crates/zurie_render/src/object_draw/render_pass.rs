@@ -10,9 +10,11 @@ use vulkano::{
     render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass},
     sync::GpuFuture,
 };
-use zurie_types::Object;
+use vulkano::pipeline::graphics::viewport::Viewport;
+use zurie_types::{Object, camera::Cameras};
 
 use super::pipeline::{self, ObjectDrawPipeline};
+use super::tiles::{TilemapDraw, visible_chunks};
 
 /// A render pass which places an incoming image over the frame, filling it.
 pub struct ObjectRenderPass {
@@ -57,12 +59,13 @@ impl ObjectRenderPass {
     }
 
     pub fn render<F>(
-        &self,
+        &mut self,
         before_future: F,
         target: Arc<ImageView>,
         background_color: [f32; 4],
-        camera: &zurie_types::camera::Camera,
+        cameras: &Cameras,
         objects: Arc<RwLock<Vec<Object>>>,
+        tilemaps: &[TilemapDraw],
     ) -> Box<dyn GpuFuture>
     where
         F: GpuFuture + 'static,
@@ -92,15 +95,35 @@ impl ObjectRenderPass {
                 },
             )
             .unwrap();
-        let proj_mat = camera.create_matrix().to_cols_array_2d();
-        let cam_pos = (camera.position / -camera.zoom_factor).into();
-        let cb = self.pixels_draw_pipeline.draw(
-            img_dims,
-            pipeline::vs::Camera { proj_mat, cam_pos },
-            objects,
-        );
+        let objects = objects.read().unwrap();
+        self.pixels_draw_pipeline.update_tilemaps(tilemaps);
+        // One draw per camera, in camera order so later cameras end up on top
+        for (handle, visible) in cameras.visibility_lists(&objects) {
+            let camera = cameras.get(handle).unwrap();
+            let (offset, extent) = camera.viewport.to_pixels(img_dims);
+            if extent[0] < 1.0 || extent[1] < 1.0 {
+                continue;
+            }
+            let proj_mat = camera.create_matrix().to_cols_array_2d();
+            // Zoom is only in the matrix, like `Camera::world_to_clip`
+            let cam_pos = camera.view_position().into();
+            let visible_objects: Vec<Object> =
+                visible.iter().map(|index| objects[*index]).collect();
+            let chunks = visible_chunks(camera, tilemaps);
+            let cb = self.pixels_draw_pipeline.draw(
+                Viewport {
+                    offset,
+                    extent,
+                    depth_range: 0.0..=1.0,
+                },
+                camera.clear_color,
+                pipeline::vs::Camera { proj_mat, cam_pos },
+                &chunks,
+                &visible_objects,
+            );
 
-        command_buffer_builder.execute_commands(cb).unwrap();
+            command_buffer_builder.execute_commands(cb).unwrap();
+        }
         command_buffer_builder
             .end_render_pass(Default::default())
             .unwrap();
@@ -0,0 +1,59 @@
+use zurie_types::camera::Camera;
+use zurie_types::glam::{IVec2, Vec2};
+use zurie_types::tilemap::{CHUNK_SIZE, EMPTY_TILE, TileChunk, Tilemap};
+
+/// Tilemap entity and chunk position, chunk batches are cached by it.
+pub type ChunkKey = (u64, IVec2);
+
+/// A tilemap entity to draw.
+pub struct TilemapDraw<'a> {
+    pub entity: u64,
+    pub position: Vec2,
+    pub color: [f32; 4],
+    pub layer: u32,
+    pub tilemap: &'a Tilemap,
+}
+
+impl TilemapDraw<'_> {
+    /// Center and half size of a chunk in the world.
+    pub fn chunk_area(&self, chunk: IVec2) -> (Vec2, Vec2) {
+        let size = self.tilemap.tile_size * CHUNK_SIZE as f32;
+        let min = self.position + chunk.as_vec2() * size;
+        (min + size / 2.0, size.abs() / 2.0)
+    }
+
+    /// World position of every tile in the chunk with its id.
+    pub fn chunk_tiles<'a>(
+        &'a self,
+        chunk: IVec2,
+        tiles: &'a TileChunk,
+    ) -> impl Iterator<Item = (Vec2, u32)> + 'a {
+        tiles
+            .tiles()
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| **tile != EMPTY_TILE)
+            .map(move |(index, tile)| {
+                let local = IVec2::new(index as i32 % CHUNK_SIZE, index as i32 / CHUNK_SIZE);
+                let position = self.position + self.tilemap.tile_center(chunk * CHUNK_SIZE + local);
+                (position, *tile)
+            })
+    }
+}
+
+/// Chunks `camera` sees, tilemaps in the given order and their chunks by position.
+pub fn visible_chunks(camera: &Camera, tilemaps: &[TilemapDraw]) -> Vec<ChunkKey> {
+    tilemaps
+        .iter()
+        .filter(|draw| camera.sees_layer(draw.layer))
+        .flat_map(|draw| {
+            draw.tilemap
+                .chunks()
+                .filter(|(chunk, _)| {
+                    let (center, half_size) = draw.chunk_area(*chunk);
+                    camera.sees_area(center, half_size)
+                })
+                .map(|(chunk, _)| (draw.entity, chunk))
+        })
+        .collect()
+}
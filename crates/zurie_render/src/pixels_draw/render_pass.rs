@@ -88,7 +88,8 @@ impl PixelsRenderPass {
             )
             .unwrap();
         let proj_mat = camera.create_matrix().to_cols_array_2d();
-        let cam_pos = ((camera.position) / camera.zoom_factor).into();
+        // Zoom is only in the matrix, like `Camera::world_to_clip`
+        let cam_pos = camera.view_position().into();
         let cb = self.pixels_draw_pipeline.draw(
             img_dims,
             image_view,
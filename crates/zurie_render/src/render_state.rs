@@ -1,11 +1,14 @@
 use std::sync::{Arc, RwLock};
+use vulkano::sync::GpuFuture;
 use winit::{event::WindowEvent, event_loop::ActiveEventLoop, window::Window};
-use zurie_types::{Object, camera::Camera, glam::Vec2};
+use zurie_shared::loader::AssetLoader;
+use zurie_shared::profiler::{Profiler, Stage};
+use zurie_types::{Object, camera::Cameras, glam::Vec2};
 
 use crate::{
     compute_sand::{CellType, SandComputePipeline},
     gui::GuiRender,
-    object_draw::render_pass::ObjectRenderPass,
+    object_draw::{render_pass::ObjectRenderPass, tiles::TilemapDraw},
     pixels_draw::render_pass::PixelsRenderPass,
     render::Renderer,
     sprite::SpriteManager,
@@ -21,7 +24,11 @@ pub struct RenderState {
 }
 
 impl RenderState {
-    pub fn new(window: Arc<Window>, event_loop: &ActiveEventLoop) -> anyhow::Result<RenderState> {
+    pub fn new(
+        window: Arc<Window>,
+        event_loop: &ActiveEventLoop,
+        loader: AssetLoader,
+    ) -> anyhow::Result<RenderState> {
         let renderer = Renderer::new(window);
         let gui = GuiRender::new(
             event_loop,
@@ -31,7 +38,7 @@ impl RenderState {
         );
 
         let sprite_manager: Arc<RwLock<SpriteManager>> =
-            Arc::new(RwLock::new(SpriteManager::new(gui.gui.context())));
+            Arc::new(RwLock::new(SpriteManager::new(gui.gui.context(), loader)));
 
         Ok(RenderState {
             //compute: SandComputePipeline::new(&renderer),
@@ -46,20 +53,30 @@ impl RenderState {
     pub fn render(
         &mut self,
         background_color: [f32; 4],
-        camera: &Camera,
+        cameras: &Cameras,
         objects: Arc<RwLock<Vec<Object>>>,
+        tilemaps: &[TilemapDraw],
+        profiler: &Profiler,
     ) -> anyhow::Result<()> {
-        {
+        let sprite_upload = {
+            let _scope = profiler.scope(Stage::SpriteQueue);
             let mut sprite_manager = self.sprite_manager.write().unwrap();
-            sprite_manager.process_queue(
+            sprite_manager.hot_reload();
+            let sprite_upload = sprite_manager.process_queue(
                 self.renderer.memory_allocator.clone(),
                 self.renderer.command_buffer_allocator.clone(),
                 self.renderer.gfx_queue.clone(),
             )?;
             sprite_manager.gui();
-        }
+            sprite_upload
+        };
 
         let before_pipeline_future = self.renderer.acquire()?;
+        // Newly loaded sprites are uploaded before the frame samples the atlas
+        let before_pipeline_future = match sprite_upload {
+            Some(sprite_upload) => before_pipeline_future.join(sprite_upload).boxed(),
+            None => before_pipeline_future,
+        };
 
         // Compute.
         // let after_compute = self
@@ -79,16 +96,20 @@ impl RenderState {
         //     //&self.object_storage.read().unwrap(),
         // );
 
+        let render_scope = profiler.scope(Stage::Render);
         let after_objects_render = self.objects_render.render(
             before_pipeline_future,
             target_image.clone(),
             background_color,
-            camera,
+            cameras,
             objects,
+            tilemaps,
         );
         let after_gui = self.gui.draw_on_image(after_objects_render, target_image);
+        drop(render_scope);
 
         // Finish the frame. Wait for the future so resources are not in use when we render.
+        let _scope = profiler.scope(Stage::Present);
         self.renderer.present(after_gui, true);
 
         Ok(())
@@ -1,26 +1,31 @@
-use anyhow::Ok;
-use asefile::AsepriteFile;
+use crate::atlas::{AtlasAllocation, AtlasPacker};
+use crate::decode::{DecodedSprite, SpritePixels};
+use anyhow::{Ok, bail};
 use egui_winit_vulkano::egui::load::SizedTexture;
 use egui_winit_vulkano::egui::{self, ColorImage, Context, TextureHandle};
 use log::info;
-use slotmap::SlotMap;
+use slotmap::SecondaryMap;
+use std::sync::mpsc::{Receiver, Sender, channel};
 use std::{path::Path, sync::Arc};
 use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage};
 use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
 use vulkano::command_buffer::{
-    AutoCommandBufferBuilder, CommandBufferUsage, CopyBufferToImageInfo,
-    PrimaryCommandBufferAbstract,
+    AutoCommandBufferBuilder, BufferImageCopy, ClearColorImageInfo, CommandBufferUsage,
+    CopyBufferToImageInfo, PrimaryAutoCommandBuffer, PrimaryCommandBufferAbstract,
 };
-use vulkano::device::Queue;
+use vulkano::device::{DeviceOwned, Queue};
 use vulkano::memory::allocator::MemoryTypeFilter;
 use vulkano::sync::GpuFuture;
 use vulkano::{
-    format::Format,
+    format::{Format, FormatFeatures},
     image::{Image, ImageCreateInfo, ImageType, ImageUsage, view::ImageView},
     memory::allocator::{AllocationCreateInfo, StandardMemoryAllocator},
 };
+use zurie_assets::{Acquired, AssetStore};
+use zurie_shared::loader::AssetLoader;
 use zurie_shared::slotmap::Key;
-use zurie_types::SpriteHandle;
+use zurie_types::animation::SpriteSheet;
+use zurie_types::{LoadStatus, SpriteHandle};
 
 #[derive(Debug)]
 pub enum LoadSpriteInfo {
@@ -28,110 +33,335 @@ pub enum LoadSpriteInfo {
     Buffer(Vec<u8>),
 }
 
+impl LoadSpriteInfo {
+    fn decode(&self) -> anyhow::Result<DecodedSprite> {
+        match self {
+            LoadSpriteInfo::Path(path) => {
+                info!("Loading sprite from {:?}", path);
+                DecodedSprite::decode(&zurie_assets::read_asset(path)?)
+            }
+            LoadSpriteInfo::Buffer(buffer) => DecodedSprite::decode(buffer),
+        }
+    }
+
+    fn path(&self) -> Option<String> {
+        match self {
+            LoadSpriteInfo::Path(path) => path.to_str().map(str::to_string),
+            LoadSpriteInfo::Buffer(_) => None,
+        }
+    }
+}
+
+/// Sprite decoded by a loader worker, waiting to be uploaded.
+type DecodeResult = (SpriteHandle, Option<String>, anyhow::Result<DecodedSprite>);
+
 pub struct SpriteManager {
-    sprites: SlotMap<SpriteHandle, Option<Sprite>>,
-    to_load_queue: Vec<(SpriteHandle, LoadSpriteInfo)>,
+    /// Hands out handles, dedups paths and watches sprite files for hot reload.
+    assets: AssetStore<SpriteHandle>,
+    sprites: SecondaryMap<SpriteHandle, Sprite>,
     error_sprite: SpriteHandle,
     egui_context: Context,
+    atlas: SpriteAtlas,
+    /// Why queued sprites failed to load, kept so mods can ask for it.
+    errors: SecondaryMap<SpriteHandle, String>,
+    loader: AssetLoader,
+    decoded_sender: Sender<DecodeResult>,
+    decoded_receiver: Receiver<DecodeResult>,
+    /// Sprites that finished loading or failed since the last `take_completed`.
+    completed: Vec<SpriteHandle>,
+    /// Loads only hand out handles, there is no GPU to upload to.
+    headless: bool,
 }
 
 impl SpriteManager {
-    pub fn new(egui_context: Context) -> Self {
-        let mut sprites: SlotMap<SpriteHandle, Option<Sprite>> = Default::default();
-        let error_sprite = sprites.insert(None);
+    /// Uploads per frame, the rest waits for the next frame so big loads don't hitch.
+    const MAX_UPLOADS_PER_FRAME: usize = 32;
+    const EXTENSIONS: &[&str] = &["aseprite", "ase", "png", "qoi", "ktx2"];
+
+    pub fn new(egui_context: Context, loader: AssetLoader) -> Self {
+        let mut assets = AssetStore::watched(Self::EXTENSIONS);
+        let error_sprite = assets.insert();
+        let (decoded_sender, decoded_receiver) = channel();
+        let sprite_manager = Self {
+            assets,
+            sprites: SecondaryMap::new(),
+            error_sprite,
+            egui_context,
+            atlas: SpriteAtlas::new(),
+            errors: SecondaryMap::new(),
+            loader,
+            decoded_sender,
+            decoded_receiver,
+            completed: Vec::new(),
+            headless: false,
+        };
+        sprite_manager.decode_in_background(
+            error_sprite,
+            LoadSpriteInfo::Buffer(include_bytes!("../../../static/error.aseprite").to_vec()),
+        );
+        sprite_manager
+    }
+
+    /// Manager that accepts sprite loads as no-ops, they are ready right away and never
+    /// decoded.
+    pub fn headless(egui_context: Context, loader: AssetLoader) -> Self {
+        let mut assets = AssetStore::default();
+        let error_sprite = assets.insert();
+        let (decoded_sender, decoded_receiver) = channel();
         Self {
-            sprites,
-            to_load_queue: vec![(
-                error_sprite,
-                LoadSpriteInfo::Buffer(include_bytes!("../../../static/error.aseprite").to_vec()),
-            )],
+            assets,
+            sprites: SecondaryMap::new(),
             error_sprite,
             egui_context,
+            atlas: SpriteAtlas::new(),
+            errors: SecondaryMap::new(),
+            loader,
+            decoded_sender,
+            decoded_receiver,
+            completed: Vec::new(),
+            headless: true,
         }
     }
+
     pub fn gui(&mut self) {
         egui::Window::new("Sprite manager").show(&self.egui_context, |ctx| {
-            for sprite in self.sprites.iter() {
-                if let Some(sprite) = &sprite.1.as_ref() {
-                    ctx.image(SizedTexture::from_handle(&sprite.egui_texture_handle));
+            for sprite in self.sprites.values() {
+                if let Some(handle) = sprite.egui_texture_handle.as_ref() {
+                    ctx.image(SizedTexture::from_handle(handle));
                 }
             }
         });
     }
 
+    fn decode_in_background(&self, handle: SpriteHandle, to_load: LoadSpriteInfo) {
+        let sender = self.decoded_sender.clone();
+        self.loader.spawn(move || {
+            let _ = sender.send((handle, to_load.path(), to_load.decode()));
+        });
+    }
+
+    /// Starts decoding on a loader worker, the sprite is uploaded by `process_queue` once ready.
+    /// A path that is already loaded returns its handle, every load needs an `unload`.
     pub fn push_to_load_queue(&mut self, to_load: LoadSpriteInfo) -> SpriteHandle {
-        let handle = self.sprites.insert(None);
+        let handle = match &to_load {
+            LoadSpriteInfo::Path(path) => match self.assets.load_path(path) {
+                Acquired::New(handle) => handle,
+                Acquired::Existing(handle) => return handle,
+            },
+            LoadSpriteInfo::Buffer(_) => self.assets.insert(),
+        };
+        if self.headless {
+            self.completed.push(handle);
+            return handle;
+        }
         info!(
             "Sprite added to load queue, {:?} {}",
             to_load,
             handle.data().as_ffi()
         );
-        self.to_load_queue.push((handle, to_load));
+        self.decode_in_background(handle, to_load);
 
         handle
     }
 
+    /// Uploads sprites decoded since the last call in one batch. The returned future has to
+    /// run before anything samples the atlas, nothing waits on the CPU.
     pub fn process_queue(
         &mut self,
         memory_allocator: Arc<StandardMemoryAllocator>,
         command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
         queue: Arc<Queue>,
-    ) -> anyhow::Result<()> {
-        if !self.to_load_queue.is_empty() {
-            info!("starting processing sprites from queue");
+    ) -> anyhow::Result<Option<Box<dyn GpuFuture>>> {
+        let decoded: Vec<DecodeResult> = self
+            .decoded_receiver
+            .try_iter()
+            .take(Self::MAX_UPLOADS_PER_FRAME)
+            .collect();
+        if decoded.is_empty() {
+            return Ok(None);
         }
-        for (handle, to_load) in self.to_load_queue.drain(..) {
-            if let Some(slot) = self.sprites.get_mut(handle) {
-                *slot = match to_load {
-                    LoadSpriteInfo::Path(path) => {
-                        info!("processing {:?}", handle);
-                        Some(Sprite::from_file(
-                            &path,
-                            memory_allocator.clone(),
-                            command_buffer_allocator.clone(),
-                            queue.clone(),
-                            self.egui_context.clone(),
-                        )?)
+        info!("uploading {} decoded sprites", decoded.len());
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            command_buffer_allocator.as_ref(),
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        for (handle, path, sprite) in decoded {
+            // Unloaded while it was decoding
+            if !self.assets.contains(handle) {
+                continue;
+            }
+            let sprite = sprite.and_then(|sprite| {
+                self.create_sprite(sprite, path.clone(), &mut builder, memory_allocator.clone())
+            });
+            let previous = self.sprites.get(handle).map(|previous| previous.atlas);
+            match (sprite, previous) {
+                (Result::Ok(sprite), previous) => {
+                    // Hot reloaded sprites give their old atlas space back
+                    if let Some(previous) = previous {
+                        self.atlas.packer.free(previous);
                     }
-                    LoadSpriteInfo::Buffer(buf) => Some(Sprite::from_buffer(
-                        &buf,
-                        memory_allocator.clone(),
-                        command_buffer_allocator.clone(),
-                        queue.clone(),
-                        self.egui_context.clone(),
-                    )?),
+                    self.sprites.insert(handle, sprite);
+                    self.errors.remove(handle);
+                }
+                (Err(err), Some(_)) => {
+                    log::error!("Failed to reload sprite {path:?}, keeping the old one: {err:#}")
+                }
+                (Err(err), None) => {
+                    log::error!(
+                        "Failed to load sprite {} from {:?}: {err:#}",
+                        handle.data().as_ffi(),
+                        path
+                    );
+                    self.errors.insert(handle, format!("{err:#}"));
                 }
             }
+            self.completed.push(handle);
+        }
+
+        let command_buffer = builder.build()?;
+        Ok(Some(command_buffer.execute(queue)?.boxed()))
+    }
+
+    /// Records the upload of `sprite` into `builder`.
+    fn create_sprite(
+        &mut self,
+        sprite: DecodedSprite,
+        path: Option<String>,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+    ) -> anyhow::Result<Sprite> {
+        let allocation = self.atlas.upload(&sprite, builder, memory_allocator)?;
+        let egui_texture_handle = match &sprite.pixels {
+            SpritePixels::Rgba(rgba_data) => Some(self.egui_context.load_texture(
+                "",
+                ColorImage::from_rgba_unmultiplied(
+                    [sprite.width as usize, sprite.height as usize],
+                    rgba_data,
+                ),
+                Default::default(),
+            )),
+            SpritePixels::Compressed { .. } => None,
+        };
+        Ok(Sprite {
+            width: sprite.frame_width(),
+            height: sprite.height,
+            path,
+            egui_texture_handle,
+            sheet: sprite.sheet,
+            atlas: allocation,
+        })
+    }
+
+    /// Handle of the sprite loaded from `path`, without taking a reference to it.
+    pub fn handle_of(&self, path: impl AsRef<Path>) -> Option<SpriteHandle> {
+        self.assets.handle(path)
+    }
+
+    /// File the sprite was loaded from, `None` for sprites loaded from memory.
+    pub fn path_of(&self, handle: SpriteHandle) -> Option<&Path> {
+        self.assets.path(handle)
+    }
+
+    /// Error of a sprite that failed to load, `None` while it is loading or once loaded.
+    pub fn load_error(&self, handle: SpriteHandle) -> Option<&str> {
+        self.errors.get(handle).map(String::as_str)
+    }
+
+    pub fn status(&self, handle: SpriteHandle) -> LoadStatus {
+        if !self.assets.contains(handle) {
+            return LoadStatus::Failed("Unknown sprite handle".into());
+        }
+        match (self.sprites.contains_key(handle), self.errors.get(handle)) {
+            (true, _) => LoadStatus::Ready,
+            (false, Some(error)) => LoadStatus::Failed(error.clone()),
+            (false, None) if self.headless => LoadStatus::Ready,
+            (false, None) => LoadStatus::Pending,
         }
-        Ok(())
     }
 
-    pub fn get_texture(&self, handle: SpriteHandle) -> Option<Arc<ImageView>> {
-        let sprite = self.sprites.get(handle);
-        let result = sprite
-            .and_then(|sprite| sprite.as_ref())
-            .map(|sprite| sprite.texture.clone());
+    /// Drops one load of the sprite, it is freed from the atlas once nothing uses it.
+    pub fn unload(&mut self, handle: SpriteHandle) {
+        if handle == self.error_sprite || !self.assets.release(handle) {
+            return;
+        }
+        if let Some(sprite) = self.sprites.remove(handle) {
+            self.atlas.packer.free(sprite.atlas);
+        }
+        self.errors.remove(handle);
+    }
+
+    /// Sprites that finished loading, successfully or not, since the last call.
+    pub fn take_completed(&mut self) -> Vec<SpriteHandle> {
+        std::mem::take(&mut self.completed)
+    }
+
+    /// Atlas pages holding every loaded sprite.
+    pub fn pages(&self) -> &[Arc<ImageView>] {
+        &self.atlas.pages
+    }
 
-        if result.is_none() {
+    /// Atlas page and UV offset/scale of `frame`, falls back to the error sprite.
+    pub fn frame_uv(
+        &self,
+        handle: SpriteHandle,
+        frame: u32,
+    ) -> Option<(usize, [f32; 2], [f32; 2])> {
+        let sprite = self.sprites.get(handle).or_else(|| {
             log::warn!(
                 "Failed to get texture for sprite handle {:?}",
                 handle.data().as_ffi()
             );
-            return self
-                .sprites
-                .get(self.error_sprite)
-                .and_then(|sprite| sprite.as_ref())
-                .map(|sprite| sprite.texture.clone());
-        }
+            self.sprites.get(self.error_sprite)
+        })?;
+        let page_size = self.atlas.packer.page_size(sprite.atlas.page)?;
+        let (offset, scale) = sprite
+            .atlas
+            .rect
+            .column(frame, sprite.sheet.frame_count())
+            .uv(page_size);
+        Some((sprite.atlas.page, offset, scale))
+    }
 
-        result
+    /// Frames and tags of a sprite, the error sprite's if it is not loaded.
+    pub fn get_sheet(&self, handle: SpriteHandle) -> Option<&SpriteSheet> {
+        self.sprites
+            .get(handle)
+            .or_else(|| self.sprites.get(self.error_sprite))
+            .map(|sprite| &sprite.sheet)
     }
 
-    pub fn get_sprite(&self, handle: SpriteHandle) -> &Option<Sprite> {
-        if let Some(sprite) = self.sprites.get(handle) {
-            return sprite;
-        }
-        &None
+    pub fn get_sprite(&self, handle: SpriteHandle) -> Option<&Sprite> {
+        self.sprites.get(handle)
+    }
+
+    /// Loads a sprite right away, blocking until it is on the GPU.
+    fn load_now(
+        &mut self,
+        to_load: LoadSpriteInfo,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        queue: Arc<Queue>,
+    ) -> anyhow::Result<SpriteHandle> {
+        let mut builder = AutoCommandBufferBuilder::primary(
+            command_buffer_allocator.as_ref(),
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        let sprite = self.create_sprite(
+            to_load.decode()?,
+            to_load.path(),
+            &mut builder,
+            memory_allocator,
+        )?;
+        let command_buffer = builder.build()?;
+        let future = command_buffer.execute(queue)?;
+        // Wait for the GPU to finish
+        future.then_signal_fence_and_flush()?.wait(None)?;
+        let handle = self.assets.insert();
+        self.sprites.insert(handle, sprite);
+        Ok(handle)
     }
 
     pub fn load_from_file(
@@ -141,13 +371,12 @@ impl SpriteManager {
         command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
         queue: Arc<Queue>,
     ) -> anyhow::Result<SpriteHandle> {
-        Ok(self.sprites.insert(Some(Sprite::from_file(
-            path,
+        self.load_now(
+            LoadSpriteInfo::Path(Box::from(path)),
             memory_allocator,
             command_buffer_allocator,
             queue,
-            self.egui_context.clone(),
-        )?)))
+        )
     }
 
     pub fn load_from_buffer(
@@ -157,137 +386,144 @@ impl SpriteManager {
         command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
         queue: Arc<Queue>,
     ) -> anyhow::Result<SpriteHandle> {
-        Ok(self.sprites.insert(Some(Sprite::from_buffer(
-            buffer,
+        self.load_now(
+            LoadSpriteInfo::Buffer(buffer.to_vec()),
             memory_allocator,
             command_buffer_allocator,
             queue,
-            self.egui_context.clone(),
-        )?)))
+        )
     }
 
-    pub fn reload_sprites(
-        &mut self,
-        memory_allocator: Arc<StandardMemoryAllocator>,
-        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
-        queue: Arc<Queue>,
-    ) -> anyhow::Result<()> {
-        for sprite in self.sprites.iter_mut() {
-            if let Some(sprite_data) = sprite.1.as_ref() {
-                if let Some(path) = &sprite_data.path {
-                    *sprite.1 = Some(Sprite::from_file(
-                        Path::new(path),
-                        memory_allocator.clone(),
-                        command_buffer_allocator.clone(),
-                        queue.clone(),
-                        self.egui_context.clone(),
-                    )?);
-                }
+    /// Decodes every sprite loaded from a file again, they are swapped in by `process_queue`.
+    pub fn reload_sprites(&mut self) {
+        if self.headless {
+            return;
+        }
+        for (handle, path) in self.assets.paths() {
+            self.decode_in_background(handle, LoadSpriteInfo::Path(Box::from(path)));
+        }
+    }
+
+    /// Decodes sprites whose file changed on disk again.
+    pub fn hot_reload(&mut self) {
+        for handle in self.assets.changed() {
+            if let Some(path) = self.assets.path(handle) {
+                info!("Hot reloading sprite {path:?}");
+                self.decode_in_background(handle, LoadSpriteInfo::Path(Box::from(path)));
             }
         }
-        Ok(())
     }
 }
 
-pub struct Sprite {
-    pub texture: Arc<ImageView>,
-    pub width: u32,
-    pub height: u32,
-    pub path: Option<String>,
-    pub egui_texture_handle: TextureHandle,
+/// Atlas pages on the GPU, sprites are copied into the rect the packer gives them.
+struct SpriteAtlas {
+    packer: AtlasPacker,
+    pages: Vec<Arc<ImageView>>,
 }
 
-impl Sprite {
-    pub fn from_buffer(
-        buffer: &[u8],
-        memory_allocator: Arc<StandardMemoryAllocator>,
-        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
-        queue: Arc<Queue>,
-        ctx: Context,
-    ) -> anyhow::Result<Self> {
-        let ase = AsepriteFile::read(buffer)?;
-        let (texture, width, height, handle) =
-            texture_from_ase(ase, memory_allocator, command_buffer_allocator, queue, ctx)?;
-        Ok(Self {
-            texture,
-            width,
-            height,
-            path: None,
-            egui_texture_handle: handle,
-        })
+impl SpriteAtlas {
+    const PAGE_SIZE: u32 = 2048;
+
+    fn new() -> Self {
+        Self {
+            packer: AtlasPacker::new(Self::PAGE_SIZE, 1),
+            pages: Vec::new(),
+        }
     }
-    pub fn from_file(
-        path: &Path,
+
+    /// Records copying the pixels of `sprite` into the atlas, compressed sprites get a page of
+    /// their own.
+    fn upload(
+        &mut self,
+        sprite: &DecodedSprite,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
         memory_allocator: Arc<StandardMemoryAllocator>,
-        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
-        queue: Arc<Queue>,
-        ctx: Context,
-    ) -> anyhow::Result<Self> {
-        info!("Loading sprite from {:?}", path);
-        let ase = AsepriteFile::read_file(path)?;
-
-        let path_str = path.to_str().expect("Error getting path").to_string();
-        let (texture, width, height, handle) =
-            texture_from_ase(ase, memory_allocator, command_buffer_allocator, queue, ctx)?;
-        info!("sprite loaded");
-        Ok(Self {
-            texture,
-            width,
-            height,
-            path: Some(path_str),
-            egui_texture_handle: handle,
-        })
-    }
-    pub fn texture(&self) -> Arc<ImageView> {
-        self.texture.clone()
+    ) -> anyhow::Result<AtlasAllocation> {
+        let (format, data) = match &sprite.pixels {
+            SpritePixels::Rgba(data) => (Format::R8G8B8A8_UNORM, data),
+            SpritePixels::Compressed { format, data } => {
+                let supported = memory_allocator
+                    .device()
+                    .physical_device()
+                    .format_properties(*format)?
+                    .optimal_tiling_features
+                    .intersects(FormatFeatures::SAMPLED_IMAGE);
+                if !supported {
+                    bail!("{format:?} textures are not supported by this GPU");
+                }
+                (*format, data)
+            }
+        };
+
+        let allocation = if format == Format::R8G8B8A8_UNORM {
+            let allocation = self.packer.pack(sprite.width, sprite.height);
+            while self.pages.len() < self.packer.page_count() {
+                let size = self.packer.page_size(self.pages.len()).unwrap();
+                let image = create_page_image(memory_allocator.clone(), format, size)?;
+                // Start transparent so padding never shows garbage
+                builder.clear_color_image(ClearColorImageInfo {
+                    clear_value: [0.0; 4].into(),
+                    ..ClearColorImageInfo::image(image.clone())
+                })?;
+                self.pages.push(ImageView::new_default(image)?);
+            }
+            allocation
+        } else {
+            let allocation = self.packer.reserve_page(sprite.width, sprite.height);
+            let size = self.packer.page_size(allocation.page).unwrap();
+            let image = create_page_image(memory_allocator.clone(), format, size)?;
+            self.pages.push(ImageView::new_default(image)?);
+            allocation
+        };
+
+        // Create a buffer with the pixel data
+        let upload_buffer = Buffer::from_iter(
+            memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            data.iter().copied(),
+        )?;
+
+        // Compressed data always covers whole blocks
+        let [block_width, block_height, _] = format.block_extent();
+        let image = self.pages[allocation.page].image().clone();
+        builder.copy_buffer_to_image(CopyBufferToImageInfo {
+            regions: [BufferImageCopy {
+                image_subresource: image.subresource_layers(),
+                image_offset: [allocation.rect.x, allocation.rect.y, 0],
+                image_extent: [
+                    sprite.width.next_multiple_of(block_width),
+                    sprite.height.next_multiple_of(block_height),
+                    1,
+                ],
+                ..Default::default()
+            }]
+            .into(),
+            ..CopyBufferToImageInfo::buffer_image(upload_buffer, image)
+        })?;
+
+        Ok(allocation)
     }
 }
 
-fn texture_from_ase(
-    ase: AsepriteFile,
+fn create_page_image(
     memory_allocator: Arc<StandardMemoryAllocator>,
-    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
-    queue: Arc<Queue>,
-    ctx: Context,
-) -> anyhow::Result<(Arc<ImageView>, u32, u32, TextureHandle)> {
-    let frame = ase.frame(0).image();
-
-    let width = frame.width();
-    let height = frame.height();
-    let rgba_data: Vec<u8> = frame
-        .pixels()
-        .flat_map(|p| [p[0], p[1], p[2], p[3]])
-        .collect();
-    let egui_handle = ctx.load_texture(
-        "",
-        ColorImage::from_rgba_unmultiplied(
-            [frame.width() as usize, frame.height() as usize],
-            &rgba_data,
-        ),
-        Default::default(),
-    );
-    // Create a buffer with the pixel data
-    let upload_buffer = Buffer::from_iter(
-        memory_allocator.clone(),
-        BufferCreateInfo {
-            usage: BufferUsage::TRANSFER_SRC,
-            ..Default::default()
-        },
-        AllocationCreateInfo {
-            memory_type_filter: MemoryTypeFilter::PREFER_HOST
-                | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
-            ..Default::default()
-        },
-        rgba_data,
-    )?;
-
-    // Create the image
-    let image = Image::new(
+    format: Format,
+    size: u32,
+) -> anyhow::Result<Arc<Image>> {
+    Ok(Image::new(
         memory_allocator,
         ImageCreateInfo {
             image_type: ImageType::Dim2d,
-            format: Format::R8G8B8A8_UNORM,
-            extent: [width, height, 1],
+            format,
+            extent: [size, size, 1],
             usage: ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
             ..Default::default()
         },
@@ -295,26 +531,16 @@ fn texture_from_ase(
             memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
             ..Default::default()
         },
-    )?;
-
-    // Create command buffer to copy buffer to image
-    let mut builder = AutoCommandBufferBuilder::primary(
-        command_buffer_allocator.as_ref(),
-        queue.queue_family_index(),
-        CommandBufferUsage::OneTimeSubmit,
-    )?;
-
-    builder.copy_buffer_to_image(CopyBufferToImageInfo::buffer_image(
-        upload_buffer,
-        image.clone(),
-    ))?;
-
-    let command_buffer = builder.build()?;
-    let future = command_buffer.execute(queue)?;
-    // Wait for the GPU to finish
-    future.then_signal_fence_and_flush()?.wait(None)?;
-
-    let texture = ImageView::new_default(image)?;
+    )?)
+}
 
-    Ok((texture, width, height, egui_handle))
+pub struct Sprite {
+    pub width: u32,
+    pub height: u32,
+    pub path: Option<String>,
+    /// Preview for the sprite manager window, compressed sprites have none.
+    pub egui_texture_handle: Option<TextureHandle>,
+    pub sheet: SpriteSheet,
+    /// Where the frames of the sprite are stored, side by side.
+    pub atlas: AtlasAllocation,
 }
@@ -0,0 +1,32 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use wasmtime_wasi::HostMonotonicClock;
+
+/// Monotonic clock the mods see, it only moves when the engine ticks. `Instant` in a mod
+/// measures engine time, so a replay sees the same times as its recording.
+#[derive(Clone, Default)]
+pub struct ModClock {
+    nanos: Arc<AtomicU64>,
+}
+
+impl ModClock {
+    pub fn advance(&self, delta_time: f32) {
+        let nanos = (delta_time.max(0.0) as f64 * 1e9) as u64;
+        self.nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::Relaxed))
+    }
+}
+
+impl HostMonotonicClock for ModClock {
+    fn resolution(&self) -> u64 {
+        1
+    }
+
+    fn now(&self) -> u64 {
+        self.nanos.load(Ordering::Relaxed)
+    }
+}
@@ -22,27 +22,36 @@ use zurie_event::{EventManager, ModEventQueue};
 use zurie_input::InputState;
 use zurie_types::ModHandle;
 
+use crate::functions::zurie::engine::console::ArgValue;
 use crate::functions::zurie::engine::core::EventHandle;
 use crate::functions::{EventData, ZurieMod};
 
 use crate::ScriptingState;
+use crate::clock::ModClock;
+use crate::functions::rand::mod_seed;
+use crate::noise::Perlin;
+use crate::profile::{self, time_host_calls};
 use anyhow::Ok;
 use egui::Context;
 use hashbrown::HashSet;
-use log::info;
-#[cfg(target_os = "android")]
-use std::ffi::CString;
+use log::trace;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::path::Path;
 use std::sync::{Arc, RwLock};
+use std::time::Instant;
 use wasmtime::component::*;
 use wasmtime::{Engine, Store};
-#[cfg(target_os = "android")]
-use winit::platform::android::activity::AndroidApp;
 use zurie_audio::AudioManager;
 use zurie_ecs::World;
 use zurie_event::EventData as EngineEventData;
+use zurie_physics::Physics;
+use zurie_prefab::Prefabs;
 use zurie_render::sprite::SpriteManager;
+use zurie_shared::profiler::{ModFrame, zone};
 use zurie_shared::slotmap::{Key, KeyData};
-use zurie_types::{KeyCode, camera::Camera, glam::Vec2};
+use zurie_types::console::{ArgValue as EngineArgValue, Command};
+use zurie_types::{KeyCode, camera::Cameras, glam::Vec2};
 pub struct EngineMod {
     pub path: String,
     pub bindings: ZurieMod,
@@ -50,6 +59,27 @@ pub struct EngineMod {
     pub mod_name: Arc<RwLock<String>>,
     pub subscribed_keys: Arc<RwLock<HashSet<KeyCode>>>,
     pub event_queue: ModEventQueue,
+    /// Console commands the mod registered.
+    pub console_commands: Arc<RwLock<Vec<Command>>>,
+    name: String,
+    zones: ZoneNames,
+}
+
+/// Profiler zones of a mod, named once since they're entered every frame.
+struct ZoneNames {
+    events: String,
+    update: String,
+    key_event: String,
+}
+
+impl ZoneNames {
+    fn new(name: &str) -> Self {
+        Self {
+            events: format!("{name} events"),
+            update: format!("{name} update"),
+            key_event: format!("{name} key event"),
+        }
+    }
 }
 
 impl EngineMod {
@@ -59,51 +89,82 @@ impl EngineMod {
         gui_context: Context,
         input_state: InputState,
         world: Arc<RwLock<World>>,
-        camera: Arc<RwLock<Camera>>,
+        cameras: Arc<RwLock<Cameras>>,
         event_manager: Arc<RwLock<EventManager>>,
         mod_handle: ModHandle,
         sprite_manager: Arc<RwLock<SpriteManager>>,
         audio_manager: AudioManager,
-
-        #[cfg(target_os = "android")] android_app: AndroidApp,
+        physics: Arc<RwLock<Physics>>,
+        prefabs: Arc<RwLock<Prefabs>>,
+        seed: u64,
+        clock: ModClock,
     ) -> anyhow::Result<Self> {
         let engine = Engine::default();
-        #[cfg(not(target_os = "android"))]
-        let component = Component::from_file(&engine, &mod_path)?;
-        #[cfg(target_os = "android")]
-        let component = Component::from_binary(
-            &engine,
-            include_bytes!("../../.././target/wasm32-wasip2/release/vampire_like_demo.wasm"),
-        )?;
+        let component = Component::from_binary(&engine, &zurie_assets::read_asset(&mod_path)?)?;
 
         let mut linker: Linker<ScriptingState> = Linker::new(&engine);
 
         wasmtime_wasi::add_to_linker_sync(&mut linker)?;
-        ZurieMod::add_to_linker(&mut linker, |state: &mut ScriptingState| state)?;
-        let wasi = WasiCtxBuilder::new().inherit_stdio().inherit_args().build();
+        profile::add_to_linker(&mut linker)?;
+        // Clock and randomness come from the engine so replays run the same way
+        let seed = mod_seed(seed, mod_handle);
+        let wasi = WasiCtxBuilder::new()
+            .inherit_stdio()
+            .inherit_args()
+            .monotonic_clock(clock)
+            .secure_random(ChaCha8Rng::seed_from_u64(seed.rotate_left(16)))
+            .insecure_random(ChaCha8Rng::seed_from_u64(seed.rotate_left(32)))
+            .insecure_random_seed(seed as u128)
+            .build();
         let subscribed_keys: Arc<RwLock<HashSet<KeyCode>>> = Default::default();
+        let console_commands: Arc<RwLock<Vec<Command>>> = Default::default();
         let sprite_component = world.write().unwrap().register_component("sprite".into());
+        let animation_component = world
+            .write()
+            .unwrap()
+            .register_component("animation".into());
+        let layer_component = world.write().unwrap().register_component("layer".into());
+        let position_component = world.write().unwrap().register_component("position".into());
+        let tilemap_component = world.write().unwrap().register_component("tilemap".into());
+        let selected_camera = cameras.read().unwrap().main();
         let scripting_state = ScriptingState {
             sprite_manager,
             sprite_component,
+            animation_component,
             gui_context,
             windows: Default::default(),
             world,
             audio_manager,
+            position_component,
             wasi_ctx: wasi,
             resource_table: ResourceTable::new(),
             subscribed_keys: subscribed_keys.clone(),
             input_state,
-            camera,
+            cameras,
+            selected_camera,
+            layer_component,
             event_manager,
             mod_handle,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            noise: Perlin::new(seed),
+            physics,
+            tilemap_component,
+            prefabs,
+            console_commands: console_commands.clone(),
+            profile: Default::default(),
         };
 
         let mut store = Store::new(&engine, scripting_state);
+        time_host_calls(&mut store);
 
         let bindings = ZurieMod::instantiate(&mut store, &component, &linker)?;
 
         bindings.call_init(&mut store)?;
+        let name = match Path::new(&mod_path).file_stem() {
+            Some(stem) => stem.to_string_lossy().into_owned(),
+            None => mod_path.clone(),
+        };
+        let zones = ZoneNames::new(&name);
         Ok(Self {
             path: mod_path,
             bindings,
@@ -111,31 +172,59 @@ impl EngineMod {
             mod_name: Default::default(),
             subscribed_keys,
             event_queue: Default::default(),
+            console_commands,
+            name,
+            zones,
         })
     }
 
     pub fn update(&mut self) -> anyhow::Result<()> {
         //Proccesing events
-        for event in self.event_queue.drain().iter() {
-            self.bindings.call_event(
-                &mut self.store,
-                KeyData::as_ffi(event.handle.data()),
-                &EventData::from(event.data.clone()),
-            )?;
+        let events = self.event_queue.drain();
+        if !events.is_empty() {
+            let _zone = zone(&self.zones.events);
+            for event in events.iter() {
+                let start = Instant::now();
+                self.bindings.call_event(
+                    &mut self.store,
+                    KeyData::as_ffi(event.handle.data()),
+                    &EventData::from(event.data.clone()),
+                )?;
+                let time = start.elapsed();
+                self.store.data_mut().profile.frame.event.add(time);
+            }
         }
+        let _zone = zone(&self.zones.update);
+        let start = Instant::now();
         self.bindings.call_update(&mut self.store)?;
+        let time = start.elapsed();
+        self.store.data_mut().profile.frame.update.add(time);
         Ok(())
     }
 
+    /// Name the profiler shows, the file name of the mod.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// What the mod did since the last call.
+    pub fn take_profile(&mut self) -> ModFrame {
+        std::mem::take(&mut self.store.data_mut().profile.frame)
+    }
+
     pub fn key_event(&mut self, key_code: KeyCode) -> anyhow::Result<()> {
         let keys_lock = self.subscribed_keys.read().unwrap();
-        info!("key clicked {:?}", &key_code);
+        trace!("key clicked {:?}", &key_code);
         if keys_lock.contains(&key_code) {
-            info!("calling key event fn in module for {:?}", &key_code);
+            trace!("calling key event fn in module for {:?}", &key_code);
+            let _zone = zone(&self.zones.key_event);
+            let start = Instant::now();
             self.bindings
-                .call_key_event(&mut self.store, key_code as u32)?
+                .call_key_event(&mut self.store, key_code as u32)?;
+            let time = start.elapsed();
+            self.store.data_mut().profile.frame.key_event.add(time);
         } else {
-            info!("{:?}", &keys_lock)
+            trace!("{:?}", &keys_lock)
         }
         Ok(())
     }
@@ -145,6 +234,17 @@ impl EngineMod {
         Ok(())
     }
 
+    /// Runs a console command the mod registered, the inner result is what the mod returned.
+    pub fn console_command(
+        &mut self,
+        name: &str,
+        args: &[EngineArgValue],
+    ) -> anyhow::Result<Result<String, String>> {
+        let args: Vec<ArgValue> = args.iter().cloned().map(ArgValue::from).collect();
+        self.bindings
+            .call_console_command(&mut self.store, name, &args)
+    }
+
     pub fn get_event_queue(&self) -> ModEventQueue {
         self.event_queue.clone()
     }
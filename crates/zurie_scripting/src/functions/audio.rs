@@ -3,14 +3,293 @@ use crate::functions::zurie::engine::audio::*;
 
 use crate::ScriptingState;
 use crate::functions::KeyData;
+use crate::functions::zurie::engine::core::{EntityId, Vec2};
+use zurie_audio::mixer::FILTER_OPEN;
+use zurie_audio::playback::{
+    InstanceCommand, LoopRegion as EngineLoopRegion, PlaySettings as EnginePlaySettings,
+    PlaybackState as EnginePlaybackState,
+};
+use zurie_audio::spatial::{
+    AttenuationCurve as EngineAttenuationCurve, EmitterTarget, Listener,
+    SpatialSettings as EngineSpatialSettings,
+};
 use zurie_shared::slotmap::Key;
+use zurie_types::ComponentData;
+
+impl ScriptingState {
+    fn control_sound(&self, instance: SoundInstance, command: InstanceCommand) {
+        self.audio_manager
+            .control(KeyData::from_ffi(instance).into(), command);
+    }
+
+    fn entity_position(&self, entity: EntityId) -> Option<zurie_types::glam::Vec2> {
+        match self
+            .world
+            .read()
+            .unwrap()
+            .get_component(KeyData::from_ffi(entity).into(), self.position_component)
+        {
+            Some(ComponentData::Vector(position)) => Some(*position),
+            _ => None,
+        }
+    }
+
+    fn play_sound_at(
+        &self,
+        handle: SoundHandle,
+        target: EmitterTarget,
+        position: Option<zurie_types::glam::Vec2>,
+        settings: PlaySettings,
+    ) -> SoundInstance {
+        KeyData::as_ffi(
+            self.audio_manager
+                .play_at(
+                    KeyData::from_ffi(handle).into(),
+                    target,
+                    position,
+                    settings.into(),
+                )
+                .data(),
+        )
+    }
+}
 
 impl audio::Host for ScriptingState {
     fn load_sound(&mut self, path: String) -> SoundHandle {
         KeyData::as_ffi(self.audio_manager.load_sound(path).data())
     }
 
-    fn play_sound(&mut self, handle: SoundHandle) {
-        self.audio_manager.play(KeyData::from_ffi(handle).into());
+    fn unload_sound(&mut self, handle: SoundHandle) {
+        self.audio_manager
+            .unload_sound(KeyData::from_ffi(handle).into());
+    }
+
+    fn sound_status(&mut self, handle: SoundHandle) -> LoadStatus {
+        self.audio_manager
+            .status(KeyData::from_ffi(handle).into())
+            .into()
+    }
+
+    fn play_sound(&mut self, handle: SoundHandle) -> SoundInstance {
+        KeyData::as_ffi(
+            self.audio_manager
+                .play(KeyData::from_ffi(handle).into())
+                .data(),
+        )
+    }
+
+    fn play_sound_with(&mut self, handle: SoundHandle, settings: PlaySettings) -> SoundInstance {
+        KeyData::as_ffi(
+            self.audio_manager
+                .play_with(KeyData::from_ffi(handle).into(), settings.into())
+                .data(),
+        )
+    }
+
+    fn stop_sound(&mut self, instance: SoundInstance, fade: f32) {
+        self.control_sound(instance, InstanceCommand::Stop { fade });
+    }
+
+    fn pause_sound(&mut self, instance: SoundInstance, fade: f32) {
+        self.control_sound(instance, InstanceCommand::Pause { fade });
+    }
+
+    fn resume_sound(&mut self, instance: SoundInstance, fade: f32) {
+        self.control_sound(instance, InstanceCommand::Resume { fade });
+    }
+
+    fn set_sound_volume(&mut self, instance: SoundInstance, volume: f32, fade: f32) {
+        self.control_sound(instance, InstanceCommand::SetVolume { volume, fade });
+    }
+
+    fn set_sound_pitch(&mut self, instance: SoundInstance, pitch: f32, fade: f32) {
+        self.control_sound(instance, InstanceCommand::SetPitch { pitch, fade });
+    }
+
+    fn set_sound_panning(&mut self, instance: SoundInstance, panning: f32, fade: f32) {
+        self.control_sound(instance, InstanceCommand::SetPanning { panning, fade });
+    }
+
+    fn set_sound_loop_region(&mut self, instance: SoundInstance, region: Option<LoopRegion>) {
+        self.control_sound(
+            instance,
+            InstanceCommand::SetLoopRegion(region.map(EngineLoopRegion::from)),
+        );
+    }
+
+    fn seek_sound(&mut self, instance: SoundInstance, position: f64) {
+        self.control_sound(instance, InstanceCommand::Seek(position));
+    }
+
+    fn sound_state(&mut self, instance: SoundInstance) -> PlaybackState {
+        match self
+            .audio_manager
+            .instance_state(KeyData::from_ffi(instance).into())
+        {
+            EnginePlaybackState::Playing => PlaybackState::Playing,
+            EnginePlaybackState::Paused => PlaybackState::Paused,
+            EnginePlaybackState::Stopped => PlaybackState::Stopped,
+        }
+    }
+
+    fn sound_position(&mut self, instance: SoundInstance) -> Option<f64> {
+        self.audio_manager
+            .instance_position(KeyData::from_ffi(instance).into())
+    }
+
+    fn play_sound_at_entity(
+        &mut self,
+        handle: SoundHandle,
+        entity: EntityId,
+        settings: PlaySettings,
+    ) -> SoundInstance {
+        let position = self.entity_position(entity);
+        self.play_sound_at(handle, EmitterTarget::Entity(entity), position, settings)
+    }
+
+    fn play_sound_at_position(
+        &mut self,
+        handle: SoundHandle,
+        position: Vec2,
+        settings: PlaySettings,
+    ) -> SoundInstance {
+        let target = EmitterTarget::Position(position.into());
+        self.play_sound_at(handle, target, None, settings)
+    }
+
+    fn set_spatial_settings(&mut self, settings: SpatialSettings) {
+        self.audio_manager.set_spatial_settings(settings.into());
+    }
+
+    fn get_spatial_settings(&mut self) -> SpatialSettings {
+        self.audio_manager.spatial_settings().into()
+    }
+
+    fn set_emitter_settings(&mut self, instance: SoundInstance, settings: SpatialSettings) {
+        self.audio_manager
+            .set_emitter_settings(KeyData::from_ffi(instance).into(), settings.into());
+    }
+
+    fn set_listener_camera(&mut self) {
+        self.audio_manager.set_listener(Listener::Camera);
+    }
+
+    fn set_listener_entity(&mut self, entity: EntityId) {
+        self.audio_manager.set_listener(Listener::Entity(entity));
+    }
+
+    fn play_music(&mut self, path: String, crossfade: f32) {
+        self.audio_manager.play_music(path, crossfade);
+    }
+
+    fn set_playlist(&mut self, paths: Vec<String>, looping: bool, crossfade: f32) {
+        self.audio_manager.set_playlist(paths, looping, crossfade);
+    }
+
+    fn skip_track(&mut self) {
+        self.audio_manager.skip_track();
+    }
+
+    fn stop_music(&mut self, fade: f32) {
+        self.audio_manager.stop_music(fade);
+    }
+
+    fn pause_music(&mut self, fade: f32) {
+        self.audio_manager.pause_music(fade);
+    }
+
+    fn resume_music(&mut self, fade: f32) {
+        self.audio_manager.resume_music(fade);
+    }
+
+    fn current_music(&mut self) -> Option<String> {
+        self.audio_manager.current_music()
+    }
+
+    fn bus_names(&mut self) -> Vec<String> {
+        self.audio_manager.bus_names()
+    }
+
+    fn set_master_volume(&mut self, volume: f32, fade: f32) {
+        self.audio_manager.set_master_volume(volume, fade);
+    }
+
+    fn set_bus_volume(&mut self, bus: String, volume: f32, fade: f32) {
+        self.audio_manager
+            .update_bus(&bus, fade, |settings| settings.volume = volume.max(0.0));
+    }
+
+    fn bus_volume(&mut self, bus: String) -> f32 {
+        self.audio_manager
+            .mixer_settings()
+            .buses
+            .get(&bus)
+            .map_or(1.0, |settings| settings.volume)
+    }
+
+    fn set_bus_filter(&mut self, bus: String, cutoff: f32, fade: f32) {
+        self.audio_manager.update_bus(&bus, fade, |settings| {
+            settings.filter_cutoff = cutoff.clamp(20.0, FILTER_OPEN)
+        });
+    }
+
+    fn set_bus_reverb(&mut self, bus: String, mix: f32, fade: f32) {
+        self.audio_manager.update_bus(&bus, fade, |settings| {
+            settings.reverb_mix = mix.clamp(0.0, 1.0)
+        });
+    }
+}
+
+impl From<LoopRegion> for EngineLoopRegion {
+    fn from(region: LoopRegion) -> Self {
+        EngineLoopRegion {
+            start: region.start,
+            end: region.end,
+        }
+    }
+}
+
+impl From<PlaySettings> for EnginePlaySettings {
+    fn from(settings: PlaySettings) -> Self {
+        EnginePlaySettings {
+            volume: settings.volume,
+            pitch: settings.pitch,
+            panning: settings.panning,
+            loop_region: settings.loop_region.map(EngineLoopRegion::from),
+            fade_in: settings.fade_in,
+            bus: settings.bus,
+        }
+    }
+}
+
+impl From<SpatialSettings> for EngineSpatialSettings {
+    fn from(settings: SpatialSettings) -> Self {
+        EngineSpatialSettings {
+            min_distance: settings.min_distance,
+            max_distance: settings.max_distance,
+            curve: match settings.curve {
+                AttenuationCurve::Linear => EngineAttenuationCurve::Linear,
+                AttenuationCurve::Inverse => EngineAttenuationCurve::Inverse,
+                AttenuationCurve::Exponential => EngineAttenuationCurve::Exponential,
+            },
+            rolloff: settings.rolloff,
+            pan_width: settings.pan_width,
+        }
+    }
+}
+
+impl From<EngineSpatialSettings> for SpatialSettings {
+    fn from(settings: EngineSpatialSettings) -> Self {
+        SpatialSettings {
+            min_distance: settings.min_distance,
+            max_distance: settings.max_distance,
+            curve: match settings.curve {
+                EngineAttenuationCurve::Linear => AttenuationCurve::Linear,
+                EngineAttenuationCurve::Inverse => AttenuationCurve::Inverse,
+                EngineAttenuationCurve::Exponential => AttenuationCurve::Exponential,
+            },
+            rolloff: settings.rolloff,
+            pan_width: settings.pan_width,
+        }
     }
 }
@@ -1,50 +1,153 @@
 use super::ScriptingState;
 use crate::functions::zurie::engine::camera;
+use zurie_shared::slotmap::{Key, KeyData};
+use zurie_types::ComponentData;
+use zurie_types::CameraHandle as EngineCameraHandle;
+use zurie_types::camera::{Camera, Cameras, Viewport};
+
+use crate::functions::zurie::engine::core::{CameraHandle, Color, EntityId, Vec2};
+
+impl ScriptingState {
+    /// Selected camera, falls back to the main camera if it was removed.
+    fn selected_camera_handle(&self, cameras: &Cameras) -> EngineCameraHandle {
+        if cameras.get(self.selected_camera).is_some() {
+            self.selected_camera
+        } else {
+            cameras.main()
+        }
+    }
+
+    fn camera<R>(&self, reader: impl FnOnce(&Camera) -> R) -> R {
+        let cameras = self.cameras.read().unwrap();
+        reader(cameras.get(self.selected_camera_handle(&cameras)).unwrap())
+    }
+
+    fn camera_mut<R>(&self, writer: impl FnOnce(&mut Camera) -> R) -> R {
+        let mut cameras = self.cameras.write().unwrap();
+        let handle = self.selected_camera_handle(&cameras);
+        writer(cameras.get_mut(handle).unwrap())
+    }
+}
 
-use crate::functions::zurie::engine::core::Vec2;
 impl camera::Host for ScriptingState {
     fn get_camera(&mut self) -> camera::Camera {
-        let camera = self.camera.read().unwrap();
-        camera::Camera {
+        self.camera(|camera| camera::Camera {
             position: Vec2 {
                 x: camera.position.x,
                 y: camera.position.y,
             },
             zoom_factor: camera.zoom_factor,
-        }
+        })
     }
 
     fn set_camera(&mut self, camera: camera::Camera) -> () {
-        let mut engine_camera = self.camera.write().unwrap();
-        engine_camera.zoom_factor = camera.zoom_factor;
-        engine_camera.position = zurie_types::glam::Vec2 {
-            x: camera.position.x,
-            y: camera.position.y,
-        };
+        self.camera_mut(|engine_camera| {
+            engine_camera.zoom_factor = camera.zoom_factor;
+            engine_camera.position = zurie_types::glam::Vec2 {
+                x: camera.position.x,
+                y: camera.position.y,
+            };
+        });
     }
 
     fn set_zoom(&mut self, factor: f32) -> () {
-        let mut engine_camera = self.camera.write().unwrap();
-        engine_camera.zoom_factor = factor;
+        self.camera_mut(|camera| camera.set_zoom(factor));
     }
 
     fn get_zoom(&mut self) -> f32 {
-        self.camera.read().unwrap().zoom_factor
+        self.camera(|camera| camera.zoom_factor)
     }
 
     fn set_position(&mut self, position: Vec2) -> () {
-        self.camera.write().unwrap().position = zurie_types::glam::Vec2 {
-            x: position.x,
-            y: position.y,
-        }
+        self.camera_mut(|camera| {
+            camera.position = zurie_types::glam::Vec2 {
+                x: position.x,
+                y: position.y,
+            }
+        });
     }
 
     fn get_position(&mut self) -> Vec2 {
-        let camera = self.camera.read().unwrap();
-
-        Vec2 {
+        self.camera(|camera| Vec2 {
             x: camera.position.x,
             y: camera.position.y,
-        }
+        })
+    }
+
+    fn follow_entity(&mut self, entity: EntityId, damping: f32, deadzone: Vec2) {
+        self.camera_mut(|camera| camera.follow(entity, damping, deadzone.into()));
+    }
+
+    fn stop_following(&mut self) {
+        self.camera_mut(|camera| camera.stop_following());
+    }
+
+    fn set_bounds(&mut self, min: Vec2, max: Vec2) {
+        self.camera_mut(|camera| camera.set_bounds(min.into(), max.into()));
+    }
+
+    fn clear_bounds(&mut self) {
+        self.camera_mut(|camera| camera.clear_bounds());
+    }
+
+    fn add_trauma(&mut self, amount: f32) {
+        self.camera_mut(|camera| camera.add_trauma(amount));
+    }
+
+    fn configure_shake(&mut self, max_offset: Vec2, frequency: f32, decay: f32) {
+        self.camera_mut(|camera| camera.configure_shake(max_offset.into(), frequency, decay));
+    }
+
+    fn zoom_to(&mut self, factor: f32, speed: f32) {
+        self.camera_mut(|camera| camera.zoom_to(factor, speed));
+    }
+
+    fn create_camera(&mut self) -> CameraHandle {
+        KeyData::as_ffi(self.cameras.write().unwrap().create().data())
+    }
+
+    fn remove_camera(&mut self, handle: CameraHandle) {
+        self.cameras
+            .write()
+            .unwrap()
+            .remove(KeyData::from_ffi(handle).into());
+    }
+
+    fn main_camera(&mut self) -> CameraHandle {
+        KeyData::as_ffi(self.cameras.read().unwrap().main().data())
+    }
+
+    fn select_camera(&mut self, handle: CameraHandle) {
+        self.selected_camera = KeyData::from_ffi(handle).into();
+    }
+
+    fn set_viewport(&mut self, viewport: camera::Viewport) {
+        let mut cameras = self.cameras.write().unwrap();
+        let handle = self.selected_camera_handle(&cameras);
+        cameras.set_viewport(handle, Viewport {
+            position: viewport.position.into(),
+            size: viewport.size.into(),
+        });
+    }
+
+    fn set_clear_color(&mut self, color: Option<Color>) {
+        self.camera_mut(|camera| {
+            camera.clear_color = color.map(|color| [color.r, color.g, color.b, color.a])
+        });
+    }
+
+    fn set_layer_mask(&mut self, mask: u32) {
+        self.camera_mut(|camera| camera.layer_mask = mask);
+    }
+
+    fn set_order(&mut self, order: i32) {
+        self.camera_mut(|camera| camera.order = order);
+    }
+
+    fn set_render_layer(&mut self, entity: EntityId, layer: u32) {
+        self.world.write().unwrap().set_component(
+            KeyData::from_ffi(entity).into(),
+            (self.layer_component, ComponentData::I32(layer as i32)),
+        );
     }
 }
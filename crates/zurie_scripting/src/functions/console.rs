@@ -0,0 +1,60 @@
+use log::info;
+use zurie_types::console::{
+    ArgKind as EngineArgKind, ArgValue as EngineArgValue, Command as EngineCommand, CommandArg,
+};
+
+use super::ScriptingState;
+use crate::functions::zurie::engine::console::{self, ArgKind, ArgValue, Command};
+
+impl console::Host for ScriptingState {
+    fn register_command(&mut self, command: Command) -> Result<(), String> {
+        let command = EngineCommand::from(command);
+        command.validate()?;
+        info!("Console command registered: {}", command.usage());
+        let mut commands = self.console_commands.write().unwrap();
+        commands.retain(|registered| registered.name != command.name);
+        commands.push(command);
+        Ok(())
+    }
+}
+
+impl From<Command> for EngineCommand {
+    fn from(command: Command) -> Self {
+        EngineCommand {
+            name: command.name,
+            description: command.description,
+            args: command
+                .args
+                .into_iter()
+                .map(|arg| CommandArg {
+                    name: arg.name,
+                    kind: arg.kind.into(),
+                    optional: arg.optional,
+                    choices: arg.choices,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<ArgKind> for EngineArgKind {
+    fn from(kind: ArgKind) -> Self {
+        match kind {
+            ArgKind::Integer => EngineArgKind::Integer,
+            ArgKind::Float => EngineArgKind::Float,
+            ArgKind::Text => EngineArgKind::Text,
+            ArgKind::Boolean => EngineArgKind::Boolean,
+        }
+    }
+}
+
+impl From<EngineArgValue> for ArgValue {
+    fn from(value: EngineArgValue) -> Self {
+        match value {
+            EngineArgValue::Integer(value) => ArgValue::Integer(value),
+            EngineArgValue::Float(value) => ArgValue::Float(value),
+            EngineArgValue::Text(text) => ArgValue::Text(text),
+            EngineArgValue::Boolean(value) => ArgValue::Boolean(value),
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use super::ScriptingState;
-use log::info;
+use log::trace;
+use zurie_ecs::spatial::ComponentFilter as EngineComponentFilter;
 use zurie_shared::slotmap::{Key, KeyData};
 use zurie_types::ComponentData as EngineComponentData;
+use zurie_types::animation::Animation as EngineAnimation;
 
 use crate::functions::zurie::engine::ecs;
 use crate::functions::zurie::engine::ecs::*;
@@ -43,7 +45,7 @@ impl ecs::Host for ScriptingState {
     }
 
     fn get_entities_with_component(&mut self, component: u64) -> Vec<EntityId> {
-        info!("component: {}", component);
+        trace!("component: {}", component);
         self.world
             .read()
             .unwrap()
@@ -82,6 +84,78 @@ impl ecs::Host for ScriptingState {
             .get_entity_data(KeyData::from_ffi(entity).into())
             .is_some()
     }
+
+    fn query_radius(
+        &mut self,
+        center: Vec2,
+        radius: f32,
+        filter: ComponentFilter,
+    ) -> Vec<EntityId> {
+        if !finite(&center) || !radius.is_finite() || radius < 0.0 {
+            return Vec::new();
+        }
+        self.world
+            .read()
+            .unwrap()
+            .query_radius(center.into(), radius, &filter.into())
+            .iter()
+            .map(|entity| KeyData::as_ffi(entity.data()))
+            .collect()
+    }
+
+    fn query_aabb(&mut self, min: Vec2, max: Vec2, filter: ComponentFilter) -> Vec<EntityId> {
+        if !finite(&min) || !finite(&max) {
+            return Vec::new();
+        }
+        self.world
+            .read()
+            .unwrap()
+            .query_aabb(min.into(), max.into(), &filter.into())
+            .iter()
+            .map(|entity| KeyData::as_ffi(entity.data()))
+            .collect()
+    }
+
+    fn nearest_k(
+        &mut self,
+        point: Vec2,
+        k: u32,
+        max_distance: Option<f32>,
+        filter: ComponentFilter,
+    ) -> Vec<EntityId> {
+        let invalid_distance =
+            max_distance.is_some_and(|distance| distance.is_nan() || distance < 0.0);
+        if !finite(&point) || invalid_distance {
+            return Vec::new();
+        }
+        self.world
+            .read()
+            .unwrap()
+            .nearest_k(point.into(), k as usize, max_distance, &filter.into())
+            .iter()
+            .map(|entity| KeyData::as_ffi(entity.data()))
+            .collect()
+    }
+}
+
+/// Queries reject guest input that isn't finite, mods get no entities back.
+fn finite(vector: &Vec2) -> bool {
+    vector.x.is_finite() && vector.y.is_finite()
+}
+
+impl From<ComponentFilter> for EngineComponentFilter {
+    fn from(filter: ComponentFilter) -> Self {
+        let ids = |components: Vec<ComponentId>| {
+            components
+                .into_iter()
+                .map(|component| KeyData::from_ffi(component).into())
+                .collect()
+        };
+        EngineComponentFilter {
+            required: ids(filter.required),
+            excluded: ids(filter.excluded),
+        }
+    }
 }
 
 impl From<ComponentData> for EngineComponentData {
@@ -95,6 +169,19 @@ impl From<ComponentData> for EngineComponentData {
             ComponentData::I32(i) => EngineComponentData::I32(i),
             ComponentData::I64(i) => EngineComponentData::I64(i),
             ComponentData::Sprite(sprite_handle) => EngineComponentData::Sprite(sprite_handle),
+            ComponentData::Animation(animation) => {
+                let mut engine_animation = EngineAnimation::new(animation.tag, animation.looping);
+                engine_animation.set_frame(animation.frame);
+                engine_animation.playing = animation.playing;
+                engine_animation.speed = animation.speed;
+                engine_animation.finished = animation.finished;
+                EngineComponentData::Animation(engine_animation)
+            }
+            ComponentData::RigidBody(body) => EngineComponentData::RigidBody(body.into()),
+            ComponentData::Collider(collider) => EngineComponentData::Collider(collider.into()),
+            ComponentData::Tilemap(tilemap) => {
+                EngineComponentData::Tilemap(Box::new(tilemap.into()))
+            }
         }
     }
 }
@@ -116,6 +203,14 @@ impl From<EngineComponentData> for ComponentData {
             EngineComponentData::I32(i) => ComponentData::I32(i),
             EngineComponentData::I64(i) => ComponentData::I64(i),
             EngineComponentData::Sprite(sprite_handle) => ComponentData::Sprite(sprite_handle),
+            EngineComponentData::Animation(animation) => {
+                ComponentData::Animation((&animation).into())
+            }
+            EngineComponentData::RigidBody(body) => ComponentData::RigidBody(body.into()),
+            EngineComponentData::Collider(collider) => ComponentData::Collider(collider.into()),
+            EngineComponentData::Tilemap(tilemap) => {
+                ComponentData::Tilemap(tilemap.as_ref().into())
+            }
         }
     }
 }
@@ -136,6 +231,25 @@ impl From<&EngineComponentData> for ComponentData {
             EngineComponentData::I32(i) => ComponentData::I32(*i),
             EngineComponentData::I64(i) => ComponentData::I64(*i),
             EngineComponentData::Sprite(sprite_handle) => ComponentData::Sprite(*sprite_handle),
+            EngineComponentData::Animation(animation) => ComponentData::Animation(animation.into()),
+            EngineComponentData::RigidBody(body) => ComponentData::RigidBody((*body).into()),
+            EngineComponentData::Collider(collider) => ComponentData::Collider((*collider).into()),
+            EngineComponentData::Tilemap(tilemap) => {
+                ComponentData::Tilemap(tilemap.as_ref().into())
+            }
+        }
+    }
+}
+
+impl From<&EngineAnimation> for Animation {
+    fn from(animation: &EngineAnimation) -> Self {
+        Animation {
+            tag: animation.tag.clone(),
+            frame: animation.frame,
+            playing: animation.playing,
+            looping: animation.looping,
+            speed: animation.speed,
+            finished: animation.finished,
         }
     }
 }
@@ -1,4 +1,4 @@
-use log::info;
+use log::trace;
 use zurie_event::EventData as EngineEventData;
 
 use super::{ScriptingState, zurie::engine::events};
@@ -26,7 +26,7 @@ impl events::Host for ScriptingState {
     fn emit(&mut self, handle: events::EventHandle, data: EventData) -> () {
         let mut event_manager = self.event_manager.write().unwrap();
         let handle = KeyData::from_ffi(handle);
-        info!(
+        trace!(
             "Event emited: {}",
             event_manager.event_storage.get(handle.into()).unwrap()
         );
@@ -47,6 +47,12 @@ impl From<EventData> for EngineEventData {
             EventData::Raw(bytes) => EngineEventData::Raw(bytes),
             EventData::I32(i) => EngineEventData::I32(i),
             EventData::I64(i) => EngineEventData::I64(i),
+            EventData::Contact(contact) => EngineEventData::Contact {
+                a: contact.a,
+                b: contact.b,
+                normal: contact.normal.into(),
+                point: contact.point.into(),
+            },
         }
     }
 }
@@ -67,6 +73,17 @@ impl From<EngineEventData> for EventData {
             EngineEventData::Raw(bytes) => EventData::Raw(bytes),
             EngineEventData::I32(i) => EventData::I32(i),
             EngineEventData::I64(i) => EventData::I64(i),
+            EngineEventData::Contact {
+                a,
+                b,
+                normal,
+                point,
+            } => EventData::Contact(Contact {
+                a,
+                b,
+                normal: normal.into(),
+                point: point.into(),
+            }),
         }
     }
 }
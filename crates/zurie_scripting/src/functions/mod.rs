@@ -1,16 +1,23 @@
 pub mod audio;
 pub mod camera;
+pub mod console;
 pub mod ecs;
 pub mod events;
 pub mod gui;
 pub mod input;
+pub mod physics;
+pub mod prefab;
 pub mod rand;
 pub mod sprite;
+pub mod tilemap;
 pub mod utils;
 
 use crate::functions::zurie::engine::audio::SoundHandle;
+use crate::noise::Perlin;
+use crate::profile::ModProfile;
 use egui::{Context, Ui, Window};
 use hashbrown::HashSet;
+use rand_chacha::ChaCha8Rng;
 use std::sync::{Arc, RwLock};
 use wasmtime::component::{ResourceTable, bindgen};
 use wasmtime_wasi::{WasiCtx, WasiView};
@@ -20,11 +27,15 @@ use zurie_ecs::ComponentID;
 use zurie_ecs::World;
 use zurie_event::EventManager;
 use zurie_input::InputState;
+use zurie_physics::Physics;
+use zurie_prefab::Prefabs;
 use zurie_render::sprite::SpriteManager;
 use zurie_shared::slotmap::{Key, KeyData, SlotMap, new_key_type};
 use zurie_types::KeyCode;
 use zurie_types::ModHandle;
-use zurie_types::camera::Camera;
+use zurie_types::CameraHandle;
+use zurie_types::camera::Cameras;
+use zurie_types::console::Command;
 use zurie_types::glam::Vec2;
 
 bindgen!("zurie-mod" in "zurie_engine.wit");
@@ -33,6 +44,7 @@ pub struct ScriptingState {
     //Sprite
     pub sprite_manager: Arc<RwLock<SpriteManager>>,
     pub sprite_component: ComponentID,
+    pub animation_component: ComponentID,
 
     //GUI
     pub gui_context: Context,
@@ -43,18 +55,40 @@ pub struct ScriptingState {
 
     //Audio
     pub audio_manager: AudioManager,
+    pub position_component: ComponentID,
 
     //Input
     pub subscribed_keys: Arc<RwLock<HashSet<KeyCode>>>,
     pub input_state: InputState,
 
     //Camera
-    pub camera: Arc<RwLock<Camera>>,
+    pub cameras: Arc<RwLock<Cameras>>,
+    pub selected_camera: CameraHandle,
+    pub layer_component: ComponentID,
 
     //Event
     pub event_manager: Arc<RwLock<EventManager>>,
     pub mod_handle: ModHandle,
 
+    //Rand
+    pub rng: ChaCha8Rng,
+    pub noise: Perlin,
+
+    //Physics
+    pub physics: Arc<RwLock<Physics>>,
+
+    //Tilemap
+    pub tilemap_component: ComponentID,
+
+    //Prefab
+    pub prefabs: Arc<RwLock<Prefabs>>,
+
+    //Console
+    pub console_commands: Arc<RwLock<Vec<Command>>>,
+
+    //Profiling
+    pub profile: ModProfile,
+
     //Wasi spacific fields
     pub wasi_ctx: WasiCtx,
     pub resource_table: ResourceTable,
@@ -0,0 +1,230 @@
+use crate::functions::zurie::engine::physics;
+use crate::functions::zurie::engine::physics::*;
+
+use crate::ScriptingState;
+use crate::functions::KeyData;
+use crate::functions::zurie::engine::core::{EntityId, Vec2};
+use zurie_physics::{Hit, QueryFilter as EngineQueryFilter};
+use zurie_shared::slotmap::Key;
+use zurie_types::physics::{
+    BodyKind as EngineBodyKind, Collider as EngineCollider, RigidBody as EngineRigidBody,
+    Shape as EngineShape,
+};
+
+impl physics::Host for ScriptingState {
+    fn raycast(
+        &mut self,
+        origin: Vec2,
+        direction: Vec2,
+        max_distance: f32,
+        filter: QueryFilter,
+    ) -> Option<RayHit> {
+        let world = self.world.read().unwrap();
+        self.physics
+            .read()
+            .unwrap()
+            .raycast(
+                &world,
+                origin.into(),
+                direction.into(),
+                max_distance,
+                &filter.into(),
+            )
+            .map(RayHit::from)
+    }
+
+    fn shape_cast(
+        &mut self,
+        shape: Shape,
+        origin: Vec2,
+        direction: Vec2,
+        max_distance: f32,
+        filter: QueryFilter,
+    ) -> Option<RayHit> {
+        let world = self.world.read().unwrap();
+        self.physics
+            .read()
+            .unwrap()
+            .shape_cast(
+                &world,
+                &shape.into(),
+                origin.into(),
+                direction.into(),
+                max_distance,
+                &filter.into(),
+            )
+            .map(RayHit::from)
+    }
+
+    fn overlap(&mut self, shape: Shape, position: Vec2, filter: QueryFilter) -> Vec<EntityId> {
+        let world = self.world.read().unwrap();
+        self.physics
+            .read()
+            .unwrap()
+            .overlap(&world, &shape.into(), position.into(), &filter.into())
+            .iter()
+            .map(|entity| KeyData::as_ffi(entity.data()))
+            .collect()
+    }
+
+    fn set_gravity(&mut self, gravity: Vec2) {
+        self.physics.write().unwrap().gravity = gravity.into();
+    }
+
+    fn gravity(&mut self) -> Vec2 {
+        self.physics.read().unwrap().gravity.into()
+    }
+
+    fn set_velocity(&mut self, entity: EntityId, velocity: Vec2) {
+        self.physics.read().unwrap().set_velocity(
+            &mut self.world.write().unwrap(),
+            KeyData::from_ffi(entity).into(),
+            velocity.into(),
+        );
+    }
+
+    fn velocity(&mut self, entity: EntityId) -> Vec2 {
+        self.physics
+            .read()
+            .unwrap()
+            .velocity(
+                &self.world.read().unwrap(),
+                KeyData::from_ffi(entity).into(),
+            )
+            .into()
+    }
+
+    fn apply_impulse(&mut self, entity: EntityId, impulse: Vec2) {
+        self.physics.read().unwrap().apply_impulse(
+            &mut self.world.write().unwrap(),
+            KeyData::from_ffi(entity).into(),
+            impulse.into(),
+        );
+    }
+}
+
+impl From<Hit> for RayHit {
+    fn from(hit: Hit) -> Self {
+        RayHit {
+            entity: KeyData::as_ffi(hit.entity.data()),
+            distance: hit.distance,
+            point: hit.point.into(),
+            normal: hit.normal.into(),
+        }
+    }
+}
+
+impl From<QueryFilter> for EngineQueryFilter {
+    fn from(filter: QueryFilter) -> Self {
+        EngineQueryFilter {
+            mask: filter.mask,
+            exclude: filter
+                .exclude
+                .into_iter()
+                .map(|entity| KeyData::from_ffi(entity).into())
+                .collect(),
+            include_sensors: filter.include_sensors,
+        }
+    }
+}
+
+impl From<BodyKind> for EngineBodyKind {
+    fn from(kind: BodyKind) -> Self {
+        match kind {
+            BodyKind::Dynamic => EngineBodyKind::Dynamic,
+            BodyKind::Kinematic => EngineBodyKind::Kinematic,
+            BodyKind::Static => EngineBodyKind::Static,
+        }
+    }
+}
+
+impl From<EngineBodyKind> for BodyKind {
+    fn from(kind: EngineBodyKind) -> Self {
+        match kind {
+            EngineBodyKind::Dynamic => BodyKind::Dynamic,
+            EngineBodyKind::Kinematic => BodyKind::Kinematic,
+            EngineBodyKind::Static => BodyKind::Static,
+        }
+    }
+}
+
+impl From<RigidBody> for EngineRigidBody {
+    fn from(body: RigidBody) -> Self {
+        EngineRigidBody {
+            kind: body.kind.into(),
+            mass: body.mass,
+            gravity_scale: body.gravity_scale,
+            linear_damping: body.linear_damping,
+        }
+    }
+}
+
+impl From<EngineRigidBody> for RigidBody {
+    fn from(body: EngineRigidBody) -> Self {
+        RigidBody {
+            kind: body.kind.into(),
+            mass: body.mass,
+            gravity_scale: body.gravity_scale,
+            linear_damping: body.linear_damping,
+        }
+    }
+}
+
+impl From<Shape> for EngineShape {
+    fn from(shape: Shape) -> Self {
+        match shape {
+            Shape::Circle(radius) => EngineShape::Circle { radius },
+            Shape::Box(half_extents) => EngineShape::Box {
+                half_extents: half_extents.into(),
+            },
+            Shape::Capsule(capsule) => EngineShape::Capsule {
+                half_height: capsule.half_height,
+                radius: capsule.radius,
+            },
+        }
+    }
+}
+
+impl From<EngineShape> for Shape {
+    fn from(shape: EngineShape) -> Self {
+        match shape {
+            EngineShape::Circle { radius } => Shape::Circle(radius),
+            EngineShape::Box { half_extents } => Shape::Box(half_extents.into()),
+            EngineShape::Capsule {
+                half_height,
+                radius,
+            } => Shape::Capsule(Capsule {
+                half_height,
+                radius,
+            }),
+        }
+    }
+}
+
+impl From<Collider> for EngineCollider {
+    fn from(collider: Collider) -> Self {
+        EngineCollider {
+            shape: collider.shape.into(),
+            offset: collider.offset.into(),
+            sensor: collider.sensor,
+            restitution: collider.restitution,
+            friction: collider.friction,
+            layer: collider.layer,
+            mask: collider.mask,
+        }
+    }
+}
+
+impl From<EngineCollider> for Collider {
+    fn from(collider: EngineCollider) -> Self {
+        Collider {
+            shape: collider.shape.into(),
+            offset: collider.offset.into(),
+            sensor: collider.sensor,
+            restitution: collider.restitution,
+            friction: collider.friction,
+            layer: collider.layer,
+            mask: collider.mask,
+        }
+    }
+}
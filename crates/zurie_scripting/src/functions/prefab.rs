@@ -0,0 +1,57 @@
+use std::path::Path;
+
+use super::ScriptingState;
+use zurie_ecs::{Entity, World};
+use zurie_prefab::{Components, Prefabs};
+use zurie_render::sprite::{LoadSpriteInfo, SpriteManager};
+use zurie_shared::slotmap::{Key, KeyData};
+use zurie_types::ComponentData as EngineComponentData;
+
+use crate::functions::zurie::engine::core::EntityId;
+use crate::functions::zurie::engine::ecs::ComponentData;
+use crate::functions::zurie::engine::prefab;
+
+/// Spawns the prefab called `name` with `overrides`, turning sprite paths into handles.
+pub fn spawn_prefab(
+    world: &mut World,
+    prefabs: &mut Prefabs,
+    sprite_manager: &mut SpriteManager,
+    name: &str,
+    overrides: &Components,
+) -> anyhow::Result<Entity> {
+    let prefab = prefabs.resolve(name, overrides)?;
+    Ok(prefab.spawn(world, &mut |component, data| match data {
+        // Every spawn shares the sprite instead of loading it again
+        EngineComponentData::String(path) if component == "sprite" => {
+            let handle = sprite_manager.handle_of(&path).unwrap_or_else(|| {
+                sprite_manager.push_to_load_queue(LoadSpriteInfo::Path(Box::from(Path::new(&path))))
+            });
+            EngineComponentData::Sprite(KeyData::as_ffi(handle.data()))
+        }
+        data => data,
+    }))
+}
+
+impl prefab::Host for ScriptingState {
+    fn spawn_prefab(
+        &mut self,
+        name: String,
+        overrides: Vec<(String, ComponentData)>,
+    ) -> Result<EntityId, String> {
+        let overrides: Components = overrides
+            .into_iter()
+            .map(|(component, data)| (component, data.into()))
+            .collect();
+        let mut prefabs = self.prefabs.write().unwrap();
+        let mut sprite_manager = self.sprite_manager.write().unwrap();
+        let entity = spawn_prefab(
+            &mut self.world.write().unwrap(),
+            &mut prefabs,
+            &mut sprite_manager,
+            &name,
+            &overrides,
+        )
+        .map_err(|error| format!("{error:#}"))?;
+        Ok(KeyData::as_ffi(entity.data()))
+    }
+}
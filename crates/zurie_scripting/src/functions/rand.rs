@@ -1,14 +1,208 @@
-use rand::Rng;
-use std::ops::Range;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use zurie_shared::slotmap::{Key, KeyData};
+use zurie_types::ModHandle;
 
 use super::{ScriptingState, zurie::engine};
+use crate::noise::Perlin;
+
+/// Seed of a mod's generators, every mod gets its own stream from the engine seed.
+pub fn mod_seed(seed: u64, mod_handle: ModHandle) -> u64 {
+    // splitmix64, so neighbouring handles get unrelated seeds
+    let mut z = seed ^ KeyData::as_ffi(mod_handle.data());
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Index picked with a chance proportional to its weight, weights below zero count as zero.
+pub fn weighted_choice(rng: &mut impl Rng, weights: &[f32]) -> Option<u32> {
+    let total: f64 = weights.iter().map(|weight| weight.max(0.0) as f64).sum();
+    if total <= 0.0 || !total.is_finite() {
+        return None;
+    }
+    let mut target = rng.gen_range(0.0..total);
+    let mut last = None;
+    for (index, weight) in weights.iter().enumerate() {
+        let weight = weight.max(0.0) as f64;
+        if weight == 0.0 {
+            continue;
+        }
+        if target < weight {
+            return Some(index as u32);
+        }
+        target -= weight;
+        last = Some(index as u32);
+    }
+    // Rounding can leave the target just past the last weight
+    last
+}
+
+/// Float in `start..end`, `start` when the range is empty or either end isn't finite.
+pub fn float_in_range(rng: &mut impl Rng, start: f32, end: f32) -> f32 {
+    if !start.is_finite() || !end.is_finite() || start >= end {
+        return start;
+    }
+    if (end - start).is_finite() {
+        rng.gen_range(start..end)
+    } else {
+        // The width doesn't fit an f32, sampling would panic
+        (rng.gen_range(start as f64..end as f64) as f32).clamp(start, end)
+    }
+}
+
+/// True with a chance of `probability`, clamped to 0..1. Probabilities that aren't finite count
+/// as zero.
+pub fn chance(rng: &mut impl Rng, probability: f32) -> bool {
+    if !probability.is_finite() {
+        return false;
+    }
+    rng.gen_bool(probability.clamp(0.0, 1.0) as f64)
+}
+
+/// Indices `0..len` in a random order, Fisher-Yates on `u32` so it doesn't depend on the
+/// pointer size.
+pub fn shuffled(rng: &mut impl Rng, len: u32) -> Vec<u32> {
+    let mut indices: Vec<u32> = (0..len).collect();
+    for i in (1..len).rev() {
+        let j = rng.gen_range(0..=i);
+        indices.swap(i as usize, j as usize);
+    }
+    indices
+}
 
 impl engine::rand::Host for ScriptingState {
+    fn set_seed(&mut self, seed: u64) {
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+        self.noise = Perlin::new(seed);
+    }
+
     fn rand_f32(&mut self, start: f32, end: f32) -> f32 {
-        rand::thread_rng().gen_range(Range { start, end })
+        float_in_range(&mut self.rng, start, end)
     }
 
     fn rand_i32(&mut self, start: i32, end: i32) -> i32 {
-        rand::thread_rng().gen_range(Range { start, end })
+        if start >= end {
+            return start;
+        }
+        self.rng.gen_range(start..end)
+    }
+
+    fn rand_u64(&mut self) -> u64 {
+        self.rng.r#gen()
+    }
+
+    fn rand_bool(&mut self, probability: f32) -> bool {
+        chance(&mut self.rng, probability)
+    }
+
+    fn weighted_choice(&mut self, weights: Vec<f32>) -> Option<u32> {
+        weighted_choice(&mut self.rng, &weights)
+    }
+
+    fn shuffle(&mut self, len: u32) -> Vec<u32> {
+        shuffled(&mut self.rng, len)
+    }
+
+    fn noise(&mut self, x: f32, y: f32) -> f32 {
+        self.noise.get(x, y)
+    }
+
+    fn fractal_noise(
+        &mut self,
+        x: f32,
+        y: f32,
+        octaves: u32,
+        persistence: f32,
+        lacunarity: f32,
+    ) -> f32 {
+        self.noise.fractal(x, y, octaves, persistence, lacunarity)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn test_mod_seeds() {
+        let first = ModHandle::from(KeyData::from_ffi(1 << 32 | 1));
+        let second = ModHandle::from(KeyData::from_ffi(1 << 32 | 2));
+        assert_eq!(mod_seed(7, first), mod_seed(7, first));
+        assert_ne!(mod_seed(7, first), mod_seed(7, second));
+        assert_ne!(mod_seed(7, first), mod_seed(8, first));
+
+        let mut a = ChaCha8Rng::seed_from_u64(mod_seed(7, first));
+        let mut b = ChaCha8Rng::seed_from_u64(mod_seed(7, first));
+        let a: Vec<i32> = (0..8).map(|_| a.gen_range(0..1000)).collect();
+        let b: Vec<i32> = (0..8).map(|_| b.gen_range(0..1000)).collect();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_sequences_are_pinned() {
+        // Changing these breaks saved replays and seeds mods rely on
+        let mut rng = ChaCha8Rng::seed_from_u64(1234);
+        assert_eq!(rng.r#gen::<u64>(), 7127326097029154973);
+        assert_eq!(shuffled(&mut rng, 6), vec![1, 3, 5, 2, 4, 0]);
+        assert_eq!(weighted_choice(&mut rng, &[1.0, 2.0, 3.0]), Some(2));
+    }
+
+    #[test]
+    fn test_float_in_range() {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        for _ in 0..100 {
+            assert!((2.0..5.0).contains(&float_in_range(&mut rng, 2.0, 5.0)));
+        }
+        assert_eq!(float_in_range(&mut rng, 5.0, 2.0), 5.0);
+        assert_eq!(float_in_range(&mut rng, 1.0, 1.0), 1.0);
+        assert!(float_in_range(&mut rng, f32::NAN, 1.0).is_nan());
+        assert_eq!(float_in_range(&mut rng, 1.0, f32::NAN), 1.0);
+        assert_eq!(float_in_range(&mut rng, 0.0, f32::INFINITY), 0.0);
+        assert_eq!(
+            float_in_range(&mut rng, f32::NEG_INFINITY, 0.0),
+            f32::NEG_INFINITY
+        );
+        let wide = float_in_range(&mut rng, f32::MIN, f32::MAX);
+        assert!(wide.is_finite());
+    }
+
+    #[test]
+    fn test_chance() {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        for _ in 0..100 {
+            assert!(chance(&mut rng, 1.0));
+            assert!(chance(&mut rng, 2.0));
+            assert!(!chance(&mut rng, 0.0));
+            assert!(!chance(&mut rng, -1.0));
+        }
+        assert!(!chance(&mut rng, f32::NAN));
+        assert!(!chance(&mut rng, f32::INFINITY));
+        assert!(!chance(&mut rng, f32::NEG_INFINITY));
+    }
+
+    #[test]
+    fn test_weighted_choice() {
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        assert_eq!(weighted_choice(&mut rng, &[]), None);
+        assert_eq!(weighted_choice(&mut rng, &[0.0, -1.0]), None);
+        assert_eq!(weighted_choice(&mut rng, &[0.0, 3.0, 0.0]), Some(1));
+        let mut counts = [0; 3];
+        for _ in 0..3000 {
+            counts[weighted_choice(&mut rng, &[1.0, 0.0, 2.0]).unwrap() as usize] += 1;
+        }
+        assert_eq!(counts[1], 0);
+        assert!((1800..2200).contains(&counts[2]), "{counts:?}");
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        assert!(shuffled(&mut rng, 0).is_empty());
+        let mut order = shuffled(&mut rng, 20);
+        assert_ne!(order, (0..20).collect::<Vec<u32>>());
+        order.sort();
+        assert_eq!(order, (0..20).collect::<Vec<u32>>());
     }
 }
@@ -7,11 +7,37 @@ use zurie_shared::slotmap::Key;
 use zurie_shared::slotmap::KeyData;
 use zurie_types::ComponentData;
 use zurie_types::SpriteHandle as EngineSpriteHandle;
+use zurie_types::animation::Animation;
 
 use crate::functions::zurie::engine::core::EntityId;
+use crate::functions::zurie::engine::core::LoadStatus;
 use crate::functions::zurie::engine::core::SpriteHandle;
 use crate::functions::zurie::engine::sprite;
 
+impl ScriptingState {
+    /// Runs `f` on the animation of `entity`, adding a default one if it has none.
+    fn with_animation<R>(&self, entity: EntityId, f: impl FnOnce(&mut Animation) -> R) -> R {
+        let entity = KeyData::from_ffi(entity).into();
+        let mut world = self.world.write().unwrap();
+        if !matches!(
+            world.get_component(entity, self.animation_component),
+            Some(ComponentData::Animation(_))
+        ) {
+            world.set_component(
+                entity,
+                (
+                    self.animation_component,
+                    ComponentData::Animation(Animation::default()),
+                ),
+            );
+        }
+        match world.get_component_mut(entity, self.animation_component) {
+            Some(ComponentData::Animation(animation)) => f(animation),
+            _ => f(&mut Animation::default()),
+        }
+    }
+}
+
 impl sprite::Host for ScriptingState {
     fn load_sprite_file(&mut self, path: String) -> SpriteHandle {
         KeyData::as_ffi(
@@ -33,6 +59,29 @@ impl sprite::Host for ScriptingState {
         )
     }
 
+    fn unload_sprite(&mut self, sprite: SpriteHandle) {
+        self.sprite_manager
+            .write()
+            .unwrap()
+            .unload(KeyData::from_ffi(sprite).into());
+    }
+
+    fn sprite_load_error(&mut self, sprite: SpriteHandle) -> Option<String> {
+        self.sprite_manager
+            .read()
+            .unwrap()
+            .load_error(KeyData::from_ffi(sprite).into())
+            .map(str::to_string)
+    }
+
+    fn sprite_status(&mut self, sprite: SpriteHandle) -> LoadStatus {
+        self.sprite_manager
+            .read()
+            .unwrap()
+            .status(KeyData::from_ffi(sprite).into())
+            .into()
+    }
+
     fn set_sprite(&mut self, entity: EntityId, sprite: SpriteHandle) {
         self.world.write().unwrap().set_component(
             KeyData::from_ffi(entity).into(),
@@ -46,4 +95,50 @@ impl sprite::Host for ScriptingState {
             .unwrap()
             .remove_component(KeyData::from_ffi(entity).into(), self.sprite_component);
     }
+
+    fn sprite_tags(&mut self, sprite: SpriteHandle) -> Vec<String> {
+        self.sprite_manager
+            .read()
+            .unwrap()
+            .get_sheet(KeyData::from_ffi(sprite).into())
+            .map(|sheet| sheet.tags.iter().map(|tag| tag.name.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    fn play_animation(&mut self, entity: EntityId, tag: Option<String>, looping: bool) {
+        self.with_animation(entity, |animation| animation.play(tag, looping));
+    }
+
+    fn pause_animation(&mut self, entity: EntityId) {
+        self.with_animation(entity, |animation| animation.pause());
+    }
+
+    fn resume_animation(&mut self, entity: EntityId) {
+        self.with_animation(entity, |animation| animation.resume());
+    }
+
+    fn set_animation_looping(&mut self, entity: EntityId, looping: bool) {
+        self.with_animation(entity, |animation| animation.looping = looping);
+    }
+
+    fn set_animation_speed(&mut self, entity: EntityId, speed: f32) {
+        self.with_animation(entity, |animation| animation.speed = speed);
+    }
+
+    fn stop_animation(&mut self, entity: EntityId) {
+        self.world
+            .write()
+            .unwrap()
+            .remove_component(KeyData::from_ffi(entity).into(), self.animation_component);
+    }
+
+    fn animation_finished(&mut self, entity: EntityId) -> bool {
+        matches!(
+            self.world.read().unwrap().get_component(
+                KeyData::from_ffi(entity).into(),
+                self.animation_component
+            ),
+            Some(ComponentData::Animation(animation)) if animation.finished
+        )
+    }
 }
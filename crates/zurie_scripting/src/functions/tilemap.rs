@@ -0,0 +1,233 @@
+use std::path::Path;
+
+use super::ScriptingState;
+use zurie_ecs::EntityData;
+use zurie_render::sprite::LoadSpriteInfo;
+use zurie_shared::slotmap::{Key, KeyData};
+use zurie_types::ComponentData;
+use zurie_types::glam::{IVec2, UVec2, Vec2 as EngineVec2};
+use zurie_types::tilemap::{
+    CHUNK_SIZE, EMPTY_TILE, Tilemap as EngineTilemap, Tileset as EngineTileset,
+};
+
+use crate::functions::zurie::engine::core::{EntityId, Vec2};
+use crate::functions::zurie::engine::tilemap;
+use crate::functions::zurie::engine::tilemap::*;
+
+impl ScriptingState {
+    /// Runs `f` on the tilemap of `entity`, none if it has no tilemap.
+    fn with_tilemap<R>(
+        &self,
+        entity: EntityId,
+        f: impl FnOnce(&mut EngineTilemap) -> R,
+    ) -> Option<R> {
+        let mut world = self.world.write().unwrap();
+        match world.get_component_mut(KeyData::from_ffi(entity).into(), self.tilemap_component) {
+            Some(ComponentData::Tilemap(tilemap)) => Some(f(tilemap)),
+            _ => None,
+        }
+    }
+}
+
+impl tilemap::Host for ScriptingState {
+    fn create_tilemap(
+        &mut self,
+        entity: EntityId,
+        tileset: Tileset,
+        tile_size: Vec2,
+        collision_layer: u32,
+    ) {
+        let mut tilemap = EngineTilemap::new(tileset.into(), tile_size.into());
+        tilemap.collision_layer = collision_layer;
+        let entity = KeyData::from_ffi(entity).into();
+        let mut world = self.world.write().unwrap();
+        if world
+            .get_component(entity, self.position_component)
+            .is_none()
+        {
+            world.set_component(
+                entity,
+                (
+                    self.position_component,
+                    ComponentData::Vector(EngineVec2::ZERO),
+                ),
+            );
+        }
+        world.set_component(
+            entity,
+            (
+                self.tilemap_component,
+                ComponentData::Tilemap(Box::new(tilemap)),
+            ),
+        );
+    }
+
+    fn get_tile(&mut self, entity: EntityId, x: i32, y: i32) -> u32 {
+        match self
+            .world
+            .read()
+            .unwrap()
+            .get_component(KeyData::from_ffi(entity).into(), self.tilemap_component)
+        {
+            Some(ComponentData::Tilemap(tilemap)) => tilemap.get(IVec2::new(x, y)),
+            _ => EMPTY_TILE,
+        }
+    }
+
+    fn set_tile(&mut self, entity: EntityId, x: i32, y: i32, tile: u32) {
+        self.with_tilemap(entity, |tilemap| tilemap.set(IVec2::new(x, y), tile));
+    }
+
+    fn fill_rect(&mut self, entity: EntityId, x: i32, y: i32, width: u32, height: u32, tile: u32) {
+        self.with_tilemap(entity, |tilemap| {
+            tilemap.fill_rect(IVec2::new(x, y), UVec2::new(width, height), tile)
+        });
+    }
+
+    fn tile_at(&mut self, entity: EntityId, point: Vec2) -> (i32, i32) {
+        let entity = KeyData::from_ffi(entity).into();
+        let world = self.world.read().unwrap();
+        let position = match world.get_component(entity, self.position_component) {
+            Some(ComponentData::Vector(position)) => *position,
+            _ => EngineVec2::ZERO,
+        };
+        match world.get_component(entity, self.tilemap_component) {
+            Some(ComponentData::Tilemap(tilemap)) => {
+                tilemap.tile_at(EngineVec2::from(point) - position).into()
+            }
+            _ => (0, 0),
+        }
+    }
+
+    fn load_tiled(&mut self, path: String, tile_size: Vec2) -> Result<Vec<EntityId>, String> {
+        let map = zurie_tiled::load(&path, |file| zurie_assets::read_asset(file))
+            .map_err(|error| format!("{error:#}"))?;
+        let sprites: Vec<u64> = {
+            let mut sprite_manager = self.sprite_manager.write().unwrap();
+            map.tilesets
+                .iter()
+                .map(|tileset| {
+                    let image = LoadSpriteInfo::Path(Box::from(Path::new(&tileset.image)));
+                    KeyData::as_ffi(sprite_manager.push_to_load_queue(image).data())
+                })
+                .collect()
+        };
+        let mut world = self.world.write().unwrap();
+        Ok(map
+            .tilemaps(&sprites, tile_size.into())
+            .into_iter()
+            .map(|(_, tilemap)| {
+                let entity = world.spawn_entity_with_data(EntityData {
+                    data: vec![
+                        (
+                            self.position_component,
+                            ComponentData::Vector(EngineVec2::ZERO),
+                        ),
+                        (
+                            self.tilemap_component,
+                            ComponentData::Tilemap(Box::new(tilemap)),
+                        ),
+                    ],
+                });
+                KeyData::as_ffi(entity.data())
+            })
+            .collect())
+    }
+}
+
+impl From<Tileset> for EngineTileset {
+    fn from(tileset: Tileset) -> Self {
+        EngineTileset {
+            sprite: tileset.sprite,
+            columns: tileset.columns,
+            rows: tileset.rows,
+            solid: tileset.solid,
+        }
+    }
+}
+
+impl From<&EngineTileset> for Tileset {
+    fn from(tileset: &EngineTileset) -> Self {
+        Tileset {
+            sprite: tileset.sprite,
+            columns: tileset.columns,
+            rows: tileset.rows,
+            solid: tileset.solid.clone(),
+        }
+    }
+}
+
+impl From<TilemapData> for EngineTilemap {
+    fn from(data: TilemapData) -> Self {
+        let mut tilemap = EngineTilemap::new(data.tileset.into(), data.tile_size.into());
+        tilemap.collision_layer = data.collision_layer;
+        for chunk in data.chunks {
+            // Chunks past the ends of the i32 tile range can't hold tiles
+            let (Some(x), Some(y)) = (
+                chunk.x.checked_mul(CHUNK_SIZE),
+                chunk.y.checked_mul(CHUNK_SIZE),
+            ) else {
+                continue;
+            };
+            let origin = IVec2::new(x, y);
+            let tiles = chunk
+                .tiles
+                .into_iter()
+                .take((CHUNK_SIZE * CHUNK_SIZE) as usize);
+            for (index, tile) in tiles.enumerate() {
+                let local = IVec2::new(index as i32 % CHUNK_SIZE, index as i32 / CHUNK_SIZE);
+                tilemap.set(origin + local, tile);
+            }
+        }
+        tilemap
+    }
+}
+
+impl From<&EngineTilemap> for TilemapData {
+    fn from(tilemap: &EngineTilemap) -> Self {
+        TilemapData {
+            tileset: (&tilemap.tileset).into(),
+            tile_size: tilemap.tile_size.into(),
+            collision_layer: tilemap.collision_layer,
+            chunks: tilemap
+                .chunks()
+                .map(|(position, chunk)| TileChunk {
+                    x: position.x,
+                    y: position.y,
+                    tiles: chunk.tiles().to_vec(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn test_chunks_outside_the_tile_range_are_dropped() {
+        let tileset = Tileset {
+            sprite: 0,
+            columns: 1,
+            rows: 1,
+            solid: Vec::new(),
+        };
+        let chunk = |x: i32, y: i32| TileChunk {
+            x,
+            y,
+            tiles: vec![1; (CHUNK_SIZE * CHUNK_SIZE) as usize],
+        };
+        let last = i32::MAX / CHUNK_SIZE;
+        let data = TilemapData {
+            tileset,
+            tile_size: Vec2 { x: 1.0, y: 1.0 },
+            collision_layer: 1,
+            chunks: vec![chunk(i32::MAX, 0), chunk(0, i32::MIN), chunk(last, -1)],
+        };
+        let tilemap = EngineTilemap::from(data);
+        let chunks: Vec<IVec2> = tilemap.chunks().map(|(position, _)| position).collect();
+        assert_eq!(chunks, vec![IVec2::new(last, -1)]);
+        assert_eq!(tilemap.get(IVec2::new(i32::MAX, -1)), 1);
+    }
+}
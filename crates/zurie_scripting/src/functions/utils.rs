@@ -1,5 +1,7 @@
 use crate::Host;
+use crate::functions::zurie::engine::core::LoadStatus;
 use log::{debug, error, info, trace, warn};
+use zurie_types::LoadStatus as EngineLoadStatus;
 
 use super::ScriptingState;
 
@@ -22,3 +24,13 @@ impl Host for ScriptingState {
         trace!(target: &module_name, "{}", text)
     }
 }
+
+impl From<EngineLoadStatus> for LoadStatus {
+    fn from(status: EngineLoadStatus) -> Self {
+        match status {
+            EngineLoadStatus::Pending => LoadStatus::Pending,
+            EngineLoadStatus::Ready => LoadStatus::Ready,
+            EngineLoadStatus::Failed(err) => LoadStatus::Failed(err),
+        }
+    }
+}
@@ -1,8 +1,11 @@
 use functions::ScriptingState;
 use zurie_shared::slotmap::new_key_type;
 
+pub mod clock;
 pub mod engine_mod;
 pub mod functions;
 pub mod mod_manager;
+pub mod noise;
+pub mod profile;
 
 use crate::functions::zurie::engine::core::Host;
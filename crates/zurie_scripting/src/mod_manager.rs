@@ -1,24 +1,34 @@
 use anyhow::Ok;
 use egui::{self, Context};
-use hashbrown::HashSet;
+use hashbrown::{HashMap, HashSet};
 use log::{error, info};
 use std::sync::{Arc, RwLock};
 use wasmtime::Engine;
-#[cfg(target_os = "android")]
-use winit::platform::android::activity::AndroidApp;
-use winit::{
-    dpi::PhysicalPosition,
-    event::{MouseScrollDelta, WindowEvent},
-};
+use winit::event::WindowEvent;
 use zurie_audio::AudioManager;
-use zurie_ecs::World;
-use zurie_event::EventManager;
-use zurie_input::InputState;
+use zurie_ecs::{Entity, World};
+use zurie_event::{
+    CONTACT_BEGIN_EVENT, CONTACT_END_EVENT, EventData, EventManager, PREFAB_RELOADED_EVENT,
+    SOUND_LOADED_EVENT, SPRITE_LOADED_EVENT, WORLD_RESTORED_EVENT,
+};
+use zurie_input::{InputEvent, InputState};
+use zurie_physics::{ContactPhase, Physics};
+use zurie_prefab::{Components, PREFAB_DIRECTORY, Prefabs};
 use zurie_render::sprite::SpriteManager;
-use zurie_shared::slotmap::SlotMap;
-use zurie_types::{KeyCode, ModHandle, camera::Camera, glam::Vec2};
+use zurie_shared::loader::AssetLoader;
+use zurie_shared::profiler::{Profiler, Stage};
+use zurie_shared::slotmap::{Key, KeyData, SlotMap};
+use zurie_types::console::{ArgValue, Command};
+use zurie_types::{KeyCode, ModHandle, camera::Cameras, glam::Vec2};
 
+use super::clock::ModClock;
 use super::engine_mod::EngineMod;
+use super::functions::prefab::spawn_prefab;
+
+/// Mod the engine starts with.
+pub const DEFAULT_MOD: &str = "./target/wasm32-wasip2/release/vampire_like_demo.wasm";
+/// Engine seed of the mods' random generators, a random one is picked when it isn't set.
+pub const SEED_ENV: &str = "ZURIE_SEED";
 
 pub struct ModManager {
     engine: Engine,
@@ -27,47 +37,188 @@ pub struct ModManager {
     new_mod_path: String,
     input_state: InputState,
     world: Arc<RwLock<World>>,
-    camera: Arc<RwLock<Camera>>,
+    cameras: Arc<RwLock<Cameras>>,
     event_manager: Arc<RwLock<EventManager>>,
     sprite_manager: Arc<RwLock<SpriteManager>>,
     audio_manager: AudioManager,
-    #[cfg(target_os = "android")]
-    app: AndroidApp,
+    physics: Arc<RwLock<Physics>>,
+    prefabs: Arc<RwLock<Prefabs>>,
+    seed: u64,
+    clock: ModClock,
+    /// Mods get no updates or input while paused, events sent to them stay queued until the
+    /// next update.
+    paused: bool,
+    profiler: Profiler,
 }
 
 impl ModManager {
-    pub fn window_event(&mut self, ev: WindowEvent) -> anyhow::Result<()> {
-        if let WindowEvent::KeyboardInput { event, .. } = ev.clone() {
-            match event.physical_key {
-                winit::keyboard::PhysicalKey::Code(key_code) => {
-                    let key_code = key_code as u32;
-                    let key_code: KeyCode = KeyCode::try_from(key_code).unwrap();
-                    for (_, engine_mod) in self.mods.iter() {
-                        let mut mod_lock = engine_mod.write().unwrap();
-                        mod_lock.key_event(key_code)?;
-                    }
-                }
-                winit::keyboard::PhysicalKey::Unidentified(_) => {}
+    pub fn audio_manager(&self) -> &AudioManager {
+        &self.audio_manager
+    }
+
+    /// Sends a key press to every mod subscribed to the key.
+    pub fn key_event(&mut self, key_code: KeyCode) -> anyhow::Result<()> {
+        for (_, engine_mod) in self.mods.iter() {
+            let mut mod_lock = engine_mod.write().unwrap();
+            mod_lock.key_event(key_code)?;
+        }
+        Ok(())
+    }
+
+    /// Seed the mods' random generators are derived from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Paths of the loaded mods in load order.
+    pub fn mod_paths(&self) -> Vec<String> {
+        self.mods
+            .values()
+            .map(|engine_mod| engine_mod.read().unwrap().path.clone())
+            .collect()
+    }
+
+    pub fn physics(&self) -> &Arc<RwLock<Physics>> {
+        &self.physics
+    }
+
+    pub fn sprite_manager(&self) -> &Arc<RwLock<SpriteManager>> {
+        &self.sprite_manager
+    }
+
+    /// Frame timings of the mods, the other engine parts add theirs to it.
+    pub fn profiler(&self) -> &Profiler {
+        &self.profiler
+    }
+
+    /// Names of the prefabs in the prefab directory.
+    pub fn prefab_names(&self) -> Vec<String> {
+        self.prefabs.read().unwrap().names()
+    }
+
+    /// Spawns a prefab like mods do, for engine tools.
+    pub fn spawn_prefab(
+        &self,
+        world: &mut World,
+        name: &str,
+        overrides: &Components,
+    ) -> anyhow::Result<Entity> {
+        spawn_prefab(
+            world,
+            &mut self.prefabs.write().unwrap(),
+            &mut self.sprite_manager.write().unwrap(),
+            name,
+            overrides,
+        )
+    }
+
+    /// Console commands the mods registered, in load order.
+    pub fn console_commands(&self) -> Vec<Command> {
+        self.mods
+            .values()
+            .flat_map(|engine_mod| {
+                engine_mod
+                    .read()
+                    .unwrap()
+                    .console_commands
+                    .read()
+                    .unwrap()
+                    .clone()
+            })
+            .collect()
+    }
+
+    /// Runs a console command in the first mod that registered it, None when none did. Traps
+    /// of the mod come back as errors.
+    pub fn run_console_command(
+        &self,
+        name: &str,
+        args: &[ArgValue],
+    ) -> Option<Result<String, String>> {
+        let engine_mod = self.mods.values().find(|engine_mod| {
+            let engine_mod = engine_mod.read().unwrap();
+            let commands = engine_mod.console_commands.read().unwrap();
+            commands.iter().any(|command| command.name == name)
+        })?;
+        let mut engine_mod = engine_mod.write().unwrap();
+        match engine_mod.console_command(name, args) {
+            Ok(result) => Some(result),
+            Err(err) => {
+                error!("Error running {name} in mod {}: {err:#}", engine_mod.path);
+                Some(Err(format!("{err:#}")))
             }
         }
-        if let WindowEvent::MouseWheel { delta, .. } = ev {
-            let scroll_amount: f32 = match delta {
-                MouseScrollDelta::LineDelta(_, y) => y,
-                MouseScrollDelta::PixelDelta(pos) => {
-                    if pos == PhysicalPosition::new(-0.0, -0.0) {
-                        0.0
-                    } else {
-                        -(pos.y as f32)
-                    }
-                }
+    }
+
+    /// Steps the physics by the time of the frame and tells mods about contacts that began
+    /// and ended, they receive them before their next update.
+    pub fn update_physics(&self, delta_time: f32) {
+        let _scope = self.profiler.scope(Stage::Physics);
+        let contacts = self
+            .physics
+            .write()
+            .unwrap()
+            .update(&mut self.world.write().unwrap(), delta_time);
+        let mut event_manager = self.event_manager.write().unwrap();
+        for event in contacts {
+            let name = match event.phase {
+                ContactPhase::Begin => CONTACT_BEGIN_EVENT,
+                ContactPhase::End => CONTACT_END_EVENT,
             };
-            if scroll_amount == 0.0 {
-                return Ok(());
-            }
-            for (_, engine_mod) in self.mods.iter() {
-                let mut mod_lock = engine_mod.write().unwrap();
-                mod_lock.scroll(scroll_amount)?;
+            let data = EventData::Contact {
+                a: KeyData::as_ffi(event.a.data()),
+                b: KeyData::as_ffi(event.b.data()),
+                normal: event.contact.normal,
+                point: event.contact.point,
+            };
+            event_manager.emit_engine_event(name, data);
+        }
+    }
+
+    /// Tells mods the world was put back, with the new ids of entities that came back.
+    /// Entities spawned since are gone.
+    pub fn world_restored(&self, respawned: &HashMap<Entity, Entity>) {
+        let data = respawned
+            .iter()
+            .flat_map(|(old, new)| [old, new])
+            .flat_map(|entity| KeyData::as_ffi(entity.data()).to_le_bytes())
+            .collect();
+        self.event_manager
+            .write()
+            .unwrap()
+            .emit_engine_event(WORLD_RESTORED_EVENT, EventData::Raw(data));
+    }
+
+    /// Moves the clock mods read time from, once per tick.
+    pub fn advance_clock(&self, delta_time: f32) {
+        self.clock.advance(delta_time);
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn window_event(&mut self, ev: WindowEvent) -> anyhow::Result<()> {
+        if self.paused {
+            return Ok(());
+        }
+        match InputEvent::from_window_event(&ev) {
+            Some(event) => self.input_event(event),
+            None => Ok(()),
+        }
+    }
+
+    /// Calls the key and scroll handlers of the mods.
+    pub fn input_event(&mut self, event: InputEvent) -> anyhow::Result<()> {
+        match event {
+            InputEvent::Key { key, .. } => self.key_event(key)?,
+            InputEvent::Scroll(amount) => {
+                for (_, engine_mod) in self.mods.iter() {
+                    let mut mod_lock = engine_mod.write().unwrap();
+                    mod_lock.scroll(amount)?;
+                }
             }
+            _ => {}
         }
         Ok(())
     }
@@ -88,6 +239,7 @@ impl ModManager {
                 ));
             }
         });
+        self.audio_manager.mixer_gui(&self.gui_context);
         Ok((reload_mods, load_new_mod))
     }
 
@@ -102,13 +254,15 @@ impl ModManager {
             self.gui_context.clone(),
             self.input_state.clone(),
             self.world.clone(),
-            self.camera.clone(),
+            self.cameras.clone(),
             self.event_manager.clone(),
             handle,
             self.sprite_manager.clone(),
             self.audio_manager.clone(),
-            #[cfg(target_os = "android")]
-            self.app.clone(),
+            self.physics.clone(),
+            self.prefabs.clone(),
+            self.seed,
+            self.clock.clone(),
         )?)))
     }
 
@@ -143,13 +297,15 @@ impl ModManager {
                     self.gui_context.clone(),
                     self.input_state.clone(),
                     self.world.clone(),
-                    self.camera.clone(),
+                    self.cameras.clone(),
                     event_manager,
                     handle,
                     self.sprite_manager.clone(),
                     self.audio_manager.clone(),
-                    #[cfg(target_os = "android")]
-                    self.app.clone(),
+                    self.physics.clone(),
+                    self.prefabs.clone(),
+                    self.seed,
+                    self.clock.clone(),
                 )
                 .unwrap();
                 self.event_manager
@@ -159,61 +315,139 @@ impl ModManager {
                 Arc::new(RwLock::new(engine_mod))
             });
         }
+        self.audio_manager.hot_reload();
+        self.emit_load_events();
+        if !self.paused {
+            let _scope = self.profiler.scope(Stage::Mods);
+            for (_, engine_mod) in self.mods.iter() {
+                let mut mod_lock = engine_mod.write().unwrap();
+                if let Err(e) = mod_lock.update() {
+                    error!("Error updating mod {}: {}", mod_lock.path, e);
+                    continue; // Skip this mod but continue with others
+                }
+            }
+        }
+        // Taken while paused too, console commands still run then
         for (_, engine_mod) in self.mods.iter() {
             let mut mod_lock = engine_mod.write().unwrap();
-            if let Err(e) = mod_lock.update() {
-                error!("Error updating mod {}: {}", mod_lock.path, e);
-                continue; // Skip this mod but continue with others
-            }
+            let frame = mod_lock.take_profile();
+            self.profiler.add_mod(mod_lock.name(), &frame);
         }
         Ok(())
     }
+
+    /// Tells mods about sprites and sounds that finished loading and prefabs that were read
+    /// again since the last frame.
+    fn emit_load_events(&mut self) {
+        let sprites = self.sprite_manager.write().unwrap().take_completed();
+        let sounds = self.audio_manager.take_completed();
+        let prefabs = self.prefabs.write().unwrap().reload_changed();
+        let mut event_manager = self.event_manager.write().unwrap();
+        for sprite in sprites {
+            let data = EventData::I64(KeyData::as_ffi(sprite.data()) as i64);
+            event_manager.emit_engine_event(SPRITE_LOADED_EVENT, data);
+        }
+        for sound in sounds {
+            let data = EventData::I64(KeyData::as_ffi(sound.data()) as i64);
+            event_manager.emit_engine_event(SOUND_LOADED_EVENT, data);
+        }
+        for prefab in prefabs {
+            event_manager.emit_engine_event(PREFAB_RELOADED_EVENT, EventData::String(prefab));
+        }
+    }
+
     pub fn new(
         gui_context: Context,
         input_state: zurie_input::InputState,
         world: Arc<RwLock<World>>,
-        camera: Arc<RwLock<Camera>>,
+        cameras: Arc<RwLock<Cameras>>,
         sprite_manager: Arc<RwLock<SpriteManager>>,
-        #[cfg(target_os = "android")] android_app: AndroidApp,
+        loader: AssetLoader,
     ) -> Self {
+        let seed = match std::env::var(SEED_ENV).map(|seed| seed.parse()) {
+            Ok(Ok(seed)) => seed,
+            Ok(Err(_)) => {
+                error!("{SEED_ENV} is not a number, using a random seed");
+                rand::random()
+            }
+            Err(_) => rand::random(),
+        };
+        info!("Mods are seeded with {seed}");
+        Self::with_mods(
+            &[DEFAULT_MOD.into()],
+            seed,
+            gui_context,
+            input_state,
+            world,
+            cameras,
+            sprite_manager,
+            AudioManager::new(loader),
+        )
+        .unwrap()
+    }
+
+    /// Manager running the mods at `mod_paths` in order, their random generators are seeded
+    /// from `seed`.
+    pub fn with_mods(
+        mod_paths: &[String],
+        seed: u64,
+        gui_context: Context,
+        input_state: zurie_input::InputState,
+        world: Arc<RwLock<World>>,
+        cameras: Arc<RwLock<Cameras>>,
+        sprite_manager: Arc<RwLock<SpriteManager>>,
+        audio_manager: AudioManager,
+    ) -> anyhow::Result<Self> {
         let engine = Engine::default();
-        let mut mods = SlotMap::with_key();
+        let mut mods: SlotMap<ModHandle, Arc<RwLock<EngineMod>>> = SlotMap::with_key();
         let event_manager: Arc<RwLock<EventManager>> = Default::default();
-        let audio_manager = AudioManager::new();
+        let clock = ModClock::default();
+        let physics = Arc::new(RwLock::new(Physics::new(&mut world.write().unwrap())));
+        let prefabs = Arc::new(RwLock::new(Prefabs::watched(PREFAB_DIRECTORY)));
 
-        mods.insert_with_key(|handle| {
-            Arc::new(RwLock::new(
-                EngineMod::new(
-                    "./target/wasm32-wasip2/release/vampire_like_demo.wasm".into(),
+        for mod_path in mod_paths {
+            mods.try_insert_with_key(|handle| {
+                let engine_mod = EngineMod::new(
+                    mod_path.clone(),
                     &engine,
                     gui_context.clone(),
                     input_state.clone(),
                     world.clone(),
-                    camera.clone(),
+                    cameras.clone(),
                     event_manager.clone(),
                     handle,
                     sprite_manager.clone(),
                     audio_manager.clone(),
-                    #[cfg(target_os = "android")]
-                    android_app.clone(),
-                )
-                .unwrap(),
-            ))
-        });
+                    physics.clone(),
+                    prefabs.clone(),
+                    seed,
+                    clock.clone(),
+                )?;
+                event_manager
+                    .write()
+                    .unwrap()
+                    .mod_subscribe(engine_mod.get_event_queue(), handle);
+                Ok(Arc::new(RwLock::new(engine_mod)))
+            })?;
+        }
 
-        Self {
+        Ok(Self {
             engine,
             gui_context,
             mods,
             new_mod_path: String::new(),
             input_state,
             world,
-            camera,
+            cameras,
             event_manager,
             sprite_manager,
             audio_manager,
-            #[cfg(target_os = "android")]
-            app: android_app,
-        }
+            physics,
+            prefabs,
+            seed,
+            clock,
+            paused: false,
+            profiler: Profiler::default(),
+        })
     }
 }
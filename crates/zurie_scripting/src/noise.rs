@@ -0,0 +1,118 @@
+use rand::SeedableRng;
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+
+/// Seeded 2D Perlin noise. Only uses float operations IEEE rounds exactly, so the same seed
+/// gives the same values on every platform.
+#[derive(Clone)]
+pub struct Perlin {
+    permutation: [u8; 512],
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut table: Vec<u8> = (0..=255).collect();
+        table.shuffle(&mut ChaCha8Rng::seed_from_u64(seed));
+        let mut permutation = [0; 512];
+        for (i, value) in permutation.iter_mut().enumerate() {
+            *value = table[i % 256];
+        }
+        Self { permutation }
+    }
+
+    /// Noise at a point, in -1 to 1. Whole coordinates are always 0.
+    pub fn get(&self, x: f32, y: f32) -> f32 {
+        let (cell_x, cell_y) = (x.floor(), y.floor());
+        let (x, y) = (x - cell_x, y - cell_y);
+        let (cell_x, cell_y) = (
+            (cell_x as i64 & 255) as usize,
+            (cell_y as i64 & 255) as usize,
+        );
+        let perm = &self.permutation;
+        let corner = |dx: usize, dy: usize| perm[perm[cell_x + dx] as usize + cell_y + dy];
+        let (u, v) = (fade(x), fade(y));
+        let bottom = lerp(
+            gradient(corner(0, 0), x, y),
+            gradient(corner(1, 0), x - 1.0, y),
+            u,
+        );
+        let top = lerp(
+            gradient(corner(0, 1), x, y - 1.0),
+            gradient(corner(1, 1), x - 1.0, y - 1.0),
+            u,
+        );
+        lerp(bottom, top, v).clamp(-1.0, 1.0)
+    }
+
+    /// Octaves of noise added together, each `lacunarity` times finer and `persistence` times
+    /// weaker than the last. Normalized back to -1 to 1.
+    pub fn fractal(&self, x: f32, y: f32, octaves: u32, persistence: f32, lacunarity: f32) -> f32 {
+        let (mut total, mut max) = (0.0, 0.0);
+        let (mut amplitude, mut frequency) = (1.0, 1.0);
+        for _ in 0..octaves.clamp(1, 16) {
+            total += self.get(x * frequency, y * frequency) * amplitude;
+            max += amplitude;
+            amplitude *= persistence;
+            frequency *= lacunarity;
+        }
+        if max == 0.0 { 0.0 } else { total / max }
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Dot product with one of eight unit gradient directions.
+fn gradient(hash: u8, x: f32, y: f32) -> f32 {
+    const DIAGONAL: f32 = std::f32::consts::FRAC_1_SQRT_2;
+    match hash & 7 {
+        0 => (x + y) * DIAGONAL,
+        1 => (x - y) * DIAGONAL,
+        2 => (y - x) * DIAGONAL,
+        3 => -(x + y) * DIAGONAL,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_noise() {
+        let (a, b, other) = (Perlin::new(9), Perlin::new(9), Perlin::new(10));
+        let points: Vec<(f32, f32)> = (0..50)
+            .map(|i| (i as f32 * 0.37, i as f32 * -0.71))
+            .collect();
+        let sample = |perlin: &Perlin| -> Vec<f32> {
+            points.iter().map(|(x, y)| perlin.get(*x, *y)).collect()
+        };
+        assert_eq!(sample(&a), sample(&b));
+        assert_ne!(sample(&a), sample(&other));
+    }
+
+    #[test]
+    fn test_noise_is_smooth_and_bounded() {
+        let perlin = Perlin::new(1);
+        assert_eq!(perlin.get(3.0, -7.0), 0.0);
+        let mut varies = false;
+        for i in 0..2000 {
+            let (x, y) = (i as f32 * 0.013, i as f32 * 0.029 - 10.0);
+            let value = perlin.get(x, y);
+            assert!((-1.0..=1.0).contains(&value));
+            assert!((perlin.get(x + 0.001, y) - value).abs() < 0.01);
+            varies |= value.abs() > 0.1;
+            let fractal = perlin.fractal(x, y, 4, 0.5, 2.0);
+            assert!((-1.0..=1.0).contains(&fractal));
+        }
+        assert!(varies);
+    }
+}
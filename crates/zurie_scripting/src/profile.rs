@@ -0,0 +1,164 @@
+use std::time::Instant;
+
+use wasmtime::component::Linker;
+use wasmtime::{CallHook, Store};
+use zurie_shared::profiler::{HostCategory, ModFrame};
+
+use crate::functions::ScriptingState;
+use crate::functions::zurie::engine;
+
+/// What a mod did since the mod manager last took it.
+#[derive(Default)]
+pub struct ModProfile {
+    pub frame: ModFrame,
+    host_call_start: Option<Instant>,
+    /// Set by the linker when the host function belongs to one of the engine's interfaces.
+    host_category: Option<HostCategory>,
+}
+
+impl ModProfile {
+    /// Counts the host call `hook` ends under the interface the linker set, or WASI.
+    fn call_hook(&mut self, hook: CallHook) {
+        match hook {
+            CallHook::CallingHost => {
+                self.host_call_start = Some(Instant::now());
+                self.host_category = None;
+            }
+            CallHook::ReturningFromHost => {
+                if let Some(start) = self.host_call_start.take() {
+                    let category = self.host_category.take().unwrap_or(HostCategory::Wasi);
+                    self.frame.host_mut(category).add(start.elapsed());
+                }
+            }
+            CallHook::CallingWasm | CallHook::ReturningFromWasm => {}
+        }
+    }
+}
+
+impl AsMut<ModProfile> for ScriptingState {
+    fn as_mut(&mut self) -> &mut ModProfile {
+        &mut self.profile
+    }
+}
+
+/// Host functions call this to get the state, it's how the call hook learns which interface a
+/// call went to.
+fn counted<T: AsMut<ModProfile>>(
+    category: HostCategory,
+) -> impl Fn(&mut T) -> &mut T + Send + Sync + Copy + 'static {
+    move |state| {
+        state.as_mut().host_category = Some(category);
+        state
+    }
+}
+
+/// Links the engine interface of every `HostCategory`, with every call counted under it. WASI
+/// is linked by `wasmtime_wasi`, its calls are the ones no interface claims.
+pub fn add_to_linker(linker: &mut Linker<ScriptingState>) -> anyhow::Result<()> {
+    for category in HostCategory::ALL {
+        let get = counted(category);
+        match category {
+            HostCategory::Core => engine::core::add_to_linker(linker, get)?,
+            HostCategory::Audio => engine::audio::add_to_linker(linker, get)?,
+            HostCategory::Camera => engine::camera::add_to_linker(linker, get)?,
+            HostCategory::Ecs => engine::ecs::add_to_linker(linker, get)?,
+            HostCategory::Events => engine::events::add_to_linker(linker, get)?,
+            HostCategory::Input => engine::input::add_to_linker(linker, get)?,
+            HostCategory::Gui => engine::gui::add_to_linker(linker, get)?,
+            HostCategory::Sprite => engine::sprite::add_to_linker(linker, get)?,
+            HostCategory::Rand => engine::rand::add_to_linker(linker, get)?,
+            HostCategory::Physics => engine::physics::add_to_linker(linker, get)?,
+            HostCategory::Tilemap => engine::tilemap::add_to_linker(linker, get)?,
+            HostCategory::Prefab => engine::prefab::add_to_linker(linker, get)?,
+            HostCategory::Console => engine::console::add_to_linker(linker, get)?,
+            HostCategory::Wasi => {}
+        }
+    }
+    Ok(())
+}
+
+/// Times the host calls of the mod in `store`.
+pub fn time_host_calls(store: &mut Store<ScriptingState>) {
+    store.call_hook(|mut store, hook| {
+        store.data_mut().profile.call_hook(hook);
+        Ok(())
+    });
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use wit_parser::{Resolve, WorldItem};
+
+    #[derive(Default)]
+    struct State {
+        profile: ModProfile,
+    }
+
+    impl AsMut<ModProfile> for State {
+        fn as_mut(&mut self) -> &mut ModProfile {
+            &mut self.profile
+        }
+    }
+
+    fn host_call(state: &mut State, category: Option<HostCategory>) {
+        state.profile.call_hook(CallHook::CallingHost);
+        if let Some(category) = category {
+            counted(category)(state);
+        }
+        state.profile.call_hook(CallHook::ReturningFromHost);
+    }
+
+    #[test]
+    fn test_host_calls_counted_by_interface() {
+        let mut state = State::default();
+        host_call(&mut state, Some(HostCategory::Ecs));
+        host_call(&mut state, Some(HostCategory::Ecs));
+        host_call(&mut state, Some(HostCategory::Physics));
+        host_call(&mut state, None);
+        let frame = &state.profile.frame;
+        assert_eq!(frame.host(HostCategory::Ecs).calls, 2);
+        assert_eq!(frame.host(HostCategory::Physics).calls, 1);
+        assert_eq!(frame.host(HostCategory::Wasi).calls, 1);
+        assert_eq!(frame.host(HostCategory::Audio).calls, 0);
+
+        // A category left from outside a host call isn't given to the next one
+        counted(HostCategory::Gui)(&mut state);
+        host_call(&mut state, None);
+        assert_eq!(state.profile.frame.host(HostCategory::Gui).calls, 0);
+        assert_eq!(state.profile.frame.host(HostCategory::Wasi).calls, 2);
+
+        // Returns without a start and calls into the mod aren't host calls
+        state.profile.call_hook(CallHook::ReturningFromHost);
+        state.profile.call_hook(CallHook::CallingWasm);
+        state.profile.call_hook(CallHook::ReturningFromWasm);
+        let host = &state.profile.frame.host;
+        let calls: u32 = host.iter().map(|stats| stats.calls).sum();
+        assert_eq!(calls, 5);
+    }
+
+    #[test]
+    fn test_categories_match_wit_imports() {
+        let mut resolve = Resolve::default();
+        let wit = concat!(env!("CARGO_MANIFEST_DIR"), "/zurie_engine.wit");
+        let package = resolve.push_file(wit).unwrap();
+        let world = resolve.select_world(package, Some("zurie-mod")).unwrap();
+        // Imports are ordered by their dependencies, not like the world lists them
+        let mut imports: Vec<&str> = resolve.worlds[world]
+            .imports
+            .values()
+            .filter_map(|item| match item {
+                WorldItem::Interface { id, .. } => resolve.interfaces[*id].name.as_deref(),
+                _ => None,
+            })
+            .collect();
+        let mut categories: Vec<&str> = HostCategory::ALL
+            .iter()
+            .filter(|category| **category != HostCategory::Wasi)
+            .map(|category| category.name())
+            .collect();
+        imports.sort();
+        categories.sort();
+        assert_eq!(imports, categories);
+    }
+}
@@ -1,6 +1,8 @@
 pub use hashbrown;
 pub use slotmap;
 
+pub mod loader;
+pub mod profiler;
 pub mod sim_clock;
 
 pub static mut DELTA_TIME: f32 = 0.0;
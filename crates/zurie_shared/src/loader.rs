@@ -0,0 +1,81 @@
+use log::error;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::mpsc::{Sender, channel};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Pool of worker threads that decode assets off the main and render threads.
+/// Clones share the same workers.
+#[derive(Clone)]
+pub struct AssetLoader {
+    sender: Sender<Job>,
+}
+
+impl AssetLoader {
+    pub fn new(threads: usize) -> Self {
+        let (sender, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for index in 0..threads.max(1) {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("Asset loader {index}"))
+                .spawn(move || {
+                    loop {
+                        let job = receiver.lock().unwrap().recv();
+                        let Ok(job) = job else {
+                            break;
+                        };
+                        // A broken asset must not take the worker down with it
+                        if catch_unwind(AssertUnwindSafe(job)).is_err() {
+                            error!("Asset loading job panicked");
+                        }
+                    }
+                })
+                .expect("Failed to spawn asset loader thread");
+        }
+        Self { sender }
+    }
+
+    /// Runs `job` on one of the workers, results are sent back by the job itself.
+    pub fn spawn(&self, job: impl FnOnce() + Send + 'static) {
+        if self.sender.send(Box::new(job)).is_err() {
+            error!("Asset loader workers are gone");
+        }
+    }
+}
+
+impl Default for AssetLoader {
+    fn default() -> Self {
+        let threads = thread::available_parallelism().map_or(2, |threads| threads.get());
+        Self::new(threads.saturating_sub(1))
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn test_jobs_run_on_workers() {
+        let loader = AssetLoader::new(3);
+        let (sender, receiver) = channel();
+        for i in 0..32 {
+            let sender = sender.clone();
+            loader.spawn(move || sender.send(i * 2).unwrap());
+        }
+        let mut results: Vec<i32> = receiver.iter().take(32).collect();
+        results.sort();
+        assert_eq!(results, (0..32).map(|i| i * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_panicking_job_keeps_worker_alive() {
+        let loader = AssetLoader::new(1);
+        let (sender, receiver) = channel();
+        loader.spawn(|| panic!("corrupt asset"));
+        loader.spawn(move || sender.send(()).unwrap());
+        assert!(receiver.recv().is_ok());
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
+use std::time::{Duration, Instant};
+
+use tracy_client::{Client, Span};
+
+/// Frames kept for the profiler graphs.
+pub const HISTORY: usize = 300;
+
+/// Parts of a frame the engine times.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stage {
+    Mods,
+    Physics,
+    /// Inspector, editor and console.
+    Tools,
+    SpriteQueue,
+    Render,
+    Present,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 6] = [
+        Stage::Mods,
+        Stage::Physics,
+        Stage::Tools,
+        Stage::SpriteQueue,
+        Stage::Render,
+        Stage::Present,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Mods => "mods",
+            Stage::Physics => "physics",
+            Stage::Tools => "tools",
+            Stage::SpriteQueue => "sprite queue",
+            Stage::Render => "render",
+            Stage::Present => "present",
+        }
+    }
+}
+
+/// Host functions grouped by the WIT interface they belong to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HostCategory {
+    Core,
+    Audio,
+    Camera,
+    Ecs,
+    Events,
+    Input,
+    Gui,
+    Sprite,
+    Rand,
+    Physics,
+    Tilemap,
+    Prefab,
+    Console,
+    /// Clocks, randomness and stdio of the WASI imports.
+    Wasi,
+}
+
+impl HostCategory {
+    pub const ALL: [HostCategory; 14] = [
+        HostCategory::Core,
+        HostCategory::Audio,
+        HostCategory::Camera,
+        HostCategory::Ecs,
+        HostCategory::Events,
+        HostCategory::Input,
+        HostCategory::Gui,
+        HostCategory::Sprite,
+        HostCategory::Rand,
+        HostCategory::Physics,
+        HostCategory::Tilemap,
+        HostCategory::Prefab,
+        HostCategory::Console,
+        HostCategory::Wasi,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HostCategory::Core => "core",
+            HostCategory::Audio => "audio",
+            HostCategory::Camera => "camera",
+            HostCategory::Ecs => "ecs",
+            HostCategory::Events => "events",
+            HostCategory::Input => "input",
+            HostCategory::Gui => "gui",
+            HostCategory::Sprite => "sprite",
+            HostCategory::Rand => "rand",
+            HostCategory::Physics => "physics",
+            HostCategory::Tilemap => "tilemap",
+            HostCategory::Prefab => "prefab",
+            HostCategory::Console => "console",
+            HostCategory::Wasi => "wasi",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct CallStats {
+    pub calls: u32,
+    pub time: Duration,
+}
+
+impl CallStats {
+    pub fn add(&mut self, time: Duration) {
+        self.calls += 1;
+        self.time += time;
+    }
+
+    fn merge(&mut self, other: CallStats) {
+        self.calls += other.calls;
+        self.time += other.time;
+    }
+}
+
+/// What a mod did in a frame. Host calls are part of the time of the mod call they were
+/// made from.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct ModFrame {
+    pub update: CallStats,
+    pub event: CallStats,
+    pub key_event: CallStats,
+    /// Indexed like `HostCategory::ALL`.
+    pub host: [CallStats; HostCategory::ALL.len()],
+}
+
+impl ModFrame {
+    pub fn host(&self, category: HostCategory) -> CallStats {
+        self.host[category as usize]
+    }
+
+    pub fn host_mut(&mut self, category: HostCategory) -> &mut CallStats {
+        &mut self.host[category as usize]
+    }
+
+    pub fn host_calls(&self) -> u32 {
+        self.host.iter().map(|stats| stats.calls).sum()
+    }
+
+    pub fn host_time(&self) -> Duration {
+        self.host.iter().map(|stats| stats.time).sum()
+    }
+
+    /// Time spent in the mod, host calls included.
+    pub fn time(&self) -> Duration {
+        self.update.time + self.event.time + self.key_event.time
+    }
+
+    fn merge(&mut self, other: &ModFrame) {
+        self.update.merge(other.update);
+        self.event.merge(other.event);
+        self.key_event.merge(other.key_event);
+        for (stats, other) in self.host.iter_mut().zip(other.host) {
+            stats.merge(other);
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct FrameProfile {
+    /// From the end of the previous frame to the end of this one.
+    pub time: Duration,
+    /// Indexed like `Stage::ALL`.
+    pub stages: [Duration; Stage::ALL.len()],
+    /// Mods by name in the order they ran.
+    pub mods: Vec<(String, ModFrame)>,
+}
+
+impl FrameProfile {
+    pub fn stage(&self, stage: Stage) -> Duration {
+        self.stages[stage as usize]
+    }
+
+    pub fn mod_frame(&self, name: &str) -> Option<&ModFrame> {
+        self.mods
+            .iter()
+            .find(|(mod_name, _)| mod_name == name)
+            .map(|(_, frame)| frame)
+    }
+}
+
+#[derive(Default)]
+struct Current {
+    frame: FrameProfile,
+    start: Option<Instant>,
+}
+
+/// Timings of the last `HISTORY` frames. Clones share the frames, so each engine part can
+/// keep one.
+#[derive(Clone, Default)]
+pub struct Profiler {
+    frames: Arc<RwLock<VecDeque<FrameProfile>>>,
+    current: Arc<Mutex<Current>>,
+}
+
+impl Profiler {
+    /// Times `stage` until the scope is dropped, the scope is a tracy zone too.
+    pub fn scope(&self, stage: Stage) -> StageScope {
+        StageScope {
+            profiler: self.clone(),
+            stage,
+            start: Instant::now(),
+            _zone: zone(stage.name()),
+        }
+    }
+
+    pub fn add_stage(&self, stage: Stage, time: Duration) {
+        self.current.lock().unwrap().frame.stages[stage as usize] += time;
+    }
+
+    /// Adds what the mod named `name` did, a mod reported twice in a frame is summed.
+    pub fn add_mod(&self, name: &str, mod_frame: &ModFrame) {
+        let mods = &mut self.current.lock().unwrap().frame.mods;
+        match mods.iter_mut().find(|(mod_name, _)| mod_name == name) {
+            Some((_, frame)) => frame.merge(mod_frame),
+            None => mods.push((name.to_owned(), mod_frame.clone())),
+        }
+    }
+
+    /// Closes the frame, its time runs from the previous call.
+    pub fn end_frame(&self) {
+        let now = Instant::now();
+        let frame = {
+            let mut current = self.current.lock().unwrap();
+            let start = current.start.replace(now);
+            let mut frame = std::mem::take(&mut current.frame);
+            frame.time = start.map_or(Duration::ZERO, |start| now - start);
+            frame
+        };
+        let mut frames = self.frames.write().unwrap();
+        if frames.len() >= HISTORY {
+            frames.pop_front();
+        }
+        frames.push_back(frame);
+    }
+
+    /// Finished frames, oldest first.
+    pub fn frames(&self) -> RwLockReadGuard<'_, VecDeque<FrameProfile>> {
+        self.frames.read().unwrap()
+    }
+}
+
+/// Adds its lifetime to a stage of the profiler when dropped.
+pub struct StageScope {
+    profiler: Profiler,
+    stage: Stage,
+    start: Instant,
+    _zone: Option<Span>,
+}
+
+impl Drop for StageScope {
+    fn drop(&mut self) {
+        self.profiler.add_stage(self.stage, self.start.elapsed());
+    }
+}
+
+/// Tracy zone named `name` that ends when dropped, None when tracy isn't running like in
+/// headless runs.
+pub fn zone(name: &str) -> Option<Span> {
+    Client::running().map(|client| client.span_alloc(Some(name), "", file!(), line!(), 0))
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    fn mod_frame(update_calls: u32, ecs_calls: u32) -> ModFrame {
+        let mut frame = ModFrame::default();
+        for _ in 0..update_calls {
+            frame.update.add(Duration::from_millis(2));
+        }
+        for _ in 0..ecs_calls {
+            frame
+                .host_mut(HostCategory::Ecs)
+                .add(Duration::from_micros(10));
+        }
+        frame
+            .host_mut(HostCategory::Wasi)
+            .add(Duration::from_micros(5));
+        frame
+    }
+
+    #[test]
+    fn test_mod_frames() {
+        let profiler = Profiler::default();
+        profiler.add_mod("demo", &mod_frame(1, 40));
+        profiler.add_mod("other", &mod_frame(1, 0));
+        profiler.add_mod("demo", &mod_frame(1, 10));
+        profiler.end_frame();
+
+        let frames = profiler.frames();
+        let frame = &frames[0];
+        assert_eq!(frame.mods.len(), 2);
+        let demo = frame.mod_frame("demo").unwrap();
+        assert_eq!(demo.update.calls, 2);
+        assert_eq!(demo.time(), Duration::from_millis(4));
+        assert_eq!(demo.host(HostCategory::Ecs).calls, 50);
+        assert_eq!(demo.host_calls(), 52);
+        assert_eq!(demo.host_time(), Duration::from_micros(510));
+        assert_eq!(frame.mod_frame("other").unwrap().host_calls(), 1);
+    }
+
+    #[test]
+    fn test_stages_and_history() {
+        let profiler = Profiler::default();
+        {
+            let _scope = profiler.scope(Stage::Render);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        profiler.add_stage(Stage::Physics, Duration::from_millis(3));
+        profiler.end_frame();
+        profiler.add_mod("demo", &mod_frame(1, 1));
+        profiler.end_frame();
+        {
+            let frames = profiler.frames();
+            assert!(frames[0].stage(Stage::Render) >= Duration::from_millis(1));
+            assert_eq!(frames[0].stage(Stage::Physics), Duration::from_millis(3));
+            assert_eq!(frames[0].time, Duration::ZERO);
+            // Stages and mods start over every frame
+            assert_eq!(frames[1].stage(Stage::Physics), Duration::ZERO);
+            assert!(frames[0].mods.is_empty());
+            assert!(frames[1].time > Duration::ZERO);
+        }
+        for _ in 0..HISTORY {
+            profiler.end_frame();
+        }
+        let frames = profiler.frames();
+        assert_eq!(frames.len(), HISTORY);
+        assert!(frames.iter().all(|frame| frame.mods.is_empty()));
+    }
+}
@@ -0,0 +1,390 @@
+//! Import of Tiled maps (.tmx/.tmj) and their tilesets (.tsx/.tsj) as tilemaps.
+
+use anyhow::{Context, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+use zurie_types::glam::{IVec2, Vec2};
+use zurie_types::tilemap::{Tilemap, Tileset};
+
+mod tmj;
+mod tmx;
+
+/// High bits of a gid telling how the tile is flipped, flipped tiles are drawn unflipped.
+const FLIP_FLAGS: u32 = 0xF000_0000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TiledTileset {
+    pub first_gid: u32,
+    /// Image path, relative to the map file it was loaded with.
+    pub image: String,
+    pub columns: u32,
+    pub rows: u32,
+    /// Ids inside the tileset, from 0, of tiles with a `solid` property or collision shapes.
+    pub solid: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TiledLayer {
+    pub name: String,
+    /// From the layer's `collision_layer` int property.
+    pub collision_layer: Option<u32>,
+    /// Tile positions with their gid, flip flags stripped.
+    pub tiles: Vec<(IVec2, u32)>,
+}
+
+/// Tile layers of a map in drawing order, groups flattened and other layers left out.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TiledMap {
+    pub tilesets: Vec<TiledTileset>,
+    pub layers: Vec<TiledLayer>,
+}
+
+pub(crate) enum TilesetEntry {
+    Inline(TiledTileset),
+    External { first_gid: u32, source: String },
+}
+
+#[derive(Default)]
+pub(crate) struct ParsedMap {
+    pub tilesets: Vec<TilesetEntry>,
+    pub layers: Vec<TiledLayer>,
+}
+
+/// Loads the map at `path`, with `read` returning the bytes of the map and its tilesets.
+pub fn load(
+    path: &str,
+    mut read: impl FnMut(&str) -> anyhow::Result<Vec<u8>>,
+) -> anyhow::Result<TiledMap> {
+    let text = String::from_utf8(read(path)?).with_context(|| format!("{path} isn't utf-8"))?;
+    let parsed = if path.ends_with(".tmx") {
+        tmx::parse(&text)
+    } else {
+        tmj::parse_map(&text)
+    }
+    .with_context(|| format!("Failed to parse {path}"))?;
+
+    let mut map = TiledMap {
+        tilesets: Vec::new(),
+        layers: parsed.layers,
+    };
+    for entry in parsed.tilesets {
+        let mut tileset = match entry {
+            TilesetEntry::Inline(mut tileset) => {
+                tileset.image = relative_to(path, &tileset.image);
+                tileset
+            }
+            TilesetEntry::External { first_gid, source } => {
+                let source = relative_to(path, &source);
+                let mut tileset = load_tileset(&source, &mut read)?;
+                tileset.first_gid = first_gid;
+                tileset
+            }
+        };
+        tileset.solid.sort();
+        tileset.solid.dedup();
+        map.tilesets.push(tileset);
+    }
+    map.tilesets.sort_by_key(|tileset| tileset.first_gid);
+    Ok(map)
+}
+
+fn load_tileset(
+    path: &str,
+    read: &mut impl FnMut(&str) -> anyhow::Result<Vec<u8>>,
+) -> anyhow::Result<TiledTileset> {
+    let text = String::from_utf8(read(path)?).with_context(|| format!("{path} isn't utf-8"))?;
+    let tileset = if path.ends_with(".tsx") {
+        tmx::parse(&text).and_then(|parsed| match parsed.tilesets.into_iter().next() {
+            Some(TilesetEntry::Inline(tileset)) => Ok(tileset),
+            _ => bail!("No tileset in the file"),
+        })
+    } else {
+        tmj::parse_tileset(&text)
+    }
+    .with_context(|| format!("Failed to parse {path}"))?;
+    Ok(TiledTileset {
+        image: relative_to(path, &tileset.image),
+        ..tileset
+    })
+}
+
+/// `path` as written in `file`, relative to the directory of `file`.
+fn relative_to(file: &str, path: &str) -> String {
+    match Path::new(file).parent() {
+        Some(directory) if !path.is_empty() => {
+            directory.join(path).to_string_lossy().replace('\\', "/")
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// Gids of layer data or a chunk, row by row.
+pub(crate) fn decode_gids(
+    encoding: Option<&str>,
+    compression: Option<&str>,
+    data: &str,
+) -> anyhow::Result<Vec<u32>> {
+    match encoding {
+        Some("csv") => data
+            .split(',')
+            .map(str::trim)
+            .filter(|gid| !gid.is_empty())
+            .map(|gid| gid.parse().with_context(|| format!("Invalid gid {gid}")))
+            .collect(),
+        Some("base64") => {
+            let bytes = STANDARD.decode(data.split_whitespace().collect::<String>())?;
+            let mut decoded = Vec::new();
+            match compression {
+                None | Some("") => decoded = bytes,
+                Some("zlib") => {
+                    ZlibDecoder::new(bytes.as_slice()).read_to_end(&mut decoded)?;
+                }
+                Some("gzip") => {
+                    GzDecoder::new(bytes.as_slice()).read_to_end(&mut decoded)?;
+                }
+                Some(other) => bail!("Unsupported layer compression {other}"),
+            }
+            if decoded.len() % 4 != 0 {
+                bail!("Layer data isn't made of 32 bit gids");
+            }
+            Ok(decoded
+                .chunks_exact(4)
+                .map(|gid| u32::from_le_bytes([gid[0], gid[1], gid[2], gid[3]]))
+                .collect())
+        }
+        Some(other) => bail!("Unsupported layer encoding {other}"),
+        None => bail!("Layer data without an encoding"),
+    }
+}
+
+/// Adds the non empty `gids` of a `width` wide area starting at `origin` to `tiles`.
+pub(crate) fn place_gids(tiles: &mut Vec<(IVec2, u32)>, origin: IVec2, width: u32, gids: &[u32]) {
+    let width = width.max(1) as usize;
+    for (index, gid) in gids.iter().enumerate() {
+        let gid = gid & !FLIP_FLAGS;
+        if gid != 0 {
+            let local = IVec2::new((index % width) as i32, (index / width) as i32);
+            tiles.push((origin + local, gid));
+        }
+    }
+}
+
+impl TiledMap {
+    /// One tilemap per layer and tileset the layer uses, in drawing order, with the layer name.
+    /// `sprites` are the tileset images in tileset order.
+    pub fn tilemaps(&self, sprites: &[u64], tile_size: Vec2) -> Vec<(String, Tilemap)> {
+        let mut tilemaps = Vec::new();
+        for layer in self.layers.iter() {
+            let mut by_tileset: BTreeMap<usize, Tilemap> = BTreeMap::new();
+            for (position, gid) in layer.tiles.iter() {
+                let Some(index) = self.tilesets.iter().rposition(|t| t.first_gid <= *gid) else {
+                    continue;
+                };
+                let tileset = &self.tilesets[index];
+                let tilemap = by_tileset.entry(index).or_insert_with(|| {
+                    let tiles = Tileset {
+                        sprite: sprites.get(index).copied().unwrap_or_default(),
+                        columns: tileset.columns,
+                        rows: tileset.rows,
+                        solid: tileset.solid.iter().map(|id| id + 1).collect(),
+                    };
+                    let mut tilemap = Tilemap::new(tiles, tile_size);
+                    if let Some(collision_layer) = layer.collision_layer {
+                        tilemap.collision_layer = collision_layer;
+                    }
+                    tilemap
+                });
+                tilemap.set(*position, gid - tileset.first_gid + 1);
+            }
+            tilemaps.extend(by_tileset.into_values().map(|t| (layer.name.clone(), t)));
+        }
+        tilemaps
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::ZlibEncoder;
+    use std::io::Write;
+
+    fn files(files: &[(&str, String)]) -> impl FnMut(&str) -> anyhow::Result<Vec<u8>> {
+        let files: Vec<(String, String)> = files
+            .iter()
+            .map(|(path, text)| (path.to_string(), text.clone()))
+            .collect();
+        move |path| {
+            files
+                .iter()
+                .find(|(name, _)| name == path)
+                .map(|(_, text)| text.clone().into_bytes())
+                .with_context(|| format!("No file {path}"))
+        }
+    }
+
+    #[test]
+    fn test_tmx_with_inline_tileset() {
+        let tmx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="3" height="2" tilewidth="16" tileheight="16">
+ <tileset firstgid="1" name="ground" tilewidth="16" tileheight="16" tilecount="8" columns="4">
+  <image source="ground.png" width="64" height="32"/>
+  <tile id="1">
+   <properties>
+    <property name="solid" type="bool" value="true"/>
+   </properties>
+  </tile>
+  <tile id="5">
+   <objectgroup draworder="index"><object id="1" x="0" y="0" width="16" height="16"/></objectgroup>
+  </tile>
+ </tileset>
+ <group name="level">
+  <layer id="1" name="walls" width="3" height="2">
+   <properties>
+    <property name="collision_layer" type="int" value="4"/>
+   </properties>
+   <data encoding="csv">
+2,0,3,
+0,2147483654,0
+</data>
+  </layer>
+ </group>
+ <objectgroup name="spawns"><object id="2" x="5" y="5"/></objectgroup>
+</map>"#;
+        let map = load("maps/level.tmx", files(&[("maps/level.tmx", tmx.into())])).unwrap();
+        assert_eq!(
+            map.tilesets,
+            vec![TiledTileset {
+                first_gid: 1,
+                image: "maps/ground.png".into(),
+                columns: 4,
+                rows: 2,
+                solid: vec![1, 5],
+            }]
+        );
+        assert_eq!(
+            map.layers,
+            vec![TiledLayer {
+                name: "walls".into(),
+                collision_layer: Some(4),
+                tiles: vec![
+                    (IVec2::new(0, 0), 2),
+                    (IVec2::new(2, 0), 3),
+                    (IVec2::new(1, 1), 6)
+                ],
+            }]
+        );
+
+        let tilemaps = map.tilemaps(&[7], Vec2::splat(0.5));
+        assert_eq!(tilemaps.len(), 1);
+        let (name, tilemap) = &tilemaps[0];
+        assert_eq!(name, "walls");
+        assert_eq!(tilemap.tileset.sprite, 7);
+        assert_eq!(tilemap.tileset.solid, vec![2, 6]);
+        assert_eq!(tilemap.collision_layer, 4);
+        assert_eq!(tilemap.get(IVec2::new(1, 1)), 6);
+    }
+
+    #[test]
+    fn test_infinite_tmx_with_external_tilesets() {
+        let tmx = r#"<map infinite="1">
+ <tileset firstgid="1" source="a.tsx"/>
+ <tileset firstgid="5" source="../shared/b.tsx"/>
+ <layer name="ground">
+  <data encoding="base64">
+   <chunk x="-16" y="0" width="2" height="1">BQAAAAEAAAA=</chunk>
+  </data>
+ </layer>
+</map>"#;
+        let tileset = |image: &str| {
+            format!(
+                r#"<tileset name="t" tilecount="4" columns="2"><image source="{image}"/></tileset>"#
+            )
+        };
+        let map = load(
+            "maps/level.tmx",
+            files(&[
+                ("maps/level.tmx", tmx.into()),
+                ("maps/a.tsx", tileset("a.png")),
+                ("maps/../shared/b.tsx", tileset("b.png")),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(map.tilesets[1].first_gid, 5);
+        assert_eq!(map.tilesets[1].image, "maps/../shared/b.png");
+        assert_eq!(map.tilesets[1].rows, 2);
+        assert_eq!(
+            map.layers[0].tiles,
+            vec![(IVec2::new(-16, 0), 5), (IVec2::new(-15, 0), 1)]
+        );
+
+        // One tilemap per tileset, both with the gids turned into tileset ids
+        let tilemaps = map.tilemaps(&[1, 2], Vec2::ONE);
+        assert_eq!(tilemaps.len(), 2);
+        assert_eq!(tilemaps[0].1.get(IVec2::new(-15, 0)), 1);
+        assert_eq!(tilemaps[1].1.tileset.sprite, 2);
+        assert_eq!(tilemaps[1].1.get(IVec2::new(-16, 0)), 1);
+    }
+
+    #[test]
+    fn test_tmj_with_compressed_data() {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        for gid in [0u32, 3, 1, 0] {
+            encoder.write_all(&gid.to_le_bytes()).unwrap();
+        }
+        let data = STANDARD.encode(encoder.finish().unwrap());
+        let tmj = format!(
+            r#"{{
+  "type": "map", "width": 2, "height": 2,
+  "tilesets": [{{"firstgid": 1, "source": "tiles.tsj"}}],
+  "layers": [
+    {{"type": "group", "name": "g", "layers": [
+      {{"type": "tilelayer", "name": "compressed", "width": 2, "height": 2,
+        "encoding": "base64", "compression": "zlib", "data": "{data}"}}
+    ]}},
+    {{"type": "objectgroup", "name": "objects", "objects": []}},
+    {{"type": "tilelayer", "name": "plain", "width": 2, "height": 2, "data": [1, 0, 0, 0],
+      "properties": [{{"name": "collision_layer", "type": "int", "value": 0}}]}}
+  ]
+}}"#
+        );
+        let tsj = r#"{
+  "type": "tileset", "image": "tiles.png", "columns": 3, "tilecount": 9,
+  "tiles": [{"id": 2, "properties": [{"name": "solid", "type": "bool", "value": true}]}]
+}"#;
+        let map = load(
+            "level.tmj",
+            files(&[("level.tmj", tmj), ("tiles.tsj", tsj.into())]),
+        )
+        .unwrap();
+        assert_eq!(
+            map.tilesets,
+            vec![TiledTileset {
+                first_gid: 1,
+                image: "tiles.png".into(),
+                columns: 3,
+                rows: 3,
+                solid: vec![2],
+            }]
+        );
+        assert_eq!(map.layers.len(), 2);
+        assert_eq!(
+            map.layers[0].tiles,
+            vec![(IVec2::new(1, 0), 3), (IVec2::new(0, 1), 1)]
+        );
+        assert_eq!(map.layers[1].collision_layer, Some(0));
+    }
+
+    #[test]
+    fn test_unsupported_compression() {
+        assert!(decode_gids(Some("base64"), Some("zstd"), "AAAA").is_err());
+        assert_eq!(
+            decode_gids(Some("csv"), None, " 1,\n2 ").unwrap(),
+            vec![1, 2]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{ParsedMap, TiledLayer, TiledTileset, TilesetEntry, decode_gids, place_gids};
+use serde::Deserialize;
+use serde_json::Value;
+use zurie_types::glam::IVec2;
+
+#[derive(Deserialize)]
+struct Map {
+    #[serde(default)]
+    tilesets: Vec<Tileset>,
+    #[serde(default)]
+    layers: Vec<Layer>,
+}
+
+#[derive(Deserialize)]
+struct Tileset {
+    #[serde(default)]
+    firstgid: u32,
+    source: Option<String>,
+    #[serde(default)]
+    image: String,
+    #[serde(default)]
+    columns: u32,
+    #[serde(default)]
+    tilecount: u32,
+    #[serde(default)]
+    tiles: Vec<Tile>,
+}
+
+#[derive(Deserialize)]
+struct Tile {
+    id: u32,
+    #[serde(default)]
+    properties: Vec<Property>,
+    objectgroup: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct Property {
+    name: String,
+    value: Value,
+}
+
+#[derive(Deserialize)]
+struct Layer {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    width: u32,
+    data: Option<Data>,
+    #[serde(default)]
+    chunks: Vec<Chunk>,
+    #[serde(default)]
+    layers: Vec<Layer>,
+    #[serde(default)]
+    properties: Vec<Property>,
+    encoding: Option<String>,
+    compression: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Chunk {
+    x: i32,
+    y: i32,
+    width: u32,
+    data: Data,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Data {
+    Gids(Vec<u32>),
+    Encoded(String),
+}
+
+impl Tileset {
+    fn into_tiled(self) -> TiledTileset {
+        let solid = self
+            .tiles
+            .iter()
+            .filter(|tile| {
+                tile.objectgroup.is_some()
+                    || tile
+                        .properties
+                        .iter()
+                        .any(|p| p.name == "solid" && p.value.as_bool() == Some(true))
+            })
+            .map(|tile| tile.id)
+            .collect();
+        TiledTileset {
+            first_gid: self.firstgid,
+            image: self.image,
+            columns: self.columns,
+            rows: self.tilecount.div_ceil(self.columns.max(1)),
+            solid,
+        }
+    }
+}
+
+impl Layer {
+    fn gids(&self, data: &Data) -> anyhow::Result<Vec<u32>> {
+        match data {
+            Data::Gids(gids) => Ok(gids.clone()),
+            Data::Encoded(text) => {
+                decode_gids(self.encoding.as_deref(), self.compression.as_deref(), text)
+            }
+        }
+    }
+
+    /// Tile layers of this layer and the groups inside it.
+    fn flatten(self, layers: &mut Vec<TiledLayer>) -> anyhow::Result<()> {
+        match self.kind.as_str() {
+            "group" => {
+                for layer in self.layers {
+                    layer.flatten(layers)?;
+                }
+            }
+            "tilelayer" => {
+                let mut tiles = Vec::new();
+                if let Some(data) = self.data.as_ref() {
+                    place_gids(&mut tiles, IVec2::ZERO, self.width, &self.gids(data)?);
+                }
+                for chunk in self.chunks.iter() {
+                    let origin = IVec2::new(chunk.x, chunk.y);
+                    place_gids(&mut tiles, origin, chunk.width, &self.gids(&chunk.data)?);
+                }
+                let collision_layer = self
+                    .properties
+                    .iter()
+                    .find(|p| p.name == "collision_layer")
+                    .and_then(|p| p.value.as_u64())
+                    .map(|layer| layer as u32);
+                layers.push(TiledLayer {
+                    name: self.name,
+                    collision_layer,
+                    tiles,
+                });
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn parse_map(text: &str) -> anyhow::Result<ParsedMap> {
+    let map: Map = serde_json::from_str(text)?;
+    let mut parsed = ParsedMap::default();
+    for tileset in map.tilesets {
+        parsed.tilesets.push(match tileset.source.clone() {
+            Some(source) => TilesetEntry::External {
+                first_gid: tileset.firstgid,
+                source,
+            },
+            None => TilesetEntry::Inline(tileset.into_tiled()),
+        });
+    }
+    for layer in map.layers {
+        layer.flatten(&mut parsed.layers)?;
+    }
+    Ok(parsed)
+}
+
+pub(crate) fn parse_tileset(text: &str) -> anyhow::Result<TiledTileset> {
+    let tileset: Tileset = serde_json::from_str(text)?;
+    Ok(tileset.into_tiled())
+}
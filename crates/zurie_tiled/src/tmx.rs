@@ -0,0 +1,179 @@
+use crate::{ParsedMap, TiledLayer, TiledTileset, TilesetEntry, decode_gids, place_gids};
+use anyhow::Context;
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use std::collections::HashMap;
+use zurie_types::glam::IVec2;
+
+#[derive(Default)]
+struct Parser {
+    map: ParsedMap,
+    /// Names of the open elements.
+    stack: Vec<String>,
+    /// Id of the `<tile>` being read inside a tileset.
+    tile: Option<u32>,
+    layer_width: u32,
+    encoding: Option<String>,
+    compression: Option<String>,
+    /// Origin and width of the open `<chunk>`.
+    chunk: Option<(IVec2, u32)>,
+    /// Tiles read so far from `<tile>` elements of the open `<data>`.
+    xml_tiles: usize,
+}
+
+fn attributes(element: &BytesStart) -> anyhow::Result<HashMap<String, String>> {
+    element
+        .attributes()
+        .map(|attribute| {
+            let attribute = attribute?;
+            let key = String::from_utf8_lossy(attribute.key.as_ref()).into_owned();
+            Ok((key, attribute.unescape_value()?.into_owned()))
+        })
+        .collect()
+}
+
+fn number<T: std::str::FromStr>(attributes: &HashMap<String, String>, name: &str) -> Option<T> {
+    attributes.get(name).and_then(|value| value.parse().ok())
+}
+
+impl Parser {
+    fn parent(&self) -> Option<&str> {
+        self.stack.last().map(String::as_str)
+    }
+
+    fn open(&mut self, element: &BytesStart) -> anyhow::Result<()> {
+        let name = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+        let attributes = attributes(element)?;
+        match (name.as_str(), self.parent()) {
+            ("tileset", _) => {
+                let first_gid = number(&attributes, "firstgid").unwrap_or(0);
+                let entry = match attributes.get("source") {
+                    Some(source) => TilesetEntry::External {
+                        first_gid,
+                        source: source.clone(),
+                    },
+                    None => {
+                        let columns: u32 = number(&attributes, "columns").unwrap_or(1);
+                        let count: u32 = number(&attributes, "tilecount").unwrap_or(0);
+                        TilesetEntry::Inline(TiledTileset {
+                            first_gid,
+                            image: String::new(),
+                            columns,
+                            rows: count.div_ceil(columns.max(1)),
+                            solid: Vec::new(),
+                        })
+                    }
+                };
+                self.map.tilesets.push(entry);
+            }
+            ("image", Some("tileset")) => {
+                if let Some(TilesetEntry::Inline(tileset)) = self.map.tilesets.last_mut() {
+                    tileset.image = attributes.get("source").cloned().unwrap_or_default();
+                }
+            }
+            ("tile", Some("tileset")) => self.tile = number(&attributes, "id"),
+            ("tile", Some("data")) => {
+                let gid = number(&attributes, "gid").unwrap_or(0);
+                let index = self.xml_tiles as u32;
+                let width = self.layer_width.max(1);
+                let origin = IVec2::new((index % width) as i32, (index / width) as i32);
+                if let Some(layer) = self.map.layers.last_mut() {
+                    place_gids(&mut layer.tiles, origin, 1, &[gid]);
+                }
+                self.xml_tiles += 1;
+            }
+            ("objectgroup", Some("tile")) => self.mark_solid(),
+            ("property", Some("properties")) => {
+                let owner = self.stack.iter().rev().nth(1).map(String::as_str);
+                let value = attributes.get("value").map(String::as_str);
+                match (owner, attributes.get("name").map(String::as_str)) {
+                    (Some("tile"), Some("solid")) if value == Some("true") => self.mark_solid(),
+                    (Some("layer"), Some("collision_layer")) => {
+                        if let Some(layer) = self.map.layers.last_mut() {
+                            layer.collision_layer = number(&attributes, "value");
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            ("layer", _) => {
+                self.layer_width = number(&attributes, "width").unwrap_or(0);
+                self.map.layers.push(TiledLayer {
+                    name: attributes.get("name").cloned().unwrap_or_default(),
+                    collision_layer: None,
+                    tiles: Vec::new(),
+                });
+            }
+            ("data", Some("layer")) => {
+                self.encoding = attributes.get("encoding").cloned();
+                self.compression = attributes.get("compression").cloned();
+                self.xml_tiles = 0;
+            }
+            ("chunk", Some("data")) => {
+                let origin = IVec2::new(
+                    number(&attributes, "x").unwrap_or(0),
+                    number(&attributes, "y").unwrap_or(0),
+                );
+                self.chunk = Some((origin, number(&attributes, "width").unwrap_or(0)));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        match self.stack.pop().as_deref() {
+            Some("tile") => self.tile = None,
+            Some("chunk") => self.chunk = None,
+            _ => {}
+        }
+    }
+
+    fn mark_solid(&mut self) {
+        let Some(tile) = self.tile else {
+            return;
+        };
+        if let Some(TilesetEntry::Inline(tileset)) = self.map.tilesets.last_mut() {
+            tileset.solid.push(tile);
+        }
+    }
+
+    fn text(&mut self, text: &str) -> anyhow::Result<()> {
+        let (origin, width) = match self.parent() {
+            Some("data") => (IVec2::ZERO, self.layer_width),
+            Some("chunk") => self.chunk.unwrap_or_default(),
+            _ => return Ok(()),
+        };
+        let gids = decode_gids(self.encoding.as_deref(), self.compression.as_deref(), text)?;
+        if let Some(layer) = self.map.layers.last_mut() {
+            place_gids(&mut layer.tiles, origin, width, &gids);
+        }
+        Ok(())
+    }
+}
+
+/// Reads a map, or a tileset file as a map with just that tileset.
+pub(crate) fn parse(text: &str) -> anyhow::Result<ParsedMap> {
+    let mut reader = Reader::from_str(text);
+    reader.config_mut().trim_text(true);
+    let mut parser = Parser::default();
+    loop {
+        let position = reader.buffer_position();
+        let event = reader
+            .read_event()
+            .with_context(|| format!("Invalid xml at byte {position}"))?;
+        match event {
+            Event::Start(element) => {
+                parser.open(&element)?;
+                let name = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+                parser.stack.push(name);
+            }
+            Event::Empty(element) => parser.open(&element)?,
+            Event::End(_) => parser.close(),
+            Event::Text(text) => parser.text(&text.unescape()?)?,
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(parser.map)
+}
@@ -0,0 +1,303 @@
+use super::serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub enum AnimationDirection {
+    #[default]
+    Forward,
+    Reverse,
+    PingPong,
+}
+
+/// Named frame range from an Aseprite file, `from` and `to` are inclusive.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct AnimationTag {
+    pub name: String,
+    pub from: u32,
+    pub to: u32,
+    pub direction: AnimationDirection,
+}
+
+/// Frame timing and tags of a sprite. Frames are stored side by side in the texture.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct SpriteSheet {
+    /// Duration of every frame in milliseconds.
+    pub frame_durations: Vec<u32>,
+    pub tags: Vec<AnimationTag>,
+}
+
+impl SpriteSheet {
+    pub fn frame_count(&self) -> u32 {
+        self.frame_durations.len().max(1) as u32
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&AnimationTag> {
+        self.tags.iter().find(|tag| tag.name == name)
+    }
+
+    /// Frame range and direction of `tag`, the whole sheet if there is no tag.
+    fn range(&self, tag: Option<&str>) -> (u32, u32, AnimationDirection) {
+        match tag.and_then(|name| self.tag(name)) {
+            Some(tag) => (
+                tag.from.min(self.frame_count() - 1),
+                tag.to.min(self.frame_count() - 1),
+                tag.direction,
+            ),
+            None => (0, self.frame_count() - 1, AnimationDirection::Forward),
+        }
+    }
+
+    fn duration(&self, frame: u32) -> f32 {
+        self.frame_durations
+            .get(frame as usize)
+            .copied()
+            .unwrap_or(100)
+            .max(1) as f32
+    }
+}
+
+/// Playback state of an animated sprite, stored on the entity and advanced by the host.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Animation {
+    /// Tag being played, `None` plays every frame of the sheet.
+    pub tag: Option<String>,
+    /// Current frame, an index into the whole sheet.
+    pub frame: u32,
+    /// Milliseconds spent on the current frame.
+    pub elapsed: f32,
+    pub playing: bool,
+    pub looping: bool,
+    pub speed: f32,
+    pub finished: bool,
+    backwards: bool,
+    started: bool,
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Self {
+            tag: None,
+            frame: 0,
+            elapsed: 0.0,
+            playing: true,
+            looping: true,
+            speed: 1.0,
+            finished: false,
+            backwards: false,
+            started: false,
+        }
+    }
+}
+
+/// Only what mods set is hashed. Playback progress is advanced by the renderer from the
+/// sprite sheets, which headless runs don't have, so replays would never match with it.
+impl Hash for Animation {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.tag.hash(state);
+        self.playing.hash(state);
+        self.looping.hash(state);
+        self.speed.to_bits().hash(state);
+    }
+}
+
+impl Animation {
+    pub fn new(tag: Option<String>, looping: bool) -> Self {
+        Self {
+            tag,
+            looping,
+            ..Default::default()
+        }
+    }
+
+    /// Restarts playback of `tag` from its first frame.
+    pub fn play(&mut self, tag: Option<String>, looping: bool) {
+        *self = Self {
+            speed: self.speed,
+            ..Self::new(tag, looping)
+        };
+    }
+
+    /// Jumps to `frame` of the sheet, keeping the current tag.
+    pub fn set_frame(&mut self, frame: u32) {
+        self.frame = frame;
+        self.elapsed = 0.0;
+        self.started = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn resume(&mut self) {
+        self.playing = true;
+    }
+
+    /// Advances the animation by `delta_ms` milliseconds of `sheet`.
+    pub fn advance(&mut self, sheet: &SpriteSheet, delta_ms: f32) {
+        let (from, to, direction) = sheet.range(self.tag.as_deref());
+        if direction != AnimationDirection::PingPong {
+            self.backwards = direction == AnimationDirection::Reverse;
+        }
+        if !self.started || self.frame < from || self.frame > to {
+            self.started = true;
+            self.frame = if self.backwards { to } else { from };
+            self.elapsed = 0.0;
+        }
+        if !self.playing || self.finished {
+            return;
+        }
+
+        self.elapsed += delta_ms * self.speed.max(0.0);
+        while self.elapsed >= sheet.duration(self.frame) {
+            self.elapsed -= sheet.duration(self.frame);
+            if !self.step(from, to, direction) {
+                self.elapsed = 0.0;
+                self.finished = true;
+                return;
+            }
+        }
+    }
+
+    /// Moves to the next frame, returns false once a non looping animation is over.
+    fn step(&mut self, from: u32, to: u32, direction: AnimationDirection) -> bool {
+        if from == to {
+            return self.looping;
+        }
+        match (direction, self.backwards) {
+            (AnimationDirection::PingPong, false) if self.frame == to => {
+                self.backwards = true;
+                self.frame -= 1;
+            }
+            (AnimationDirection::PingPong, true) if self.frame == from => {
+                if !self.looping {
+                    return false;
+                }
+                self.backwards = false;
+                self.frame += 1;
+            }
+            (_, false) if self.frame == to => {
+                if !self.looping {
+                    return false;
+                }
+                self.frame = from;
+            }
+            (_, true) if self.frame == from => {
+                if !self.looping {
+                    return false;
+                }
+                self.frame = to;
+            }
+            (_, false) => self.frame += 1,
+            (_, true) => self.frame -= 1,
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    fn sheet() -> SpriteSheet {
+        SpriteSheet {
+            frame_durations: vec![100, 100, 200, 100, 100],
+            tags: vec![
+                AnimationTag {
+                    name: "walk".into(),
+                    from: 0,
+                    to: 2,
+                    direction: AnimationDirection::Forward,
+                },
+                AnimationTag {
+                    name: "back".into(),
+                    from: 2,
+                    to: 4,
+                    direction: AnimationDirection::Reverse,
+                },
+                AnimationTag {
+                    name: "bounce".into(),
+                    from: 1,
+                    to: 3,
+                    direction: AnimationDirection::PingPong,
+                },
+            ],
+        }
+    }
+
+    fn frames(animation: &mut Animation, sheet: &SpriteSheet, steps: usize) -> Vec<u32> {
+        (0..steps)
+            .map(|_| {
+                animation.advance(sheet, 100.0);
+                animation.frame
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_forward_loop_uses_frame_durations() {
+        let sheet = sheet();
+        let mut animation = Animation::new(Some("walk".into()), true);
+        assert_eq!(frames(&mut animation, &sheet, 6), vec![1, 2, 2, 0, 1, 2]);
+    }
+
+    #[test]
+    fn test_reverse_tag() {
+        let sheet = sheet();
+        let mut animation = Animation::new(Some("back".into()), true);
+        assert_eq!(frames(&mut animation, &sheet, 4), vec![3, 2, 2, 4]);
+    }
+
+    #[test]
+    fn test_ping_pong_tag() {
+        let sheet = sheet();
+        let mut animation = Animation::new(Some("bounce".into()), true);
+        assert_eq!(frames(&mut animation, &sheet, 6), vec![2, 2, 3, 2, 2, 1]);
+    }
+
+    #[test]
+    fn test_non_looping_animation_finishes_on_last_frame() {
+        let sheet = sheet();
+        let mut animation = Animation::new(Some("walk".into()), false);
+        assert_eq!(frames(&mut animation, &sheet, 6), vec![1, 2, 2, 2, 2, 2]);
+        assert!(animation.finished);
+    }
+
+    #[test]
+    fn test_paused_animation_does_not_advance() {
+        let sheet = sheet();
+        let mut animation = Animation::default();
+        animation.advance(&sheet, 100.0);
+        animation.pause();
+        assert_eq!(frames(&mut animation, &sheet, 3), vec![1, 1, 1]);
+        animation.resume();
+        assert_eq!(frames(&mut animation, &sheet, 1), vec![2]);
+    }
+
+    #[test]
+    fn test_unknown_tag_plays_whole_sheet() {
+        let sheet = sheet();
+        let mut animation = Animation::new(Some("missing".into()), true);
+        assert_eq!(frames(&mut animation, &sheet, 6), vec![1, 2, 2, 3, 4, 0]);
+    }
+
+    #[test]
+    fn test_hash_ignores_playback_progress() {
+        let hash = |animation: &Animation| {
+            let mut hasher = std::hash::DefaultHasher::new();
+            animation.hash(&mut hasher);
+            hasher.finish()
+        };
+        let sheet = sheet();
+        let mut animation = Animation::new(Some("walk".into()), false);
+        let before = hash(&animation);
+        frames(&mut animation, &sheet, 6);
+        assert!(animation.finished);
+        assert_eq!(hash(&animation), before);
+
+        animation.play(Some("back".into()), false);
+        assert_ne!(hash(&animation), before);
+        let playing = hash(&animation);
+        animation.pause();
+        assert_ne!(hash(&animation), playing);
+    }
+}
@@ -1,7 +1,11 @@
-use super::glam::{Mat4, Vec2};
+use super::glam::{Mat4, Vec2, Vec4};
 use super::serde::{Deserialize, Serialize};
+use crate::{CameraHandle, Object};
+use slotmap::{Key, SlotMap};
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub const ALL_LAYERS: u32 = u32::MAX;
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
 pub struct Camera {
     pub right: f32,
 
@@ -18,6 +22,124 @@ pub struct Camera {
     pub zoom_factor: f32,
 
     pub position: Vec2,
+
+    pub follow: Option<CameraFollow>,
+
+    pub bounds: Option<CameraBounds>,
+
+    pub shake: CameraShake,
+
+    pub zoom_transition: Option<ZoomTransition>,
+
+    pub viewport: Viewport,
+
+    /// Colour the viewport is cleared with before drawing, `None` draws over what is there.
+    pub clear_color: Option<[f32; 4]>,
+
+    /// Bit `n` set means objects on layer `n` are drawn by this camera.
+    pub layer_mask: u32,
+
+    /// Cameras are drawn from lowest to highest order.
+    pub order: i32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            right: 0.0,
+            left: 0.0,
+            top: 0.0,
+            bottom: 0.0,
+            near: 0.0,
+            far: 0.0,
+            zoom_factor: 0.0,
+            position: Vec2::ZERO,
+            follow: None,
+            bounds: None,
+            shake: CameraShake::default(),
+            zoom_transition: None,
+            viewport: Viewport::default(),
+            clear_color: None,
+            layer_mask: ALL_LAYERS,
+            order: 0,
+        }
+    }
+}
+
+/// Part of the window a camera draws to, in normalized `0.0..=1.0` coordinates
+/// with the origin in the top left corner.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub struct Viewport {
+    pub position: Vec2,
+    pub size: Vec2,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self {
+            position: Vec2::ZERO,
+            size: Vec2::ONE,
+        }
+    }
+}
+
+impl Viewport {
+    /// Pixel offset and extent of the viewport on a target of `dimensions`.
+    pub fn to_pixels(&self, dimensions: [u32; 2]) -> ([f32; 2], [f32; 2]) {
+        let dimensions = Vec2::new(dimensions[0] as f32, dimensions[1] as f32);
+        let position = self.position.clamp(Vec2::ZERO, Vec2::ONE);
+        let size = self.size.clamp(Vec2::ZERO, Vec2::ONE - position);
+        ((position * dimensions).into(), (size * dimensions).into())
+    }
+}
+
+/// Keeps the camera centred on an entity. `target` is the raw entity id, the host
+/// resolves its position every frame and passes it to [`Camera::update`].
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub struct CameraFollow {
+    pub target: u64,
+    /// How fast the camera catches up with the target, 0.0 snaps instantly.
+    pub damping: f32,
+    /// Half size of the box around the camera centre in which the target can move freely.
+    pub deadzone: Vec2,
+}
+
+/// World rectangle the visible area is kept inside of.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub struct CameraBounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+/// Trauma based screen shake. Trauma is in `0.0..=1.0` and decays over time, the
+/// offset grows with the square of it.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub struct CameraShake {
+    pub trauma: f32,
+    pub max_offset: Vec2,
+    pub frequency: f32,
+    pub decay: f32,
+    pub offset: Vec2,
+    time: f32,
+}
+
+impl Default for CameraShake {
+    fn default() -> Self {
+        Self {
+            trauma: 0.0,
+            max_offset: Vec2::new(0.5, 0.5),
+            frequency: 15.0,
+            decay: 1.5,
+            offset: Vec2::ZERO,
+            time: 0.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub struct ZoomTransition {
+    pub target: f32,
+    pub speed: f32,
 }
 
 impl Camera {
@@ -44,6 +166,7 @@ impl Camera {
             far,
             zoom_factor,
             position: position.into(),
+            ..Default::default()
         }
     }
 
@@ -109,6 +232,166 @@ impl Camera {
         self.create_matrix()
     }
 
+    pub fn sees_layer(&self, layer: u32) -> bool {
+        layer < 32 && self.layer_mask & (1 << layer) != 0
+    }
+
+    /// Whether the box around `center` overlaps the visible area.
+    pub fn sees_area(&self, center: Vec2, half_size: Vec2) -> bool {
+        let half_extent = self.half_extent();
+        let distance = (center - self.view_position()).abs();
+        distance.x <= half_extent.x + half_size.x && distance.y <= half_extent.y + half_size.y
+    }
+
+    /// Indices of the `objects` this camera draws: objects on a layer in the mask
+    /// whose quad overlaps the visible area, sorted by z index and then by index.
+    pub fn visible_objects(&self, objects: &[Object]) -> Vec<usize> {
+        let mut visible: Vec<usize> = objects
+            .iter()
+            .enumerate()
+            .filter(|(_, object)| {
+                let half_scale = Vec2::from(object.scale).abs() / 2.0;
+                self.sees_layer(object.layer) && self.sees_area(object.position, half_scale)
+            })
+            .map(|(index, _)| index)
+            .collect();
+        visible.sort_by(|a, b| {
+            objects[*a]
+                .z_index
+                .total_cmp(&objects[*b].z_index)
+                .then(a.cmp(b))
+        });
+        visible
+    }
+
+    /// Position used for rendering, includes the shake offset.
+    pub fn view_position(&self) -> Vec2 {
+        self.position + self.shake.offset
+    }
+
+    /// Half of the visible area in world units.
+    pub fn half_extent(&self) -> Vec2 {
+        let zoom_factor = if self.zoom_factor == 0.0 {
+            1.0
+        } else {
+            self.zoom_factor
+        };
+        Vec2::new(self.right, self.top).abs() * (1.0 + zoom_factor)
+    }
+
+    /// World position under `point`, in pixels from the top left of a window of `screen_size`.
+    pub fn screen_to_world(&self, point: Vec2, screen_size: Vec2) -> Vec2 {
+        let origin = self.viewport.position * screen_size;
+        let size = (self.viewport.size * screen_size).max(Vec2::ONE);
+        // -1 to 1 across the viewport, y grows downwards on screen and in the world
+        let normalized = (point - origin) / size * 2.0 - Vec2::ONE;
+        self.view_position() + normalized * self.half_extent()
+    }
+
+    /// Where `point` ends up in clip space, the math the object and pixels shaders do with
+    /// `view_position` as their camera position.
+    pub fn world_to_clip(&self, point: Vec2) -> Vec2 {
+        let relative = point - self.view_position();
+        // The shaders multiply the position as a row vector
+        let clip = self.create_matrix().transpose() * Vec4::new(relative.x, relative.y, 0.0, 1.0);
+        Vec2::new(clip.x, clip.y) / clip.w
+    }
+
+    /// Inverse of `screen_to_world`.
+    pub fn world_to_screen(&self, point: Vec2, screen_size: Vec2) -> Vec2 {
+        let origin = self.viewport.position * screen_size;
+        let size = (self.viewport.size * screen_size).max(Vec2::ONE);
+        let normalized = (point - self.view_position()) / self.half_extent();
+        origin + (normalized + Vec2::ONE) / 2.0 * size
+    }
+
+    pub fn follow(&mut self, target: u64, damping: f32, deadzone: Vec2) {
+        self.follow = Some(CameraFollow {
+            target,
+            damping: damping.max(0.0),
+            deadzone: deadzone.abs(),
+        });
+    }
+
+    pub fn stop_following(&mut self) {
+        self.follow = None;
+    }
+
+    pub fn set_bounds(&mut self, min: Vec2, max: Vec2) {
+        self.bounds = Some(CameraBounds {
+            min: min.min(max),
+            max: min.max(max),
+        });
+    }
+
+    pub fn clear_bounds(&mut self) {
+        self.bounds = None;
+    }
+
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.shake.trauma = (self.shake.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    pub fn configure_shake(&mut self, max_offset: Vec2, frequency: f32, decay: f32) {
+        self.shake.max_offset = max_offset.abs();
+        self.shake.frequency = frequency.max(0.0);
+        self.shake.decay = decay.max(0.0);
+    }
+
+    pub fn set_zoom(&mut self, factor: f32) {
+        self.zoom_factor = factor;
+        self.zoom_transition = None;
+    }
+
+    /// Starts a smooth transition to `factor`, a speed of 0.0 jumps immediately.
+    pub fn zoom_to(&mut self, factor: f32, speed: f32) {
+        if speed <= 0.0 {
+            self.set_zoom(factor);
+        } else {
+            self.zoom_transition = Some(ZoomTransition {
+                target: factor,
+                speed,
+            });
+        }
+    }
+
+    /// Advances all camera behaviours by `delta_time` seconds. `follow_target` is the
+    /// current position of the followed entity, `None` if there is nothing to follow.
+    pub fn update(&mut self, delta_time: f32, follow_target: Option<Vec2>) {
+        if let Some(transition) = self.zoom_transition {
+            self.zoom_factor = approach(
+                self.zoom_factor,
+                transition.target,
+                transition.speed,
+                delta_time,
+            );
+            if (self.zoom_factor - transition.target).abs() < 0.001 {
+                self.zoom_factor = transition.target;
+                self.zoom_transition = None;
+            }
+        }
+
+        if let (Some(follow), Some(target)) = (self.follow, follow_target) {
+            let offset = target - self.position;
+            let excess = Vec2::new(
+                deadzone_excess(offset.x, follow.deadzone.x),
+                deadzone_excess(offset.y, follow.deadzone.y),
+            );
+            let desired = self.position + excess;
+            self.position = if follow.damping == 0.0 {
+                desired
+            } else {
+                self.position.lerp(desired, smoothing(follow.damping, delta_time))
+            };
+        }
+
+        if let Some(bounds) = self.bounds {
+            self.position = clamp_to_bounds(self.position, self.half_extent(), bounds);
+        }
+
+        self.shake.update(delta_time);
+    }
+
     pub fn event(&mut self, scroll: f32) {
         // if let WindowEvent::MouseWheel { delta, .. } = ev {
         //     if let MouseScrollDelta::LineDelta(_, y) = delta {
@@ -126,3 +409,444 @@ impl Camera {
         //self.update_matrix();
     }
 }
+
+/// All cameras in the scene. The main camera always exists and is the one mods
+/// control unless they select another.
+pub struct Cameras {
+    cameras: SlotMap<CameraHandle, Camera>,
+    main: CameraHandle,
+    screen_size: Vec2,
+}
+
+impl Cameras {
+    pub fn new(main_camera: Camera, width: f32, height: f32) -> Self {
+        let mut cameras = SlotMap::with_key();
+        let main = cameras.insert(main_camera);
+        let mut cameras = Self {
+            cameras,
+            main,
+            screen_size: Vec2::new(width, height),
+        };
+        cameras.resize(width, height);
+        cameras
+    }
+
+    pub fn screen_size(&self) -> Vec2 {
+        self.screen_size
+    }
+
+    pub fn main(&self) -> CameraHandle {
+        self.main
+    }
+
+    pub fn main_camera(&self) -> &Camera {
+        &self.cameras[self.main]
+    }
+
+    pub fn main_camera_mut(&mut self) -> &mut Camera {
+        &mut self.cameras[self.main]
+    }
+
+    pub fn get(&self, handle: CameraHandle) -> Option<&Camera> {
+        self.cameras.get(handle)
+    }
+
+    pub fn get_mut(&mut self, handle: CameraHandle) -> Option<&mut Camera> {
+        self.cameras.get_mut(handle)
+    }
+
+    /// Creates a camera with the main camera's projection that covers the whole window.
+    pub fn create(&mut self) -> CameraHandle {
+        let main = self.main_camera();
+        let camera = Camera::new(
+            main.right,
+            main.left,
+            main.top,
+            main.bottom,
+            main.near,
+            main.far,
+            main.zoom_factor,
+            Vec2::ZERO,
+        );
+        self.insert(camera)
+    }
+
+    pub fn insert(&mut self, mut camera: Camera) -> CameraHandle {
+        let size = self.screen_size * camera.viewport.size;
+        camera.update_matrix_from_screen_size(size.x, size.y);
+        self.cameras.insert(camera)
+    }
+
+    /// Removes a camera, the main camera can't be removed.
+    pub fn remove(&mut self, handle: CameraHandle) -> Option<Camera> {
+        if handle == self.main {
+            return None;
+        }
+        self.cameras.remove(handle)
+    }
+
+    pub fn set_viewport(&mut self, handle: CameraHandle, viewport: Viewport) {
+        let screen_size = self.screen_size;
+        if let Some(camera) = self.cameras.get_mut(handle) {
+            camera.viewport = viewport;
+            let size = screen_size * viewport.size;
+            camera.update_matrix_from_screen_size(size.x, size.y);
+        }
+    }
+
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.screen_size = Vec2::new(width, height);
+        for camera in self.cameras.values_mut() {
+            let size = self.screen_size * camera.viewport.size;
+            camera.update_matrix_from_screen_size(size.x, size.y);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (CameraHandle, &Camera)> {
+        self.cameras.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (CameraHandle, &mut Camera)> {
+        self.cameras.iter_mut()
+    }
+
+    /// Cameras sorted by order, ties are broken by handle so the result is stable.
+    pub fn draw_order(&self) -> Vec<CameraHandle> {
+        let mut handles: Vec<CameraHandle> = self.cameras.keys().collect();
+        handles.sort_by_key(|handle| (self.cameras[*handle].order, handle.data().as_ffi()));
+        handles
+    }
+
+    /// Visibility list of every camera in draw order.
+    pub fn visibility_lists(&self, objects: &[Object]) -> Vec<(CameraHandle, Vec<usize>)> {
+        self.draw_order()
+            .into_iter()
+            .map(|handle| (handle, self.cameras[handle].visible_objects(objects)))
+            .collect()
+    }
+}
+
+impl CameraShake {
+    fn update(&mut self, delta_time: f32) {
+        self.time += delta_time;
+        self.trauma = (self.trauma - self.decay * delta_time).max(0.0);
+        if self.trauma == 0.0 {
+            self.offset = Vec2::ZERO;
+            return;
+        }
+        let strength = self.trauma * self.trauma;
+        let t = self.time * self.frequency;
+        self.offset = Vec2::new(
+            self.max_offset.x * strength * value_noise(t, 0),
+            self.max_offset.y * strength * value_noise(t, 1),
+        );
+    }
+}
+
+fn smoothing(damping: f32, delta_time: f32) -> f32 {
+    1.0 - (-damping * delta_time).exp()
+}
+
+fn approach(from: f32, to: f32, speed: f32, delta_time: f32) -> f32 {
+    from + (to - from) * smoothing(speed, delta_time)
+}
+
+fn deadzone_excess(offset: f32, deadzone: f32) -> f32 {
+    if offset > deadzone {
+        offset - deadzone
+    } else if offset < -deadzone {
+        offset + deadzone
+    } else {
+        0.0
+    }
+}
+
+fn clamp_to_bounds(position: Vec2, half_extent: Vec2, bounds: CameraBounds) -> Vec2 {
+    let clamp_axis = |value: f32, half: f32, min: f32, max: f32| {
+        if max - min <= half * 2.0 {
+            (min + max) / 2.0
+        } else {
+            value.clamp(min + half, max - half)
+        }
+    };
+    Vec2::new(
+        clamp_axis(position.x, half_extent.x, bounds.min.x, bounds.max.x),
+        clamp_axis(position.y, half_extent.y, bounds.min.y, bounds.max.y),
+    )
+}
+
+/// Smooth 1D noise in `-1.0..=1.0`, `seed` picks an independent channel.
+fn value_noise(t: f32, seed: u32) -> f32 {
+    let hash = |i: i32| {
+        let mut x = (i as u32).wrapping_mul(0x27d4_eb2d) ^ seed.wrapping_mul(0x1656_67b1);
+        x = (x ^ (x >> 15)).wrapping_mul(0x85eb_ca6b);
+        x ^= x >> 13;
+        (x as f32 / u32::MAX as f32) * 2.0 - 1.0
+    };
+    let i = t.floor();
+    let f = t - i;
+    let f = f * f * (3.0 - 2.0 * f);
+    let a = hash(i as i32);
+    let b = hash(i as i32 + 1);
+    a + (b - a) * f
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    fn object(position: Vec2, layer: u32, z_index: f32) -> Object {
+        Object {
+            position,
+            layer,
+            z_index,
+            ..Default::default()
+        }
+    }
+
+    fn cameras() -> Cameras {
+        Cameras::new(
+            Camera::create_camera_from_screen_size(200.0, 100.0, 0.1, 100.0, 1.0, Vec2::ZERO),
+            200.0,
+            100.0,
+        )
+    }
+
+    #[test]
+    fn test_layer_mask_filters_objects() {
+        let mut camera = *cameras().main_camera();
+        let objects = [
+            object(Vec2::ZERO, 0, 1.0),
+            object(Vec2::ZERO, 3, 1.0),
+            object(Vec2::ZERO, 40, 1.0),
+        ];
+        assert_eq!(camera.visible_objects(&objects), vec![0, 1]);
+
+        camera.layer_mask = 1 << 3;
+        assert_eq!(camera.visible_objects(&objects), vec![1]);
+    }
+
+    #[test]
+    fn test_objects_outside_view_are_culled() {
+        let camera = *cameras().main_camera();
+        // Half extent is (2.0, 1.0) for a 2:1 window at zoom 1.0
+        assert_eq!(camera.half_extent(), Vec2::new(2.0, 1.0));
+        let objects = [
+            object(Vec2::new(2.4, 0.0), 0, 1.0),
+            object(Vec2::new(2.6, 0.0), 0, 1.0),
+            object(Vec2::new(0.0, -1.5), 0, 1.0),
+            object(Vec2::new(0.0, -1.6), 0, 1.0),
+        ];
+        assert_eq!(camera.visible_objects(&objects), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_visibility_sorted_by_z_index_then_index() {
+        let camera = *cameras().main_camera();
+        let objects = [
+            object(Vec2::ZERO, 0, 2.0),
+            object(Vec2::ZERO, 0, 1.0),
+            object(Vec2::ZERO, 0, 2.0),
+            object(Vec2::ZERO, 0, -1.0),
+        ];
+        assert_eq!(camera.visible_objects(&objects), vec![3, 1, 0, 2]);
+    }
+
+    #[test]
+    fn test_cameras_draw_order_and_visibility_lists() {
+        let mut cameras = cameras();
+        let minimap = cameras.create();
+        let ui = cameras.create();
+        cameras.get_mut(minimap).unwrap().order = 1;
+        cameras.get_mut(minimap).unwrap().position = Vec2::new(10.0, 0.0);
+        cameras.get_mut(ui).unwrap().order = 2;
+        cameras.get_mut(ui).unwrap().layer_mask = 1 << 1;
+        cameras.get_mut(cameras.main()).unwrap().layer_mask = 1;
+
+        let objects = [
+            object(Vec2::ZERO, 0, 1.0),
+            object(Vec2::new(10.0, 0.0), 0, 1.0),
+            object(Vec2::ZERO, 1, 1.0),
+        ];
+        assert_eq!(cameras.visibility_lists(&objects), vec![
+            (cameras.main(), vec![0]),
+            (minimap, vec![1]),
+            (ui, vec![2]),
+        ]);
+    }
+
+    #[test]
+    fn test_main_camera_cant_be_removed() {
+        let mut cameras = cameras();
+        let extra = cameras.create();
+        assert!(cameras.remove(cameras.main()).is_none());
+        assert!(cameras.remove(extra).is_some());
+        assert_eq!(cameras.draw_order(), vec![cameras.main()]);
+    }
+
+    #[test]
+    fn test_viewport_sets_aspect() {
+        let mut cameras = cameras();
+        let left_half = cameras.create();
+        cameras.set_viewport(left_half, Viewport {
+            position: Vec2::ZERO,
+            size: Vec2::new(0.5, 1.0),
+        });
+        let camera = cameras.get(left_half).unwrap();
+        assert_eq!(camera.right, 0.5);
+        assert_eq!(
+            camera.viewport.to_pixels([200, 100]),
+            ([0.0, 0.0], [100.0, 100.0])
+        );
+    }
+
+    #[test]
+    fn test_screen_to_world() {
+        let mut camera = *cameras().main_camera();
+        camera.position = Vec2::new(10.0, 5.0);
+        let screen = Vec2::new(200.0, 100.0);
+        assert_eq!(
+            camera.screen_to_world(screen / 2.0, screen),
+            camera.position
+        );
+        assert_eq!(
+            camera.screen_to_world(Vec2::ZERO, screen),
+            Vec2::new(8.0, 4.0)
+        );
+        assert_eq!(camera.screen_to_world(screen, screen), Vec2::new(12.0, 6.0));
+
+        camera.viewport.position = Vec2::new(0.5, 0.0);
+        camera.viewport.size = Vec2::new(0.5, 1.0);
+        assert_eq!(
+            camera.screen_to_world(Vec2::new(150.0, 50.0), screen),
+            camera.position
+        );
+        let point = Vec2::new(160.0, 20.0);
+        let world = camera.screen_to_world(point, screen);
+        assert!(
+            camera
+                .world_to_screen(world, screen)
+                .abs_diff_eq(point, 1e-4)
+        );
+    }
+
+    #[test]
+    fn test_followed_target_is_centred_when_zoomed() {
+        let mut camera = *cameras().main_camera();
+        camera.zoom_to(10.0, 0.0);
+        camera.follow(1, 0.0, Vec2::ZERO);
+        let target = Vec2::new(37.0, -12.0);
+        camera.update(0.1, Some(target));
+        let screen = Vec2::new(200.0, 100.0);
+        assert!(camera.world_to_screen(target, screen).abs_diff_eq(screen / 2.0, 1e-3));
+        assert!(camera.world_to_clip(target).abs_diff_eq(Vec2::ZERO, 1e-6));
+    }
+
+    #[test]
+    fn test_culling_matches_clip_space() {
+        let mut camera = *cameras().main_camera();
+        camera.zoom_to(10.0, 0.0);
+        camera.position = Vec2::new(50.0, -20.0);
+        // Corners of the clip space are the corners of the visible area
+        let corner = camera.view_position() + camera.half_extent();
+        assert!(camera.world_to_clip(corner).abs_diff_eq(Vec2::ONE, 1e-5));
+        let dot = Vec2::splat(0.01);
+        for x in -30..=30 {
+            for y in -30..=30 {
+                // Off the grid of the view edges so rounding can't decide
+                let offset = (Vec2::new(x as f32, y as f32) + 0.5) * 0.5;
+                let point = camera.view_position() + offset;
+                let clip = camera.world_to_clip(point).abs();
+                let on_screen = clip.x <= 1.0 && clip.y <= 1.0;
+                assert_eq!(camera.sees_area(point, dot), on_screen, "{point}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_follow_smoothing() {
+        let mut camera = *cameras().main_camera();
+        camera.follow(1, 5.0, Vec2::ZERO);
+        let target = Vec2::new(10.0, 0.0);
+        camera.update(0.1, Some(target));
+        let expected = 10.0 * (1.0 - (-0.5f32).exp());
+        assert!((camera.position.x - expected).abs() < 1e-4);
+        for _ in 0..200 {
+            camera.update(0.1, Some(target));
+        }
+        assert!(camera.position.abs_diff_eq(target, 1e-3));
+
+        camera.follow(1, 0.0, Vec2::ZERO);
+        camera.update(0.1, Some(Vec2::new(-3.0, 4.0)));
+        assert_eq!(camera.position, Vec2::new(-3.0, 4.0));
+    }
+
+    #[test]
+    fn test_follow_deadzone() {
+        let mut camera = *cameras().main_camera();
+        camera.follow(1, 0.0, Vec2::ONE);
+        camera.update(0.1, Some(Vec2::new(0.5, -0.9)));
+        assert_eq!(camera.position, Vec2::ZERO);
+        // Only the part outside the deadzone is followed
+        camera.update(0.1, Some(Vec2::new(3.0, -0.5)));
+        assert_eq!(camera.position, Vec2::new(2.0, 0.0));
+        // Nothing to follow keeps the camera still
+        camera.update(0.1, None);
+        assert_eq!(camera.position, Vec2::new(2.0, 0.0));
+    }
+
+    #[test]
+    fn test_bounds_clamping() {
+        let mut camera = *cameras().main_camera();
+        camera.set_bounds(Vec2::splat(5.0), Vec2::splat(-5.0));
+        camera.position = Vec2::new(10.0, -10.0);
+        camera.update(0.1, None);
+        // Half extent is (2.0, 1.0)
+        assert_eq!(camera.position, Vec2::new(3.0, -4.0));
+
+        // Bounds smaller than the view centre the camera on them
+        camera.set_bounds(Vec2::new(0.0, 0.0), Vec2::new(2.0, 20.0));
+        camera.update(0.1, None);
+        assert_eq!(camera.position, Vec2::new(1.0, 1.0));
+    }
+
+    #[test]
+    fn test_zoom_transition_completes() {
+        let mut camera = *cameras().main_camera();
+        camera.zoom_to(3.0, 10.0);
+        camera.update(0.01, None);
+        assert!(camera.zoom_factor > 1.0 && camera.zoom_factor < 3.0);
+        for _ in 0..100 {
+            camera.update(0.1, None);
+        }
+        assert_eq!(camera.zoom_factor, 3.0);
+        assert!(camera.zoom_transition.is_none());
+
+        camera.zoom_to(0.5, 0.0);
+        assert_eq!(camera.zoom_factor, 0.5);
+        assert!(camera.zoom_transition.is_none());
+    }
+
+    #[test]
+    fn test_shake_decays_to_zero() {
+        let mut camera = *cameras().main_camera();
+        camera.add_trauma(2.0);
+        assert_eq!(camera.shake.trauma, 1.0);
+        let mut moved = false;
+        for _ in 0..10 {
+            camera.update(0.01, None);
+            let offset = camera.shake.offset.abs();
+            moved |= offset != Vec2::ZERO;
+            assert!(offset.cmple(camera.shake.max_offset).all());
+        }
+        assert!(moved);
+        assert!(camera.shake.trauma < 1.0);
+        // Decay of 1.5 per second empties full trauma in under a second
+        for _ in 0..100 {
+            camera.update(0.01, None);
+        }
+        assert_eq!(camera.shake.trauma, 0.0);
+        assert_eq!(camera.shake.offset, Vec2::ZERO);
+        assert_eq!(camera.view_position(), camera.position);
+    }
+}
@@ -0,0 +1,320 @@
+/// What a console command argument is parsed as.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ArgKind {
+    Integer,
+    Float,
+    Text,
+    /// true, false, on, off, 1 or 0.
+    Boolean,
+}
+
+impl ArgKind {
+    fn describe(&self) -> &'static str {
+        match self {
+            ArgKind::Integer => "whole number",
+            ArgKind::Float => "number",
+            ArgKind::Text => "text",
+            ArgKind::Boolean => "true or false",
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum ArgValue {
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    Boolean(bool),
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct CommandArg {
+    pub name: String,
+    pub kind: ArgKind,
+    /// Optional arguments come after the others and can be left out.
+    pub optional: bool,
+    /// Values tab completion offers, other values are still accepted.
+    pub choices: Vec<String>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Command {
+    pub name: String,
+    pub description: String,
+    pub args: Vec<CommandArg>,
+}
+
+impl Command {
+    /// Name and arguments, optional ones in brackets, as in `spawn <prefab> [count]`.
+    pub fn usage(&self) -> String {
+        let mut usage = self.name.clone();
+        for arg in self.args.iter() {
+            if arg.optional {
+                usage.push_str(&format!(" [{}]", arg.name));
+            } else {
+                usage.push_str(&format!(" <{}>", arg.name));
+            }
+        }
+        usage
+    }
+
+    /// Checks a command a mod registers can be typed and parsed.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() || self.name.contains(char::is_whitespace) {
+            return Err(format!("`{}` isn't a command name", self.name));
+        }
+        let first_optional = self.args.iter().position(|arg| arg.optional);
+        if first_optional.is_some_and(|first| self.args[first..].iter().any(|arg| !arg.optional)) {
+            return Err(format!(
+                "{}: optional arguments must come last",
+                self.usage()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Parses the words after the command name into its arguments, left out optional ones
+    /// are missing from the result.
+    pub fn parse(&self, words: &[String]) -> Result<Vec<ArgValue>, String> {
+        if words.len() > self.args.len() {
+            return Err(format!("Too many arguments, usage: {}", self.usage()));
+        }
+        let mut values = Vec::with_capacity(words.len());
+        for (index, arg) in self.args.iter().enumerate() {
+            let Some(word) = words.get(index) else {
+                if arg.optional {
+                    break;
+                }
+                return Err(format!("Missing {}, usage: {}", arg.name, self.usage()));
+            };
+            let value = match arg.kind {
+                ArgKind::Integer => word.parse().map(ArgValue::Integer).ok(),
+                ArgKind::Float => word.parse().map(ArgValue::Float).ok(),
+                ArgKind::Text => Some(ArgValue::Text(word.clone())),
+                ArgKind::Boolean => parse_bool(word).map(ArgValue::Boolean),
+            };
+            let value = value
+                .ok_or_else(|| format!("{} is {}, not `{word}`", arg.name, arg.kind.describe()))?;
+            values.push(value);
+        }
+        Ok(values)
+    }
+}
+
+fn parse_bool(word: &str) -> Option<bool> {
+    match word.to_lowercase().as_str() {
+        "true" | "on" | "1" => Some(true),
+        "false" | "off" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// Splits a command line into words on whitespace, double quotes keep spaces in a word.
+pub fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quoted = false;
+    for char in line.chars() {
+        match char {
+            '"' => {
+                quoted = !quoted;
+                word.get_or_insert_with(String::new);
+            }
+            char if char.is_whitespace() && !quoted => words.extend(word.take()),
+            char => word.get_or_insert_with(String::new).push(char),
+        }
+    }
+    if quoted {
+        return Err("Missing closing quote".into());
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Lines the word being typed at the end of `line` can complete to, command names for the
+/// first word and choices of the argument for the others.
+pub fn complete(commands: &[Command], line: &str) -> Vec<String> {
+    let Ok(mut words) = split_words(line) else {
+        return Vec::new();
+    };
+    let typing = if line.ends_with(char::is_whitespace) {
+        String::new()
+    } else {
+        words.pop().unwrap_or_default()
+    };
+    let candidates: Vec<String> = match words.first() {
+        None => commands
+            .iter()
+            .map(|command| command.name.clone())
+            .collect(),
+        Some(name) => {
+            let arg = commands
+                .iter()
+                .find(|command| command.name == *name)
+                .and_then(|command| command.args.get(words.len() - 1));
+            match arg {
+                Some(arg) if arg.kind == ArgKind::Boolean && arg.choices.is_empty() => {
+                    vec!["true".into(), "false".into()]
+                }
+                Some(arg) => arg.choices.clone(),
+                None => Vec::new(),
+            }
+        }
+    };
+    let typed = typing.to_lowercase();
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.to_lowercase().starts_with(&typed))
+        .map(|candidate| {
+            let mut line: Vec<String> = words.iter().map(|word| quote(word)).collect();
+            line.push(quote(&candidate));
+            line.join(" ")
+        })
+        .collect()
+}
+
+/// `word` as it has to be typed to stay one word.
+fn quote(word: &str) -> String {
+    if word.is_empty() || word.contains(char::is_whitespace) {
+        format!("\"{word}\"")
+    } else {
+        word.to_owned()
+    }
+}
+
+/// Longest start all `lines` share, what tab fills in when there's more than one completion.
+pub fn common_prefix(lines: &[String]) -> String {
+    let Some(first) = lines.first() else {
+        return String::new();
+    };
+    let mut prefix = first.as_str();
+    for line in lines.iter().skip(1) {
+        let shared = prefix
+            .char_indices()
+            .zip(line.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(prefix.len().min(line.len()), |((index, _), _)| index);
+        prefix = &prefix[..shared];
+    }
+    prefix.to_owned()
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    fn arg(name: &str, kind: ArgKind, optional: bool, choices: &[&str]) -> CommandArg {
+        CommandArg {
+            name: name.into(),
+            kind,
+            optional,
+            choices: choices.iter().map(|choice| choice.to_string()).collect(),
+        }
+    }
+
+    fn commands() -> Vec<Command> {
+        let spawn = Command {
+            name: "spawn".into(),
+            description: "Spawns prefabs around the player".into(),
+            args: vec![
+                arg(
+                    "prefab",
+                    ArgKind::Text,
+                    false,
+                    &["enemy", "elite_enemy", "big orb"],
+                ),
+                arg("count", ArgKind::Integer, true, &[]),
+            ],
+        };
+        let god = Command {
+            name: "god".into(),
+            description: "Toggles invincibility".into(),
+            args: vec![arg("on", ArgKind::Boolean, true, &[])],
+        };
+        let speed = Command {
+            name: "speed".into(),
+            description: String::new(),
+            args: vec![arg("factor", ArgKind::Float, false, &[])],
+        };
+        vec![spawn, god, speed]
+    }
+
+    fn words(line: &str) -> Vec<String> {
+        split_words(line).unwrap()
+    }
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(words("  spawn   enemy 10 "), vec!["spawn", "enemy", "10"]);
+        assert_eq!(
+            words("say \"hello there\" \"\""),
+            vec!["say", "hello there", ""]
+        );
+        assert!(words("").is_empty());
+        assert!(split_words("say \"hello").is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        let [spawn, god, speed] = commands().try_into().unwrap();
+        assert_eq!(spawn.usage(), "spawn <prefab> [count]");
+        assert_eq!(
+            spawn.parse(&words("enemy 10")),
+            Ok(vec![ArgValue::Text("enemy".into()), ArgValue::Integer(10)])
+        );
+        assert_eq!(
+            spawn.parse(&words("enemy")),
+            Ok(vec![ArgValue::Text("enemy".into())])
+        );
+        assert_eq!(
+            spawn.parse(&[]),
+            Err("Missing prefab, usage: spawn <prefab> [count]".into())
+        );
+        assert_eq!(
+            spawn.parse(&words("enemy ten")),
+            Err("count is whole number, not `ten`".into())
+        );
+        assert!(spawn.parse(&words("enemy 1 2")).is_err());
+        assert_eq!(god.parse(&words("OFF")), Ok(vec![ArgValue::Boolean(false)]));
+        assert_eq!(god.parse(&[]), Ok(vec![]));
+        assert_eq!(speed.parse(&words("-0.5")), Ok(vec![ArgValue::Float(-0.5)]));
+    }
+
+    #[test]
+    fn test_validate() {
+        for command in commands() {
+            assert_eq!(command.validate(), Ok(()));
+        }
+        let mut command = commands().remove(0);
+        command.args.reverse();
+        assert!(command.validate().is_err());
+        command.name = "two words".into();
+        assert!(command.validate().is_err());
+    }
+
+    #[test]
+    fn test_complete() {
+        let commands = commands();
+        assert_eq!(complete(&commands, "s"), vec!["spawn", "speed"]);
+        assert_eq!(complete(&commands, "").len(), 3);
+        assert_eq!(complete(&commands, "spawn en"), vec!["spawn enemy"]);
+        assert_eq!(complete(&commands, "spawn EL"), vec!["spawn elite_enemy"]);
+        assert_eq!(complete(&commands, "spawn b"), vec!["spawn \"big orb\""]);
+        assert_eq!(complete(&commands, "spawn ").len(), 3);
+        assert_eq!(complete(&commands, "god t"), vec!["god true"]);
+        assert!(complete(&commands, "spawn enemy ").is_empty());
+        assert!(complete(&commands, "unknown ").is_empty());
+        assert!(complete(&commands, "spawn \"big").is_empty());
+    }
+
+    #[test]
+    fn test_common_prefix() {
+        let lines = complete(&commands(), "spawn ");
+        assert_eq!(common_prefix(&lines), "spawn ");
+        let lines = complete(&commands(), "sp");
+        assert_eq!(common_prefix(&lines), "sp");
+        let lines = vec!["spawn enemy".into(), "spawn elite_enemy".into()];
+        assert_eq!(common_prefix(&lines), "spawn e");
+        assert_eq!(common_prefix(&[]), "");
+    }
+}
@@ -1,18 +1,36 @@
 pub use glam;
+use animation::Animation;
 use glam::Vec2;
 use num_enum::TryFromPrimitive;
+use physics::{Collider, RigidBody};
+use tilemap::Tilemap;
+pub mod animation;
 pub mod camera;
+pub mod console;
+pub mod physics;
+pub mod tilemap;
 pub use serde;
 use serde::Deserialize;
 use serde::Serialize;
 use slotmap::new_key_type;
+use std::hash::{Hash, Hasher};
 
 new_key_type! { pub struct SpriteHandle; }
 new_key_type! { pub struct SoundHandle; }
+new_key_type! { pub struct SoundInstanceHandle; }
+new_key_type! { pub struct CameraHandle; }
 new_key_type! {
     pub struct ModHandle;
 }
 
+/// Progress of an asset that is loaded in the background.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum LoadStatus {
+    Pending,
+    Ready,
+    Failed(String),
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Query {
     pub name: String,
@@ -28,9 +46,38 @@ pub enum ComponentData {
     Color([f32; 4]),
     Raw(Vec<u8>),
     Sprite(u64),
+    Animation(Animation),
+    RigidBody(RigidBody),
+    Collider(Collider),
+    /// Boxed, a tilemap is far bigger than any other component.
+    Tilemap(Box<Tilemap>),
     None,
 }
 
+/// Floats hash by their bits, so equal data always gives the same hash.
+impl Hash for ComponentData {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            ComponentData::I32(value) => value.hash(state),
+            ComponentData::I64(value) => value.hash(state),
+            ComponentData::String(value) => value.hash(state),
+            ComponentData::Vector(value) => {
+                value.x.to_bits().hash(state);
+                value.y.to_bits().hash(state);
+            }
+            ComponentData::Color(color) => color.map(f32::to_bits).hash(state),
+            ComponentData::Raw(bytes) => bytes.hash(state),
+            ComponentData::Sprite(handle) => handle.hash(state),
+            ComponentData::Animation(animation) => animation.hash(state),
+            ComponentData::RigidBody(body) => body.hash(state),
+            ComponentData::Collider(collider) => collider.hash(state),
+            ComponentData::Tilemap(tilemap) => tilemap.hash(state),
+            ComponentData::None => {}
+        }
+    }
+}
+
 impl From<String> for ComponentData {
     fn from(value: String) -> Self {
         ComponentData::String(value)
@@ -74,6 +121,8 @@ pub struct Object {
     pub color: [f32; 4],
     pub sprite: u64,
     pub z_index: f32,
+    pub layer: u32,
+    pub frame: u32,
 }
 
 impl Default for Object {
@@ -84,6 +133,8 @@ impl Default for Object {
             color: [1.0, 1.0, 1.0, 1.0],
             sprite: 0,
             z_index: 1.0,
+            layer: 0,
+            frame: 0,
         }
     }
 }
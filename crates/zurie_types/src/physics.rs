@@ -0,0 +1,133 @@
+use super::serde::{Deserialize, Serialize};
+use glam::Vec2;
+use std::hash::{Hash, Hasher};
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Default, Hash)]
+pub enum BodyKind {
+    /// Moved by velocity, gravity and collisions.
+    #[default]
+    Dynamic,
+    /// Moved by its velocity only, pushes dynamic bodies without being pushed.
+    Kinematic,
+    /// Never moves.
+    Static,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub struct RigidBody {
+    pub kind: BodyKind,
+    pub mass: f32,
+    /// Multiplier of the world gravity.
+    pub gravity_scale: f32,
+    /// Fraction of the velocity lost per second.
+    pub linear_damping: f32,
+}
+
+impl Default for RigidBody {
+    fn default() -> Self {
+        Self {
+            kind: BodyKind::Dynamic,
+            mass: 1.0,
+            gravity_scale: 1.0,
+            linear_damping: 0.0,
+        }
+    }
+}
+
+impl RigidBody {
+    /// Zero for bodies collisions don't move.
+    pub fn inverse_mass(&self) -> f32 {
+        match self.kind {
+            BodyKind::Dynamic if self.mass > 0.0 => 1.0 / self.mass,
+            BodyKind::Dynamic => 1.0,
+            BodyKind::Kinematic | BodyKind::Static => 0.0,
+        }
+    }
+}
+
+/// Shapes don't rotate, boxes stay axis aligned and capsules upright.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub enum Shape {
+    Circle {
+        radius: f32,
+    },
+    Box {
+        half_extents: Vec2,
+    },
+    /// Vertical segment of `2 * half_height` grown by `radius`.
+    Capsule {
+        half_height: f32,
+        radius: f32,
+    },
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub struct Collider {
+    pub shape: Shape,
+    /// From the entity position to the shape center.
+    pub offset: Vec2,
+    /// Reports contacts without pushing anything.
+    pub sensor: bool,
+    /// Bounciness, 0 stops and 1 bounces back at full speed.
+    pub restitution: f32,
+    pub friction: f32,
+    /// Layers the collider is on.
+    pub layer: u32,
+    /// Layers the collider touches, two colliders touch when each is in the other's mask.
+    pub mask: u32,
+}
+
+impl Default for Collider {
+    fn default() -> Self {
+        Self {
+            shape: Shape::Circle { radius: 0.5 },
+            offset: Vec2::ZERO,
+            sensor: false,
+            restitution: 0.0,
+            friction: 0.5,
+            layer: 1,
+            mask: u32::MAX,
+        }
+    }
+}
+
+impl Collider {
+    pub fn interacts_with(&self, other: &Collider) -> bool {
+        self.layer & other.mask != 0 && other.layer & self.mask != 0
+    }
+}
+
+impl Hash for RigidBody {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.kind.hash(state);
+        self.mass.to_bits().hash(state);
+        self.gravity_scale.to_bits().hash(state);
+        self.linear_damping.to_bits().hash(state);
+    }
+}
+
+impl Hash for Shape {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Shape::Circle { radius } => radius.to_bits().hash(state),
+            Shape::Box { half_extents } => half_extents.to_array().map(f32::to_bits).hash(state),
+            Shape::Capsule {
+                half_height,
+                radius,
+            } => [*half_height, *radius].map(f32::to_bits).hash(state),
+        }
+    }
+}
+
+impl Hash for Collider {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.shape.hash(state);
+        self.offset.to_array().map(f32::to_bits).hash(state);
+        self.sensor.hash(state);
+        self.restitution.to_bits().hash(state);
+        self.friction.to_bits().hash(state);
+        self.layer.hash(state);
+        self.mask.hash(state);
+    }
+}
@@ -0,0 +1,364 @@
+use super::serde::{Deserialize, Serialize};
+use glam::{IVec2, UVec2, Vec2};
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Tiles along each side of a chunk.
+pub const CHUNK_SIZE: i32 = 16;
+/// Id of cells without a tile.
+pub const EMPTY_TILE: u32 = 0;
+/// Most tiles a single `fill_rect` sets.
+pub const MAX_FILL_TILES: u64 = 1 << 20;
+
+static REVISION: AtomicU64 = AtomicU64::new(1);
+
+fn next_revision() -> u64 {
+    REVISION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Grid image the tiles are cut from. Tile `n` is cell `n - 1`, counted row by row from the
+/// top left.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Default, Hash)]
+pub struct Tileset {
+    pub sprite: u64,
+    pub columns: u32,
+    pub rows: u32,
+    /// Tiles that collide.
+    pub solid: Vec<u32>,
+}
+
+impl Tileset {
+    pub fn is_solid(&self, tile: u32) -> bool {
+        tile != EMPTY_TILE && self.solid.contains(&tile)
+    }
+
+    /// UV offset and scale of `tile`, given those of the whole tileset sprite.
+    pub fn tile_uv(
+        &self,
+        tile: u32,
+        offset: [f32; 2],
+        scale: [f32; 2],
+    ) -> Option<([f32; 2], [f32; 2])> {
+        let (columns, rows) = (self.columns.max(1), self.rows.max(1));
+        let index = tile
+            .checked_sub(1)
+            .filter(|index| *index < columns * rows)?;
+        let cell = Vec2::from(scale) / Vec2::new(columns as f32, rows as f32);
+        let position = Vec2::new((index % columns) as f32, (index / columns) as f32);
+        Some(((Vec2::from(offset) + position * cell).into(), cell.into()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TileChunk {
+    tiles: Vec<u32>,
+    /// Changes with the tiles and is never shared with another chunk, renderers cache by it.
+    #[serde(skip, default = "next_revision")]
+    revision: u64,
+}
+
+impl PartialEq for TileChunk {
+    fn eq(&self, other: &Self) -> bool {
+        self.tiles == other.tiles
+    }
+}
+
+impl TileChunk {
+    fn new() -> Self {
+        Self {
+            tiles: vec![EMPTY_TILE; (CHUNK_SIZE * CHUNK_SIZE) as usize],
+            revision: next_revision(),
+        }
+    }
+
+    /// Tiles row by row.
+    pub fn tiles(&self) -> &[u32] {
+        &self.tiles
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn get(&self, local: IVec2) -> u32 {
+        self.tiles[(local.y * CHUNK_SIZE + local.x) as usize]
+    }
+}
+
+/// Grid of tiles kept in square chunks, so empty areas cost nothing. Tile `(x, y)` starts
+/// `(x, y) * tile_size` from the entity position, rows go the way they do in the tileset image.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Tilemap {
+    pub tileset: Tileset,
+    /// World size of a tile.
+    pub tile_size: Vec2,
+    /// Physics layers the solid tiles are on, 0 turns collision off.
+    pub collision_layer: u32,
+    chunks: BTreeMap<(i32, i32), TileChunk>,
+}
+
+impl Tilemap {
+    pub fn new(tileset: Tileset, tile_size: Vec2) -> Self {
+        Self {
+            tileset,
+            tile_size,
+            collision_layer: 1,
+            chunks: BTreeMap::new(),
+        }
+    }
+
+    /// Chunk holding `tile` and the tile's position inside it.
+    pub fn chunk_of(tile: IVec2) -> (IVec2, IVec2) {
+        (
+            tile.div_euclid(IVec2::splat(CHUNK_SIZE)),
+            tile.rem_euclid(IVec2::splat(CHUNK_SIZE)),
+        )
+    }
+
+    pub fn get(&self, tile: IVec2) -> u32 {
+        let (chunk, local) = Self::chunk_of(tile);
+        self.chunks
+            .get(&chunk.into())
+            .map_or(EMPTY_TILE, |chunk| chunk.get(local))
+    }
+
+    /// Returns whether the tile changed. Chunks left without tiles are dropped.
+    pub fn set(&mut self, tile: IVec2, id: u32) -> bool {
+        let (chunk_position, local) = Self::chunk_of(tile);
+        let key: (i32, i32) = chunk_position.into();
+        if id == EMPTY_TILE && !self.chunks.contains_key(&key) {
+            return false;
+        }
+        let chunk = self.chunks.entry(key).or_insert_with(TileChunk::new);
+        let index = (local.y * CHUNK_SIZE + local.x) as usize;
+        if chunk.tiles[index] == id {
+            return false;
+        }
+        chunk.tiles[index] = id;
+        chunk.revision = next_revision();
+        if chunk.tiles.iter().all(|tile| *tile == EMPTY_TILE) {
+            self.chunks.remove(&key);
+        }
+        true
+    }
+
+    /// Sets every tile of the `size` rect starting at `min`, tiles past the `i32` range are cut
+    /// off. Rects of more than `MAX_FILL_TILES` are left alone and false is returned.
+    pub fn fill_rect(&mut self, min: IVec2, size: UVec2, id: u32) -> bool {
+        // One past the last tile, as i64 so it can't overflow
+        let end = |start: i32, length: u32| (start as i64 + length as i64).min(i32::MAX as i64 + 1);
+        let (end_x, end_y) = (end(min.x, size.x), end(min.y, size.y));
+        let tiles = (end_x - min.x as i64).checked_mul(end_y - min.y as i64);
+        if tiles.is_none_or(|tiles| tiles as u64 > MAX_FILL_TILES) {
+            return false;
+        }
+        for y in min.y as i64..end_y {
+            for x in min.x as i64..end_x {
+                self.set(IVec2::new(x as i32, y as i32), id);
+            }
+        }
+        true
+    }
+
+    /// Chunks with at least one tile, by chunk position.
+    pub fn chunks(&self) -> impl Iterator<Item = (IVec2, &TileChunk)> {
+        self.chunks
+            .iter()
+            .map(|(position, chunk)| (IVec2::from(*position), chunk))
+    }
+
+    /// Tile covering `point`, relative to the entity position.
+    pub fn tile_at(&self, point: Vec2) -> IVec2 {
+        (point / self.tile_size).floor().as_ivec2()
+    }
+
+    /// Center of `tile`, relative to the entity position.
+    pub fn tile_center(&self, tile: IVec2) -> Vec2 {
+        (tile.as_vec2() + 0.5) * self.tile_size
+    }
+
+    /// First and last tile of the chunks in use.
+    pub fn bounds(&self) -> Option<(IVec2, IVec2)> {
+        let mut chunks = self.chunks.keys().map(|position| IVec2::from(*position));
+        let first = chunks.next()?;
+        let (min, max) = chunks.fold((first, first), |(min, max), chunk| {
+            (min.min(chunk), max.max(chunk))
+        });
+        // The last tile of the last chunk is at most i32::MAX, the chunk after it isn't
+        Some((min * CHUNK_SIZE, max * CHUNK_SIZE + (CHUNK_SIZE - 1)))
+    }
+
+    /// Rows of adjacent solid tiles between `min` and `max`, as first tile and length. Runs
+    /// reaching past the sides are returned whole, so every area touching a run sees the same
+    /// run. Merging tiles keeps bodies sliding along a floor from catching on their seams.
+    pub fn solid_runs(&self, min: IVec2, max: IVec2) -> Vec<(IVec2, i32)> {
+        let Some((low, high)) = self.bounds() else {
+            return Vec::new();
+        };
+        let (min, max) = (min.max(low), max.min(high));
+        let solid = |x: i32, y: i32| self.tileset.is_solid(self.get(IVec2::new(x, y)));
+        let mut runs = Vec::new();
+        for y in min.y..=max.y {
+            let mut x = min.x;
+            while x > low.x && solid(x - 1, y) {
+                x -= 1;
+            }
+            let mut run: Option<(IVec2, i32)> = None;
+            while x <= high.x {
+                if solid(x, y) {
+                    let tile = IVec2::new(x, y);
+                    run = Some(run.map_or((tile, 1), |(start, length)| (start, length + 1)));
+                } else {
+                    runs.extend(run.take());
+                    if x >= max.x {
+                        break;
+                    }
+                }
+                if x == high.x {
+                    break;
+                }
+                x += 1;
+            }
+            runs.extend(run);
+        }
+        runs
+    }
+}
+
+impl Hash for Tilemap {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.tileset.hash(state);
+        self.tile_size.to_array().map(f32::to_bits).hash(state);
+        self.collision_layer.hash(state);
+        for (position, chunk) in self.chunks.iter() {
+            position.hash(state);
+            chunk.tiles.hash(state);
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    fn tilemap() -> Tilemap {
+        let tileset = Tileset {
+            sprite: 0,
+            columns: 4,
+            rows: 2,
+            solid: vec![2],
+        };
+        Tilemap::new(tileset, Vec2::splat(0.5))
+    }
+
+    #[test]
+    fn test_tiles_across_chunks() {
+        let mut map = tilemap();
+        assert!(map.set(IVec2::new(-1, -1), 3));
+        assert!(!map.set(IVec2::new(-1, -1), 3));
+        assert!(map.set(IVec2::new(CHUNK_SIZE, 0), 1));
+        assert_eq!(map.get(IVec2::new(-1, -1)), 3);
+        assert_eq!(map.get(IVec2::new(CHUNK_SIZE, 0)), 1);
+        assert_eq!(map.get(IVec2::new(0, 0)), EMPTY_TILE);
+        let chunks: Vec<IVec2> = map.chunks().map(|(position, _)| position).collect();
+        assert_eq!(chunks, vec![IVec2::new(-1, -1), IVec2::new(1, 0)]);
+        assert_eq!(
+            map.bounds(),
+            Some((
+                IVec2::splat(-CHUNK_SIZE),
+                IVec2::new(2 * CHUNK_SIZE - 1, CHUNK_SIZE - 1)
+            ))
+        );
+
+        // Clearing the last tile drops the chunk
+        map.set(IVec2::new(-1, -1), EMPTY_TILE);
+        assert_eq!(map.chunks().count(), 1);
+        assert_eq!(map.tile_at(Vec2::new(-0.1, 0.6)), IVec2::new(-1, 1));
+        assert_eq!(map.tile_center(IVec2::new(1, 0)), Vec2::new(0.75, 0.25));
+    }
+
+    #[test]
+    fn test_tiles_at_the_ends_of_the_range() {
+        let mut map = tilemap();
+        map.set(IVec2::new(i32::MAX, 0), 2);
+        map.set(IVec2::new(i32::MIN, 0), 2);
+        assert_eq!(
+            map.bounds(),
+            Some((
+                IVec2::new(i32::MIN, 0),
+                IVec2::new(i32::MAX, CHUNK_SIZE - 1)
+            ))
+        );
+        let last = IVec2::new(i32::MAX, 0);
+        assert_eq!(map.solid_runs(last - 1, last), vec![(last, 1)]);
+        let first = IVec2::new(i32::MIN, 0);
+        assert_eq!(map.solid_runs(first, first + 1), vec![(first, 1)]);
+    }
+
+    #[test]
+    fn test_revisions_follow_changes() {
+        let mut map = tilemap();
+        map.fill_rect(IVec2::ZERO, UVec2::new(2, 2), 1);
+        map.set(IVec2::new(CHUNK_SIZE, 0), 1);
+        let revisions = |map: &Tilemap| -> Vec<u64> {
+            map.chunks().map(|(_, chunk)| chunk.revision()).collect()
+        };
+        let before = revisions(&map);
+        map.set(IVec2::new(1, 1), 2);
+        let after = revisions(&map);
+        assert_ne!(before[0], after[0]);
+        assert_eq!(before[1], after[1]);
+        // Same tiles are equal no matter when they changed
+        assert_eq!(map, map.clone());
+    }
+
+    #[test]
+    fn test_solid_runs() {
+        let mut map = tilemap();
+        map.fill_rect(IVec2::new(-2, 3), UVec2::new(5, 1), 2);
+        map.set(IVec2::new(0, 3), 1);
+        map.set(IVec2::new(0, 4), 2);
+        assert_eq!(
+            map.solid_runs(IVec2::new(-10, 0), IVec2::new(10, 10)),
+            vec![
+                (IVec2::new(-2, 3), 2),
+                (IVec2::new(1, 3), 2),
+                (IVec2::new(0, 4), 1)
+            ]
+        );
+        assert_eq!(
+            map.solid_runs(IVec2::new(2, 3), IVec2::new(2, 3)),
+            vec![(IVec2::new(1, 3), 2)]
+        );
+    }
+
+    #[test]
+    fn test_tile_uv() {
+        let tileset = tilemap().tileset;
+        assert_eq!(
+            tileset.tile_uv(6, [0.5, 0.0], [0.5, 0.25]),
+            Some(([0.625, 0.125], [0.125, 0.125]))
+        );
+        assert_eq!(tileset.tile_uv(EMPTY_TILE, [0.0; 2], [1.0; 2]), None);
+        assert_eq!(tileset.tile_uv(9, [0.0; 2], [1.0; 2]), None);
+    }
+
+    #[test]
+    fn test_fill_rect_limits() {
+        let mut map = tilemap();
+        assert!(!map.fill_rect(IVec2::ZERO, UVec2::new(u32::MAX, u32::MAX), 1));
+        assert!(!map.fill_rect(IVec2::ZERO, UVec2::new(2048, 1024), 1));
+        assert_eq!(map.chunks().count(), 0);
+        assert!(map.fill_rect(IVec2::ZERO, UVec2::new(1024, 0), 1));
+        assert_eq!(map.chunks().count(), 0);
+
+        // Cut off at the edge of the i32 range instead of wrapping around
+        let corner = IVec2::splat(i32::MAX - 1);
+        assert!(map.fill_rect(corner, UVec2::new(u32::MAX, 4), 3));
+        assert_eq!(map.get(corner), 3);
+        assert_eq!(map.get(IVec2::splat(i32::MAX)), 3);
+        assert_eq!(map.get(IVec2::new(i32::MIN, i32::MAX)), EMPTY_TILE);
+        assert_eq!(map.chunks().count(), 1);
+    }
+}
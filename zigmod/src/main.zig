@@ -28,3 +28,6 @@ export fn get_mod_name() void {
     const len: usize = my_string.len;
     get_mod_name_callback(ptr, len);
 }
+
+// Registers no console commands, so the engine never calls this
+export fn console_command() void {}